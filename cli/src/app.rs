@@ -1,17 +1,18 @@
+use std::time::Duration;
+
 use clap::Parser;
 use gsv::config::CliConfig;
 
 use crate::auth_flow::{
-    resolve_device_gateway_auth, run_auth_login, run_auth_logout, run_auth_setup,
-    run_with_auto_setup_and_login_retry, run_with_auto_setup_options_retry,
-    run_with_auto_setup_retry, AuthSetupOptions,
+    run_auth_login, run_auth_logout, run_auth_setup, run_with_auto_setup_and_login_retry,
+    run_with_auto_setup_options_retry, run_with_auto_setup_retry, AuthSetupOptions,
 };
 use crate::cli::{
     AuthAction, Cli, Commands, ConfigAction, DeviceAction, DeviceServiceAction, LocalConfigAction,
 };
 use crate::commands;
 use crate::device::{
-    resolve_device_id, resolve_device_workspace, run_device, run_device_service, run_shell,
+    resolve_device_id, run_device_service, run_devices, run_shell, DeviceRunFlags,
 };
 use crate::local_config::run_local_config;
 use crate::version::run_version;
@@ -164,32 +165,52 @@ pub(crate) async fn run() -> Result<(), Box<dyn std::error::Error>> {
             }
         },
         Commands::Device { action } => match action {
-            DeviceAction::Run { id, workspace } => {
-                let device_id = resolve_device_id(id.clone(), &cfg);
-                let workspace = resolve_device_workspace(workspace.clone(), &cfg);
+            DeviceAction::Run {
+                id,
+                workspace,
+                mounts,
+                stateless,
+                grace_period,
+            } => {
+                let flags = DeviceRunFlags {
+                    id,
+                    workspace,
+                    mounts,
+                    url: cli_url_override.clone(),
+                    username: cli_user_override.clone(),
+                    token: cli_token_override.clone(),
+                    stateless,
+                    grace_period: grace_period.map(Duration::from_secs),
+                };
+                // Interactive setup saves credentials; a stateless device
+                // gets them from its environment instead.
+                if stateless || flags.uses_device_entries(&cfg) {
+                    return run_devices(flags).await;
+                }
+
                 run_with_auto_setup_options_retry(
                     &url,
                     &cfg,
                     AuthSetupOptions {
                         username: cli_user_override.clone(),
                         password: cli_password_override.clone(),
-                        device_id: Some(device_id.clone()),
+                        device_id: Some(resolve_device_id(flags.id.clone(), &cfg)),
                         ..AuthSetupOptions::default()
                     },
-                    || async {
-                        let attempt_cfg = CliConfig::load();
-                        let auth = resolve_device_gateway_auth(
-                            &attempt_cfg,
-                            cli_token_override.clone(),
-                            cli_user_override.clone(),
-                        )?;
-                        run_device(&url, auth, device_id.clone(), workspace.clone()).await
-                    },
+                    || async { run_devices(flags.clone()).await },
                 )
                 .await
             }
-            DeviceAction::Install { id, workspace } => run_device_service(
-                DeviceServiceAction::Install { id, workspace },
+            DeviceAction::Install {
+                id,
+                workspace,
+                service_manager,
+            } => run_device_service(
+                DeviceServiceAction::Install {
+                    id,
+                    workspace,
+                    service_manager,
+                },
                 &cfg,
                 cli_url_override.as_deref(),
                 cli_user_override.as_deref(),
@@ -209,8 +230,29 @@ pub(crate) async fn run() -> Result<(), Box<dyn std::error::Error>> {
                 cli_user_override.as_deref(),
                 cli_token_override.as_deref(),
             ),
-            DeviceAction::Status => run_device_service(
-                DeviceServiceAction::Status,
+            DeviceAction::Reload => run_device_service(
+                DeviceServiceAction::Reload,
+                &cfg,
+                cli_url_override.as_deref(),
+                cli_user_override.as_deref(),
+                cli_token_override.as_deref(),
+            ),
+            DeviceAction::Approve { id, deny, json } => run_device_service(
+                DeviceServiceAction::Approve { id, deny, json },
+                &cfg,
+                cli_url_override.as_deref(),
+                cli_user_override.as_deref(),
+                cli_token_override.as_deref(),
+            ),
+            DeviceAction::Status { json, prometheus } => run_device_service(
+                DeviceServiceAction::Status { json, prometheus },
+                &cfg,
+                cli_url_override.as_deref(),
+                cli_user_override.as_deref(),
+                cli_token_override.as_deref(),
+            ),
+            DeviceAction::Probe { ready } => run_device_service(
+                DeviceServiceAction::Probe { ready },
                 &cfg,
                 cli_url_override.as_deref(),
                 cli_user_override.as_deref(),
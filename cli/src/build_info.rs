@@ -13,3 +13,20 @@ pub fn is_ci_build() -> bool {
 pub fn version_display() -> &'static str {
     BUILD_VERSION
 }
+
+/// Platform part of the release asset names, for example `linux-x64`.
+pub fn release_target() -> Option<&'static str> {
+    match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("linux", "x86_64") => Some("linux-x64"),
+        ("linux", "aarch64") => Some("linux-arm64"),
+        ("macos", "x86_64") => Some("darwin-x64"),
+        ("macos", "aarch64") => Some("darwin-arm64"),
+        ("windows", "x86_64") => Some("windows-x64"),
+        _ => None,
+    }
+}
+
+/// Release asset carrying the CLI binary for this platform.
+pub fn release_asset_name() -> Option<String> {
+    release_target().map(|target| format!("gsv-{}{}", target, std::env::consts::EXE_SUFFIX))
+}
@@ -1,5 +1,6 @@
 use clap::{Parser, Subcommand, ValueEnum};
 use gsv::deploy::CodeModePreference;
+use gsv::device_service::ServiceManagerKind;
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -99,6 +100,18 @@ pub(crate) enum DeviceAction {
         /// Workspace directory for file tools
         #[arg(long)]
         workspace: Option<PathBuf>,
+
+        /// Extra named mount, NAME=PATH with optional :ro or :rw suffix (repeatable)
+        #[arg(long = "mount", value_name = "NAME=PATH[:ro]")]
+        mounts: Vec<String>,
+
+        /// Configure from GSV_* environment variables, log JSON to stdout and never write config
+        #[arg(long)]
+        stateless: bool,
+
+        /// Seconds requests still running at shutdown get to finish (default: device.grace_period_secs, else 25)
+        #[arg(long, value_name = "SECS")]
+        grace_period: Option<u64>,
     },
 
     /// Install and start device daemon service
@@ -110,6 +123,10 @@ pub(crate) enum DeviceAction {
         /// Workspace directory (saved to local config during install)
         #[arg(long)]
         workspace: Option<PathBuf>,
+
+        /// Service manager to install with (default: detected; saved to local config)
+        #[arg(long, value_enum)]
+        service_manager: Option<ServiceManagerKind>,
     },
 
     /// Start device daemon service
@@ -118,8 +135,40 @@ pub(crate) enum DeviceAction {
     /// Stop device daemon service
     Stop,
 
+    /// Reload the running daemon's configuration
+    Reload,
+
+    /// List requests waiting for approval at this device, or answer one
+    Approve {
+        /// Approval to answer, as listed (default: list them)
+        id: Option<u64>,
+
+        /// Deny the request instead of approving it
+        #[arg(long, requires = "id")]
+        deny: bool,
+
+        /// Print pending approvals as JSON
+        #[arg(long, conflicts_with = "id")]
+        json: bool,
+    },
+
     /// Show device daemon service status
-    Status,
+    Status {
+        /// Print the daemon's raw status as JSON
+        #[arg(long, conflicts_with = "prometheus")]
+        json: bool,
+
+        /// Print daemon metrics in Prometheus text format
+        #[arg(long)]
+        prometheus: bool,
+    },
+
+    /// Check a running daemon, for container liveness and readiness probes
+    Probe {
+        /// Succeed only when every device is connected to its gateway
+        #[arg(long)]
+        ready: bool,
+    },
 
     /// Show device daemon service logs
     Logs {
@@ -280,6 +329,10 @@ pub(crate) enum DeviceServiceAction {
         /// Workspace directory (saved to local config during install)
         #[arg(long)]
         workspace: Option<PathBuf>,
+
+        /// Service manager to install with (default: detected; saved to local config)
+        #[arg(long, value_enum)]
+        service_manager: Option<ServiceManagerKind>,
     },
 
     /// Uninstall and stop device daemon service
@@ -291,8 +344,40 @@ pub(crate) enum DeviceServiceAction {
     /// Stop device daemon service
     Stop,
 
+    /// Reload the running daemon's configuration
+    Reload,
+
+    /// List requests waiting for approval at this device, or answer one
+    Approve {
+        /// Approval to answer, as listed (default: list them)
+        id: Option<u64>,
+
+        /// Deny the request instead of approving it
+        #[arg(long, requires = "id")]
+        deny: bool,
+
+        /// Print pending approvals as JSON
+        #[arg(long, conflicts_with = "id")]
+        json: bool,
+    },
+
     /// Show device daemon service status
-    Status,
+    Status {
+        /// Print the daemon's raw status as JSON
+        #[arg(long, conflicts_with = "prometheus")]
+        json: bool,
+
+        /// Print daemon metrics in Prometheus text format
+        #[arg(long)]
+        prometheus: bool,
+    },
+
+    /// Check a running daemon, for container liveness and readiness probes
+    Probe {
+        /// Succeed only when every device is connected to its gateway
+        #[arg(long)]
+        ready: bool,
+    },
 
     /// Show device daemon service logs
     Logs {
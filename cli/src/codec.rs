@@ -0,0 +1,273 @@
+//! Wire codec negotiated during `sys.connect`.
+//!
+//! Until the gateway agrees otherwise, control frames are JSON text messages
+//! and body chunks travel as-is. A negotiated codec moves control frames onto
+//! binary stream 0 (MessagePack and/or deflate) and deflates body chunks that
+//! actually shrink. Decoding is self-describing through the frame flags, so
+//! either side may switch as soon as the handshake response is out.
+
+use std::io::{Read, Write};
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::protocol::{
+    build_binary_frame, CodecOffer, Frame, FrameCompression, FrameEncoding, NegotiatedCodec,
+    BINARY_FRAME_DATA, BINARY_FRAME_DEFLATE, BINARY_FRAME_HEADER_BYTES, BINARY_FRAME_MSGPACK,
+    CONTROL_FRAME_STREAM_ID,
+};
+
+/// Payloads smaller than this are never worth compressing.
+const MIN_COMPRESS_BYTES: usize = 512;
+/// Refuse to inflate a single message beyond this size.
+const MAX_INFLATED_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Codec offered by this client: MessagePack preferred, JSON as fallback.
+pub fn default_offer() -> CodecOffer {
+    CodecOffer {
+        encodings: vec![FrameEncoding::Msgpack, FrameEncoding::Json],
+        compression: vec![FrameCompression::Deflate],
+    }
+}
+
+/// A decoded inbound binary message.
+#[derive(Debug)]
+pub enum Inbound {
+    /// Control frame carried on stream 0.
+    Frame(Frame),
+    /// Body frame, already inflated and with the compression flag cleared.
+    Body(Vec<u8>),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameCodec(NegotiatedCodec);
+
+impl FrameCodec {
+    pub fn new(negotiated: NegotiatedCodec) -> Self {
+        Self(negotiated)
+    }
+
+    pub fn negotiated(&self) -> NegotiatedCodec {
+        self.0
+    }
+
+    fn deflate(&self) -> bool {
+        self.0.compression == Some(FrameCompression::Deflate)
+    }
+
+    /// Encode a control frame for the wire.
+    pub fn encode(&self, frame: &Frame) -> Result<Message, String> {
+        let (mut flags, payload) = match self.0.encoding {
+            FrameEncoding::Json => {
+                let text = serde_json::to_string(frame)
+                    .map_err(|e| format!("Failed to encode frame: {}", e))?;
+                if !self.deflate() {
+                    return Ok(Message::Text(text));
+                }
+                (0, text.into_bytes())
+            }
+            FrameEncoding::Msgpack => (
+                BINARY_FRAME_MSGPACK,
+                rmp_serde::to_vec_named(frame)
+                    .map_err(|e| format!("Failed to encode frame: {}", e))?,
+            ),
+        };
+        let payload = match self.compress(&payload) {
+            Some(compressed) => {
+                flags |= BINARY_FRAME_DEFLATE;
+                compressed
+            }
+            None => payload,
+        };
+        Ok(Message::Binary(build_binary_frame(
+            CONTROL_FRAME_STREAM_ID,
+            flags,
+            &payload,
+        )))
+    }
+
+    /// Compress the payload of an outgoing body frame when that pays off.
+    /// Frames without data (END, ERROR, CANCEL) pass through untouched.
+    pub fn encode_body(&self, frame: Vec<u8>) -> Vec<u8> {
+        if !self.deflate() {
+            return frame;
+        }
+        let Some((stream_id, flags, payload)) = split_frame(&frame) else {
+            return frame;
+        };
+        if stream_id == CONTROL_FRAME_STREAM_ID
+            || flags & BINARY_FRAME_DATA == 0
+            || flags & BINARY_FRAME_DEFLATE != 0
+        {
+            return frame;
+        }
+        match self.compress(payload) {
+            Some(compressed) => {
+                build_binary_frame(stream_id, flags | BINARY_FRAME_DEFLATE, &compressed)
+            }
+            None => frame,
+        }
+    }
+
+    fn compress(&self, payload: &[u8]) -> Option<Vec<u8>> {
+        if !self.deflate() || payload.len() < MIN_COMPRESS_BYTES {
+            return None;
+        }
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::fast());
+        encoder.write_all(payload).ok()?;
+        let compressed = encoder.finish().ok()?;
+        (compressed.len() < payload.len()).then_some(compressed)
+    }
+}
+
+/// Decode an inbound binary message, whatever codec the peer chose.
+pub fn decode_binary(data: Vec<u8>) -> Result<Inbound, String> {
+    let Some((stream_id, flags, payload)) = split_frame(&data) else {
+        return Ok(Inbound::Body(data));
+    };
+    let compressed = flags & BINARY_FRAME_DEFLATE != 0;
+    if stream_id != CONTROL_FRAME_STREAM_ID && !compressed {
+        return Ok(Inbound::Body(data));
+    }
+    let payload = if compressed {
+        inflate(payload)?
+    } else {
+        payload.to_vec()
+    };
+    if stream_id != CONTROL_FRAME_STREAM_ID {
+        return Ok(Inbound::Body(build_binary_frame(
+            stream_id,
+            flags & !BINARY_FRAME_DEFLATE,
+            &payload,
+        )));
+    }
+    let frame = if flags & BINARY_FRAME_MSGPACK != 0 {
+        rmp_serde::from_slice(&payload)
+            .map_err(|e| format!("Invalid MessagePack control frame: {}", e))?
+    } else {
+        serde_json::from_slice(&payload)
+            .map_err(|e| format!("Invalid JSON control frame: {}", e))?
+    };
+    Ok(Inbound::Frame(frame))
+}
+
+/// Split a binary message into stream id, flags and payload. Unlike
+/// [`crate::protocol::parse_binary_frame`] this accepts the control stream.
+fn split_frame(data: &[u8]) -> Option<(u32, u8, &[u8])> {
+    let header = data.get(..BINARY_FRAME_HEADER_BYTES)?;
+    let stream_id = u32::from_le_bytes(header.get(..4)?.try_into().ok()?);
+    Some((stream_id, header[4], data.get(BINARY_FRAME_HEADER_BYTES..)?))
+}
+
+fn inflate(payload: &[u8]) -> Result<Vec<u8>, String> {
+    let mut inflated = Vec::new();
+    DeflateDecoder::new(payload)
+        .take(MAX_INFLATED_BYTES + 1)
+        .read_to_end(&mut inflated)
+        .map_err(|e| format!("Invalid deflate payload: {}", e))?;
+    if inflated.len() as u64 > MAX_INFLATED_BYTES {
+        return Err(format!(
+            "Inflated frame exceeds {} bytes",
+            MAX_INFLATED_BYTES
+        ));
+    }
+    Ok(inflated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{RequestFrame, BINARY_FRAME_END};
+    use serde_json::json;
+
+    fn codec(encoding: FrameEncoding, compression: Option<FrameCompression>) -> FrameCodec {
+        FrameCodec::new(NegotiatedCodec {
+            encoding,
+            compression,
+        })
+    }
+
+    fn large_request() -> Frame {
+        Frame::Req(RequestFrame::new(
+            "fs.search",
+            Some(json!({ "pattern": "needle ".repeat(200), "limit": 10 })),
+        ))
+    }
+
+    fn decode_frame(message: Message) -> Frame {
+        match message {
+            Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            Message::Binary(data) => match decode_binary(data).unwrap() {
+                Inbound::Frame(frame) => frame,
+                Inbound::Body(_) => panic!("expected a control frame"),
+            },
+            other => panic!("unexpected message {other:?}"),
+        }
+    }
+
+    #[test]
+    fn default_codec_keeps_json_text_frames() {
+        let message = FrameCodec::default().encode(&large_request()).unwrap();
+        assert!(matches!(message, Message::Text(_)));
+    }
+
+    #[test]
+    fn control_frames_round_trip_through_every_codec() {
+        for codec in [
+            codec(FrameEncoding::Json, Some(FrameCompression::Deflate)),
+            codec(FrameEncoding::Msgpack, None),
+            codec(FrameEncoding::Msgpack, Some(FrameCompression::Deflate)),
+        ] {
+            let frame = large_request();
+            let message = codec.encode(&frame).unwrap();
+            let Message::Binary(data) = &message else {
+                panic!("{codec:?} should use binary control frames");
+            };
+            let (stream_id, flags, _) = split_frame(data).unwrap();
+            assert_eq!(stream_id, CONTROL_FRAME_STREAM_ID);
+            assert_eq!(flags & BINARY_FRAME_DEFLATE != 0, codec.deflate());
+            assert_eq!(
+                serde_json::to_value(decode_frame(message)).unwrap(),
+                serde_json::to_value(frame).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn body_frames_are_compressed_only_when_smaller() {
+        let codec = codec(FrameEncoding::Json, Some(FrameCompression::Deflate));
+        let text = build_binary_frame(7, BINARY_FRAME_DATA, "line\n".repeat(500).as_bytes());
+        let encoded = codec.encode_body(text.clone());
+        assert!(encoded.len() < text.len());
+        let Inbound::Body(decoded) = decode_binary(encoded).unwrap() else {
+            panic!("expected a body frame");
+        };
+        assert_eq!(decoded, text);
+
+        let mut state = 0x2545_f491_u32;
+        let noise = (0..4096)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state.to_le_bytes()[0]
+            })
+            .collect::<Vec<_>>();
+        let random = build_binary_frame(7, BINARY_FRAME_DATA, &noise);
+        assert_eq!(codec.encode_body(random.clone()), random);
+
+        let end = build_binary_frame(7, BINARY_FRAME_END, &[]);
+        assert_eq!(codec.encode_body(end.clone()), end);
+    }
+
+    #[test]
+    fn uncompressed_body_frames_pass_through() {
+        let frame = build_binary_frame(3, BINARY_FRAME_DATA, b"abc");
+        let Inbound::Body(decoded) = decode_binary(frame.clone()).unwrap() else {
+            panic!("expected a body frame");
+        };
+        assert_eq!(decoded, frame);
+    }
+}
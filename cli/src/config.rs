@@ -1,5 +1,7 @@
+use crate::device_service::ServiceManagerKind;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
 pub const DEFAULT_SESSION_KEY: &str = "agent:main:cli:dm:main";
 
@@ -37,6 +39,10 @@ pub struct CliConfig {
     #[serde(default, alias = "node")]
     pub device: DeviceConfig,
 
+    /// Additional device identities served by one daemon
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub devices: Vec<DeviceIdentityConfig>,
+
     /// Default session settings
     #[serde(default)]
     pub session: SessionConfig,
@@ -103,6 +109,136 @@ pub struct DeviceConfig {
 
     /// Workspace directory for file tools
     pub workspace: Option<PathBuf>,
+
+    /// Upper bound for the reconnect backoff, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_retry_delay_secs: Option<u64>,
+
+    /// Install releases the gateway recommends (default: true)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_update: Option<bool>,
+
+    /// Seconds requests still running at shutdown get to finish (default: 25)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grace_period_secs: Option<u64>,
+
+    /// Let `host.kill` signal processes of other users (default: false)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kill_any_user: Option<bool>,
+
+    /// Serve `desktop.notify` and `desktop.clipboard.*` (default: false)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub desktop: Option<bool>,
+
+    /// Service manager for `gsv device install` and friends (default: detected)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_manager: Option<ServiceManagerKind>,
+
+    /// Concurrency limits and request queueing for driver syscalls
+    #[serde(default, skip_serializing_if = "DeviceLimitsConfig::is_empty")]
+    pub limits: DeviceLimitsConfig,
+
+    /// Syscalls that wait for approval by the person at the device
+    #[serde(default, skip_serializing_if = "DeviceApprovalConfig::is_empty")]
+    pub approval: DeviceApprovalConfig,
+
+    /// Named roots reachable from tools as `@name/...`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mounts: Vec<DeviceMountConfig>,
+}
+
+/// One `[[devices]]` entry. Unset connection fields fall back to `[gateway]`;
+/// unset device fields fall back to `[device]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceIdentityConfig {
+    /// Device ID
+    pub id: String,
+
+    /// WebSocket URL of the gateway this device connects to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+
+    /// Gateway username owning the device
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+
+    /// Device gateway token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+
+    /// Workspace directory for file tools
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<PathBuf>,
+
+    /// Upper bound for the reconnect backoff, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_retry_delay_secs: Option<u64>,
+
+    /// Concurrency limits; replaces `[device.limits]` when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limits: Option<DeviceLimitsConfig>,
+
+    /// Named roots reachable from tools as `@name/...`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mounts: Vec<DeviceMountConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceMountConfig {
+    /// Mount name, used as `@name` in tool paths
+    pub name: String,
+
+    /// Directory the mount exposes
+    pub path: PathBuf,
+
+    /// Refuse writes, edits and deletes under this mount
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceLimitsConfig {
+    /// Requests allowed to wait per syscall family before new ones are refused
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue_size: Option<usize>,
+
+    /// How long a queued request may wait for a slot, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue_timeout_secs: Option<u64>,
+
+    /// Concurrent requests per syscall family, keyed by a syscall name
+    /// (`fs.search`) or namespace (`shell`); `0` lifts the limit
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub concurrency: BTreeMap<String, usize>,
+}
+
+impl DeviceLimitsConfig {
+    pub fn is_empty(&self) -> bool {
+        self.queue_size.is_none()
+            && self.queue_timeout_secs.is_none()
+            && self.concurrency.is_empty()
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceApprovalConfig {
+    /// Syscalls to approve, by name (`fs.delete`), namespace (`host.*`) or `*`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub calls: Vec<String>,
+
+    /// `shell.exec` and cron job commands to approve, as glob patterns (`sudo *`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<String>,
+
+    /// Seconds a request waits for an answer before it is denied (default: 60)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+}
+
+impl DeviceApprovalConfig {
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty() && self.commands.is_empty() && self.timeout_secs.is_none()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -127,24 +263,32 @@ impl CliConfig {
 
     /// Load config from file, returning default if file doesn't exist
     pub fn load() -> Self {
+        Self::try_load().unwrap_or_else(|e| {
+            eprintln!("Warning: {}", e);
+            Self::default()
+        })
+    }
+
+    /// Load config from file like [`CliConfig::load`], but report a file that
+    /// cannot be read or parsed instead of falling back to defaults.
+    pub fn try_load() -> Result<Self, String> {
         let Some(path) = Self::config_path() else {
-            return Self::default();
+            return Ok(Self::default());
         };
 
         if !path.exists() {
-            return Self::default();
+            return Ok(Self::default());
         }
 
-        let cfg = match std::fs::read_to_string(&path) {
-            Ok(content) => toml::from_str(&content).unwrap_or_else(|e| {
-                eprintln!("Warning: Failed to parse config: {}", e);
-                Self::default()
-            }),
-            Err(e) => {
-                eprintln!("Warning: Failed to read config: {}", e);
-                Self::default()
-            }
-        };
+        Self::try_load_from(&path)
+    }
+
+    /// Load the config file at `path`; it must exist.
+    pub fn try_load_from(path: &Path) -> Result<Self, String> {
+        let content =
+            std::fs::read_to_string(path).map_err(|e| format!("Failed to read config: {}", e))?;
+        let cfg: Self =
+            toml::from_str(&content).map_err(|e| format!("Failed to parse config: {}", e))?;
 
         #[cfg(unix)]
         let mut cfg = cfg;
@@ -152,7 +296,7 @@ impl CliConfig {
         #[cfg(unix)]
         {
             use std::os::unix::fs::PermissionsExt;
-            if let Ok(meta) = std::fs::metadata(&path) {
+            if let Ok(meta) = std::fs::metadata(path) {
                 let mode = meta.permissions().mode();
                 if (mode & 0o077) != 0 {
                     if cfg.gateway.session_token.is_some() {
@@ -169,7 +313,7 @@ impl CliConfig {
             }
         }
 
-        cfg
+        Ok(cfg)
     }
 
     /// Save config to file
@@ -272,6 +416,14 @@ impl CliConfig {
         self.device.workspace.clone()
     }
 
+    /// Get the device reconnect backoff ceiling (if configured)
+    pub fn device_max_retry_delay(&self) -> Option<std::time::Duration> {
+        self.device
+            .max_retry_delay_secs
+            .filter(|secs| *secs > 0)
+            .map(std::time::Duration::from_secs)
+    }
+
     /// Get default device token (if configured)
     pub fn default_device_token(&self) -> Option<String> {
         self.device.token.clone()
@@ -335,6 +487,40 @@ default_key = "agent:main:cli:dm:main"
 # id = "device-macbook"
 # token = "your-device-token"
 # workspace = "/Users/you/projects"
+# max_retry_delay_secs = 60  # reconnect backoff ceiling
+# auto_update = true  # install releases the gateway recommends
+# grace_period_secs = 25  # time running requests get to finish at shutdown
+# kill_any_user = false  # host.kill may signal processes of other users
+# desktop = false  # desktop notifications and clipboard on a workstation
+# service_manager = "systemd-system"  # or systemd-user, openrc, runit, launchd, windows-task
+
+[device.limits]
+# Per syscall family concurrency and queueing for driver requests
+# queue_size = 64
+# queue_timeout_secs = 30
+# concurrency = { shell = 4, "fs.search" = 2, net = 8, fs = 16 }
+
+[device.approval]
+# Requests that wait for approval at the device: a prompt in the terminal of
+# a foreground daemon, or `gsv device approve`. Unanswered requests are denied.
+# calls = ["fs.delete", "host.kill"]
+# commands = ["sudo *", "*rm -rf*", "git push*"]  # shell.exec and cron commands
+# timeout_secs = 60
+
+# Named mounts, addressed from tools as @name/path
+# [[device.mounts]]
+# name = "data"
+# path = "/data"
+# read_only = true
+
+# Serve further device identities from the same daemon. Each entry connects
+# on its own; unset fields fall back to [gateway] and [device].
+# [[devices]]
+# id = "work-laptop"
+# url = "wss://gsv.example.com/ws"
+# username = "alice"
+# token = "another-device-token"
+# workspace = "/Users/you/work"
 
 "#
 }
@@ -1,7 +1,9 @@
 use crate::build_info;
+use crate::codec::{decode_binary, FrameCodec, Inbound};
 use crate::protocol::{
-    AuthInfo, ClientInfo, ConnectArgs, ConnectResult, DriverInfo, ErrorShape, Frame, RequestFrame,
-    ResponseFrame, PROTOCOL_VERSION,
+    AuthInfo, ClientBuild, ClientInfo, CodecOffer, ConnectArgs, ConnectResult, DriverInfo,
+    DriverMount, ErrorShape, Frame, RequestFrame, ResponseFrame, SessionResume, SystemInfo,
+    UpdateHint, PROTOCOL_VERSION,
 };
 use futures_util::{SinkExt, StreamExt};
 use serde_json::Value;
@@ -10,13 +12,14 @@ use std::error::Error as StdError;
 use std::fmt::{self, Display, Formatter};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::{mpsc, oneshot, Mutex, RwLock};
+use tokio::sync::{mpsc, oneshot, watch, Mutex, RwLock};
 use tokio_tungstenite::{connect_async, tungstenite::Message};
 
 pub type PendingRequests = Arc<Mutex<HashMap<String, oneshot::Sender<ResponseFrame>>>>;
 pub type FrameHandler = Arc<RwLock<Option<Box<dyn Fn(Frame) + Send + Sync>>>>;
 pub type BinaryHandler = Arc<RwLock<Option<Box<dyn Fn(Vec<u8>) + Send + Sync>>>>;
 pub type DisconnectFlag = Arc<AtomicBool>;
+pub type SharedCodec = Arc<std::sync::RwLock<FrameCodec>>;
 
 use std::sync::atomic::{AtomicBool, Ordering};
 
@@ -55,6 +58,11 @@ impl GatewayRpcError {
             .and_then(|v| v.as_bool())
             .unwrap_or(false)
     }
+
+    /// Version hint attached to a rejected connect, if the gateway sent one.
+    pub fn update_hint(&self) -> Option<UpdateHint> {
+        serde_json::from_value(self.details.as_ref()?.get("update")?.clone()).ok()
+    }
 }
 
 impl Display for GatewayRpcError {
@@ -106,9 +114,13 @@ pub struct ConnectOptions {
     pub role: String,
     pub client_id: Option<String>,
     pub implements: Option<Vec<String>>,
+    pub mounts: Option<Vec<DriverMount>>,
+    pub system: Option<SystemInfo>,
     pub auth_username: Option<String>,
     pub auth_password: Option<String>,
     pub auth_token: Option<String>,
+    pub session: Option<SessionResume>,
+    pub codec: Option<CodecOffer>,
 }
 
 pub struct Connection {
@@ -117,9 +129,28 @@ pub struct Connection {
     frame_handler: FrameHandler,
     binary_handler: BinaryHandler,
     disconnected: DisconnectFlag,
+    codec: SharedCodec,
+    pongs: watch::Receiver<u64>,
     pub connect_result: Option<ConnectResult>,
 }
 
+async fn dispatch_frame(frame: Frame, pending: &PendingRequests, frame_handler: &FrameHandler) {
+    match frame {
+        Frame::Res(res) => {
+            let mut pending = pending.lock().await;
+            if let Some(sender) = pending.remove(&res.id) {
+                let _ = sender.send(res);
+            }
+        }
+        frame => {
+            let handler = frame_handler.read().await;
+            if let Some(ref h) = *handler {
+                h(frame);
+            }
+        }
+    }
+}
+
 impl Connection {
     pub async fn connect(
         opts: ConnectOptions,
@@ -150,6 +181,7 @@ impl Connection {
         let frame_handler: FrameHandler = Arc::new(RwLock::new(Some(Box::new(on_frame))));
         let binary_handler: BinaryHandler = Arc::new(RwLock::new(None));
         let disconnected: DisconnectFlag = Arc::new(AtomicBool::new(false));
+        let (pong_tx, pongs) = watch::channel(0u64);
 
         let pending_for_write = pending.clone();
         let disconnected_for_write = disconnected.clone();
@@ -179,32 +211,29 @@ impl Connection {
                 match msg {
                     Message::Text(text) => {
                         if let Ok(frame) = serde_json::from_str::<Frame>(&text) {
-                            match &frame {
-                                Frame::Res(res) => {
-                                    let mut pending = pending_clone.lock().await;
-                                    if let Some(sender) = pending.remove(&res.id) {
-                                        let _ = sender.send(res.clone());
-                                    }
-                                }
-                                _ => {
-                                    let handler = frame_handler_clone.read().await;
-                                    if let Some(ref h) = *handler {
-                                        h(frame);
-                                    }
-                                }
-                            }
+                            dispatch_frame(frame, &pending_clone, &frame_handler_clone).await;
                         }
                     }
-                    Message::Binary(data) => {
-                        let handler = binary_handler_clone.read().await;
-                        if let Some(ref h) = *handler {
-                            h(data);
+                    Message::Binary(data) => match decode_binary(data) {
+                        Ok(Inbound::Frame(frame)) => {
+                            dispatch_frame(frame, &pending_clone, &frame_handler_clone).await;
                         }
-                    }
+                        Ok(Inbound::Body(data)) => {
+                            let handler = binary_handler_clone.read().await;
+                            if let Some(ref h) = *handler {
+                                h(data);
+                            }
+                        }
+                        Err(error) => {
+                            tracing::warn!(event = "connection.frame_decode_failed", error = %error);
+                        }
+                    },
                     Message::Ping(payload) => {
                         let _ = tx_for_read.send(Message::Pong(payload)).await;
                     }
-                    Message::Pong(_) => {}
+                    Message::Pong(_) => {
+                        pong_tx.send_modify(|count| *count = count.wrapping_add(1));
+                    }
                     _ => {}
                 }
             }
@@ -223,6 +252,8 @@ impl Connection {
             frame_handler,
             binary_handler,
             disconnected,
+            codec: SharedCodec::default(),
+            pongs,
             connect_result: None,
         };
         Ok(conn)
@@ -238,11 +269,29 @@ impl Connection {
         *h = Some(Box::new(handler));
     }
 
+    /// Codec currently used for outgoing frames.
+    pub fn codec(&self) -> FrameCodec {
+        *self.codec.read().expect("frame codec lock poisoned")
+    }
+
     pub async fn send_binary(&self, data: Vec<u8>) -> Result<(), Box<dyn std::error::Error>> {
+        let data = self.codec().encode_body(data);
         self.tx.send(Message::Binary(data)).await?;
         Ok(())
     }
 
+    /// Send a control frame using the negotiated codec.
+    pub async fn send_frame(&self, frame: &Frame) -> Result<(), Box<dyn std::error::Error>> {
+        let message = self.codec().encode(frame)?;
+        self.send_message(message).await
+    }
+
+    /// Send a message already encoded with [`Connection::codec`].
+    pub async fn send_message(&self, message: Message) -> Result<(), Box<dyn std::error::Error>> {
+        self.tx.send(message).await?;
+        Ok(())
+    }
+
     /// Send a raw JSON string as a text frame.
     pub async fn send_raw(&self, text: String) -> Result<(), Box<dyn std::error::Error>> {
         self.tx.send(Message::Text(text)).await?;
@@ -254,6 +303,28 @@ impl Connection {
         Ok(())
     }
 
+    /// Send a ping and wait for the peer's pong. Unlike [`Connection::send_ping`]
+    /// this detects half-open sockets, where writes still succeed locally.
+    /// Returns the round-trip time.
+    pub async fn ping(&self, timeout: Duration) -> Result<Duration, Box<dyn std::error::Error>> {
+        let mut pongs = self.pongs.clone();
+        pongs.borrow_and_update();
+        let started = tokio::time::Instant::now();
+        self.send_ping(b"gsv-keepalive".to_vec()).await?;
+        match tokio::time::timeout(timeout, pongs.changed()).await {
+            Ok(Ok(())) => Ok(started.elapsed()),
+            Ok(Err(_closed)) => Err("Connection closed while waiting for pong".into()),
+            Err(_elapsed) => Err(format!("No pong within {:?}", timeout).into()),
+        }
+    }
+
+    /// Start a close handshake. The socket is released once the peer
+    /// answers, which also ends the reader task.
+    pub async fn close(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.tx.send(Message::Close(None)).await?;
+        Ok(())
+    }
+
     pub fn is_disconnected(&self) -> bool {
         self.disconnected.load(Ordering::SeqCst)
     }
@@ -286,6 +357,8 @@ impl Connection {
                     .implements
                     .clone()
                     .unwrap_or_else(|| vec!["fs.*".to_string(), "shell.*".to_string()]),
+                mounts: opts.mounts.clone().unwrap_or_default(),
+                system: opts.system.clone(),
             })
         } else {
             None
@@ -299,9 +372,12 @@ impl Connection {
                 platform: std::env::consts::OS.to_string(),
                 role: opts.role.clone(),
                 channel: None,
+                build: Some(client_build()),
             },
             driver,
             auth,
+            session: opts.session.clone(),
+            codec: opts.codec.clone(),
         };
 
         let res = self
@@ -321,7 +397,26 @@ impl Connection {
             return Err(Box::new(rpc_error));
         }
 
-        self.connect_result = Some(parse_connect_result(res.data)?);
+        // A gateway too new for this protocol may still say which release
+        // would understand it.
+        let update = res
+            .data
+            .as_ref()
+            .and_then(|data| data.get("update"))
+            .cloned()
+            .filter(|update| update.is_object());
+        let connect_result = parse_connect_result(res.data).map_err(|error| {
+            GatewayRpcError::new(
+                "sys.connect",
+                426,
+                error,
+                update.map(|update| serde_json::json!({ "update": update })),
+            )
+        })?;
+        if let Some(negotiated) = connect_result.codec.filter(|_| opts.codec.is_some()) {
+            *self.codec.write().expect("frame codec lock poisoned") = FrameCodec::new(negotiated);
+        }
+        self.connect_result = Some(connect_result);
 
         Ok(())
     }
@@ -376,7 +471,7 @@ impl Connection {
         }
 
         let frame = Frame::Req(req);
-        let msg = Message::Text(serde_json::to_string(&frame)?);
+        let msg = self.codec().encode(&frame)?;
         if let Err(error) = self.tx.send(msg).await {
             let mut pending = self.pending.lock().await;
             pending.remove(&id);
@@ -387,6 +482,17 @@ impl Connection {
     }
 }
 
+fn client_build() -> ClientBuild {
+    let non_empty = |value: &str| Some(value.to_string()).filter(|value| !value.is_empty());
+    ClientBuild {
+        channel: non_empty(build_info::BUILD_CHANNEL),
+        commit: non_empty(build_info::BUILD_SHA),
+        tag: non_empty(build_info::BUILD_TAG),
+        timestamp: non_empty(build_info::BUILD_TIMESTAMP),
+        target: build_info::release_target().map(str::to_string),
+    }
+}
+
 fn parse_connect_result(data: Option<Value>) -> Result<ConnectResult, String> {
     let result: ConnectResult =
         serde_json::from_value(data.ok_or_else(|| "sys.connect returned no data".to_string())?)
@@ -403,6 +509,11 @@ fn parse_connect_result(data: Option<Value>) -> Result<ConnectResult, String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::protocol::{
+        build_binary_frame, parse_binary_frame, FrameCompression, FrameEncoding, NegotiatedCodec,
+        BINARY_FRAME_DATA, BINARY_FRAME_DEFLATE, BINARY_FRAME_END, BINARY_FRAME_MSGPACK,
+        CONTROL_FRAME_STREAM_ID,
+    };
 
     #[tokio::test]
     async fn fail_all_pending_requests_resolves_waiters() {
@@ -435,4 +546,180 @@ mod tests {
         let error = parse_connect_result(Some(data)).unwrap_err();
         assert_eq!(error, "Gateway selected protocol 1, expected 2");
     }
+
+    #[test]
+    fn update_hints_are_read_from_results_and_rejections() {
+        let data = serde_json::json!({
+            "protocol": 2,
+            "server": { "version": "test", "connectionId": "conn-1" },
+            "identity": {},
+            "syscalls": [],
+            "signals": [],
+            "update": { "minimumVersion": "0.4.0", "recommendedVersion": "0.5.2" }
+        });
+        let result = parse_connect_result(Some(data)).unwrap();
+        let hint = result.update.unwrap();
+        assert_eq!(hint.minimum_version.as_deref(), Some("0.4.0"));
+        assert_eq!(hint.recommended_version.as_deref(), Some("0.5.2"));
+        assert_eq!(hint.release_tag, None);
+
+        let rejection = GatewayRpcError::new(
+            "sys.connect",
+            426,
+            "Client too old",
+            Some(
+                serde_json::json!({ "update": { "minimumVersion": "0.5.0", "releaseTag": "v0.5.0" } }),
+            ),
+        );
+        let hint = rejection.update_hint().unwrap();
+        assert_eq!(hint.release_tag.as_deref(), Some("v0.5.0"));
+        assert_eq!(
+            GatewayRpcError::new("sys.connect", 401, "Bad token", None).update_hint(),
+            None
+        );
+    }
+
+    /// Minimal gateway stand-in: answers `sys.connect` with `codec`, then
+    /// echoes one request back as a response followed by a body frame.
+    /// Resolves to the raw message the client used for that request.
+    async fn stand_in_gateway(
+        codec: Option<NegotiatedCodec>,
+        body: Vec<u8>,
+    ) -> (String, tokio::task::JoinHandle<Message>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+            let Some(Ok(Message::Text(text))) = ws.next().await else {
+                panic!("sys.connect should arrive as JSON text");
+            };
+            let Frame::Req(connect) = serde_json::from_str(&text).unwrap() else {
+                panic!("expected sys.connect request");
+            };
+            let offer = connect.args.as_ref().and_then(|args| args.get("codec"));
+            assert!(offer.is_some(), "client should offer a codec");
+            let connected = Frame::Res(ResponseFrame {
+                id: connect.id,
+                ok: true,
+                data: Some(serde_json::json!({
+                    "protocol": PROTOCOL_VERSION,
+                    "server": { "version": "test", "connectionId": "conn-1" },
+                    "identity": {},
+                    "syscalls": [],
+                    "signals": [],
+                    "codec": codec,
+                })),
+                error: None,
+                body: None,
+            });
+            ws.send(Message::Text(serde_json::to_string(&connected).unwrap()))
+                .await
+                .unwrap();
+
+            let codec = FrameCodec::new(codec.unwrap_or_default());
+            let raw = ws.next().await.unwrap().unwrap();
+            let request = match &raw {
+                Message::Text(text) => serde_json::from_str(text).unwrap(),
+                Message::Binary(data) => match decode_binary(data.clone()).unwrap() {
+                    Inbound::Frame(frame) => frame,
+                    Inbound::Body(_) => panic!("expected a control frame"),
+                },
+                other => panic!("unexpected message {other:?}"),
+            };
+            let Frame::Req(request) = request else {
+                panic!("expected a request frame");
+            };
+            let response = Frame::Res(ResponseFrame {
+                id: request.id,
+                ok: true,
+                data: request.args,
+                error: None,
+                body: None,
+            });
+            ws.send(codec.encode(&response).unwrap()).await.unwrap();
+            let chunk = build_binary_frame(1, BINARY_FRAME_DATA | BINARY_FRAME_END, &body);
+            ws.send(Message::Binary(codec.encode_body(chunk)))
+                .await
+                .unwrap();
+            let _ = ws.next().await;
+            raw
+        });
+        (url, server)
+    }
+
+    async fn round_trip(codec: Option<NegotiatedCodec>) -> (Message, Connection) {
+        let body = "listing\n".repeat(1024).into_bytes();
+        let (url, server) = stand_in_gateway(codec, body.clone()).await;
+        let conn = Connection::connect(
+            ConnectOptions {
+                url,
+                role: "user".to_string(),
+                client_id: None,
+                implements: None,
+                mounts: None,
+                system: None,
+                auth_username: None,
+                auth_password: None,
+                auth_token: None,
+                session: None,
+                codec: Some(crate::codec::default_offer()),
+            },
+            |_| {},
+        )
+        .await
+        .unwrap();
+        let (body_tx, mut body_rx) = mpsc::unbounded_channel();
+        conn.set_binary_handler(move |data| {
+            let _ = body_tx.send(data);
+        })
+        .await;
+
+        let args = serde_json::json!({ "pattern": "x".repeat(2048) });
+        let res = conn.request("fs.search", Some(args.clone())).await.unwrap();
+        assert!(res.ok);
+        assert_eq!(res.data, Some(args));
+
+        let received = body_rx.recv().await.unwrap();
+        let (stream_id, flags, payload) = parse_binary_frame(&received).unwrap();
+        assert_eq!(stream_id, 1);
+        assert_eq!(flags, BINARY_FRAME_DATA | BINARY_FRAME_END);
+        assert_eq!(payload, body);
+
+        conn.send_ping(Vec::new()).await.unwrap();
+        (server.await.unwrap(), conn)
+    }
+
+    #[tokio::test]
+    async fn negotiated_msgpack_and_deflate_are_used_after_connect() {
+        let negotiated = NegotiatedCodec {
+            encoding: FrameEncoding::Msgpack,
+            compression: Some(FrameCompression::Deflate),
+        };
+        let (raw, conn) = round_trip(Some(negotiated)).await;
+
+        assert_eq!(conn.codec().negotiated(), negotiated);
+        let Message::Binary(data) = raw else {
+            panic!("request should use a binary control frame");
+        };
+        assert_eq!(
+            u32::from_le_bytes(data[..4].try_into().unwrap()),
+            CONTROL_FRAME_STREAM_ID
+        );
+        let flags = data[4];
+        assert_eq!(
+            flags,
+            BINARY_FRAME_MSGPACK | BINARY_FRAME_DEFLATE,
+            "large request should be MessagePack and compressed"
+        );
+    }
+
+    #[tokio::test]
+    async fn gateway_without_codec_support_keeps_json() {
+        let (raw, conn) = round_trip(None).await;
+
+        assert_eq!(conn.codec(), FrameCodec::default());
+        assert!(matches!(raw, Message::Text(_)));
+    }
 }
@@ -664,7 +664,7 @@ fn base_release_url(tag: &str) -> String {
     )
 }
 
-fn release_download_url(tag: &str, file_name: &str) -> String {
+pub fn release_download_url(tag: &str, file_name: &str) -> String {
     let base = format!("{}/{}", base_release_url(tag), file_name);
     if !is_mutable_release_tag(tag) {
         return base;
@@ -737,7 +737,7 @@ fn read_local_latest_tag(cfg: &CliConfig) -> Option<String> {
         .filter(|s| !s.is_empty())
 }
 
-fn parse_checksums(content: &str) -> BTreeMap<String, String> {
+pub fn parse_checksums(content: &str) -> BTreeMap<String, String> {
     let mut checksums = BTreeMap::new();
     for line in content.lines() {
         let trimmed = line.trim();
@@ -759,7 +759,7 @@ fn parse_checksums(content: &str) -> BTreeMap<String, String> {
     checksums
 }
 
-fn sha256_hex(bytes: &[u8]) -> String {
+pub fn sha256_hex(bytes: &[u8]) -> String {
     let mut hasher = Sha256::new();
     hasher.update(bytes);
     let digest = hasher.finalize();
@@ -3646,9 +3646,13 @@ async fn connect_gateway_with_retry(
                 role: "user".to_string(),
                 client_id: Some("deploy-bootstrap".to_string()),
                 implements: None,
+                mounts: None,
+                system: None,
                 auth_username: None,
                 auth_password: None,
                 auth_token: auth_token.map(|t| t.to_string()),
+                session: None,
+                codec: None,
             },
             |_| {},
         )
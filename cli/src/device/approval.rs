@@ -0,0 +1,516 @@
+//! Local approval of risky requests by the person at the device.
+//!
+//! Requests matching `[device.approval]` wait before they run. A daemon
+//! started in a terminal asks there; any daemon also lists them through the
+//! control socket, where `gsv device approve` answers. A request nobody
+//! answers within the timeout is denied, and one the gateway cancels stops
+//! waiting.
+
+use std::collections::BTreeMap;
+use std::io::BufRead;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use gsv::config::DeviceApprovalConfig;
+use gsv::tools::preview::is_dry_run;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::{mpsc, oneshot, Notify};
+use tracing::{info, warn};
+
+const DEFAULT_APPROVAL_TIMEOUT: Duration = Duration::from_secs(60);
+/// Longer request details are cut in prompts and listings.
+const MAX_DETAIL_CHARS: usize = 300;
+/// Calls that change nothing when asked for `dryRun: true`.
+const DRY_RUN_CALLS: &[&str] = &[
+    "fs.write",
+    "fs.edit",
+    "fs.delete",
+    "fs.copy",
+    "fs.transfer.receive",
+];
+
+/// Which requests need approval, from the identity's configuration.
+#[derive(Debug, Clone)]
+pub(crate) struct ApprovalPolicy {
+    calls: Vec<String>,
+    commands: Vec<glob::Pattern>,
+    timeout: Duration,
+}
+
+impl Default for ApprovalPolicy {
+    fn default() -> Self {
+        Self {
+            calls: Vec::new(),
+            commands: Vec::new(),
+            timeout: DEFAULT_APPROVAL_TIMEOUT,
+        }
+    }
+}
+
+impl ApprovalPolicy {
+    pub(crate) fn from_config(config: &DeviceApprovalConfig) -> Result<Self, String> {
+        let commands = config
+            .commands
+            .iter()
+            .map(|pattern| {
+                glob::Pattern::new(pattern).map_err(|e| {
+                    format!(
+                        "Invalid device.approval.commands pattern '{}': {}",
+                        pattern, e
+                    )
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            calls: config
+                .calls
+                .iter()
+                .map(|call| call.trim().to_string())
+                .collect(),
+            commands,
+            timeout: config
+                .timeout_secs
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_APPROVAL_TIMEOUT),
+        })
+    }
+
+    pub(super) fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// What to show the approver when `call` with `args` needs approval.
+    pub(super) fn review(&self, call: &str, args: Option<&Value>) -> Option<String> {
+        if DRY_RUN_CALLS.contains(&call) && args.is_some_and(is_dry_run) {
+            return None;
+        }
+        // A `shell.exec` start carries its command in `input`; with a
+        // `sessionId`, `input` is stdin for a running command, and each of
+        // its lines is checked as a command of its own. Cron jobs carry theirs
+        // in `command` (the caller fills it in for `cron.run`).
+        let field = match call {
+            "shell.exec" => Some("input"),
+            "cron.add" | "cron.run" => Some("command"),
+            _ => None,
+        };
+        let command = args
+            .zip(field)
+            .and_then(|(args, field)| args.get(field))
+            .and_then(Value::as_str);
+        let stdin =
+            call == "shell.exec" && args.is_some_and(|args| args.get("sessionId").is_some());
+        let listed = self.calls.iter().any(|pattern| {
+            pattern == "*"
+                || pattern == call
+                || pattern
+                    .strip_suffix('*')
+                    .is_some_and(|prefix| prefix.ends_with('.') && call.starts_with(prefix))
+        });
+        let matches = |command: &str| {
+            self.commands
+                .iter()
+                .any(|pattern| pattern.matches(command.trim()))
+        };
+        let matched_command = command.is_some_and(|command| {
+            if stdin {
+                command.lines().any(matches)
+            } else {
+                matches(command)
+            }
+        });
+        if !listed && !matched_command {
+            return None;
+        }
+        let detail = match command {
+            Some(command) => command.to_string(),
+            None => args.map(Value::to_string).unwrap_or_default(),
+        };
+        Some(truncate(detail.trim(), MAX_DETAIL_CHARS))
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", text.get(..end).unwrap_or(text)),
+        None => text.to_string(),
+    }
+}
+
+/// A request waiting for an answer, as listed by the control socket.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PendingApproval {
+    pub(crate) id: u64,
+    pub(crate) device_id: String,
+    pub(crate) request_id: String,
+    pub(crate) call: String,
+    pub(crate) detail: String,
+    pub(crate) requested_at_ms: i64,
+    pub(crate) expires_at_ms: i64,
+}
+
+struct Waiting {
+    pending: PendingApproval,
+    answer: oneshot::Sender<bool>,
+}
+
+#[derive(Default)]
+struct Queue {
+    next_id: u64,
+    waiting: BTreeMap<u64, Waiting>,
+}
+
+/// Requests waiting for approval across the daemon's identities.
+#[derive(Clone, Default)]
+pub(crate) struct Approvals {
+    queue: Arc<Mutex<Queue>>,
+    /// Woken whenever a request stops waiting.
+    settled: Arc<Notify>,
+    /// New requests, for the terminal prompt.
+    prompts: Option<mpsc::UnboundedSender<u64>>,
+}
+
+/// Removes a request that stops waiting for any reason, including its
+/// future being dropped when the gateway cancels it.
+struct WaitGuard<'a> {
+    approvals: &'a Approvals,
+    id: u64,
+}
+
+impl Drop for WaitGuard<'_> {
+    fn drop(&mut self) {
+        self.approvals.take(self.id);
+    }
+}
+
+impl Approvals {
+    fn queue(&self) -> MutexGuard<'_, Queue> {
+        self.queue.lock().expect("approval queue mutex poisoned")
+    }
+
+    fn take(&self, id: u64) -> Option<Waiting> {
+        let waiting = self.queue().waiting.remove(&id);
+        if waiting.is_some() {
+            self.settled.notify_waiters();
+        }
+        waiting
+    }
+
+    /// Wait until the request is approved. Denial and timeout return the
+    /// reason to send back instead.
+    pub(super) async fn ask(
+        &self,
+        device_id: &str,
+        request_id: &str,
+        call: &str,
+        detail: String,
+        timeout: Duration,
+    ) -> Result<(), String> {
+        let (answer, answered) = oneshot::channel();
+        let requested_at_ms = chrono::Utc::now().timestamp_millis();
+        let pending = PendingApproval {
+            id: 0,
+            device_id: device_id.to_string(),
+            request_id: request_id.to_string(),
+            call: call.to_string(),
+            detail,
+            requested_at_ms,
+            expires_at_ms: requested_at_ms
+                .saturating_add(i64::try_from(timeout.as_millis()).unwrap_or(i64::MAX)),
+        };
+        let id = {
+            let mut queue = self.queue();
+            queue.next_id += 1;
+            let id = queue.next_id;
+            let pending = PendingApproval { id, ..pending };
+            info!(
+                event = "approval.pending",
+                approval = id,
+                request_id = %pending.request_id,
+                call = %pending.call,
+                detail = %pending.detail,
+            );
+            queue.waiting.insert(id, Waiting { pending, answer });
+            id
+        };
+        let _guard = WaitGuard {
+            approvals: self,
+            id,
+        };
+        if let Some(prompts) = &self.prompts {
+            let _ = prompts.send(id);
+        }
+
+        match tokio::time::timeout(timeout, answered).await {
+            Ok(Ok(true)) => Ok(()),
+            Ok(Ok(false)) | Ok(Err(_)) => Err(format!("{} was denied at the device", call)),
+            Err(_elapsed) => {
+                warn!(event = "approval.timeout", approval = id, call = %call);
+                Err(format!(
+                    "{} was denied: no approval at the device within {} s",
+                    call,
+                    timeout.as_secs()
+                ))
+            }
+        }
+    }
+
+    pub(crate) fn list(&self) -> Vec<PendingApproval> {
+        self.queue()
+            .waiting
+            .values()
+            .map(|waiting| waiting.pending.clone())
+            .collect()
+    }
+
+    fn get(&self, id: u64) -> Option<PendingApproval> {
+        self.queue()
+            .waiting
+            .get(&id)
+            .map(|waiting| waiting.pending.clone())
+    }
+
+    /// Answer a waiting request.
+    pub(crate) fn decide(
+        &self,
+        id: u64,
+        approve: bool,
+        by: &str,
+    ) -> Result<PendingApproval, String> {
+        let waiting = self
+            .take(id)
+            .ok_or_else(|| format!("No pending approval {}", id))?;
+        info!(
+            event = "approval.decided",
+            approval = id,
+            call = %waiting.pending.call,
+            approved = approve,
+            by,
+        );
+        let _ = waiting.answer.send(approve);
+        Ok(waiting.pending)
+    }
+
+    /// Ask on this process's terminal as well, one request at a time.
+    pub(super) fn prompt_on_terminal(&mut self) {
+        let (prompts, mut requested) = mpsc::unbounded_channel();
+        self.prompts = Some(prompts);
+        let approvals = self.clone();
+
+        let (lines_tx, mut lines) = mpsc::unbounded_channel::<String>();
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else { break };
+                if lines_tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            while let Some(id) = requested.recv().await {
+                // Typing done before the question must not answer it.
+                while lines.try_recv().is_ok() {}
+                let Some(pending) = approvals.get(id) else {
+                    continue;
+                };
+                eprintln!("\n{}", describe_pending(&pending));
+                eprint!("Allow? [y/N] ");
+                loop {
+                    let settled = approvals.settled.notified();
+                    if approvals.get(id).is_none() {
+                        eprintln!("(answered elsewhere or timed out)");
+                        break;
+                    }
+                    tokio::select! {
+                        line = lines.recv() => {
+                            let Some(line) = line else { return };
+                            let approve = matches!(
+                                line.trim().to_ascii_lowercase().as_str(),
+                                "y" | "yes"
+                            );
+                            let _ = approvals.decide(id, approve, "terminal");
+                            break;
+                        }
+                        _ = settled => {}
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// One pending request, for the terminal prompt and `gsv device approve`.
+pub(crate) fn describe_pending(pending: &PendingApproval) -> String {
+    let remaining = (pending.expires_at_ms - chrono::Utc::now().timestamp_millis()).max(0) / 1000;
+    format!(
+        "[{}] {} wants to run {}: {} (denied in {}s)",
+        pending.id, pending.device_id, pending.call, pending.detail, remaining
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn policy(calls: &[&str], commands: &[&str]) -> ApprovalPolicy {
+        ApprovalPolicy::from_config(&DeviceApprovalConfig {
+            calls: calls.iter().map(|call| call.to_string()).collect(),
+            commands: commands.iter().map(|command| command.to_string()).collect(),
+            timeout_secs: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn policy_matches_calls_and_shell_commands() {
+        let strict = policy(&["fs.delete", "host.*"], &["sudo *", "*rm -rf*"]);
+        assert_eq!(
+            strict.review("fs.delete", Some(&json!({ "path": "build" }))),
+            Some(r#"{"path":"build"}"#.to_string())
+        );
+        assert!(strict
+            .review(
+                "fs.delete",
+                Some(&json!({ "path": "build", "dryRun": true }))
+            )
+            .is_none());
+        assert!(strict.review("host.kill", None).is_some());
+        assert!(strict
+            .review("host.kill", Some(&json!({ "pid": 1, "dryRun": true })))
+            .is_some());
+        assert!(strict.review("hostile.call", None).is_none());
+        assert!(strict.review("fs.read", None).is_none());
+        assert_eq!(
+            strict.review(
+                "shell.exec",
+                Some(&json!({ "input": "cd x && rm -rf /tmp/y", "cwd": "/srv" }))
+            ),
+            Some("cd x && rm -rf /tmp/y".to_string())
+        );
+        assert!(strict
+            .review("shell.exec", Some(&json!({ "input": "ls -la" })))
+            .is_none());
+        assert_eq!(
+            strict.review(
+                "shell.exec",
+                Some(&json!({ "input": "y\nsudo reboot\n", "sessionId": "s-1" }))
+            ),
+            Some("y\nsudo reboot".to_string())
+        );
+        assert!(strict
+            .review(
+                "shell.exec",
+                Some(&json!({ "input": "y\n", "sessionId": "s-1" }))
+            )
+            .is_none());
+        assert_eq!(
+            strict.review(
+                "cron.add",
+                Some(&json!({ "schedule": "@daily", "command": "sudo apt upgrade" }))
+            ),
+            Some("sudo apt upgrade".to_string())
+        );
+        assert!(strict
+            .review(
+                "cron.run",
+                Some(&json!({ "id": "j", "command": "rm -rf /srv/x" }))
+            )
+            .is_some());
+        assert!(strict
+            .review(
+                "cron.add",
+                Some(&json!({ "schedule": "@daily", "command": "ls" }))
+            )
+            .is_none());
+        assert!(strict
+            .review("shell.exec", Some(&json!({ "command": "sudo reboot" })))
+            .is_none());
+        assert!(policy(&["*"], &[]).review("host.info", None).is_some());
+
+        let error = ApprovalPolicy::from_config(&DeviceApprovalConfig {
+            commands: vec!["[oops".to_string()],
+            ..DeviceApprovalConfig::default()
+        })
+        .unwrap_err();
+        assert!(error.contains("device.approval.commands"));
+    }
+
+    #[tokio::test]
+    async fn requests_wait_for_an_answer() {
+        let approvals = Approvals::default();
+        let waiting = tokio::spawn({
+            let approvals = approvals.clone();
+            async move {
+                approvals
+                    .ask(
+                        "laptop",
+                        "req-1",
+                        "fs.delete",
+                        "build".to_string(),
+                        Duration::from_secs(30),
+                    )
+                    .await
+            }
+        });
+        while approvals.list().is_empty() {
+            tokio::task::yield_now().await;
+        }
+        let [pending] = approvals.list().try_into().unwrap();
+        assert_eq!(pending.request_id, "req-1");
+        assert!(describe_pending(&pending).starts_with("[1] laptop wants to run fs.delete: build"));
+
+        approvals.decide(pending.id, true, "test").unwrap();
+        waiting.await.unwrap().unwrap();
+        assert!(approvals.list().is_empty());
+        assert_eq!(
+            approvals.decide(pending.id, false, "test").unwrap_err(),
+            "No pending approval 1"
+        );
+    }
+
+    #[tokio::test]
+    async fn denied_timed_out_and_cancelled_requests_do_not_run() {
+        let approvals = Approvals::default();
+        let ask = |approvals: &Approvals, timeout| {
+            let approvals = approvals.clone();
+            async move {
+                approvals
+                    .ask("laptop", "req", "host.kill", "{}".to_string(), timeout)
+                    .await
+            }
+        };
+
+        let denied = tokio::spawn(ask(&approvals, Duration::from_secs(30)));
+        while approvals.list().is_empty() {
+            tokio::task::yield_now().await;
+        }
+        approvals
+            .decide(approvals.list()[0].id, false, "test")
+            .unwrap();
+        assert_eq!(
+            denied.await.unwrap().unwrap_err(),
+            "host.kill was denied at the device"
+        );
+
+        let error = ask(&approvals, Duration::from_millis(20))
+            .await
+            .unwrap_err();
+        assert!(
+            error.contains("no approval at the device within"),
+            "{error}"
+        );
+        assert!(approvals.list().is_empty());
+
+        let cancelled = tokio::spawn(ask(&approvals, Duration::from_secs(30)));
+        while approvals.list().is_empty() {
+            tokio::task::yield_now().await;
+        }
+        cancelled.abort();
+        let _ = cancelled.await;
+        assert!(approvals.list().is_empty());
+    }
+}
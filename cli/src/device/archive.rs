@@ -0,0 +1,1306 @@
+//! `fs.archive.create` and `fs.archive.extract`: tar, tar.gz and zip archives
+//! on the device, without shelling out to `tar` or `unzip`.
+//!
+//! A created archive is written to `output`, or streamed back as the
+//! response body when there is none. An archive to extract is read from
+//! `path`, or taken from the request body into a temporary file first.
+//! Extraction only writes regular files and directories below
+//! `destination`; absolute names, names with `..`, links, and paths through
+//! an existing symlink are skipped and reported instead.
+
+use super::transfer::{
+    receive_to_file, transfer_temp_path, BinaryFrameInbox, IncomingStreamGuard, OutgoingBody,
+    TempFileGuard,
+};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use glob::{MatchOptions, Pattern};
+use gsv::protocol::FrameBodyDescriptor;
+use gsv::tools::paths::{Access, PathResolver};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::sync::mpsc;
+
+/// Entries beyond this are counted but not listed in results.
+const MAX_LISTED_ENTRIES: usize = 1000;
+const STREAM_CHUNK_BYTES: usize = 256 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+enum ArchiveFormat {
+    #[serde(rename = "tar")]
+    Tar,
+    #[serde(rename = "tar.gz", alias = "tgz")]
+    TarGz,
+    #[serde(rename = "zip")]
+    Zip,
+}
+
+impl ArchiveFormat {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Tar => "tar",
+            Self::TarGz => "tar.gz",
+            Self::Zip => "zip",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Self::Tar => "application/x-tar",
+            Self::TarGz => "application/gzip",
+            Self::Zip => "application/zip",
+        }
+    }
+
+    fn from_name(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else {
+            None
+        }
+    }
+
+    fn sniff(path: &Path) -> Result<Self, String> {
+        let mut magic = Vec::with_capacity(4);
+        File::open(path)
+            .and_then(|file| file.take(4).read_to_end(&mut magic))
+            .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+        Ok(match magic.as_slice() {
+            [0x1f, 0x8b, ..] => Self::TarGz,
+            [b'P', b'K', 3, 4] | [b'P', b'K', 5, 6] => Self::Zip,
+            _ => Self::Tar,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateArgs {
+    path: String,
+    #[serde(default)]
+    output: Option<String>,
+    #[serde(default)]
+    format: Option<ArchiveFormat>,
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default)]
+    list_only: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExtractArgs {
+    #[serde(default)]
+    path: Option<String>,
+    #[serde(default)]
+    destination: Option<String>,
+    #[serde(default)]
+    format: Option<ArchiveFormat>,
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default)]
+    list_only: bool,
+    #[serde(default)]
+    overwrite: bool,
+}
+
+pub(super) async fn handle_archive_syscall(
+    call: &str,
+    args: Value,
+    request_body: Option<FrameBodyDescriptor>,
+    paths: &PathResolver,
+    binary_inbox: &BinaryFrameInbox,
+) -> Option<Result<(Value, Option<OutgoingBody>), String>> {
+    match call {
+        "fs.archive.create" => {
+            if let Some(body) = request_body {
+                binary_inbox.cancel_incoming(body.stream_id, "Request body not accepted");
+                return Some(Err(format!("{} does not accept a request body", call)));
+            }
+            Some(create(args, paths, binary_inbox).await)
+        }
+        "fs.archive.extract" => Some(
+            extract(args, request_body, paths, binary_inbox)
+                .await
+                .map(|data| (data, None)),
+        ),
+        _ => None,
+    }
+}
+
+/// Include and exclude globs over `/`-separated entry names. A pattern
+/// without a `/` matches any single component, so `*.log` or `target`
+/// apply at every depth; excluding a directory excludes what is inside.
+struct Filter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl Filter {
+    fn new(include: &[String], exclude: &[String]) -> Result<Self, String> {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| {
+                    Pattern::new(pattern).map_err(|e| format!("Invalid glob '{}': {}", pattern, e))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Self {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    fn excludes(&self, name: &str) -> bool {
+        let mut prefix_end = 0;
+        for component in name.split('/') {
+            prefix_end += component.len();
+            let prefix = name.get(..prefix_end).unwrap_or(name);
+            if self
+                .exclude
+                .iter()
+                .any(|pattern| glob_matches(pattern, prefix))
+            {
+                return true;
+            }
+            prefix_end += 1;
+        }
+        false
+    }
+
+    /// Whether a file is wanted. Directories are only archived or
+    /// extracted on their own when no include patterns are given.
+    fn includes_file(&self, name: &str) -> bool {
+        self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|pattern| glob_matches(pattern, name))
+    }
+
+    fn includes_directories(&self) -> bool {
+        self.include.is_empty()
+    }
+}
+
+fn glob_matches(pattern: &Pattern, name: &str) -> bool {
+    let options = MatchOptions {
+        case_sensitive: true,
+        require_literal_separator: true,
+        require_literal_leading_dot: false,
+    };
+    if pattern.as_str().contains('/') {
+        pattern.matches_with(name, options)
+    } else {
+        let base = name.rsplit('/').next().unwrap_or(name);
+        pattern.matches_with(base, options)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EntryKind {
+    File,
+    Directory,
+    Link,
+    Other,
+}
+
+impl EntryKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::File => "file",
+            Self::Directory => "directory",
+            Self::Link => "link",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// The entries an archive call touched, for its result.
+#[derive(Default)]
+struct Listing {
+    entries: Vec<Value>,
+    count: usize,
+    total_bytes: u64,
+    skipped: Vec<Value>,
+}
+
+impl Listing {
+    fn record(&mut self, name: &str, kind: EntryKind, size: u64) {
+        self.count += 1;
+        self.total_bytes += size;
+        if self.entries.len() < MAX_LISTED_ENTRIES {
+            self.entries.push(json!({
+                "path": name,
+                "type": kind.as_str(),
+                "size": size,
+            }));
+        }
+    }
+
+    fn skip(&mut self, name: &str, reason: impl Into<String>) {
+        if self.skipped.len() < MAX_LISTED_ENTRIES {
+            self.skipped
+                .push(json!({ "path": name, "reason": reason.into() }));
+        }
+    }
+
+    fn into_json(self) -> Value {
+        json!({
+            "truncated": self.count > self.entries.len(),
+            "entries": self.entries,
+            "count": self.count,
+            "totalBytes": self.total_bytes,
+            "skipped": self.skipped,
+        })
+    }
+}
+
+fn merge(mut result: Value, fields: Value) -> Value {
+    if let (Some(result), Value::Object(fields)) = (result.as_object_mut(), fields) {
+        result.extend(fields);
+    }
+    result
+}
+
+struct SourceEntry {
+    source: PathBuf,
+    name: String,
+    directory: bool,
+    size: u64,
+    mode: u32,
+    modified: SystemTime,
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &std::fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn file_mode(metadata: &std::fs::Metadata) -> u32 {
+    if metadata.is_dir() {
+        0o755
+    } else {
+        0o644
+    }
+}
+
+/// Walk `root` without following symlinks, keeping what `filter` wants.
+/// Entry names are relative to `root`, or the file name when `root` is a
+/// file. `skip` leaves out the archive being written.
+fn collect_sources(
+    root: &Path,
+    filter: &Filter,
+    skip: Option<&Path>,
+    listing: &mut Listing,
+) -> Result<Vec<SourceEntry>, String> {
+    let metadata = std::fs::symlink_metadata(root)
+        .map_err(|e| format!("Failed to stat '{}': {}", root.display(), e))?;
+    if metadata.is_file() {
+        let name = root
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| format!("Not a UTF-8 file name: '{}'", root.display()))?
+            .to_string();
+        listing.record(&name, EntryKind::File, metadata.len());
+        return Ok(vec![SourceEntry {
+            source: root.to_path_buf(),
+            name,
+            directory: false,
+            size: metadata.len(),
+            mode: file_mode(&metadata),
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        }]);
+    }
+    if !metadata.is_dir() {
+        return Err(format!("Not a file or directory: '{}'", root.display()));
+    }
+
+    let relative_name = |path: &Path| -> Option<String> {
+        let relative = path.strip_prefix(root).ok()?;
+        let parts = relative
+            .components()
+            .map(|component| component.as_os_str().to_str())
+            .collect::<Option<Vec<_>>>()?;
+        Some(parts.join("/"))
+    };
+
+    let mut sources = Vec::new();
+    let walker = walkdir::WalkDir::new(root)
+        .min_depth(1)
+        .follow_links(false)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            !(entry.file_type().is_dir()
+                && relative_name(entry.path()).is_some_and(|name| filter.excludes(&name)))
+        });
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(error) => {
+                let name = error
+                    .path()
+                    .and_then(relative_name)
+                    .unwrap_or_else(|| root.display().to_string());
+                listing.skip(&name, error.to_string());
+                continue;
+            }
+        };
+        if skip == Some(entry.path()) {
+            continue;
+        }
+        let Some(name) = relative_name(entry.path()) else {
+            listing.skip(
+                &entry.path().display().to_string(),
+                "name is not valid UTF-8",
+            );
+            continue;
+        };
+        let file_type = entry.file_type();
+        if file_type.is_symlink() {
+            listing.skip(&name, "symlinks are not archived");
+            continue;
+        }
+        if !file_type.is_dir() && !file_type.is_file() {
+            listing.skip(&name, "not a regular file or directory");
+            continue;
+        }
+        if filter.excludes(&name) {
+            continue;
+        }
+        let directory = file_type.is_dir();
+        if directory && !filter.includes_directories() {
+            continue;
+        }
+        if !directory && !filter.includes_file(&name) {
+            continue;
+        }
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(error) => {
+                listing.skip(&name, error.to_string());
+                continue;
+            }
+        };
+        let size = if directory { 0 } else { metadata.len() };
+        listing.record(
+            &name,
+            if directory {
+                EntryKind::Directory
+            } else {
+                EntryKind::File
+            },
+            size,
+        );
+        sources.push(SourceEntry {
+            source: entry.into_path(),
+            name,
+            directory,
+            size,
+            mode: file_mode(&metadata),
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        });
+    }
+    Ok(sources)
+}
+
+fn write_archive(
+    format: ArchiveFormat,
+    sources: &[SourceEntry],
+    out: impl Write,
+) -> Result<(), String> {
+    match format {
+        ArchiveFormat::Tar => write_tar(sources, out).map(drop),
+        ArchiveFormat::TarGz => write_tar(sources, GzEncoder::new(out, Compression::default()))?
+            .finish()
+            .map(drop)
+            .map_err(|e| format!("Failed to write archive: {}", e)),
+        ArchiveFormat::Zip => {
+            // Sizes and CRCs go in data descriptors, so the archive can
+            // stream into a body that cannot seek.
+            let mut writer = zip::ZipWriter::new_stream(out);
+            for source in sources {
+                let options = zip_options(source);
+                let result = if source.directory {
+                    writer.add_directory(source.name.as_str(), options)
+                } else {
+                    writer
+                        .start_file(source.name.as_str(), options)
+                        .and_then(|()| {
+                            let file = File::open(&source.source)?;
+                            io::copy(&mut BufReader::new(file).take(source.size), &mut writer)?;
+                            Ok(())
+                        })
+                };
+                result.map_err(|e| format!("Failed to archive '{}': {}", source.name, e))?;
+            }
+            writer
+                .finish()
+                .map(drop)
+                .map_err(|e| format!("Failed to write archive: {}", e))
+        }
+    }
+}
+
+/// Deflate files, keep their permissions and local modification time, and
+/// switch to ZIP64 sizes for files of 4 GiB or more.
+fn zip_options(source: &SourceEntry) -> zip::write::SimpleFileOptions {
+    let modified = chrono::DateTime::<chrono::Local>::from(source.modified).naive_local();
+    let method = if source.directory {
+        zip::CompressionMethod::Stored
+    } else {
+        zip::CompressionMethod::Deflated
+    };
+    zip::write::SimpleFileOptions::default()
+        .compression_method(method)
+        .unix_permissions(source.mode)
+        .last_modified_time(zip::DateTime::try_from(modified).unwrap_or_default())
+        .large_file(source.size >= u64::from(u32::MAX))
+}
+
+fn write_tar<W: Write>(sources: &[SourceEntry], out: W) -> Result<W, String> {
+    let mut builder = tar::Builder::new(out);
+    builder.follow_symlinks(false);
+    for source in sources {
+        let result = if source.directory {
+            builder.append_dir(&source.name, &source.source)
+        } else {
+            File::open(&source.source)
+                .and_then(|mut file| builder.append_file(&source.name, &mut file))
+        };
+        result.map_err(|e| format!("Failed to archive '{}': {}", source.name, e))?;
+    }
+    builder
+        .into_inner()
+        .map_err(|e| format!("Failed to write archive: {}", e))
+}
+
+/// A blocking writer that hands chunks of an archive to the async body
+/// reading them.
+struct ChannelWriter {
+    sender: mpsc::Sender<io::Result<VecDeque<u8>>>,
+    buffer: Vec<u8>,
+}
+
+impl ChannelWriter {
+    fn send_buffer(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::replace(&mut self.buffer, Vec::with_capacity(STREAM_CHUNK_BYTES));
+        self.sender
+            .blocking_send(Ok(VecDeque::from(chunk)))
+            .map_err(|_closed| {
+                io::Error::new(io::ErrorKind::BrokenPipe, "Archive body was cancelled")
+            })
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= STREAM_CHUNK_BYTES {
+            self.send_buffer()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send_buffer()
+    }
+}
+
+async fn create(
+    args: Value,
+    paths: &PathResolver,
+    binary_inbox: &BinaryFrameInbox,
+) -> Result<(Value, Option<OutgoingBody>), String> {
+    let args: CreateArgs =
+        serde_json::from_value(args).map_err(|e| format!("Invalid arguments: {}", e))?;
+    let filter = Filter::new(&args.include, &args.exclude)?;
+    let root = paths.resolve(&args.path, Access::Read)?;
+    let output = args
+        .output
+        .as_deref()
+        .map(|output| paths.resolve(output, Access::Write))
+        .transpose()?;
+    let format = args
+        .format
+        .or_else(|| output.as_deref().and_then(ArchiveFormat::from_name))
+        .unwrap_or(ArchiveFormat::TarGz);
+
+    let walk_root = root.clone();
+    let skip = output.clone();
+    let (sources, listing) = tokio::task::spawn_blocking(move || {
+        let mut listing = Listing::default();
+        collect_sources(&walk_root, &filter, skip.as_deref(), &mut listing)
+            .map(|sources| (sources, listing))
+    })
+    .await
+    .map_err(|e| format!("Archive task failed: {}", e))??;
+
+    let result = json!({
+        "ok": true,
+        "path": root.display().to_string(),
+        "format": format.as_str(),
+    });
+    if args.list_only {
+        return Ok((merge(result, listing.into_json()), None));
+    }
+    let summary = json!({
+        "count": listing.count,
+        "totalBytes": listing.total_bytes,
+        "skipped": listing.skipped,
+    });
+
+    let Some(output) = output else {
+        let (sender, receiver) = mpsc::channel(8);
+        tokio::task::spawn_blocking(move || {
+            let mut writer = ChannelWriter {
+                sender,
+                buffer: Vec::with_capacity(STREAM_CHUNK_BYTES),
+            };
+            let result = write_archive(format, &sources, &mut writer)
+                .and_then(|()| writer.send_buffer().map_err(|e| e.to_string()));
+            if let Err(error) = result {
+                // An error item fails the body rather than ending it short.
+                let _ = writer.sender.blocking_send(Err(io::Error::other(error)));
+            }
+        });
+        let chunks = futures_util::stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|chunk| (chunk, receiver))
+        });
+        let body = OutgoingBody::new(
+            binary_inbox,
+            None,
+            None,
+            tokio_util::io::StreamReader::new(Box::pin(chunks)),
+            root.display().to_string(),
+        );
+        let result = merge(result, json!({ "contentType": format.content_type() }));
+        return Ok((merge(result, summary), Some(body)));
+    };
+
+    if let Some(parent) = output.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create '{}': {}", parent.display(), e))?;
+    }
+    if tokio::fs::metadata(&output)
+        .await
+        .is_ok_and(|metadata| metadata.is_dir())
+    {
+        return Err(format!("Output is a directory: '{}'", output.display()));
+    }
+    let temp_path = transfer_temp_path(&output, 0);
+    let temp_file = TempFileGuard(temp_path.clone());
+    let size = tokio::task::spawn_blocking(move || {
+        let file = File::create(&temp_path)
+            .map_err(|e| format!("Failed to open '{}': {}", temp_path.display(), e))?;
+        let mut out = BufWriter::new(file);
+        write_archive(format, &sources, &mut out)?;
+        let file = out
+            .into_inner()
+            .map_err(|e| format!("Failed to write '{}': {}", temp_path.display(), e))?;
+        file.sync_all()
+            .and_then(|()| file.metadata())
+            .map(|metadata| metadata.len())
+            .map_err(|e| format!("Failed to write '{}': {}", temp_path.display(), e))
+    })
+    .await
+    .map_err(|e| format!("Archive task failed: {}", e))??;
+    tokio::fs::rename(&temp_file.0, &output)
+        .await
+        .map_err(|e| format!("Failed to replace '{}': {}", output.display(), e))?;
+
+    let result = merge(
+        result,
+        json!({ "output": output.display().to_string(), "size": size }),
+    );
+    Ok((merge(result, summary), None))
+}
+
+async fn extract(
+    args: Value,
+    request_body: Option<FrameBodyDescriptor>,
+    paths: &PathResolver,
+    binary_inbox: &BinaryFrameInbox,
+) -> Result<Value, String> {
+    let mut stream_guard =
+        request_body.map(|body| IncomingStreamGuard::new(binary_inbox, body.stream_id));
+    let args: ExtractArgs =
+        serde_json::from_value(args).map_err(|e| format!("Invalid arguments: {}", e))?;
+    let filter = Filter::new(&args.include, &args.exclude)?;
+    let destination = args
+        .destination
+        .as_deref()
+        .map(|destination| paths.resolve(destination, Access::Write))
+        .transpose()?;
+    if destination.is_none() && !args.list_only {
+        return Err("fs.archive.extract requires destination unless listOnly is set".to_string());
+    }
+    if let Some(destination) = &destination {
+        tokio::fs::create_dir_all(destination)
+            .await
+            .map_err(|e| format!("Failed to create '{}': {}", destination.display(), e))?;
+    }
+
+    let (archive, _temp_file) = match (args.path.as_deref(), request_body, stream_guard.as_mut()) {
+        (Some(path), None, _) => (paths.resolve(path, Access::Read)?, None),
+        (None, Some(body), Some(stream_guard)) => {
+            if body.stream_id == 0 {
+                return Err("fs.archive.extract body requires a non-zero streamId".to_string());
+            }
+            let length = body
+                .length
+                .ok_or_else(|| "fs.archive.extract requires a request body length".to_string())?;
+            let spool_dir = destination.clone().unwrap_or_else(std::env::temp_dir);
+            let temp_path = transfer_temp_path(&spool_dir.join("archive"), body.stream_id);
+            let temp_file = TempFileGuard(temp_path.clone());
+            receive_to_file(binary_inbox, stream_guard, length, &temp_path, &temp_path).await?;
+            stream_guard.complete();
+            (temp_path, Some(temp_file))
+        }
+        (Some(_), Some(_), _) => {
+            return Err("fs.archive.extract takes path or a request body, not both".to_string())
+        }
+        _ => return Err("fs.archive.extract requires path or a request body".to_string()),
+    };
+    let format = match args.format.or_else(|| {
+        args.path
+            .as_ref()
+            .and_then(|_| ArchiveFormat::from_name(&archive))
+    }) {
+        Some(format) => format,
+        None => ArchiveFormat::sniff(&archive)?,
+    };
+
+    let result = json!({
+        "ok": true,
+        "path": args.path.as_ref().map(|_| archive.display().to_string()),
+        "destination": destination.as_ref().map(|destination| destination.display().to_string()),
+        "format": format.as_str(),
+    });
+    let extraction = Extraction {
+        destination: destination.filter(|_| !args.list_only),
+        filter,
+        overwrite: args.overwrite,
+        listing: Listing::default(),
+    };
+    let archive_path = archive.clone();
+    let listing = tokio::task::spawn_blocking(move || extraction.run(format, &archive_path))
+        .await
+        .map_err(|e| format!("Archive task failed: {}", e))??;
+    Ok(merge(result, listing.into_json()))
+}
+
+/// Why an entry name may not be extracted, or the name relative to the
+/// destination; `None` for names such as `./` that name the destination.
+fn safe_entry_name(raw: &str) -> Result<Option<String>, &'static str> {
+    if raw.contains('\0') {
+        return Err("name contains NUL");
+    }
+    let normalized = raw.replace('\\', "/");
+    if normalized.starts_with('/') {
+        return Err("absolute paths are not extracted");
+    }
+    let parts: Vec<&str> = normalized
+        .split('/')
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect();
+    if parts.contains(&"..") {
+        return Err("paths with '..' are not extracted");
+    }
+    // Drive-relative names such as `C:foo` are absolute on Windows.
+    if parts
+        .first()
+        .is_some_and(|part| part.chars().nth(1) == Some(':'))
+    {
+        return Err("absolute paths are not extracted");
+    }
+    Ok((!parts.is_empty()).then(|| parts.join("/")))
+}
+
+/// Check that writing `name` below `destination` neither follows a symlink
+/// nor replaces something it should not.
+fn check_target(
+    destination: &Path,
+    name: &str,
+    kind: EntryKind,
+    overwrite: bool,
+) -> Result<(), &'static str> {
+    let components: Vec<&str> = name.split('/').collect();
+    let last = components.len().saturating_sub(1);
+    let mut path = destination.to_path_buf();
+    for (index, component) in components.into_iter().enumerate() {
+        path.push(component);
+        let metadata = match std::fs::symlink_metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(_) => return Err("the path cannot be inspected"),
+        };
+        if metadata.file_type().is_symlink() {
+            return Err("the path goes through a symlink");
+        }
+        if index < last {
+            if !metadata.is_dir() {
+                return Err("a parent is not a directory");
+            }
+            continue;
+        }
+        return match (kind, metadata.is_dir()) {
+            (EntryKind::Directory, true) => Ok(()),
+            (EntryKind::Directory, false) => Err("a file is in the way"),
+            (_, true) => Err("a directory is in the way"),
+            (_, false) if !overwrite => Err("the file exists; set overwrite to replace it"),
+            (_, false) => Ok(()),
+        };
+    }
+    Ok(())
+}
+
+struct Extraction {
+    /// Where entries go; `None` only lists them.
+    destination: Option<PathBuf>,
+    filter: Filter,
+    overwrite: bool,
+    listing: Listing,
+}
+
+impl Extraction {
+    fn run(mut self, format: ArchiveFormat, archive: &Path) -> Result<Listing, String> {
+        let file = File::open(archive)
+            .map_err(|e| format!("Failed to open '{}': {}", archive.display(), e))?;
+        let reader = BufReader::new(file);
+        match format {
+            ArchiveFormat::Tar => self.extract_tar(tar::Archive::new(reader))?,
+            ArchiveFormat::TarGz => self.extract_tar(tar::Archive::new(GzDecoder::new(reader)))?,
+            ArchiveFormat::Zip => self.extract_zip(reader)?,
+        }
+        Ok(self.listing)
+    }
+
+    /// Decide what happens to an entry: `None` when it is filtered out or
+    /// skipped, otherwise its name and, unless only listing, the path to
+    /// write, whose parent directories now exist.
+    fn prepare(&mut self, raw: &str, kind: EntryKind) -> Option<(String, Option<PathBuf>)> {
+        let name = match safe_entry_name(raw) {
+            Ok(Some(name)) => name,
+            Ok(None) => return None,
+            Err(reason) => {
+                self.listing.skip(raw, reason);
+                return None;
+            }
+        };
+        match kind {
+            EntryKind::Link => {
+                self.listing.skip(&name, "links are not extracted");
+                return None;
+            }
+            EntryKind::Other => {
+                self.listing.skip(&name, "not a regular file or directory");
+                return None;
+            }
+            EntryKind::File | EntryKind::Directory => {}
+        }
+        if self.filter.excludes(&name)
+            || (kind == EntryKind::Directory && !self.filter.includes_directories())
+            || (kind == EntryKind::File && !self.filter.includes_file(&name))
+        {
+            return None;
+        }
+        let Some(destination) = &self.destination else {
+            return Some((name, None));
+        };
+        if let Err(reason) = check_target(destination, &name, kind, self.overwrite) {
+            self.listing.skip(&name, reason);
+            return None;
+        }
+        let target = destination.join(&name);
+        if let Some(parent) = target.parent() {
+            if let Err(error) = std::fs::create_dir_all(parent) {
+                self.listing.skip(
+                    &name,
+                    format!("Failed to create '{}': {}", parent.display(), error),
+                );
+                return None;
+            }
+        }
+        Some((name, Some(target)))
+    }
+
+    fn extract_tar<R: Read>(&mut self, mut archive: tar::Archive<R>) -> Result<(), String> {
+        let entries = archive
+            .entries()
+            .map_err(|e| format!("Failed to read archive: {}", e))?;
+        for entry in entries {
+            let mut entry = entry.map_err(|e| format!("Failed to read archive: {}", e))?;
+            let kind = match entry.header().entry_type() {
+                tar::EntryType::Regular | tar::EntryType::Continuous => EntryKind::File,
+                tar::EntryType::Directory => EntryKind::Directory,
+                tar::EntryType::Symlink | tar::EntryType::Link => EntryKind::Link,
+                tar::EntryType::XGlobalHeader
+                | tar::EntryType::XHeader
+                | tar::EntryType::GNULongName
+                | tar::EntryType::GNULongLink => continue,
+                _ => EntryKind::Other,
+            };
+            let raw = entry.path_bytes();
+            let raw = match std::str::from_utf8(&raw) {
+                Ok(raw) => raw.to_string(),
+                Err(_) => {
+                    self.listing
+                        .skip(&String::from_utf8_lossy(&raw), "name is not valid UTF-8");
+                    continue;
+                }
+            };
+            let size = if kind == EntryKind::File {
+                entry.size()
+            } else {
+                0
+            };
+            let Some((name, target)) = self.prepare(&raw, kind) else {
+                continue;
+            };
+            if let Some(target) = target {
+                entry
+                    .unpack(&target)
+                    .map_err(|e| format!("Failed to extract '{}': {}", name, e))?;
+            }
+            self.listing.record(&name, kind, size);
+        }
+        Ok(())
+    }
+
+    fn extract_zip(&mut self, reader: BufReader<File>) -> Result<(), String> {
+        let mut archive =
+            zip::ZipArchive::new(reader).map_err(|e| format!("Failed to read archive: {}", e))?;
+        for index in 0..archive.len() {
+            let mut entry = archive
+                .by_index(index)
+                .map_err(|e| format!("Failed to read archive: {}", e))?;
+            let kind = if entry.is_symlink() {
+                EntryKind::Link
+            } else if entry.is_dir() {
+                EntryKind::Directory
+            } else {
+                EntryKind::File
+            };
+            let Some((name, target)) = self.prepare(entry.name(), kind) else {
+                continue;
+            };
+            match target {
+                Some(target) if kind == EntryKind::Directory => std::fs::create_dir_all(&target)
+                    .map_err(|e| format!("Failed to create '{}': {}", target.display(), e))?,
+                Some(target) => write_zip_entry(&mut entry, &target)
+                    .map_err(|e| format!("Failed to extract '{}': {}", name, e))?,
+                None => {}
+            }
+            let size = if kind == EntryKind::File {
+                entry.size()
+            } else {
+                0
+            };
+            self.listing.record(&name, kind, size);
+        }
+        Ok(())
+    }
+}
+
+/// Copy a zip entry to `target`. Reading it to the end checks its CRC.
+fn write_zip_entry<R: Read>(
+    entry: &mut zip::read::ZipFile<'_, R>,
+    target: &Path,
+) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(target)?);
+    let written = io::copy(entry, &mut out).and_then(|_| out.flush());
+    if let Err(error) = written {
+        drop(out);
+        let _ = std::fs::remove_file(target);
+        return Err(error);
+    }
+    #[cfg(unix)]
+    if let Some(mode) = entry
+        .unix_mode()
+        .map(|mode| mode & 0o777)
+        .filter(|mode| *mode != 0)
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(target, std::fs::Permissions::from_mode(mode))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        handle_archive_syscall, safe_entry_name, write_archive, ArchiveFormat, Filter, SourceEntry,
+    };
+    use crate::device::transfer::BinaryFrameInbox;
+    use gsv::protocol::{
+        build_binary_frame, FrameBodyDescriptor, BINARY_FRAME_DATA, BINARY_FRAME_END,
+    };
+    use gsv::tools::paths::PathResolver;
+    use serde_json::{json, Value};
+    use std::path::{Path, PathBuf};
+
+    fn test_workspace(label: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "gsv-cli-archive-{}-{}",
+            label,
+            uuid::Uuid::new_v4()
+        ))
+    }
+
+    fn write_tree(root: &Path) {
+        std::fs::create_dir_all(root.join("src/nested")).unwrap();
+        std::fs::create_dir_all(root.join("target/debug")).unwrap();
+        std::fs::write(root.join("README.md"), "readme\n").unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(root.join("src/nested/lib.rs"), "pub fn lib() {}\n").unwrap();
+        std::fs::write(root.join("src/debug.log"), "log\n").unwrap();
+        std::fs::write(root.join("target/debug/app"), [0u8, 1, 2]).unwrap();
+    }
+
+    async fn call(
+        call: &str,
+        args: Value,
+        paths: &PathResolver,
+        inbox: &BinaryFrameInbox,
+        body: Option<FrameBodyDescriptor>,
+    ) -> Result<Value, String> {
+        handle_archive_syscall(call, args, body, paths, inbox)
+            .await
+            .unwrap()
+            .map(|(data, _)| data)
+    }
+
+    fn listed(result: &Value) -> Vec<String> {
+        result["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["path"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn entry_names_stay_below_the_destination() {
+        assert_eq!(safe_entry_name("./a/./b/").unwrap().as_deref(), Some("a/b"));
+        assert_eq!(safe_entry_name("./").unwrap(), None);
+        assert_eq!(safe_entry_name("a\\b").unwrap().as_deref(), Some("a/b"));
+        for name in [
+            "/etc/passwd",
+            "../x",
+            "a/../../x",
+            "a\\..\\x",
+            "C:/x",
+            "c:x",
+        ] {
+            assert!(safe_entry_name(name).unwrap_err().contains("not extracted"));
+        }
+    }
+
+    #[test]
+    fn filters_match_components_and_paths() {
+        let filter = Filter::new(
+            &["*.rs".to_string(), "README.md".to_string()],
+            &["target".to_string(), "src/nested/**".to_string()],
+        )
+        .unwrap();
+        assert!(filter.includes_file("src/main.rs"));
+        assert!(filter.includes_file("README.md"));
+        assert!(!filter.includes_file("src/debug.log"));
+        assert!(filter.excludes("target/debug/app"));
+        assert!(filter.excludes("src/nested/lib.rs"));
+        assert!(!filter.excludes("src/main.rs"));
+        assert!(!filter.includes_directories());
+
+        let error = Filter::new(&["[".to_string()], &[]).err().unwrap();
+        assert!(error.starts_with("Invalid glob '['"));
+    }
+
+    #[tokio::test]
+    async fn archives_round_trip_in_every_format() {
+        let workspace = test_workspace("round-trip");
+        write_tree(&workspace.join("project"));
+        let paths = PathResolver::new(workspace.clone());
+        let inbox = BinaryFrameInbox::new();
+
+        for (output, format) in [
+            ("out.tar", "tar"),
+            ("out.tgz", "tar.gz"),
+            ("out.zip", "zip"),
+        ] {
+            let created = call(
+                "fs.archive.create",
+                json!({ "path": "project", "output": output, "exclude": ["target"] }),
+                &paths,
+                &inbox,
+                None,
+            )
+            .await
+            .unwrap();
+            assert_eq!(created["format"], format);
+            assert_eq!(created["count"], 6);
+
+            let destination = format!("extracted-{}", format);
+            let extracted = call(
+                "fs.archive.extract",
+                json!({ "path": output, "destination": destination, "exclude": ["*.log"] }),
+                &paths,
+                &inbox,
+                None,
+            )
+            .await
+            .unwrap();
+            assert_eq!(extracted["format"], format);
+            assert_eq!(
+                listed(&extracted),
+                [
+                    "README.md",
+                    "src",
+                    "src/main.rs",
+                    "src/nested",
+                    "src/nested/lib.rs"
+                ]
+            );
+            let root = workspace.join(&destination);
+            assert_eq!(
+                std::fs::read_to_string(root.join("src/nested/lib.rs")).unwrap(),
+                "pub fn lib() {}\n"
+            );
+            assert!(!root.join("src/debug.log").exists());
+            assert!(!root.join("target").exists());
+        }
+
+        std::fs::remove_dir_all(workspace).unwrap();
+    }
+
+    #[tokio::test]
+    async fn large_zip_entries_use_zip64_sizes() {
+        let workspace = test_workspace("zip64");
+        std::fs::create_dir_all(&workspace).unwrap();
+        std::fs::write(workspace.join("big.bin"), "not really big\n").unwrap();
+        // A recorded size past 4 GiB switches the entry to ZIP64; the copy
+        // still stops at the end of the file.
+        let sources = [SourceEntry {
+            source: workspace.join("big.bin"),
+            name: "big.bin".to_string(),
+            directory: false,
+            size: u64::from(u32::MAX) + 1,
+            mode: 0o640,
+            modified: std::time::SystemTime::now(),
+        }];
+        let mut bytes = Vec::new();
+        write_archive(ArchiveFormat::Zip, &sources, &mut bytes).unwrap();
+        std::fs::write(workspace.join("big.zip"), bytes).unwrap();
+
+        let paths = PathResolver::new(workspace.clone());
+        let extracted = call(
+            "fs.archive.extract",
+            json!({ "path": "big.zip", "destination": "out" }),
+            &paths,
+            &BinaryFrameInbox::new(),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(listed(&extracted), ["big.bin"]);
+        assert_eq!(
+            std::fs::read_to_string(workspace.join("out/big.bin")).unwrap(),
+            "not really big\n"
+        );
+
+        std::fs::remove_dir_all(workspace).unwrap();
+    }
+
+    #[tokio::test]
+    async fn list_only_touches_nothing() {
+        let workspace = test_workspace("list");
+        write_tree(&workspace.join("project"));
+        let paths = PathResolver::new(workspace.clone());
+        let inbox = BinaryFrameInbox::new();
+
+        let listing = call(
+            "fs.archive.create",
+            json!({ "path": "project", "output": "out.zip", "include": ["*.rs"], "listOnly": true }),
+            &paths,
+            &inbox,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(listed(&listing), ["src/main.rs", "src/nested/lib.rs"]);
+        assert!(!workspace.join("out.zip").exists());
+
+        call(
+            "fs.archive.create",
+            json!({ "path": "project", "output": "out.zip" }),
+            &paths,
+            &inbox,
+            None,
+        )
+        .await
+        .unwrap();
+        let listing = call(
+            "fs.archive.extract",
+            json!({ "path": "out.zip", "listOnly": true }),
+            &paths,
+            &inbox,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(listing["count"], 9);
+        assert_eq!(listing["totalBytes"], 43);
+        assert_eq!(listing["destination"], Value::Null);
+
+        std::fs::remove_dir_all(workspace).unwrap();
+    }
+
+    #[tokio::test]
+    async fn extraction_skips_unsafe_entries() {
+        let workspace = test_workspace("unsafe");
+        std::fs::create_dir_all(workspace.join("outside")).unwrap();
+        std::fs::create_dir_all(workspace.join("dest")).unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(workspace.join("outside"), workspace.join("dest/link")).unwrap();
+
+        let mut builder = tar::Builder::new(Vec::new());
+        for name in ["../escape.txt", "link/inside.txt", "ok.txt"] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(2);
+            header.set_mode(0o644);
+            header.set_entry_type(tar::EntryType::Regular);
+            // set_path refuses `..`, so write the raw name as an attacker would.
+            let raw = &mut header.as_old_mut().name;
+            raw[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_cksum();
+            builder.append(&header, &b"hi"[..]).unwrap();
+        }
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "evil", "/etc/passwd")
+            .unwrap();
+        std::fs::write(workspace.join("bad.tar"), builder.into_inner().unwrap()).unwrap();
+
+        let paths = PathResolver::new(workspace.clone());
+        let inbox = BinaryFrameInbox::new();
+        let result = call(
+            "fs.archive.extract",
+            json!({ "path": "bad.tar", "destination": "dest" }),
+            &paths,
+            &inbox,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(listed(&result), ["ok.txt"]);
+        let skipped: Vec<&str> = result["skipped"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["path"].as_str().unwrap())
+            .collect();
+        #[cfg(unix)]
+        assert_eq!(skipped, ["../escape.txt", "link/inside.txt", "evil"]);
+        assert!(!workspace.join("escape.txt").exists());
+        assert!(!workspace.join("outside/inside.txt").exists());
+
+        let error = call(
+            "fs.archive.extract",
+            json!({ "path": "bad.tar", "destination": "dest" }),
+            &paths,
+            &inbox,
+            None,
+        )
+        .await
+        .unwrap()["skipped"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|entry| entry["path"] == "ok.txt")
+            .map(|entry| entry["reason"].as_str().unwrap().to_string())
+            .unwrap();
+        assert!(error.contains("set overwrite"));
+
+        std::fs::remove_dir_all(workspace).unwrap();
+    }
+
+    #[tokio::test]
+    async fn archives_stream_through_bodies() {
+        let workspace = test_workspace("body");
+        write_tree(&workspace.join("project"));
+        let paths = PathResolver::new(workspace.clone());
+        let inbox = BinaryFrameInbox::new();
+
+        let (created, body) = handle_archive_syscall(
+            "fs.archive.create",
+            json!({ "path": "project", "format": "zip" }),
+            None,
+            &paths,
+            &inbox,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(created["contentType"], "application/zip");
+        let body = body.unwrap();
+        assert_eq!(body.descriptor().length, None);
+        let bytes = body.into_bytes(1024 * 1024).await.unwrap();
+
+        let descriptor = FrameBodyDescriptor {
+            stream_id: 7,
+            length: Some(bytes.len() as u64),
+        };
+        inbox.register(Some(descriptor));
+        inbox.push(build_binary_frame(
+            7,
+            BINARY_FRAME_DATA | BINARY_FRAME_END,
+            &bytes,
+        ));
+        let extracted = call(
+            "fs.archive.extract",
+            json!({ "destination": "copy", "include": ["*.rs"] }),
+            &paths,
+            &inbox,
+            Some(descriptor),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(extracted["format"], "zip");
+        assert_eq!(listed(&extracted), ["src/main.rs", "src/nested/lib.rs"]);
+        let mut copied: Vec<_> = std::fs::read_dir(workspace.join("copy"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        copied.sort();
+        assert_eq!(copied, ["src"]);
+
+        std::fs::remove_dir_all(workspace).unwrap();
+    }
+}
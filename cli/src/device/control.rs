@@ -0,0 +1,800 @@
+//! Local control socket of a running daemon.
+//!
+//! Clients connect to `~/.gsv/device.sock` (or `$GSV_DEVICE_SOCKET`, for a
+//! read-only home), write one command line and read
+//! the reply until the daemon closes the stream:
+//!
+//! - `status`: JSON [`DaemonStatus`]
+//! - `metrics`: Prometheus text exposition
+//! - `reload`: re-read `config.toml` and report what changed
+//! - `approvals`: JSON list of requests waiting for approval
+//! - `approve <id>`, `deny <id>`: answer one of them
+//!
+//! Replies to commands the daemon cannot serve start with `error: `. The
+//! socket is owner-only, so whoever can open it already runs as the daemon
+//! user.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use gsv::build_info;
+use gsv::protocol::DeviceExecEventParams;
+use gsv::tools::{list_shell_sessions, ShellSessionSummary};
+use serde::{Deserialize, Serialize};
+
+use super::approval::Approvals;
+use super::cron::CronJobs;
+use super::limits::{QueueDepth, SyscallLimits};
+use super::metrics::{escape_label, render_prometheus, SyscallMetrics, SyscallSummary};
+use super::reload::ReloadRequest;
+use super::resume::ResponseOutbox;
+use super::state::{ConnectionState, DeviceState, DeviceStateSnapshot};
+use super::ActiveRequests;
+
+const CONTROL_IO_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+/// Resolving a reloaded configuration reads files and may ask the OS
+/// keyring for credentials; the client waits this long for its summary.
+const RELOAD_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+const MAX_COMMAND_BYTES: u64 = 4096;
+const ERROR_PREFIX: &str = "error: ";
+
+pub(crate) fn control_socket_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("GSV_DEVICE_SOCKET").filter(|path| !path.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    dirs::home_dir().map(|home| control_socket_path_under(&home))
+}
+
+/// Socket of a daemon running with `home` as its home directory.
+fn control_socket_path_under(home: &Path) -> PathBuf {
+    home.join(".gsv").join("device.sock")
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ActiveRequestInfo {
+    pub(crate) id: String,
+    pub(crate) call: String,
+    pub(crate) elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct OutboxDepth {
+    /// Background shell events waiting for a connection.
+    pub(crate) exec_events: usize,
+    /// Responses buffered for a resumed session.
+    pub(crate) responses: usize,
+}
+
+/// Reply to the `status` command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DaemonStatus {
+    pub(crate) pid: u32,
+    pub(crate) version: String,
+    /// One entry per device identity, in configuration order.
+    pub(crate) devices: Vec<DeviceStatus>,
+    pub(crate) shell_sessions: Vec<ShellSessionSummary>,
+}
+
+/// Connection and request state of one device identity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DeviceStatus {
+    pub(crate) device_id: String,
+    #[serde(flatten)]
+    pub(crate) connection: DeviceStateSnapshot,
+    pub(crate) active_requests: Vec<ActiveRequestInfo>,
+    pub(crate) outbox: OutboxDepth,
+    /// Concurrency and queue occupancy per syscall family.
+    #[serde(default)]
+    pub(crate) queues: BTreeMap<String, QueueDepth>,
+    pub(crate) syscalls: BTreeMap<String, SyscallSummary>,
+}
+
+/// Everything the control socket reports on, shared with `run_devices`.
+/// Identities come and go with configuration reloads.
+#[derive(Clone)]
+pub(super) struct DaemonHandles {
+    pub(super) devices: Arc<Mutex<Vec<DeviceHandles>>>,
+    pub(super) reload: Option<tokio::sync::mpsc::UnboundedSender<ReloadRequest>>,
+    pub(super) approvals: Approvals,
+}
+
+/// Per-identity state, shared with the identity's connection loop.
+#[derive(Clone)]
+pub(super) struct DeviceHandles {
+    pub(super) device_id: String,
+    pub(super) state: DeviceState,
+    pub(super) active_requests: ActiveRequests,
+    pub(super) exec_event_outbox: Arc<Mutex<VecDeque<DeviceExecEventParams>>>,
+    pub(super) response_outbox: ResponseOutbox,
+    pub(super) metrics: SyscallMetrics,
+    pub(super) limits: SyscallLimits,
+    pub(super) cron: CronJobs,
+}
+
+impl DeviceHandles {
+    fn status(&self) -> Option<DeviceStatus> {
+        Some(DeviceStatus {
+            device_id: self.device_id.clone(),
+            connection: self.state.snapshot()?,
+            active_requests: self.active_requests.list(),
+            outbox: OutboxDepth {
+                exec_events: super::exec_event_outbox_len(&self.exec_event_outbox),
+                responses: self.response_outbox.len(),
+            },
+            queues: self.limits.depths(),
+            syscalls: self.metrics.summary(),
+        })
+    }
+}
+
+impl DaemonHandles {
+    fn devices(&self) -> Vec<DeviceHandles> {
+        self.devices
+            .lock()
+            .expect("device handles mutex poisoned")
+            .clone()
+    }
+
+    pub(super) async fn status(&self) -> Option<DaemonStatus> {
+        Some(DaemonStatus {
+            pid: std::process::id(),
+            version: build_info::version_display().to_string(),
+            devices: self
+                .devices()
+                .iter()
+                .map(DeviceHandles::status)
+                .collect::<Option<Vec<_>>>()?,
+            shell_sessions: list_shell_sessions().await,
+        })
+    }
+
+    /// Prometheus text exposition; every series carries a `device` label.
+    pub(super) async fn prometheus(&self) -> Option<String> {
+        let status = self.status().await?;
+        let mut out = String::new();
+
+        let _ = writeln!(
+            out,
+            "# HELP gsv_device_info Daemon build and gateway.\n\
+             # TYPE gsv_device_info gauge"
+        );
+        for device in &status.devices {
+            let _ = writeln!(
+                out,
+                "gsv_device_info{{device=\"{}\",version=\"{}\",gateway=\"{}\"}} 1",
+                escape_label(&device.device_id),
+                escape_label(&status.version),
+                escape_label(&device.connection.gateway_url)
+            );
+        }
+        write_device_series(
+            &mut out,
+            "gsv_device_connected",
+            "gauge",
+            "Whether the gateway connection is up.",
+            &status.devices,
+            |device| u64::from(device.connection.state == ConnectionState::Connected),
+        );
+        write_device_series(
+            &mut out,
+            "gsv_device_connects_total",
+            "counter",
+            "Successful gateway connects.",
+            &status.devices,
+            |device| device.connection.connects,
+        );
+        write_device_series(
+            &mut out,
+            "gsv_device_active_requests",
+            "gauge",
+            "Requests currently being handled.",
+            &status.devices,
+            |device| device.active_requests.len() as u64,
+        );
+
+        let mut sessions = BTreeMap::<(&str, &str), usize>::new();
+        for session in &status.shell_sessions {
+            let device = session.device_id.as_deref().unwrap_or_default();
+            *sessions
+                .entry((device, session.status.as_str()))
+                .or_default() += 1;
+        }
+        out.push_str("# HELP gsv_device_shell_sessions Managed shell sessions, by status.\n");
+        out.push_str("# TYPE gsv_device_shell_sessions gauge\n");
+        for ((device, state), count) in sessions {
+            let _ = writeln!(
+                out,
+                "gsv_device_shell_sessions{{device=\"{}\",status=\"{}\"}} {}",
+                escape_label(device),
+                escape_label(state),
+                count
+            );
+        }
+
+        out.push_str("# HELP gsv_device_outbox_depth Messages waiting to be delivered.\n");
+        out.push_str("# TYPE gsv_device_outbox_depth gauge\n");
+        for device in &status.devices {
+            let label = escape_label(&device.device_id);
+            let _ = writeln!(
+                out,
+                "gsv_device_outbox_depth{{device=\"{}\",queue=\"exec_events\"}} {}\n\
+                 gsv_device_outbox_depth{{device=\"{}\",queue=\"responses\"}} {}",
+                label, device.outbox.exec_events, label, device.outbox.responses
+            );
+        }
+
+        out.push_str("# HELP gsv_device_syscall_running Requests holding a slot, by family.\n");
+        out.push_str("# TYPE gsv_device_syscall_running gauge\n");
+        for device in &status.devices {
+            for (family, depth) in &device.queues {
+                let _ = writeln!(
+                    out,
+                    "gsv_device_syscall_running{{device=\"{}\",family=\"{}\"}} {}",
+                    escape_label(&device.device_id),
+                    escape_label(family),
+                    depth.running
+                );
+            }
+        }
+        out.push_str(
+            "# HELP gsv_device_syscall_queue_depth Requests waiting for a slot, by family.\n",
+        );
+        out.push_str("# TYPE gsv_device_syscall_queue_depth gauge\n");
+        for device in &status.devices {
+            for (family, depth) in &device.queues {
+                let _ = writeln!(
+                    out,
+                    "gsv_device_syscall_queue_depth{{device=\"{}\",family=\"{}\"}} {}",
+                    escape_label(&device.device_id),
+                    escape_label(family),
+                    depth.queued
+                );
+            }
+        }
+
+        let devices = self.devices();
+        let metrics = devices
+            .iter()
+            .map(|device| (device.device_id.as_str(), &device.metrics))
+            .collect::<Vec<_>>();
+        render_prometheus(&metrics, &mut out);
+        Some(out)
+    }
+
+    async fn handle_command(&self, command: &str) -> String {
+        let reply = match command {
+            "status" => self.status().await.and_then(|status| {
+                serde_json::to_string_pretty(&status)
+                    .ok()
+                    .map(|json| json + "\n")
+            }),
+            "metrics" => self.prometheus().await,
+            "reload" => return self.reload().await,
+            "approvals" => serde_json::to_string_pretty(&self.approvals.list())
+                .ok()
+                .map(|json| json + "\n"),
+            other => {
+                return match other.split_once(' ') {
+                    Some(("approve", id)) => self.decide(id, true),
+                    Some(("deny", id)) => self.decide(id, false),
+                    _ => format!("{}unknown command `{}`\n", ERROR_PREFIX, other),
+                }
+            }
+        };
+        reply.unwrap_or_else(|| format!("{}daemon state unavailable\n", ERROR_PREFIX))
+    }
+
+    fn decide(&self, id: &str, approve: bool) -> String {
+        let decided = id
+            .trim()
+            .parse::<u64>()
+            .map_err(|error| format!("invalid approval id `{}`: {}", id.trim(), error))
+            .and_then(|id| self.approvals.decide(id, approve, "control socket"));
+        match decided {
+            Ok(pending) => format!(
+                "{} {} on {}\n",
+                if approve { "Approved" } else { "Denied" },
+                pending.call,
+                pending.device_id
+            ),
+            Err(error) => format!("{}{}\n", ERROR_PREFIX, error),
+        }
+    }
+
+    async fn reload(&self) -> String {
+        let (reply, result) = tokio::sync::oneshot::channel();
+        let request = ReloadRequest {
+            source: "control socket",
+            reply: Some(reply),
+        };
+        let sent = self
+            .reload
+            .as_ref()
+            .is_some_and(|reload| reload.send(request).is_ok());
+        if !sent {
+            return format!("{}daemon is not accepting reloads\n", ERROR_PREFIX);
+        }
+        match tokio::time::timeout(RELOAD_TIMEOUT, result).await {
+            Ok(Ok(Ok(summary))) => summary + "\n",
+            Ok(Ok(Err(error))) => format!("{}{}\n", ERROR_PREFIX, error),
+            Ok(Err(_dropped)) => format!("{}daemon is shutting down\n", ERROR_PREFIX),
+            Err(_elapsed) => format!("{}reload timed out\n", ERROR_PREFIX),
+        }
+    }
+}
+
+/// One sample per device, labelled with its id.
+fn write_device_series(
+    out: &mut String,
+    name: &str,
+    kind: &str,
+    help: &str,
+    devices: &[DeviceStatus],
+    value: impl Fn(&DeviceStatus) -> u64,
+) {
+    let _ = writeln!(out, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+    for device in devices {
+        let _ = writeln!(
+            out,
+            "{}{{device=\"{}\"}} {}",
+            name,
+            escape_label(&device.device_id),
+            value(device)
+        );
+    }
+}
+
+/// Listening socket; removed again when dropped.
+pub(super) struct ControlServer {
+    path: PathBuf,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl ControlServer {
+    pub(super) fn bind(handles: DaemonHandles) -> Result<Self, String> {
+        let path = control_socket_path().ok_or("Could not determine home directory")?;
+        Self::bind_at(path, handles)
+    }
+
+    #[cfg(unix)]
+    fn bind_at(path: PathBuf, handles: DaemonHandles) -> Result<Self, String> {
+        use std::os::unix::fs::PermissionsExt;
+        use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        if path.exists() {
+            if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+                return Err(format!(
+                    "Another daemon is already serving {}",
+                    path.display()
+                ));
+            }
+            std::fs::remove_file(&path)
+                .map_err(|e| format!("Failed to remove stale {}: {}", path.display(), e))?;
+        }
+        let listener = tokio::net::UnixListener::bind(&path)
+            .map_err(|e| format!("Failed to bind {}: {}", path.display(), e))?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("Failed to restrict {}: {}", path.display(), e))?;
+
+        let task = tokio::spawn(async move {
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        tracing::warn!(event = "control.accept_failed", error = %e);
+                        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                        continue;
+                    }
+                };
+                let handles = handles.clone();
+                tokio::spawn(async move {
+                    let (reader, mut writer) = stream.into_split();
+                    let mut line = String::new();
+                    let read = tokio::time::timeout(
+                        CONTROL_IO_TIMEOUT,
+                        BufReader::new(reader.take(MAX_COMMAND_BYTES)).read_line(&mut line),
+                    )
+                    .await;
+                    if !matches!(read, Ok(Ok(_))) {
+                        return;
+                    }
+                    let reply = handles.handle_command(line.trim()).await;
+                    let _ = tokio::time::timeout(CONTROL_IO_TIMEOUT, async {
+                        writer.write_all(reply.as_bytes()).await?;
+                        writer.shutdown().await
+                    })
+                    .await;
+                });
+            }
+        });
+        Ok(Self { path, task })
+    }
+
+    #[cfg(not(unix))]
+    fn bind_at(_path: PathBuf, _handles: DaemonHandles) -> Result<Self, String> {
+        Err("The control socket requires a Unix platform".to_string())
+    }
+
+    pub(super) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        self.task.abort();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Send one command to the running daemon and return its reply.
+/// Send `command` to the daemon running as us, or, for a system-wide
+/// service, to the one whose home is `service_home`.
+pub(crate) fn request(
+    service_home: Option<&Path>,
+    command: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let path = match service_home {
+        Some(home) => control_socket_path_under(home),
+        None => control_socket_path().ok_or("Could not determine home directory")?,
+    };
+    request_at(&path, command)
+}
+
+#[cfg(unix)]
+fn request_at(path: &Path, command: &str) -> Result<String, Box<dyn std::error::Error>> {
+    use std::io::{Read, Write};
+
+    let mut stream = std::os::unix::net::UnixStream::connect(path)
+        .map_err(|e| format!("Daemon is not running ({}: {})", path.display(), e))?;
+    stream.set_read_timeout(Some(CONTROL_IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(CONTROL_IO_TIMEOUT))?;
+    stream.write_all(format!("{}\n", command).as_bytes())?;
+    stream.shutdown(std::net::Shutdown::Write)?;
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    match reply.strip_prefix(ERROR_PREFIX) {
+        Some(error) => Err(error.trim().to_string().into()),
+        None => Ok(reply),
+    }
+}
+
+#[cfg(not(unix))]
+fn request_at(_path: &Path, _command: &str) -> Result<String, Box<dyn std::error::Error>> {
+    Err("The control socket requires a Unix platform".into())
+}
+
+/// Human-readable status for `gsv device status`.
+pub(crate) fn describe_status(status: &DaemonStatus, now_ms: i64) -> String {
+    let mut out = format!("Daemon: pid {}, version {}", status.pid, status.version);
+    for device in &status.devices {
+        let _ = write!(out, "\n\nDevice: {}\n", device.device_id);
+        describe_device(&mut out, device, now_ms);
+    }
+
+    let _ = write!(out, "\n\nShell sessions: {}", status.shell_sessions.len());
+    for session in &status.shell_sessions {
+        let pid = session
+            .pid
+            .map(|pid| format!("pid {}", pid))
+            .unwrap_or_else(|| "no pid".to_string());
+        let _ = write!(
+            out,
+            "\n  {}  {}  {}{}",
+            session.session_id,
+            session.status,
+            pid,
+            if session.backgrounded {
+                "  (background)"
+            } else {
+                ""
+            }
+        );
+        if status.devices.len() > 1 {
+            if let Some(device_id) = &session.device_id {
+                let _ = write!(out, "  [{}]", device_id);
+            }
+        }
+    }
+    out
+}
+
+/// Answer a container probe from the daemon's status: alive whenever the
+/// daemon answers, ready once every device is connected.
+pub(crate) fn probe(status: &DaemonStatus, ready: bool) -> Result<String, String> {
+    if !ready {
+        return Ok(format!("alive: pid {}", status.pid));
+    }
+    if status.devices.is_empty() {
+        return Err("not ready: no devices configured".to_string());
+    }
+    let pending = status
+        .devices
+        .iter()
+        .filter(|device| device.connection.state != ConnectionState::Connected)
+        .map(|device| match &device.connection.last_error {
+            Some(error) => format!("{} ({})", device.device_id, error),
+            None => device.device_id.clone(),
+        })
+        .collect::<Vec<_>>();
+    if pending.is_empty() {
+        Ok(format!("ready: {} connected", status.devices.len()))
+    } else {
+        Err(format!("not ready: waiting for {}", pending.join(", ")))
+    }
+}
+
+fn describe_device(out: &mut String, device: &DeviceStatus, now_ms: i64) {
+    out.push_str(&super::state::describe_device_state(
+        &device.connection,
+        now_ms,
+    ));
+    if let Some(connection_id) = &device.connection.connection_id {
+        let _ = write!(out, "\nConnection id: {}", connection_id);
+    }
+
+    let _ = write!(out, "\nActive requests: {}", device.active_requests.len());
+    for request in &device.active_requests {
+        let _ = write!(
+            out,
+            "\n  {:<16} {}  {:.1}s",
+            request.call,
+            request.id,
+            request.elapsed_ms as f64 / 1000.0
+        );
+    }
+
+    let _ = write!(
+        out,
+        "\nOutbox: {} exec events, {} responses",
+        device.outbox.exec_events, device.outbox.responses
+    );
+
+    let busy = device
+        .queues
+        .iter()
+        .filter(|(_, depth)| depth.running > 0 || depth.queued > 0)
+        .collect::<Vec<_>>();
+    if !busy.is_empty() {
+        out.push_str("\nQueues:");
+        for (family, depth) in busy {
+            let _ = write!(
+                out,
+                "\n  {:<16} {}/{} running, {} queued",
+                family, depth.running, depth.limit, depth.queued
+            );
+        }
+    }
+
+    if !device.syscalls.is_empty() {
+        out.push_str("\nSyscalls:");
+        for (call, summary) in &device.syscalls {
+            let _ = write!(
+                out,
+                "\n  {:<16} {} calls, {} errors, {} cancelled, avg {:.1}ms, max {:.1}ms",
+                call,
+                summary.calls,
+                summary.errors,
+                summary.cancelled,
+                summary.avg_ms,
+                summary.max_ms
+            );
+        }
+    }
+}
+
+#[cfg(all(unix, test))]
+mod tests {
+    use super::super::metrics::Outcome;
+    use super::*;
+    use gsv::protocol::RequestFrame;
+    use tokio::time::Duration;
+
+    fn device(device_id: &str) -> DeviceHandles {
+        DeviceHandles {
+            device_id: device_id.to_string(),
+            state: DeviceState::new("wss://gateway.example/ws"),
+            active_requests: ActiveRequests::default(),
+            exec_event_outbox: Arc::new(Mutex::new(VecDeque::new())),
+            response_outbox: ResponseOutbox::default(),
+            metrics: SyscallMetrics::default(),
+            limits: SyscallLimits::default(),
+            cron: CronJobs::in_memory(device_id),
+        }
+    }
+
+    fn handles() -> DaemonHandles {
+        DaemonHandles {
+            devices: Arc::new(Mutex::new(vec![device("mac"), device("work")])),
+            reload: None,
+            approvals: Approvals::default(),
+        }
+    }
+
+    fn socket_path() -> PathBuf {
+        std::env::temp_dir().join(format!("gsv-control-{}.sock", uuid::Uuid::new_v4()))
+    }
+
+    async fn query(path: &Path, command: &str) -> Result<String, String> {
+        let path = path.to_path_buf();
+        let command = command.to_string();
+        tokio::task::spawn_blocking(move || request_at(&path, &command).map_err(|e| e.to_string()))
+            .await
+            .map_err(|e| e.to_string())?
+    }
+
+    #[tokio::test]
+    async fn status_and_metrics_are_served_over_the_socket() {
+        let handles = handles();
+        let mac = &handles.devices()[0];
+        mac.state.connected(Some("conn-7".to_string()));
+        mac.active_requests.register(
+            &RequestFrame::new("fs.read", None),
+            &super::super::transfer::BinaryFrameInbox::new(),
+        );
+        mac.metrics
+            .record("fs.read", Outcome::Ok, Duration::from_millis(3));
+
+        let path = socket_path();
+        let server = ControlServer::bind_at(path.clone(), handles).unwrap();
+
+        let status: DaemonStatus =
+            serde_json::from_str(&query(&path, "status").await.unwrap()).unwrap();
+        let [mac, work] = status.devices.as_slice() else {
+            panic!("expected two devices, got {:?}", status.devices);
+        };
+        assert_eq!(mac.device_id, "mac");
+        assert_eq!(mac.connection.state, ConnectionState::Connected);
+        assert_eq!(mac.connection.connection_id.as_deref(), Some("conn-7"));
+        assert_eq!(mac.active_requests.len(), 1);
+        assert_eq!(mac.active_requests[0].call, "fs.read");
+        assert_eq!(mac.syscalls["fs.read"].calls, 1);
+        assert_eq!(work.connection.state, ConnectionState::Connecting);
+        assert!(work.active_requests.is_empty());
+
+        let metrics = query(&path, "metrics").await.unwrap();
+        assert!(metrics.contains("gsv_device_connected{device=\"mac\"} 1\n"));
+        assert!(metrics.contains("gsv_device_connected{device=\"work\"} 0\n"));
+        assert!(metrics.contains("gsv_device_active_requests{device=\"mac\"} 1\n"));
+        assert!(
+            metrics.contains("gsv_device_outbox_depth{device=\"work\",queue=\"responses\"} 0\n")
+        );
+        assert!(
+            metrics.contains("gsv_device_syscall_queue_depth{device=\"mac\",family=\"shell\"} 0\n")
+        );
+        assert!(metrics.contains(
+            "gsv_device_syscall_requests_total{device=\"mac\",call=\"fs.read\",outcome=\"ok\"} 1\n"
+        ));
+
+        let error = query(&path, "reboot").await.unwrap_err();
+        assert_eq!(error, "unknown command `reboot`");
+        let error = query(&path, "reload").await.unwrap_err();
+        assert_eq!(error, "daemon is not accepting reloads");
+
+        drop(server);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn reload_replies_with_the_daemon_summary() {
+        let (reload, mut requests) = tokio::sync::mpsc::unbounded_channel::<ReloadRequest>();
+        let handles = DaemonHandles {
+            reload: Some(reload),
+            ..handles()
+        };
+        tokio::spawn(async move {
+            let mut replies = vec![Ok("reloaded mac".to_string()), Err("bad toml".to_string())];
+            while let Some(request) = requests.recv().await {
+                assert_eq!(request.source, "control socket");
+                if let Some(reply) = request.reply {
+                    let _ = reply.send(replies.remove(0));
+                }
+            }
+        });
+
+        let path = socket_path();
+        let _server = ControlServer::bind_at(path.clone(), handles).unwrap();
+        assert_eq!(query(&path, "reload").await.unwrap(), "reloaded mac\n");
+        assert_eq!(query(&path, "reload").await.unwrap_err(), "bad toml");
+    }
+
+    #[tokio::test]
+    async fn approvals_are_listed_and_answered_over_the_socket() {
+        let handles = handles();
+        let approvals = handles.approvals.clone();
+        let path = socket_path();
+        let _server = ControlServer::bind_at(path.clone(), handles).unwrap();
+
+        let ask = |request_id: &'static str| {
+            let approvals = approvals.clone();
+            tokio::spawn(async move {
+                approvals
+                    .ask(
+                        "mac",
+                        request_id,
+                        "fs.delete",
+                        "{\"path\":\"build\"}".to_string(),
+                        Duration::from_secs(30),
+                    )
+                    .await
+            })
+        };
+        let first = ask("req-1");
+        let second = ask("req-2");
+        while approvals.list().len() < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        let listed: Vec<super::super::approval::PendingApproval> =
+            serde_json::from_str(&query(&path, "approvals").await.unwrap()).unwrap();
+        assert_eq!(listed.len(), 2);
+        let id = |request_id: &str| {
+            listed
+                .iter()
+                .find(|pending| pending.request_id == request_id)
+                .unwrap()
+                .id
+        };
+
+        let reply = query(&path, &format!("approve {}", id("req-1")))
+            .await
+            .unwrap();
+        assert_eq!(reply, "Approved fs.delete on mac\n");
+        first.await.unwrap().unwrap();
+        query(&path, &format!("deny {}", id("req-2")))
+            .await
+            .unwrap();
+        second.await.unwrap().unwrap_err();
+
+        let error = query(&path, "approve 99").await.unwrap_err();
+        assert_eq!(error, "No pending approval 99");
+        let error = query(&path, "deny x").await.unwrap_err();
+        assert_eq!(
+            error,
+            "invalid approval id `x`: invalid digit found in string"
+        );
+    }
+
+    #[tokio::test]
+    async fn probes_report_liveness_and_readiness() {
+        let handles = handles();
+        let status = handles.status().await.unwrap();
+        assert!(probe(&status, false).unwrap().starts_with("alive: pid "));
+        assert_eq!(
+            probe(&status, true).unwrap_err(),
+            "not ready: waiting for mac, work"
+        );
+
+        for device in handles.devices().iter() {
+            device.state.connected(None);
+        }
+        let status = handles.status().await.unwrap();
+        assert_eq!(probe(&status, true).unwrap(), "ready: 2 connected");
+    }
+
+    #[tokio::test]
+    async fn stale_socket_is_replaced_but_live_one_is_not() {
+        let path = socket_path();
+        {
+            let _stale = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        }
+        assert!(path.exists());
+        let server = ControlServer::bind_at(path.clone(), handles()).unwrap();
+
+        let error = ControlServer::bind_at(path.clone(), handles())
+            .err()
+            .unwrap();
+        assert!(error.contains("Another daemon"), "{error}");
+        assert_eq!(server.path(), path);
+    }
+}
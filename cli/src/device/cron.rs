@@ -0,0 +1,687 @@
+//! Recurring shell commands scheduled on the device itself.
+//!
+//! Jobs are kept per device in `~/.gsv/cron/<device>.json` (in memory only
+//! for a stateless daemon) and run as background shell sessions, so they
+//! keep running while the gateway is unreachable; their exec events wait in
+//! the outbox until it is back. Schedules take the usual five cron fields,
+//! or six with seconds first, or `@daily` and friends, evaluated in the
+//! job's IANA timezone, else in local time.
+
+use chrono::{DateTime, Local, TimeZone, Utc};
+use chrono_tz::Tz;
+use gsv::tools::paths::{Access, PathResolver};
+use gsv::tools::{list_shell_sessions, spawn_background_shell};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+use super::approval::Approvals;
+use super::reload::{self, ToolSet};
+
+/// Longest the scheduler sleeps between checks, so that clock changes and
+/// suspends are caught up with soon after.
+const MAX_SLEEP: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CronJob {
+    id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    schedule: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timezone: Option<String>,
+    command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cwd: Option<String>,
+    /// Zero or unset: runs are not timed out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timeout_ms: Option<u64>,
+    created_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_run_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_session_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_error: Option<String>,
+}
+
+impl CronJob {
+    fn next_run_after(&self, after: DateTime<Utc>) -> Result<Option<DateTime<Utc>>, String> {
+        next_run(&self.schedule, self.timezone.as_deref(), after)
+    }
+
+    fn to_json(&self) -> Value {
+        let mut value = serde_json::to_value(self).unwrap_or(Value::Null);
+        let next_run_at = self
+            .next_run_after(Utc::now())
+            .ok()
+            .flatten()
+            .map(|next| next.timestamp_millis());
+        if let (Some(object), Some(next_run_at)) = (value.as_object_mut(), next_run_at) {
+            object.insert("nextRunAt".to_string(), json!(next_run_at));
+        }
+        value
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct CronFile {
+    jobs: Vec<CronJob>,
+}
+
+/// A device's cron jobs, shared by its syscalls and its scheduler.
+#[derive(Clone)]
+pub(super) struct CronJobs(Arc<CronJobsInner>);
+
+struct CronJobsInner {
+    device_id: String,
+    path: Option<PathBuf>,
+    jobs: Mutex<Vec<CronJob>>,
+    changed: Notify,
+}
+
+impl CronJobs {
+    /// Jobs stored for `device_id` under `~/.gsv/cron`.
+    pub(super) fn load(device_id: &str) -> Self {
+        Self::at(
+            device_id,
+            dirs::home_dir().map(|home| store_path(&home, device_id)),
+        )
+    }
+
+    /// Jobs that last only as long as the daemon.
+    pub(super) fn in_memory(device_id: &str) -> Self {
+        Self::at(device_id, None)
+    }
+
+    fn at(device_id: &str, path: Option<PathBuf>) -> Self {
+        let jobs = path
+            .as_deref()
+            .and_then(|path| match std::fs::read(path) {
+                Ok(bytes) => match serde_json::from_slice::<CronFile>(&bytes) {
+                    Ok(file) => Some(file.jobs),
+                    Err(error) => {
+                        warn!(event = "cron.load.failed", path = %path.display(), error = %error);
+                        None
+                    }
+                },
+                Err(_) => None,
+            })
+            .unwrap_or_default();
+        Self(Arc::new(CronJobsInner {
+            device_id: device_id.to_string(),
+            path,
+            jobs: Mutex::new(jobs),
+            changed: Notify::new(),
+        }))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<CronJob>> {
+        self.0.jobs.lock().expect("cron job mutex poisoned")
+    }
+
+    fn snapshot(&self) -> Vec<CronJob> {
+        self.lock().clone()
+    }
+
+    fn get(&self, id: &str) -> Result<CronJob, String> {
+        self.lock()
+            .iter()
+            .find(|job| job.id == id)
+            .cloned()
+            .ok_or_else(|| format!("Unknown cron job: {}", id))
+    }
+
+    fn save(&self, jobs: &[CronJob]) -> Result<(), String> {
+        let Some(path) = &self.0.path else {
+            return Ok(());
+        };
+        let file = CronFile {
+            jobs: jobs.to_vec(),
+        };
+        let json = serde_json::to_vec_pretty(&file).map_err(|e| e.to_string())?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create '{}': {}", parent.display(), e))?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, json)
+            .and_then(|()| std::fs::rename(&tmp, path))
+            .map_err(|e| format!("Failed to write '{}': {}", path.display(), e))
+    }
+
+    /// Apply `update` and persist the result; nothing changes if saving fails.
+    fn update<T>(
+        &self,
+        update: impl FnOnce(&mut Vec<CronJob>) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut jobs = self.lock();
+        let mut next = jobs.clone();
+        let result = update(&mut next)?;
+        self.save(&next)?;
+        *jobs = next;
+        drop(jobs);
+        self.0.changed.notify_one();
+        Ok(result)
+    }
+
+    /// `cron.run` arguments with the command of the job they name, so the
+    /// request is reviewed like the `cron.add` that stored it.
+    pub(super) fn run_args_with_command(&self, args: Option<&Value>) -> Option<Value> {
+        let id = args?.get("id")?.as_str()?;
+        let command = self.get(id).ok()?.command;
+        let mut args = args?.clone();
+        args.as_object_mut()?
+            .insert("command".to_string(), Value::String(command));
+        Some(args)
+    }
+
+    /// Start a run of `job` as a background shell session.
+    async fn run(&self, job: &CronJob, paths: &PathResolver) -> Result<String, String> {
+        let started = match job.cwd.as_deref() {
+            Some(cwd) => paths.resolve(cwd, Access::Read),
+            None => Ok(paths.workspace().to_path_buf()),
+        };
+        let started = match started {
+            Ok(cwd) => {
+                spawn_background_shell(
+                    job.command.clone(),
+                    cwd,
+                    job.timeout_ms.unwrap_or(0),
+                    Some(self.0.device_id.clone()),
+                    Some(job.id.clone()),
+                )
+                .await
+            }
+            Err(error) => Err(error),
+        };
+        self.record(job, &started);
+        started
+    }
+
+    fn record(&self, job: &CronJob, started: &Result<String, String>) {
+        let recorded = self.update(|jobs| {
+            if let Some(stored) = jobs.iter_mut().find(|stored| stored.id == job.id) {
+                stored.last_run_at = Some(Utc::now().timestamp_millis());
+                stored.last_session_id = started.as_ref().ok().cloned();
+                stored.last_error = started.as_ref().err().cloned();
+            }
+            Ok(())
+        });
+        if let Err(error) = recorded {
+            warn!(event = "cron.save.failed", job_id = %job.id, error = %error);
+        }
+    }
+
+    /// A scheduled run, skipped while the job's previous run is still going.
+    /// The current `[device.approval]` applies to every run, as it would to
+    /// `cron.run`.
+    async fn run_scheduled(&self, job: &CronJob, tools: &ToolSet, approvals: &Approvals) {
+        if let Some(previous) = &job.last_session_id {
+            let running = list_shell_sessions()
+                .await
+                .into_iter()
+                .any(|session| &session.session_id == previous && session.ended_at.is_none());
+            if running {
+                info!(event = "cron.skipped", job_id = %job.id, session_id = %previous);
+                return;
+            }
+        }
+        let args = json!({ "id": job.id, "command": job.command });
+        if let Some(detail) = tools.approval.review("cron.run", Some(&args)) {
+            let request_id = format!("cron:{}", job.id);
+            let approved = approvals
+                .ask(
+                    &self.0.device_id,
+                    &request_id,
+                    "cron.run",
+                    detail,
+                    tools.approval.timeout(),
+                )
+                .await;
+            if let Err(error) = approved {
+                warn!(event = "cron.run.denied", job_id = %job.id, error = %error);
+                self.record(job, &Err(error));
+                return;
+            }
+        }
+        match self.run(job, &tools.paths).await {
+            Ok(session_id) => {
+                info!(event = "cron.run", job_id = %job.id, session_id = %session_id);
+            }
+            Err(error) => {
+                warn!(event = "cron.run.failed", job_id = %job.id, error = %error);
+            }
+        }
+    }
+}
+
+fn store_path(home: &Path, device_id: &str) -> PathBuf {
+    let file_name = device_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    home.join(".gsv")
+        .join("cron")
+        .join(format!("{}.json", file_name))
+}
+
+/// Run the device's jobs as they come due, until aborted.
+pub(super) async fn run_scheduler(
+    jobs: CronJobs,
+    tools: reload::CurrentTools,
+    approvals: Approvals,
+) {
+    // Runs due are worked out from when a job was first seen, so a missed
+    // run (the daemon was down) is not made up for.
+    let mut next_runs: HashMap<String, DateTime<Utc>> = HashMap::new();
+    loop {
+        let now = Utc::now();
+        let current = jobs.snapshot();
+        next_runs.retain(|id, _| current.iter().any(|job| &job.id == id));
+        for job in &current {
+            let due = match next_runs.get(&job.id) {
+                Some(next) => *next <= now,
+                None => false,
+            };
+            if due {
+                jobs.run_scheduled(job, &tools.get(), &approvals).await;
+            }
+            if due || !next_runs.contains_key(&job.id) {
+                match job.next_run_after(now) {
+                    Ok(Some(next)) => {
+                        next_runs.insert(job.id.clone(), next);
+                    }
+                    _ => {
+                        next_runs.remove(&job.id);
+                    }
+                }
+            }
+        }
+
+        let wait = next_runs
+            .values()
+            .min()
+            .map(|next| (*next - Utc::now()).to_std().unwrap_or_default())
+            .unwrap_or(MAX_SLEEP)
+            .min(MAX_SLEEP);
+        tokio::select! {
+            _ = jobs.0.changed.notified() => {}
+            _ = tokio::time::sleep(wait) => {}
+        }
+    }
+}
+
+/// First time `schedule` fires after `after`, in `timezone` or local time.
+fn next_run(
+    schedule: &str,
+    timezone: Option<&str>,
+    after: DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>, String> {
+    let schedule = parse_schedule(schedule)?;
+    Ok(match timezone {
+        Some(timezone) => first_after(&schedule, parse_timezone(timezone)?, after),
+        None => first_after(&schedule, Local, after),
+    })
+}
+
+fn first_after<Z: TimeZone>(
+    schedule: &cron::Schedule,
+    timezone: Z,
+    after: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    schedule
+        .after(&after.with_timezone(&timezone))
+        .next()
+        .map(|next| next.with_timezone(&Utc))
+}
+
+fn parse_timezone(timezone: &str) -> Result<Tz, String> {
+    Tz::from_str(timezone).map_err(|error| format!("Unknown timezone {}: {}", timezone, error))
+}
+
+/// Parse a cron expression. Five fields are read the way crontab(5) does,
+/// with 0 or 7 for Sunday; six or seven start with seconds (and end with
+/// years).
+fn parse_schedule(schedule: &str) -> Result<cron::Schedule, String> {
+    let invalid = |error: &dyn std::fmt::Display| {
+        format!("Invalid cron schedule '{}': {}", schedule.trim(), error)
+    };
+    let fields = schedule.split_whitespace().collect::<Vec<_>>();
+    let expression = match fields.as_slice() {
+        ["@annually"] => "@yearly".to_string(),
+        ["@midnight"] => "@daily".to_string(),
+        [macro_name] if macro_name.starts_with('@') => macro_name.to_string(),
+        [minute, hour, day, month, weekday] => format!(
+            "0 {} {} {} {} {}",
+            minute,
+            hour,
+            day,
+            month,
+            crontab_weekdays(weekday).map_err(|e| invalid(&e))?
+        ),
+        [_, _, _, _, _, _] | [_, _, _, _, _, _, _] => fields.join(" "),
+        _ => return Err(invalid(&"expected 5 fields")),
+    };
+    cron::Schedule::from_str(&expression).map_err(|e| invalid(&e))
+}
+
+const WEEKDAYS: [&str; 8] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT", "SUN"];
+
+/// Spell out crontab's numeric weekdays, which count from Sunday as 0,
+/// since the cron crate counts them from Sunday as 1.
+fn crontab_weekdays(field: &str) -> Result<String, String> {
+    let weekday = |value: &str| -> Result<String, String> {
+        match value.parse::<usize>() {
+            Ok(day) => WEEKDAYS
+                .get(day)
+                .map(|name| name.to_string())
+                .ok_or_else(|| format!("weekday {} is out of range", day)),
+            Err(_) => Ok(value.to_string()),
+        }
+    };
+    let items = field
+        .split(',')
+        .map(|item| {
+            let (range, step) = match item.split_once('/') {
+                Some((range, step)) => (range, Some(step)),
+                None => (item, None),
+            };
+            let range = match range.split_once('-') {
+                // `SAT-SUN` would wrap around the week.
+                Some((start, "7")) if step.is_none() => format!("{}-SAT,SUN", weekday(start)?),
+                Some((_, "7")) => return Err("a stepped weekday range cannot end at 7".into()),
+                Some((start, end)) => format!("{}-{}", weekday(start)?, weekday(end)?),
+                None => weekday(range)?,
+            };
+            Ok(match step {
+                Some(step) => format!("{}/{}", range, step),
+                None => range,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(items.join(","))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CronAddArgs {
+    schedule: String,
+    command: String,
+    #[serde(default)]
+    timezone: Option<String>,
+    #[serde(default)]
+    cwd: Option<String>,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    timeout_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct CronJobArgs {
+    id: String,
+}
+
+pub(super) async fn handle_cron_syscall(
+    call: &str,
+    args: Value,
+    jobs: &CronJobs,
+    paths: &PathResolver,
+) -> Option<Result<Value, String>> {
+    match call {
+        "cron.add" => Some(handle_add(args, jobs, paths)),
+        "cron.list" => Some(Ok(json!({
+            "jobs": jobs.snapshot().iter().map(CronJob::to_json).collect::<Vec<_>>(),
+        }))),
+        "cron.remove" => Some(handle_remove(args, jobs)),
+        "cron.run" => Some(handle_run(args, jobs, paths).await),
+        _ => None,
+    }
+}
+
+fn parse_args<T: serde::de::DeserializeOwned>(args: Value) -> Result<T, String> {
+    serde_json::from_value(args).map_err(|e| format!("Invalid arguments: {}", e))
+}
+
+fn handle_add(args: Value, jobs: &CronJobs, paths: &PathResolver) -> Result<Value, String> {
+    let args: CronAddArgs = parse_args(args)?;
+    if args.command.trim().is_empty() {
+        return Err("command must not be empty".to_string());
+    }
+    let timezone = args.timezone.filter(|timezone| !timezone.trim().is_empty());
+    if next_run(&args.schedule, timezone.as_deref(), Utc::now())?.is_none() {
+        return Err(format!(
+            "Cron schedule '{}' never runs again",
+            args.schedule.trim()
+        ));
+    }
+    if let Some(cwd) = &args.cwd {
+        let resolved = paths.resolve(cwd, Access::Read)?;
+        if !resolved.is_dir() {
+            return Err(format!("Not a directory: {}", resolved.display()));
+        }
+    }
+
+    let job = CronJob {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: args.name,
+        schedule: args.schedule.trim().to_string(),
+        timezone,
+        command: args.command,
+        cwd: args.cwd,
+        timeout_ms: args.timeout_ms.filter(|timeout| *timeout > 0),
+        created_at: Utc::now().timestamp_millis(),
+        last_run_at: None,
+        last_session_id: None,
+        last_error: None,
+    };
+    jobs.update(|stored| {
+        stored.push(job.clone());
+        Ok(())
+    })?;
+    info!(event = "cron.added", job_id = %job.id, schedule = %job.schedule);
+    Ok(json!({ "job": job.to_json() }))
+}
+
+fn handle_remove(args: Value, jobs: &CronJobs) -> Result<Value, String> {
+    let args: CronJobArgs = parse_args(args)?;
+    let removed = jobs.update(|stored| {
+        let index = stored
+            .iter()
+            .position(|job| job.id == args.id)
+            .ok_or_else(|| format!("Unknown cron job: {}", args.id))?;
+        Ok(stored.remove(index))
+    })?;
+    info!(event = "cron.removed", job_id = %removed.id);
+    Ok(json!({ "removed": removed.to_json() }))
+}
+
+async fn handle_run(args: Value, jobs: &CronJobs, paths: &PathResolver) -> Result<Value, String> {
+    let args: CronJobArgs = parse_args(args)?;
+    let job = jobs.get(&args.id)?;
+    let session_id = jobs.run(&job, paths).await?;
+    info!(event = "cron.run", job_id = %job.id, session_id = %session_id, manual = true);
+    Ok(json!({
+        "jobId": job.id,
+        "sessionId": session_id,
+        "status": "running",
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text)
+            .map(|time| time.with_timezone(&Utc))
+            .unwrap()
+    }
+
+    fn next(schedule: &str, timezone: &str, after: &str) -> String {
+        next_run(schedule, Some(timezone), at(after))
+            .unwrap()
+            .unwrap()
+            .to_rfc3339()
+    }
+
+    #[test]
+    fn crontab_weekdays_count_sunday_as_zero_or_seven() {
+        assert_eq!(crontab_weekdays("0").unwrap(), "SUN");
+        assert_eq!(crontab_weekdays("1-5").unwrap(), "MON-FRI");
+        assert_eq!(crontab_weekdays("5-7").unwrap(), "FRI-SAT,SUN");
+        assert_eq!(crontab_weekdays("*/2").unwrap(), "*/2");
+        assert_eq!(crontab_weekdays("mon,3").unwrap(), "mon,WED");
+        assert!(crontab_weekdays("8").unwrap_err().contains("out of range"));
+
+        // 2026-10-18 is a Sunday.
+        assert_eq!(
+            next("30 9 * * 0", "UTC", "2026-10-14T00:00:00Z"),
+            "2026-10-18T09:30:00+00:00"
+        );
+        assert_eq!(
+            next("30 9 * * 1-5", "UTC", "2026-10-17T00:00:00Z"),
+            "2026-10-19T09:30:00+00:00"
+        );
+    }
+
+    #[test]
+    fn schedules_follow_their_timezone_and_accept_macros() {
+        assert_eq!(
+            next("0 9 * * *", "Europe/Madrid", "2026-10-18T08:00:00Z"),
+            "2026-10-19T07:00:00+00:00"
+        );
+        assert_eq!(
+            next("@midnight", "America/New_York", "2026-10-18T12:00:00Z"),
+            "2026-10-19T04:00:00+00:00"
+        );
+        assert_eq!(
+            next("*/15 * * * * *", "UTC", "2026-10-18T12:00:01Z"),
+            "2026-10-18T12:00:15+00:00"
+        );
+        assert!(parse_schedule("* * *")
+            .unwrap_err()
+            .contains("expected 5 fields"));
+        assert!(parse_timezone("Mars/Olympus")
+            .unwrap_err()
+            .starts_with("Unknown timezone Mars/Olympus: "));
+    }
+
+    #[tokio::test]
+    async fn jobs_persist_and_run_as_shell_sessions() {
+        let root = std::env::temp_dir().join(format!("gsv-cron-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        let paths = PathResolver::new(root.clone());
+        let path = store_path(&root, "work/laptop");
+        let jobs = CronJobs::at("cron-test", Some(path.clone()));
+
+        let added = handle_cron_syscall(
+            "cron.add",
+            json!({ "schedule": "0 3 * * *", "command": "echo ran", "name": "nightly" }),
+            &jobs,
+            &paths,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        let id = added["job"]["id"].as_str().unwrap().to_string();
+        assert!(added["job"]["nextRunAt"].is_i64());
+        assert!(path.ends_with("cron/work_laptop.json"));
+
+        let reloaded = CronJobs::at("cron-test", Some(path.clone()));
+        assert_eq!(reloaded.snapshot().len(), 1);
+
+        let run = handle_cron_syscall("cron.run", json!({ "id": id }), &reloaded, &paths)
+            .await
+            .unwrap()
+            .unwrap();
+        let session_id = run["sessionId"].as_str().unwrap();
+        let session = list_shell_sessions()
+            .await
+            .into_iter()
+            .find(|session| session.session_id == session_id)
+            .unwrap();
+        assert_eq!(session.job_id.as_deref(), Some(id.as_str()));
+        assert_eq!(session.device_id.as_deref(), Some("cron-test"));
+        assert_eq!(
+            reloaded.get(&id).unwrap().last_session_id.as_deref(),
+            Some(session_id)
+        );
+
+        handle_cron_syscall("cron.remove", json!({ "id": id }), &reloaded, &paths)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(CronJobs::at("cron-test", Some(path)).snapshot().is_empty());
+        let missing = handle_cron_syscall("cron.run", json!({ "id": id }), &reloaded, &paths)
+            .await
+            .unwrap();
+        assert_eq!(missing.unwrap_err(), format!("Unknown cron job: {}", id));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn scheduled_runs_ask_for_approval_each_time() {
+        let jobs = CronJobs::in_memory("cron-approval-test");
+        let tools = ToolSet {
+            paths: PathResolver::new(std::env::temp_dir()),
+            tools: Vec::new(),
+            host: super::super::host::HostPolicy {
+                kill_any_user: false,
+            },
+            desktop: false,
+            approval: super::super::approval::ApprovalPolicy::from_config(
+                &gsv::config::DeviceApprovalConfig {
+                    commands: vec!["sudo *".to_string()],
+                    ..Default::default()
+                },
+            )
+            .unwrap(),
+        };
+        handle_add(
+            json!({ "schedule": "@daily", "command": "sudo true" }),
+            &jobs,
+            &tools.paths,
+        )
+        .unwrap();
+        let job = jobs.snapshot().remove(0);
+        assert_eq!(
+            jobs.run_args_with_command(Some(&json!({ "id": job.id }))),
+            Some(json!({ "id": job.id, "command": "sudo true" }))
+        );
+
+        let approvals = Approvals::default();
+        let deny = async {
+            while approvals.list().is_empty() {
+                tokio::task::yield_now().await;
+            }
+            let [pending] = approvals.list().try_into().unwrap();
+            assert_eq!(pending.call, "cron.run");
+            assert_eq!(pending.request_id, format!("cron:{}", job.id));
+            assert_eq!(pending.detail, "sudo true");
+            approvals.decide(pending.id, false, "test").unwrap();
+        };
+        tokio::join!(jobs.run_scheduled(&job, &tools, &approvals), deny);
+
+        let stored = jobs.get(&job.id).unwrap();
+        assert_eq!(
+            stored.last_error.as_deref(),
+            Some("cron.run was denied at the device")
+        );
+        assert!(stored.last_session_id.is_none());
+    }
+}
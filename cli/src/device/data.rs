@@ -0,0 +1,854 @@
+//! `data.query`: SQL over device-local data, instead of `sqlite3` in a shell.
+//!
+//! A SQLite database is opened read-only. CSV, TSV and JSON Lines files are
+//! loaded into an in-memory database, one table per file, with column types
+//! inferred from their values. Queries run with a row limit and a time limit
+//! and return typed columns with JSON rows, or a JSON Lines body when the
+//! rows are large.
+
+use super::transfer::{BinaryFrameInbox, OutgoingBody};
+use base64::Engine;
+use gsv::tools::paths::{Access, PathResolver};
+use rusqlite::limits::Limit;
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{Connection, ErrorCode, OpenFlags};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::io::{BufRead, BufReader, Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+const DEFAULT_MAX_ROWS: usize = 1000;
+const MAX_ROWS: usize = 100_000;
+const DEFAULT_TIMEOUT_MS: u64 = 10_000;
+const MAX_TIMEOUT_MS: u64 = 300_000;
+/// Rows that serialize larger than this are sent as a body instead.
+const MAX_INLINE_ROWS_BYTES: usize = 1024 * 1024;
+/// Text files are loaded into memory, so larger ones are refused.
+const MAX_TEXT_SOURCE_BYTES: u64 = 256 * 1024 * 1024;
+const DEFAULT_TABLE: &str = "data";
+const SQLITE_MAGIC: &[u8] = b"SQLite format 3\0";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SourceFormat {
+    Sqlite,
+    Csv,
+    Tsv,
+    Jsonl,
+}
+
+impl SourceFormat {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Sqlite => "sqlite",
+            Self::Csv => "csv",
+            Self::Tsv => "tsv",
+            Self::Jsonl => "jsonl",
+        }
+    }
+
+    /// The format from the file extension, or SQLite's header.
+    fn detect(path: &Path) -> Result<Self, String> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("csv") => return Ok(Self::Csv),
+            Some("tsv" | "tab") => return Ok(Self::Tsv),
+            Some("jsonl" | "ndjson") => return Ok(Self::Jsonl),
+            _ => {}
+        }
+        let mut magic = Vec::with_capacity(SQLITE_MAGIC.len());
+        std::fs::File::open(path)
+            .and_then(|file| file.take(SQLITE_MAGIC.len() as u64).read_to_end(&mut magic))
+            .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+        if magic == SQLITE_MAGIC {
+            Ok(Self::Sqlite)
+        } else {
+            Err(format!(
+                "Cannot tell the format of '{}'; set format",
+                path.display()
+            ))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QueryArgs {
+    #[serde(default)]
+    path: Option<String>,
+    #[serde(default)]
+    format: Option<SourceFormat>,
+    #[serde(default)]
+    table: Option<String>,
+    #[serde(default)]
+    tables: BTreeMap<String, String>,
+    sql: String,
+    #[serde(default)]
+    params: Vec<Value>,
+    #[serde(default)]
+    max_rows: Option<usize>,
+    #[serde(default)]
+    timeout_ms: Option<u64>,
+    #[serde(default)]
+    header: Option<bool>,
+    #[serde(default)]
+    body: bool,
+}
+
+pub(super) async fn handle_data_syscall(
+    call: &str,
+    args: Value,
+    paths: &PathResolver,
+    binary_inbox: &BinaryFrameInbox,
+) -> Option<Result<(Value, Option<OutgoingBody>), String>> {
+    (call == "data.query").then_some(())?;
+    Some(query(args, paths, binary_inbox).await)
+}
+
+/// What to load, resolved before leaving the async side.
+struct Sources {
+    main: Option<(PathBuf, Option<SourceFormat>)>,
+    table: String,
+    tables: Vec<(String, PathBuf)>,
+    header: bool,
+}
+
+struct QueryOutput {
+    columns: Vec<Value>,
+    rows: Vec<Value>,
+    truncated: bool,
+    elapsed: Duration,
+    tables: Vec<Value>,
+}
+
+async fn query(
+    args: Value,
+    paths: &PathResolver,
+    binary_inbox: &BinaryFrameInbox,
+) -> Result<(Value, Option<OutgoingBody>), String> {
+    let args: QueryArgs =
+        serde_json::from_value(args).map_err(|e| format!("Invalid arguments: {}", e))?;
+    if args.sql.trim().is_empty() {
+        return Err("sql must not be empty".to_string());
+    }
+    let main = args
+        .path
+        .as_deref()
+        .map(|path| paths.resolve(path, Access::Read))
+        .transpose()?;
+    let tables = args
+        .tables
+        .iter()
+        .map(|(name, path)| Ok((name.clone(), paths.resolve(path, Access::Read)?)))
+        .collect::<Result<Vec<_>, String>>()?;
+    if main.is_none() && tables.is_empty() {
+        return Err("data.query requires path or tables".to_string());
+    }
+    let sources = Sources {
+        main: main.clone().map(|path| (path, args.format)),
+        table: args.table.unwrap_or_else(|| DEFAULT_TABLE.to_string()),
+        tables,
+        header: args.header.unwrap_or(true),
+    };
+    let max_rows = args.max_rows.unwrap_or(DEFAULT_MAX_ROWS).clamp(1, MAX_ROWS);
+    let timeout = Duration::from_millis(
+        args.timeout_ms
+            .unwrap_or(DEFAULT_TIMEOUT_MS)
+            .clamp(1, MAX_TIMEOUT_MS),
+    );
+
+    let (interrupt_sender, interrupt_receiver) = oneshot::channel();
+    let sql = args.sql;
+    let params = args.params;
+    let mut task = tokio::task::spawn_blocking(move || {
+        run_query(&sources, &sql, &params, max_rows, interrupt_sender)
+    });
+    // The time limit starts once the sources are loaded and the query begins.
+    let result = match interrupt_receiver.await {
+        Ok(interrupt) => tokio::select! {
+            result = &mut task => result,
+            () = tokio::time::sleep(timeout) => {
+                interrupt.interrupt();
+                task.await
+            }
+        },
+        Err(_) => task.await,
+    };
+    let output = result
+        .map_err(|e| format!("Query task failed: {}", e))?
+        .map_err(|error| match error {
+            QueryError::Interrupted => {
+                format!("Query timed out after {} ms", timeout.as_millis())
+            }
+            QueryError::Failed(message) => message,
+        })?;
+
+    let mut result = json!({
+        "path": main.map(|path| path.display().to_string()),
+        "columns": output.columns,
+        "rowCount": output.rows.len(),
+        "truncated": output.truncated,
+        "elapsedMs": output.elapsed.as_millis() as u64,
+    });
+    if !output.tables.is_empty() {
+        result["tables"] = json!(output.tables);
+    }
+    let inline = serde_json::to_vec(&output.rows).map_err(|e| e.to_string())?;
+    if !args.body && inline.len() <= MAX_INLINE_ROWS_BYTES {
+        result["rows"] = json!(output.rows);
+        return Ok((result, None));
+    }
+
+    let mut lines = Vec::with_capacity(inline.len() + output.rows.len());
+    for row in &output.rows {
+        serde_json::to_writer(&mut lines, row).map_err(|e| e.to_string())?;
+        lines.push(b'\n');
+    }
+    result["contentType"] = json!("application/x-ndjson");
+    let body = OutgoingBody::new(
+        binary_inbox,
+        Some(lines.len() as u64),
+        None,
+        Cursor::new(lines),
+        "data.query".to_string(),
+    );
+    Ok((result, Some(body)))
+}
+
+enum QueryError {
+    Interrupted,
+    Failed(String),
+}
+
+impl From<String> for QueryError {
+    fn from(message: String) -> Self {
+        Self::Failed(message)
+    }
+}
+
+impl From<rusqlite::Error> for QueryError {
+    fn from(error: rusqlite::Error) -> Self {
+        if error.sqlite_error_code() == Some(ErrorCode::OperationInterrupted) {
+            Self::Interrupted
+        } else {
+            Self::Failed(format!("SQL error: {}", error))
+        }
+    }
+}
+
+fn sql_value(value: &Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(value) => SqlValue::Integer(i64::from(*value)),
+        Value::Number(number) => match number.as_i64() {
+            Some(integer) => SqlValue::Integer(integer),
+            None => SqlValue::Real(number.as_f64().unwrap_or_default()),
+        },
+        Value::String(text) => SqlValue::Text(text.clone()),
+        other => SqlValue::Text(other.to_string()),
+    }
+}
+
+fn json_value(value: ValueRef<'_>) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(integer) => json!(integer),
+        ValueRef::Real(real) => serde_json::Number::from_f64(real)
+            .map(Value::Number)
+            .unwrap_or(Value::Null),
+        ValueRef::Text(text) => Value::String(String::from_utf8_lossy(text).into_owned()),
+        ValueRef::Blob(blob) => {
+            Value::String(base64::engine::general_purpose::STANDARD.encode(blob))
+        }
+    }
+}
+
+fn value_type(value: ValueRef<'_>) -> Option<&'static str> {
+    match value {
+        ValueRef::Null => None,
+        ValueRef::Integer(_) => Some("integer"),
+        ValueRef::Real(_) => Some("real"),
+        ValueRef::Text(_) => Some("text"),
+        ValueRef::Blob(_) => Some("blob"),
+    }
+}
+
+/// The type SQLite's affinity rules give a declared column type.
+fn declared_affinity(declared: &str) -> &'static str {
+    let declared = declared.to_ascii_uppercase();
+    if declared.contains("INT") {
+        "integer"
+    } else if ["CHAR", "CLOB", "TEXT"]
+        .iter()
+        .any(|name| declared.contains(name))
+    {
+        "text"
+    } else if declared.contains("BLOB") || declared.is_empty() {
+        "blob"
+    } else if ["REAL", "FLOA", "DOUB"]
+        .iter()
+        .any(|name| declared.contains(name))
+    {
+        "real"
+    } else {
+        "numeric"
+    }
+}
+
+fn run_query(
+    sources: &Sources,
+    sql: &str,
+    params: &[Value],
+    max_rows: usize,
+    interrupt: oneshot::Sender<rusqlite::InterruptHandle>,
+) -> Result<QueryOutput, QueryError> {
+    let (connection, tables) = open(sources)?;
+    connection.set_limit(Limit::SQLITE_LIMIT_ATTACHED, 0)?;
+    connection.pragma_update(None, "query_only", true)?;
+    let _ = interrupt.send(connection.get_interrupt_handle());
+
+    let started = Instant::now();
+    let mut statement = connection.prepare(sql)?;
+    let names: Vec<String> = statement
+        .column_names()
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    let declared: Vec<Option<String>> = statement
+        .columns()
+        .iter()
+        .map(|column| column.decl_type().map(str::to_string))
+        .collect();
+    let mut types: Vec<Option<&'static str>> = vec![None; names.len()];
+    let mut rows = Vec::new();
+    let mut truncated = false;
+
+    let mut results = statement.query(rusqlite::params_from_iter(params.iter().map(sql_value)))?;
+    while let Some(row) = results.next()? {
+        if rows.len() == max_rows {
+            truncated = true;
+            break;
+        }
+        let mut values = Vec::with_capacity(names.len());
+        for (index, column_type) in types.iter_mut().enumerate() {
+            let value = row.get_ref(index)?;
+            *column_type = match (*column_type, value_type(value)) {
+                (seen, None) => seen,
+                (None, Some(found)) => Some(found),
+                (Some(seen), Some(found)) if seen == found => Some(seen),
+                (Some("integer"), Some("real")) | (Some("real"), Some("integer")) => Some("real"),
+                _ => Some("mixed"),
+            };
+            values.push(json_value(value));
+        }
+        rows.push(Value::Array(values));
+    }
+
+    let columns = names
+        .into_iter()
+        .zip(types)
+        .zip(declared)
+        .map(|((name, found), declared)| {
+            let column_type = found
+                .or_else(|| declared.as_deref().map(declared_affinity))
+                .unwrap_or("null");
+            let mut column = json!({ "name": name, "type": column_type });
+            if let Some(declared) = declared {
+                column["declaredType"] = json!(declared);
+            }
+            column
+        })
+        .collect();
+    Ok(QueryOutput {
+        columns,
+        rows,
+        truncated,
+        elapsed: started.elapsed(),
+        tables,
+    })
+}
+
+fn open(sources: &Sources) -> Result<(Connection, Vec<Value>), QueryError> {
+    let main = sources
+        .main
+        .as_ref()
+        .map(|(path, format)| {
+            format
+                .map(Ok)
+                .unwrap_or_else(|| SourceFormat::detect(path))
+                .map(|format| (path, format))
+        })
+        .transpose()?;
+
+    if let Some((path, SourceFormat::Sqlite)) = main {
+        if !sources.tables.is_empty() {
+            return Err(QueryError::Failed(
+                "tables cannot be combined with a SQLite database".to_string(),
+            ));
+        }
+        let connection = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .map_err(|e| format!("Failed to open '{}': {}", path.display(), e))?;
+        return Ok((connection, Vec::new()));
+    }
+
+    let connection = Connection::open_in_memory()?;
+    let mut loaded = Vec::new();
+    if let Some((path, format)) = main {
+        loaded.push(load_table(
+            &connection,
+            &sources.table,
+            path,
+            format,
+            sources.header,
+        )?);
+    }
+    for (name, path) in &sources.tables {
+        let format = SourceFormat::detect(path)?;
+        if format == SourceFormat::Sqlite {
+            return Err(QueryError::Failed(format!(
+                "'{}' is a SQLite database; pass it as path",
+                path.display()
+            )));
+        }
+        loaded.push(load_table(&connection, name, path, format, sources.header)?);
+    }
+    Ok((connection, loaded))
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Column names from a header, with blanks named `columnN` and duplicates
+/// numbered, since SQLite compares identifiers without case.
+fn column_names(header: &[String], width: usize) -> Vec<String> {
+    let mut seen = HashSet::new();
+    (0..width)
+        .map(|index| {
+            let base = header
+                .get(index)
+                .map(|name| name.trim())
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("column{}", index + 1));
+            let mut name = base.clone();
+            let mut suffix = 2;
+            while !seen.insert(name.to_lowercase()) {
+                name = format!("{}_{}", base, suffix);
+                suffix += 1;
+            }
+            name
+        })
+        .collect()
+}
+
+/// The narrowest of integer, real and text that holds every value.
+fn text_column_type<'a>(values: impl Iterator<Item = &'a str>) -> &'static str {
+    let mut column_type = "integer";
+    for value in values.filter(|value| !value.is_empty()) {
+        if column_type == "integer" && value.parse::<i64>().is_err() {
+            column_type = "real";
+        }
+        if column_type == "real" && !value.parse::<f64>().is_ok_and(f64::is_finite) {
+            return "text";
+        }
+    }
+    column_type
+}
+
+fn text_cell(value: &str, column_type: &str) -> SqlValue {
+    if value.is_empty() {
+        return SqlValue::Null;
+    }
+    match column_type {
+        "integer" => value
+            .parse()
+            .map(SqlValue::Integer)
+            .unwrap_or_else(|_| SqlValue::Text(value.to_string())),
+        "real" => value
+            .parse()
+            .map(SqlValue::Real)
+            .unwrap_or_else(|_| SqlValue::Text(value.to_string())),
+        _ => SqlValue::Text(value.to_string()),
+    }
+}
+
+/// Column names, column types and rows read from a text file.
+type TextTable = (Vec<String>, Vec<&'static str>, Vec<Vec<SqlValue>>);
+
+fn read_delimited(path: &Path, delimiter: u8, header: bool) -> Result<TextTable, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_path(path)
+        .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+    let mut records = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+        records.push(record.iter().map(str::to_string).collect::<Vec<_>>());
+    }
+    let header_row = if header && !records.is_empty() {
+        let mut names = records.remove(0);
+        if let Some(first) = names.first_mut() {
+            *first = first.trim_start_matches('\u{feff}').to_string();
+        }
+        names
+    } else {
+        Vec::new()
+    };
+    let width = records
+        .iter()
+        .map(Vec::len)
+        .chain(std::iter::once(header_row.len()))
+        .max()
+        .unwrap_or(0);
+    let names = column_names(&header_row, width);
+    let types: Vec<&'static str> = (0..width)
+        .map(|index| {
+            text_column_type(
+                records
+                    .iter()
+                    .map(|record| record.get(index).map(String::as_str).unwrap_or_default()),
+            )
+        })
+        .collect();
+    let rows = records
+        .iter()
+        .map(|record| {
+            types
+                .iter()
+                .enumerate()
+                .map(|(index, column_type)| {
+                    text_cell(
+                        record.get(index).map(String::as_str).unwrap_or_default(),
+                        column_type,
+                    )
+                })
+                .collect()
+        })
+        .collect();
+    Ok((names, types, rows))
+}
+
+fn read_json_lines(path: &Path) -> Result<TextTable, String> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| format!("Failed to open '{}': {}", path.display(), e))?;
+    let mut keys: Vec<String> = Vec::new();
+    let mut records: Vec<Map<String, Value>> = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Value::Object(record) = serde_json::from_str(&line).map_err(|e| {
+            format!(
+                "Invalid JSON on line {} of '{}': {}",
+                index + 1,
+                path.display(),
+                e
+            )
+        })?
+        else {
+            return Err(format!(
+                "Line {} of '{}' is not a JSON object",
+                index + 1,
+                path.display()
+            ));
+        };
+        for key in record.keys() {
+            if !keys.contains(key) {
+                keys.push(key.clone());
+            }
+        }
+        records.push(record);
+    }
+
+    let types: Vec<&'static str> = keys
+        .iter()
+        .map(|key| {
+            let mut column_type = "integer";
+            for value in records.iter().filter_map(|record| record.get(key)) {
+                match value {
+                    Value::Null | Value::Bool(_) => {}
+                    Value::Number(number) if number.is_i64() => {}
+                    Value::Number(_) => column_type = "real",
+                    _ => return "text",
+                }
+            }
+            column_type
+        })
+        .collect();
+    let rows = records
+        .iter()
+        .map(|record| {
+            keys.iter()
+                .zip(&types)
+                .map(|(key, column_type)| match (record.get(key), *column_type) {
+                    (None | Some(Value::Null), _) => SqlValue::Null,
+                    (Some(Value::String(text)), _) => SqlValue::Text(text.clone()),
+                    (Some(value @ (Value::Number(_) | Value::Bool(_))), "text") => {
+                        SqlValue::Text(value.to_string())
+                    }
+                    (Some(Value::Number(number)), "real") => {
+                        SqlValue::Real(number.as_f64().unwrap_or_default())
+                    }
+                    (Some(value), _) => sql_value(value),
+                })
+                .collect()
+        })
+        .collect();
+    let names = column_names(&keys, keys.len());
+    Ok((names, types, rows))
+}
+
+/// Load a text file into a new table and describe it.
+fn load_table(
+    connection: &Connection,
+    name: &str,
+    path: &Path,
+    format: SourceFormat,
+    header: bool,
+) -> Result<Value, QueryError> {
+    let size = std::fs::metadata(path)
+        .map_err(|e| format!("Failed to stat '{}': {}", path.display(), e))?
+        .len();
+    if size > MAX_TEXT_SOURCE_BYTES {
+        return Err(QueryError::Failed(format!(
+            "'{}' is larger than {} bytes; load it into SQLite first",
+            path.display(),
+            MAX_TEXT_SOURCE_BYTES
+        )));
+    }
+    let (names, types, rows) = match format {
+        SourceFormat::Csv => read_delimited(path, b',', header)?,
+        SourceFormat::Tsv => read_delimited(path, b'\t', header)?,
+        SourceFormat::Jsonl => read_json_lines(path)?,
+        SourceFormat::Sqlite => {
+            return Err(QueryError::Failed(format!(
+                "'{}' is a SQLite database, not a table",
+                path.display()
+            )))
+        }
+    };
+    if names.is_empty() {
+        return Err(QueryError::Failed(format!(
+            "'{}' has no columns",
+            path.display()
+        )));
+    }
+
+    let definitions = names
+        .iter()
+        .zip(&types)
+        .map(|(name, column_type)| {
+            format!("{} {}", quote_identifier(name), column_type.to_uppercase())
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let placeholders = vec!["?"; names.len()].join(", ");
+    let table = quote_identifier(name);
+    let transaction = connection.unchecked_transaction()?;
+    transaction.execute(&format!("CREATE TABLE {} ({})", table, definitions), [])?;
+    {
+        let mut insert =
+            transaction.prepare(&format!("INSERT INTO {} VALUES ({})", table, placeholders))?;
+        for row in &rows {
+            insert.execute(rusqlite::params_from_iter(row.iter()))?;
+        }
+    }
+    transaction.commit()?;
+
+    Ok(json!({
+        "table": name,
+        "path": path.display().to_string(),
+        "format": format.as_str(),
+        "rows": rows.len(),
+        "columns": names
+            .iter()
+            .zip(&types)
+            .map(|(name, column_type)| json!({ "name": name, "type": column_type }))
+            .collect::<Vec<_>>(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{column_names, handle_data_syscall, text_column_type};
+    use crate::device::transfer::BinaryFrameInbox;
+    use gsv::tools::paths::PathResolver;
+    use serde_json::{json, Value};
+    use std::path::PathBuf;
+
+    fn test_workspace(label: &str) -> PathBuf {
+        let workspace =
+            std::env::temp_dir().join(format!("gsv-cli-data-{}-{}", label, uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&workspace).unwrap();
+        workspace
+    }
+
+    async fn query(args: Value, paths: &PathResolver) -> Result<Value, String> {
+        handle_data_syscall("data.query", args, paths, &BinaryFrameInbox::new())
+            .await
+            .unwrap()
+            .map(|(data, _)| data)
+    }
+
+    #[test]
+    fn text_columns_get_the_narrowest_type() {
+        assert_eq!(text_column_type(["1", "", "-2"].into_iter()), "integer");
+        assert_eq!(text_column_type(["1", "2.5"].into_iter()), "real");
+        assert_eq!(text_column_type(["1", "x"].into_iter()), "text");
+        assert_eq!(text_column_type(["inf"].into_iter()), "text");
+        assert_eq!(
+            column_names(&["id".to_string(), "".to_string(), "ID".to_string()], 4),
+            ["id", "column2", "ID_2", "column4"]
+        );
+    }
+
+    #[tokio::test]
+    async fn csv_and_json_lines_load_as_typed_tables() {
+        let workspace = test_workspace("text");
+        std::fs::write(
+            workspace.join("people.csv"),
+            "\u{feff}id,name,score\n1,Ada,9.5\n2,Bob,\n3,\"Cy, Jr\",7\n",
+        )
+        .unwrap();
+        std::fs::write(
+            workspace.join("visits.jsonl"),
+            "{\"person\": 1, \"page\": \"/\"}\n\n{\"person\": 1, \"page\": \"/docs\", \"ok\": true}\n{\"person\": 3, \"page\": \"/\"}\n",
+        )
+        .unwrap();
+        let paths = PathResolver::new(workspace.clone());
+
+        let result = query(
+            json!({
+                "path": "people.csv",
+                "tables": { "visits": "visits.jsonl" },
+                "sql": "SELECT name, score, count(page) AS visits FROM data LEFT JOIN visits ON person = id WHERE id >= ? GROUP BY id ORDER BY id",
+                "params": [2],
+            }),
+            &paths,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            result["columns"],
+            json!([
+                { "name": "name", "type": "text", "declaredType": "TEXT" },
+                { "name": "score", "type": "real", "declaredType": "REAL" },
+                { "name": "visits", "type": "integer" },
+            ])
+        );
+        assert_eq!(
+            result["rows"],
+            json!([["Bob", null, 0], ["Cy, Jr", 7.0, 1]])
+        );
+        assert_eq!(result["tables"][0]["rows"], 3);
+        assert_eq!(
+            result["tables"][1]["columns"][2],
+            json!({ "name": "ok", "type": "integer" })
+        );
+
+        let error = query(
+            json!({ "path": "people.csv", "sql": "DELETE FROM data" }),
+            &paths,
+        )
+        .await
+        .unwrap_err();
+        assert!(error.contains("readonly"), "{}", error);
+
+        std::fs::remove_dir_all(workspace).unwrap();
+    }
+
+    #[tokio::test]
+    async fn sqlite_databases_open_read_only_with_limits() {
+        let workspace = test_workspace("sqlite");
+        let database = workspace.join("app.db");
+        {
+            let connection = rusqlite::Connection::open(&database).unwrap();
+            connection
+                .execute_batch(
+                    "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT, payload BLOB);
+                     INSERT INTO items (name, payload) VALUES ('a', x'0102'), ('b', NULL), ('c', NULL);",
+                )
+                .unwrap();
+        }
+        let paths = PathResolver::new(workspace.clone());
+
+        let result = query(
+            json!({ "path": "app.db", "sql": "SELECT id, payload FROM items ORDER BY id", "maxRows": 2 }),
+            &paths,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result["rows"], json!([[1, "AQI="], [2, null]]));
+        assert_eq!(result["truncated"], true);
+        assert_eq!(result["columns"][1]["type"], "blob");
+
+        let error = query(
+            json!({ "path": "app.db", "sql": "INSERT INTO items (name) VALUES ('d')" }),
+            &paths,
+        )
+        .await
+        .unwrap_err();
+        assert!(error.starts_with("SQL error"), "{}", error);
+
+        let error = query(
+            json!({ "path": "app.db", "sql": "ATTACH DATABASE 'other.db' AS other" }),
+            &paths,
+        )
+        .await
+        .unwrap_err();
+        assert!(error.starts_with("SQL error"), "{}", error);
+
+        let error = query(
+            json!({
+                "path": "app.db",
+                "sql": "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n) SELECT count(*) FROM n",
+                "timeoutMs": 50,
+            }),
+            &paths,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error, "Query timed out after 50 ms");
+
+        std::fs::remove_dir_all(workspace).unwrap();
+    }
+
+    #[tokio::test]
+    async fn large_results_are_sent_as_json_lines() {
+        let workspace = test_workspace("body");
+        std::fs::write(workspace.join("n.tsv"), "n\n1\n2\n").unwrap();
+        let paths = PathResolver::new(workspace.clone());
+        let inbox = BinaryFrameInbox::new();
+
+        let (data, body) = handle_data_syscall(
+            "data.query",
+            json!({ "path": "n.tsv", "sql": "SELECT n, n * 2 AS twice FROM data", "body": true }),
+            &paths,
+            &inbox,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(data.get("rows").is_none());
+        assert_eq!(data["rowCount"], 2);
+        assert_eq!(data["contentType"], "application/x-ndjson");
+        let bytes = body.unwrap().into_bytes(1024).await.unwrap();
+        assert_eq!(String::from_utf8(bytes).unwrap(), "[1,2]\n[2,4]\n");
+
+        std::fs::remove_dir_all(workspace).unwrap();
+    }
+}
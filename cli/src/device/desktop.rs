@@ -0,0 +1,408 @@
+//! Desktop syscalls for a device that is someone's workstation:
+//! `desktop.notify`, `desktop.clipboard.read` and `desktop.clipboard.write`.
+//!
+//! They are served and advertised only with `device.desktop` set. On Linux,
+//! notifications go to the freedesktop notification service on the session
+//! D-Bus, and the clipboard is the Wayland (data-control) or X11 one. Without
+//! a graphical session the calls succeed without doing anything and say why,
+//! so an agent can fall back to plain output.
+
+use serde_json::Value;
+
+/// The syscalls `desktop.*` covers.
+const DESKTOP_CALLS: [&str; 3] = [
+    "desktop.notify",
+    "desktop.clipboard.read",
+    "desktop.clipboard.write",
+];
+
+pub(super) async fn handle_desktop_syscall(
+    call: &str,
+    args: Value,
+    enabled: bool,
+) -> Option<Result<Value, String>> {
+    if !DESKTOP_CALLS.contains(&call) {
+        return None;
+    }
+    if !enabled {
+        return Some(Err(format!(
+            "{} is disabled on this device; set device.desktop to enable it",
+            call
+        )));
+    }
+    #[cfg(target_os = "linux")]
+    {
+        match call {
+            "desktop.notify" => Some(linux::notify(args).await),
+            "desktop.clipboard.read" => Some(linux::read_clipboard().await),
+            _ => Some(linux::write_clipboard(args).await),
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = args;
+        Some(Err(format!("{} is only available on Linux", call)))
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::ffi::OsString;
+    use std::sync::{Mutex, MutexGuard, OnceLock};
+    use std::time::Duration;
+    use tracing::info;
+    use zbus::zvariant::Value as DbusValue;
+
+    const NOTIFICATIONS_SERVICE: &str = "org.freedesktop.Notifications";
+    const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+    /// A notification service started on demand may take a moment; one that
+    /// does not answer in this time is reported as an error.
+    const NOTIFY_TIMEOUT: Duration = Duration::from_secs(10);
+    const APP_NAME: &str = "gsv";
+    const MAX_SUMMARY_CHARS: usize = 256;
+    const MAX_BODY_CHARS: usize = 4096;
+    /// Clipboard text is held in the daemon for as long as it owns the
+    /// selection.
+    const MAX_CLIPBOARD_BYTES: usize = 4 * 1024 * 1024;
+
+    fn parse_args<T: for<'de> Deserialize<'de>>(args: Value) -> Result<T, String> {
+        let args = if args.is_null() { json!({}) } else { args };
+        serde_json::from_value(args).map_err(|e| format!("Invalid arguments: {}", e))
+    }
+
+    #[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+    #[serde(rename_all = "lowercase")]
+    enum Urgency {
+        Low,
+        #[default]
+        Normal,
+        Critical,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(super) struct NotifyArgs {
+        summary: String,
+        #[serde(default)]
+        body: Option<String>,
+        #[serde(default)]
+        urgency: Urgency,
+        /// An icon name from the desktop's theme, or a `file://` URI.
+        #[serde(default)]
+        icon: Option<String>,
+        /// How long the notification stays up; 0 keeps it until dismissed.
+        /// Default: the notification service decides.
+        #[serde(default)]
+        timeout_ms: Option<u32>,
+        /// Replace an earlier notification, by the id it returned.
+        #[serde(default)]
+        replaces_id: Option<u32>,
+    }
+
+    fn truncate(text: &str, max_chars: usize) -> String {
+        match text.char_indices().nth(max_chars) {
+            Some((end, _)) => text.get(..end).unwrap_or(text).to_string(),
+            None => text.to_string(),
+        }
+    }
+
+    fn not_delivered(reason: impl Into<String>) -> Value {
+        let reason = reason.into();
+        info!(event = "desktop.notify.skipped", reason = %reason);
+        json!({ "delivered": false, "reason": reason })
+    }
+
+    pub(super) async fn notify(args: Value) -> Result<Value, String> {
+        let args: NotifyArgs = parse_args(args)?;
+        match zbus::Connection::session().await {
+            Ok(connection) => send_notification(&connection, &args).await,
+            Err(e) => Ok(not_delivered(format!("No D-Bus session bus: {}", e))),
+        }
+    }
+
+    /// Call `Notify` on the notification service reachable over `connection`.
+    pub(super) async fn send_notification(
+        connection: &zbus::Connection,
+        args: &NotifyArgs,
+    ) -> Result<Value, String> {
+        if args.summary.trim().is_empty() {
+            return Err("summary must not be empty".to_string());
+        }
+        let urgency: u8 = match args.urgency {
+            Urgency::Low => 0,
+            Urgency::Normal => 1,
+            Urgency::Critical => 2,
+        };
+        let hints = HashMap::from([("urgency", DbusValue::U8(urgency))]);
+        let expire_timeout = args
+            .timeout_ms
+            .map(|ms| i32::try_from(ms).unwrap_or(i32::MAX))
+            .unwrap_or(-1);
+        let body = (
+            APP_NAME,
+            args.replaces_id.unwrap_or(0),
+            args.icon.as_deref().unwrap_or(""),
+            truncate(&args.summary, MAX_SUMMARY_CHARS),
+            truncate(args.body.as_deref().unwrap_or(""), MAX_BODY_CHARS),
+            Vec::<&str>::new(),
+            hints,
+            expire_timeout,
+        );
+        let call = connection.call_method(
+            Some(NOTIFICATIONS_SERVICE),
+            NOTIFICATIONS_PATH,
+            Some(NOTIFICATIONS_SERVICE),
+            "Notify",
+            &body,
+        );
+        let reply = match tokio::time::timeout(NOTIFY_TIMEOUT, call).await {
+            Ok(Ok(reply)) => reply,
+            Ok(Err(zbus::Error::MethodError(name, _, _)))
+                if name.as_str() == "org.freedesktop.DBus.Error.ServiceUnknown" =>
+            {
+                return Ok(not_delivered("No notification service on the session bus"));
+            }
+            Ok(Err(e)) => return Err(format!("Failed to send notification: {}", e)),
+            Err(_) => {
+                return Err(format!(
+                    "The notification service did not answer within {} s",
+                    NOTIFY_TIMEOUT.as_secs()
+                ))
+            }
+        };
+        let id: u32 = reply
+            .body()
+            .deserialize()
+            .map_err(|e| format!("Unexpected reply from the notification service: {}", e))?;
+        info!(event = "desktop.notify", id);
+        Ok(json!({ "delivered": true, "id": id }))
+    }
+
+    /// Why there is no clipboard to use, judged from the environment.
+    fn headless_reason(env: impl Fn(&str) -> Option<OsString>) -> Option<&'static str> {
+        let set = |name: &str| env(name).is_some_and(|value| !value.is_empty());
+        (!set("WAYLAND_DISPLAY") && !set("DISPLAY"))
+            .then_some("No graphical session: WAYLAND_DISPLAY and DISPLAY are unset")
+    }
+
+    /// One clipboard for the daemon's lifetime: on X11 the text written is
+    /// served from it until another client takes the selection.
+    static CLIPBOARD: OnceLock<Mutex<Option<arboard::Clipboard>>> = OnceLock::new();
+
+    fn clipboard() -> MutexGuard<'static, Option<arboard::Clipboard>> {
+        CLIPBOARD
+            .get_or_init(Default::default)
+            .lock()
+            .expect("clipboard mutex poisoned")
+    }
+
+    /// Run `operation` on the clipboard, or return why there is none.
+    async fn with_clipboard<T: Send + 'static>(
+        operation: impl FnOnce(&mut arboard::Clipboard) -> Result<T, String> + Send + 'static,
+    ) -> Result<Result<T, String>, String> {
+        if let Some(reason) = headless_reason(|name| std::env::var_os(name)) {
+            return Ok(Err(reason.to_string()));
+        }
+        tokio::task::spawn_blocking(move || {
+            let mut guard = clipboard();
+            if guard.is_none() {
+                match arboard::Clipboard::new() {
+                    Ok(opened) => *guard = Some(opened),
+                    Err(e) => return Ok(Err(format!("No clipboard available: {}", e))),
+                }
+            }
+            match guard.as_mut() {
+                Some(opened) => operation(opened).map(Ok),
+                None => Ok(Err("No clipboard available".to_string())),
+            }
+        })
+        .await
+        .map_err(|e| format!("Clipboard task failed: {}", e))?
+    }
+
+    pub(super) async fn read_clipboard() -> Result<Value, String> {
+        let text = with_clipboard(|clipboard| match clipboard.get_text() {
+            Ok(text) => Ok(Some(text)),
+            Err(arboard::Error::ContentNotAvailable) => Ok(None),
+            Err(e) => Err(format!("Failed to read the clipboard: {}", e)),
+        })
+        .await?;
+        Ok(match text {
+            Ok(text) => json!({ "available": true, "text": text }),
+            Err(reason) => json!({ "available": false, "reason": reason }),
+        })
+    }
+
+    #[derive(Deserialize)]
+    struct WriteArgs {
+        text: String,
+    }
+
+    pub(super) async fn write_clipboard(args: Value) -> Result<Value, String> {
+        let args: WriteArgs = parse_args(args)?;
+        let bytes = args.text.len();
+        if bytes > MAX_CLIPBOARD_BYTES {
+            return Err(format!(
+                "Clipboard text is {} bytes; the limit is {}",
+                bytes, MAX_CLIPBOARD_BYTES
+            ));
+        }
+        let written = with_clipboard(move |clipboard| {
+            clipboard
+                .set_text(args.text)
+                .map_err(|e| format!("Failed to write the clipboard: {}", e))
+        })
+        .await?;
+        Ok(match written {
+            Ok(()) => {
+                info!(event = "desktop.clipboard.write", bytes);
+                json!({ "written": true, "bytes": bytes })
+            }
+            Err(reason) => json!({ "written": false, "reason": reason }),
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::sync::Arc;
+        use zbus::connection::Builder;
+
+        type Received = Arc<Mutex<Vec<(u32, String, String, u8, i32)>>>;
+
+        /// Stands in for a desktop's notification daemon.
+        struct Notifications {
+            received: Received,
+        }
+
+        #[zbus::interface(name = "org.freedesktop.Notifications")]
+        impl Notifications {
+            #[expect(clippy::too_many_arguments, reason = "the D-Bus method's signature")]
+            async fn notify(
+                &self,
+                app_name: String,
+                replaces_id: u32,
+                _app_icon: String,
+                summary: String,
+                body: String,
+                _actions: Vec<String>,
+                hints: HashMap<String, zbus::zvariant::OwnedValue>,
+                expire_timeout: i32,
+            ) -> u32 {
+                assert_eq!(app_name, APP_NAME);
+                let urgency = hints
+                    .get("urgency")
+                    .and_then(|value| u8::try_from(value).ok())
+                    .unwrap_or(u8::MAX);
+                let mut received = self.received.lock().unwrap();
+                received.push((replaces_id, summary, body, urgency, expire_timeout));
+                u32::try_from(received.len()).unwrap() + 40
+            }
+        }
+
+        async fn session_pair(received: Received) -> (zbus::Connection, zbus::Connection) {
+            let (service, client) = tokio::net::UnixStream::pair().unwrap();
+            let service = Builder::unix_stream(service)
+                .server(zbus::Guid::generate())
+                .unwrap()
+                .p2p()
+                .serve_at(NOTIFICATIONS_PATH, Notifications { received })
+                .unwrap()
+                .build();
+            let client = Builder::unix_stream(client).p2p().build();
+            tokio::try_join!(service, client).unwrap()
+        }
+
+        fn notify_args(args: Value) -> NotifyArgs {
+            parse_args(args).unwrap()
+        }
+
+        #[tokio::test]
+        async fn notifications_reach_the_session_service() {
+            let received = Received::default();
+            let (_service, client) = session_pair(received.clone()).await;
+
+            let sent = send_notification(
+                &client,
+                &notify_args(json!({ "summary": "Build finished", "body": "All green" })),
+            )
+            .await
+            .unwrap();
+            assert_eq!(sent, json!({ "delivered": true, "id": 41 }));
+
+            let sent = send_notification(
+                &client,
+                &notify_args(json!({
+                    "summary": "Deploy failed",
+                    "urgency": "critical",
+                    "timeoutMs": 0,
+                    "replacesId": 41,
+                })),
+            )
+            .await
+            .unwrap();
+            assert_eq!(sent["id"], 42);
+
+            assert_eq!(
+                *received.lock().unwrap(),
+                vec![
+                    (
+                        0,
+                        "Build finished".to_string(),
+                        "All green".to_string(),
+                        1,
+                        -1
+                    ),
+                    (41, "Deploy failed".to_string(), String::new(), 2, 0),
+                ]
+            );
+
+            let error = send_notification(&client, &notify_args(json!({ "summary": " " })))
+                .await
+                .unwrap_err();
+            assert_eq!(error, "summary must not be empty");
+            assert!(
+                parse_args::<NotifyArgs>(json!({ "summary": "x", "urgency": "loud" }))
+                    .unwrap_err()
+                    .contains("unknown variant")
+            );
+        }
+
+        #[test]
+        fn headless_sessions_have_no_clipboard() {
+            let env = |vars: &'static [(&'static str, &'static str)]| {
+                move |name: &str| {
+                    vars.iter()
+                        .find(|(key, _)| *key == name)
+                        .map(|(_, value)| OsString::from(value))
+                }
+            };
+            assert!(headless_reason(env(&[])).is_some());
+            assert!(headless_reason(env(&[("DISPLAY", "")])).is_some());
+            assert!(headless_reason(env(&[("DISPLAY", ":0")])).is_none());
+            assert!(headless_reason(env(&[("WAYLAND_DISPLAY", "wayland-0")])).is_none());
+            assert_eq!(truncate("héllo", 2), "hé");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::handle_desktop_syscall;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn desktop_calls_need_the_device_flag() {
+        assert!(handle_desktop_syscall("desktop.open", json!({}), true)
+            .await
+            .is_none());
+        let error = handle_desktop_syscall("desktop.clipboard.read", json!({}), false)
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert!(error.contains("device.desktop"));
+    }
+}
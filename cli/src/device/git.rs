@@ -0,0 +1,1383 @@
+//! Structured local git operations: `git.status`, `git.log`, `git.diff`,
+//! `git.branch`, `git.commit`, `git.checkout`, `git.stash` and `git.blame`.
+//!
+//! Each call runs the device's `git` binary with machine-readable output
+//! (porcelain v2, NUL-separated records, fixed formats) and parses it, so
+//! agents do not scrape `shell.exec` text. Diffs have the camelCase shape the
+//! gateway's `repo.diff` returns (`RepoDiffFile` and friends), so one parser
+//! reads diffs from either side.
+
+use gsv::tools::paths::{Access, PathResolver};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+
+const GIT_TIMEOUT: Duration = Duration::from_secs(120);
+const MAX_GIT_OUTPUT_BYTES: u64 = 32 * 1024 * 1024;
+const DEFAULT_LOG_LIMIT: usize = 50;
+const MAX_LOG_LIMIT: usize = 1000;
+const DEFAULT_DIFF_CONTEXT: usize = 3;
+const NULL_HASH: &str = "0000000000000000000000000000000000000000";
+
+pub(super) async fn handle_git_syscall(
+    call: &str,
+    args: Value,
+    paths: &PathResolver,
+) -> Option<Result<Value, String>> {
+    let result = match call {
+        "git.status" => status(args, paths).await,
+        "git.log" => log(args, paths).await,
+        "git.diff" => diff(args, paths).await,
+        "git.branch" => branch(args, paths).await,
+        "git.commit" => commit(args, paths).await,
+        "git.checkout" => checkout(args, paths).await,
+        "git.stash" => stash(args, paths).await,
+        "git.blame" => blame(args, paths).await,
+        _ => return None,
+    };
+    Some(result)
+}
+
+fn parse_args<T: for<'de> Deserialize<'de>>(args: Value) -> Result<T, String> {
+    serde_json::from_value(args).map_err(|e| format!("Invalid arguments: {}", e))
+}
+
+/// The working directory for a call, `cwd` or the workspace.
+fn repository(paths: &PathResolver, cwd: Option<&str>, access: Access) -> Result<PathBuf, String> {
+    paths.resolve(cwd.unwrap_or("."), access)
+}
+
+/// Caller `paths` as pathspecs relative to the repository directory `cwd`.
+/// Each one resolves like any other path, so mounts and read-only rules
+/// apply, and must stay inside `cwd`. The `:(literal)` magic makes `:/`,
+/// `:(top)` and globs plain file names; `GIT_LITERAL_PATHSPECS` would do the
+/// same but stops `stash push --include-untracked` cleaning untracked files.
+fn pathspecs(
+    paths: &PathResolver,
+    cwd: &Path,
+    specs: &[String],
+    access: Access,
+) -> Result<Vec<String>, String> {
+    let root = lexical(cwd);
+    specs
+        .iter()
+        .map(|spec| {
+            let resolved = if spec.starts_with('@') || Path::new(spec).is_absolute() {
+                paths.resolve(spec, access)?
+            } else {
+                paths.resolve(&cwd.join(spec).to_string_lossy(), access)?
+            };
+            let relative = lexical(&resolved)
+                .strip_prefix(&root)
+                .map(Path::to_path_buf)
+                .map_err(|_outside| format!("'{}' is outside the repository directory", spec))?;
+            if relative.as_os_str().is_empty() {
+                return Ok(":(literal).".to_string());
+            }
+            relative
+                .to_str()
+                .map(|relative| format!(":(literal){}", relative))
+                .ok_or_else(|| format!("Not a UTF-8 path: '{}'", spec))
+        })
+        .collect()
+}
+
+/// `path` with `.` and `..` applied, without touching the filesystem.
+fn lexical(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Refuse revisions git would read as options.
+fn revision<'a>(value: &'a str, what: &str) -> Result<&'a str, String> {
+    if value.is_empty() || value.starts_with('-') {
+        return Err(format!("Invalid {}: '{}'", what, value));
+    }
+    Ok(value)
+}
+
+/// Run git in `cwd` and return its standard output. A failing exit status
+/// returns git's error output instead.
+async fn git(cwd: &Path, args: &[&str]) -> Result<String, String> {
+    let subcommand = args.first().copied().unwrap_or("git");
+    let mut child = tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(cwd)
+        .args(["-c", "core.quotepath=off", "-c", "color.ui=false"])
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GIT_EDITOR", "true")
+        .env("GIT_PAGER", "cat")
+        .env("GIT_OPTIONAL_LOCKS", "0")
+        .env("LC_ALL", "C")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                "git is not installed on this device".to_string()
+            } else {
+                format!("Failed to run git: {}", e)
+            }
+        })?;
+    let mut stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| "git stdout unavailable".to_string())?;
+    let mut stderr = child
+        .stderr
+        .take()
+        .ok_or_else(|| "git stderr unavailable".to_string())?;
+    let stderr = tokio::spawn(async move {
+        let mut output = Vec::new();
+        let _ = stderr.read_to_end(&mut output).await;
+        output
+    });
+
+    tokio::time::timeout(GIT_TIMEOUT, async {
+        let mut output = Vec::new();
+        (&mut stdout)
+            .take(MAX_GIT_OUTPUT_BYTES + 1)
+            .read_to_end(&mut output)
+            .await
+            .map_err(|e| format!("Failed to read git output: {}", e))?;
+        if output.len() as u64 > MAX_GIT_OUTPUT_BYTES {
+            return Err(format!(
+                "git {} output exceeds {} bytes; narrow it with paths or a limit",
+                subcommand, MAX_GIT_OUTPUT_BYTES
+            ));
+        }
+        let status = child
+            .wait()
+            .await
+            .map_err(|e| format!("Failed to run git: {}", e))?;
+        let errors = stderr.await.unwrap_or_default();
+        if !status.success() {
+            let errors = String::from_utf8_lossy(&errors).trim().to_string();
+            return Err(if errors.is_empty() {
+                format!("git {} failed with {}", subcommand, status)
+            } else {
+                errors
+            });
+        }
+        Ok(String::from_utf8_lossy(&output).into_owned())
+    })
+    .await
+    .map_err(|_elapsed| format!("git {} timed out", subcommand))?
+}
+
+/// Undo git's C-style quoting of unusual paths in text output.
+fn unquote(path: &str) -> String {
+    let Some(inner) = path
+        .strip_prefix('"')
+        .and_then(|path| path.strip_suffix('"'))
+    else {
+        return path.to_string();
+    };
+    let mut bytes = Vec::with_capacity(inner.len());
+    let mut chars = inner.bytes().peekable();
+    while let Some(byte) = chars.next() {
+        if byte != b'\\' {
+            bytes.push(byte);
+            continue;
+        }
+        match chars.next() {
+            Some(b'n') => bytes.push(b'\n'),
+            Some(b't') => bytes.push(b'\t'),
+            Some(b'r') => bytes.push(b'\r'),
+            Some(b'a') => bytes.push(0x07),
+            Some(b'b') => bytes.push(0x08),
+            Some(b'f') => bytes.push(0x0c),
+            Some(b'v') => bytes.push(0x0b),
+            Some(digit @ b'0'..=b'7') => {
+                let mut value = u32::from(digit - b'0');
+                for _ in 0..2 {
+                    if let Some(next @ b'0'..=b'7') = chars.peek().copied() {
+                        value = value * 8 + u32::from(next - b'0');
+                        chars.next();
+                    }
+                }
+                bytes.push(u8::try_from(value).unwrap_or(b'?'));
+            }
+            Some(other) => bytes.push(other),
+            None => bytes.push(b'\\'),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn change_kind(code: char) -> &'static str {
+    match code {
+        'M' => "modified",
+        'A' => "added",
+        'D' => "deleted",
+        'R' => "renamed",
+        'C' => "copied",
+        'T' => "typechange",
+        'U' => "unmerged",
+        _ => "unknown",
+    }
+}
+
+fn change(code: char, path: &str, orig_path: Option<&str>) -> Value {
+    let mut change = json!({ "path": path, "status": change_kind(code) });
+    if let (Some(orig_path), 'R' | 'C') = (orig_path, code) {
+        change["origPath"] = json!(orig_path);
+    }
+    change
+}
+
+#[derive(Deserialize)]
+struct StatusArgs {
+    #[serde(default)]
+    cwd: Option<String>,
+}
+
+async fn status(args: Value, paths: &PathResolver) -> Result<Value, String> {
+    let args: StatusArgs = parse_args(args)?;
+    let cwd = repository(paths, args.cwd.as_deref(), Access::Read)?;
+    let output = git(
+        &cwd,
+        &[
+            "status",
+            "--porcelain=v2",
+            "--branch",
+            "-z",
+            "--untracked-files=all",
+            "--find-renames",
+        ],
+    )
+    .await?;
+    Ok(parse_status(&output))
+}
+
+/// Parse `git status --porcelain=v2 --branch -z`.
+fn parse_status(output: &str) -> Value {
+    let mut branch = json!({
+        "head": null,
+        "oid": null,
+        "upstream": null,
+        "ahead": 0,
+        "behind": 0,
+        "detached": false,
+    });
+    let mut staged = Vec::new();
+    let mut unstaged = Vec::new();
+    let mut untracked = Vec::new();
+    let mut conflicted = Vec::new();
+
+    let mut records = output.split('\0');
+    while let Some(record) = records.next() {
+        if let Some(header) = record.strip_prefix("# ") {
+            let (key, value) = header.split_once(' ').unwrap_or((header, ""));
+            match key {
+                "branch.oid" if value != "(initial)" => branch["oid"] = json!(value),
+                "branch.head" if value == "(detached)" => branch["detached"] = json!(true),
+                "branch.head" => branch["head"] = json!(value),
+                "branch.upstream" => branch["upstream"] = json!(value),
+                "branch.ab" => {
+                    for count in value.split(' ') {
+                        if let Some(ahead) = count.strip_prefix('+') {
+                            branch["ahead"] = json!(ahead.parse::<u64>().unwrap_or(0));
+                        } else if let Some(behind) = count.strip_prefix('-') {
+                            branch["behind"] = json!(behind.parse::<u64>().unwrap_or(0));
+                        }
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        let (kind, rest) = record.split_at_checked(2).unwrap_or(("", record));
+        let (xy, path, orig_path) = match kind {
+            // XY sub mH mI mW hH hI path
+            "1 " => {
+                let fields: Vec<&str> = rest.splitn(8, ' ').collect();
+                (fields.first().copied(), fields.get(7).copied(), None)
+            }
+            // XY sub mH mI mW hH hI Xscore path, then the original path
+            "2 " => {
+                let fields: Vec<&str> = rest.splitn(9, ' ').collect();
+                (
+                    fields.first().copied(),
+                    fields.get(8).copied(),
+                    records.next(),
+                )
+            }
+            // XY sub m1 m2 m3 mW h1 h2 h3 path
+            "u " => {
+                let fields: Vec<&str> = rest.splitn(10, ' ').collect();
+                if let (Some(xy), Some(path)) = (fields.first(), fields.get(9)) {
+                    conflicted.push(json!({ "path": path, "status": xy }));
+                }
+                continue;
+            }
+            "? " => {
+                untracked.push(json!(rest));
+                continue;
+            }
+            _ => continue,
+        };
+        let (Some(xy), Some(path)) = (xy, path) else {
+            continue;
+        };
+        let mut codes = xy.chars();
+        if let Some(code) = codes.next().filter(|code| *code != '.') {
+            staged.push(change(code, path, orig_path));
+        }
+        if let Some(code) = codes.next().filter(|code| *code != '.') {
+            unstaged.push(change(code, path, orig_path));
+        }
+    }
+
+    let clean =
+        staged.is_empty() && unstaged.is_empty() && untracked.is_empty() && conflicted.is_empty();
+    json!({
+        "branch": branch,
+        "staged": staged,
+        "unstaged": unstaged,
+        "untracked": untracked,
+        "conflicted": conflicted,
+        "clean": clean,
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LogArgs {
+    #[serde(default)]
+    cwd: Option<String>,
+    #[serde(default, rename = "ref")]
+    revision: Option<String>,
+    #[serde(default)]
+    paths: Vec<String>,
+    #[serde(default)]
+    limit: Option<usize>,
+    #[serde(default)]
+    skip: Option<usize>,
+    #[serde(default)]
+    files: Option<bool>,
+}
+
+/// Fields of one commit, each ended by a unit separator, after a record
+/// separator. The body is free text, so the file list follows the last
+/// separator.
+const LOG_FORMAT: &str =
+    "--format=%x1e%H%x1f%P%x1f%an%x1f%ae%x1f%at%x1f%cn%x1f%ce%x1f%ct%x1f%s%x1f%b%x1f";
+
+async fn log(args: Value, paths: &PathResolver) -> Result<Value, String> {
+    let args: LogArgs = parse_args(args)?;
+    let cwd = repository(paths, args.cwd.as_deref(), Access::Read)?;
+    let specs = pathspecs(paths, &cwd, &args.paths, Access::Read)?;
+    let limit = format!(
+        "--max-count={}",
+        args.limit
+            .unwrap_or(DEFAULT_LOG_LIMIT)
+            .clamp(1, MAX_LOG_LIMIT)
+    );
+    let skip = format!("--skip={}", args.skip.unwrap_or(0));
+    let mut command = vec!["log", "--no-color", LOG_FORMAT, &limit, &skip];
+    if args.files.unwrap_or(true) {
+        command.push("--name-status");
+    }
+    if let Some(revision_arg) = args.revision.as_deref() {
+        command.push(revision(revision_arg, "ref")?);
+    }
+    command.push("--");
+    command.extend(specs.iter().map(String::as_str));
+    let output = git(&cwd, &command).await?;
+    Ok(json!({ "commits": parse_log(&output) }))
+}
+
+fn parse_log(output: &str) -> Vec<Value> {
+    output
+        .split('\x1e')
+        .filter_map(|record| {
+            let fields: Vec<&str> = record.splitn(11, '\x1f').collect();
+            let field = |index: usize| fields.get(index).copied().unwrap_or_default();
+            let hash = fields.first().filter(|hash| !hash.is_empty())?;
+            let time = |index: usize| field(index).parse::<i64>().unwrap_or(0);
+            let files: Vec<Value> = field(10)
+                .lines()
+                .filter_map(|line| {
+                    let mut parts = line.split('\t');
+                    let code = parts.next()?.chars().next()?;
+                    let first = unquote(parts.next()?);
+                    Some(match parts.next() {
+                        Some(second) => change(code, &unquote(second), Some(&first)),
+                        None => change(code, &first, None),
+                    })
+                })
+                .collect();
+            Some(json!({
+                "hash": hash,
+                "parents": field(1).split_whitespace().collect::<Vec<_>>(),
+                "author": { "name": field(2), "email": field(3), "time": time(4) },
+                "committer": { "name": field(5), "email": field(6), "time": time(7) },
+                "subject": field(8),
+                "body": field(9).trim_end(),
+                "files": files,
+            }))
+        })
+        .collect()
+}
+
+/// `RepoDiffFile.status` in `repo.diff` results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum DiffStatus {
+    Added,
+    Deleted,
+    Modified,
+}
+
+/// `RepoDiffFile` in `repo.diff` results: ripgit's `diff::FileDiff` with
+/// camelCase fields (`oldHash`) where ripgit itself uses snake_case.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FileDiff {
+    path: String,
+    status: DiffStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    old_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hunks: Option<Vec<Hunk>>,
+}
+
+/// `RepoDiffHunk` in `repo.diff` results (`oldStart`, `oldCount`, ...):
+/// starts are 1-based, and 0 for an empty range.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Hunk {
+    old_start: usize,
+    old_count: usize,
+    new_start: usize,
+    new_count: usize,
+    lines: Vec<DiffLine>,
+}
+
+/// `RepoDiffLine` in `repo.diff` results; `content` keeps its line ending.
+#[derive(Debug, Clone, Serialize)]
+struct DiffLine {
+    tag: &'static str, // "context", "add", "delete", "binary"
+    content: String,
+}
+
+/// `RepoDiffStats` in `repo.diff` results.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DiffStats {
+    files_changed: usize,
+    additions: usize,
+    deletions: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DiffArgs {
+    #[serde(default)]
+    cwd: Option<String>,
+    #[serde(default)]
+    staged: bool,
+    #[serde(default)]
+    from: Option<String>,
+    #[serde(default)]
+    to: Option<String>,
+    #[serde(default)]
+    paths: Vec<String>,
+    #[serde(default)]
+    context: Option<usize>,
+}
+
+async fn diff(args: Value, paths: &PathResolver) -> Result<Value, String> {
+    let args: DiffArgs = parse_args(args)?;
+    let cwd = repository(paths, args.cwd.as_deref(), Access::Read)?;
+    let specs = pathspecs(paths, &cwd, &args.paths, Access::Read)?;
+    let context = format!("-U{}", args.context.unwrap_or(DEFAULT_DIFF_CONTEXT));
+    let mut command = vec![
+        "diff",
+        "--no-color",
+        "--no-ext-diff",
+        "--no-textconv",
+        "--no-renames",
+        "--full-index",
+        "--src-prefix=a/",
+        "--dst-prefix=b/",
+        &context,
+    ];
+    match (args.from.as_deref(), args.to.as_deref()) {
+        (_, Some(_)) if args.staged => {
+            return Err("git.diff takes staged or to, not both".to_string());
+        }
+        (None, Some(_)) => return Err("git.diff needs from when to is set".to_string()),
+        (from, to) => {
+            if args.staged {
+                command.push("--cached");
+            }
+            if let Some(from) = from {
+                command.push(revision(from, "from")?);
+            }
+            if let Some(to) = to {
+                command.push(revision(to, "to")?);
+            }
+        }
+    }
+    command.push("--");
+    command.extend(specs.iter().map(String::as_str));
+    let output = git(&cwd, &command).await?;
+
+    let files = parse_diff(&output);
+    let (additions, deletions) = files
+        .iter()
+        .flat_map(|file| file.hunks.iter().flatten())
+        .flat_map(|hunk| &hunk.lines)
+        .fold((0, 0), |(additions, deletions), line| match line.tag {
+            "add" => (additions + 1, deletions),
+            "delete" => (additions, deletions + 1),
+            _ => (additions, deletions),
+        });
+    let stats = DiffStats {
+        files_changed: files.len(),
+        additions,
+        deletions,
+    };
+    Ok(json!({ "files": files, "stats": stats }))
+}
+
+/// The path in a `diff --git a/<path> b/<path>` line without renames.
+fn diff_git_path(rest: &str) -> String {
+    if rest.starts_with('"') {
+        let mut escaped = false;
+        for (index, byte) in rest.bytes().enumerate().skip(1) {
+            match byte {
+                b'\\' if !escaped => escaped = true,
+                b'"' if !escaped => {
+                    let quoted = unquote(rest.get(..=index).unwrap_or(rest));
+                    return quoted.strip_prefix("a/").unwrap_or(&quoted).to_string();
+                }
+                _ => escaped = false,
+            }
+        }
+        return unquote(rest);
+    }
+    let length = rest.len().saturating_sub(5) / 2;
+    rest.get(2..2 + length).unwrap_or(rest).to_string()
+}
+
+fn diff_header_path(path: &str, prefix: &str) -> Option<String> {
+    let path = unquote(path.trim_end_matches('\t'));
+    (path != "/dev/null").then(|| path.strip_prefix(prefix).unwrap_or(&path).to_string())
+}
+
+fn hunk_range(range: &str) -> Option<(usize, usize)> {
+    let (start, count) = match range.split_once(',') {
+        Some((start, count)) => (start.parse().ok()?, count.parse().ok()?),
+        None => (range.parse().ok()?, 1),
+    };
+    Some((if count == 0 { 0 } else { start }, count))
+}
+
+fn hunk_header(line: &str) -> Option<Hunk> {
+    let ranges = line.strip_prefix("@@ -")?.split_once(" @@")?.0;
+    let (old, new) = ranges.split_once(" +")?;
+    let (old_start, old_count) = hunk_range(old)?;
+    let (new_start, new_count) = hunk_range(new)?;
+    Some(Hunk {
+        old_start,
+        old_count,
+        new_start,
+        new_count,
+        lines: Vec::new(),
+    })
+}
+
+fn object_hash(hash: &str) -> Option<String> {
+    (hash != NULL_HASH && !hash.is_empty()).then(|| hash.to_string())
+}
+
+/// Parse `git diff` patch output into ripgit-shaped file diffs.
+fn parse_diff(output: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    let (mut old_remaining, mut new_remaining) = (0usize, 0usize);
+    for line in output.split('\n') {
+        if old_remaining > 0 || new_remaining > 0 {
+            let hunk = files
+                .last_mut()
+                .and_then(|file| file.hunks.as_mut())
+                .and_then(|hunks| hunks.last_mut());
+            let (Some(hunk), Some(tag)) = (hunk, line.chars().next()) else {
+                old_remaining = 0;
+                new_remaining = 0;
+                continue;
+            };
+            let tag = match tag {
+                ' ' => {
+                    old_remaining = old_remaining.saturating_sub(1);
+                    new_remaining = new_remaining.saturating_sub(1);
+                    "context"
+                }
+                '+' => {
+                    new_remaining = new_remaining.saturating_sub(1);
+                    "add"
+                }
+                '-' => {
+                    old_remaining = old_remaining.saturating_sub(1);
+                    "delete"
+                }
+                '\\' => {
+                    if let Some(last) = hunk.lines.last_mut() {
+                        last.content.pop();
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            hunk.lines.push(DiffLine {
+                tag,
+                content: format!("{}\n", line.get(1..).unwrap_or_default()),
+            });
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            files.push(FileDiff {
+                path: diff_git_path(rest),
+                status: DiffStatus::Modified,
+                old_hash: None,
+                new_hash: None,
+                hunks: Some(Vec::new()),
+            });
+            continue;
+        }
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+        if line.starts_with("new file mode") {
+            file.status = DiffStatus::Added;
+        } else if line.starts_with("deleted file mode") {
+            file.status = DiffStatus::Deleted;
+        } else if let Some(hashes) = line.strip_prefix("index ") {
+            let hashes = hashes.split(' ').next().unwrap_or_default();
+            if let Some((old, new)) = hashes.split_once("..") {
+                file.old_hash = object_hash(old);
+                file.new_hash = object_hash(new);
+            }
+        } else if let Some(path) = line.strip_prefix("--- ") {
+            if let Some(path) = diff_header_path(path, "a/") {
+                file.path = path;
+            }
+        } else if let Some(path) = line.strip_prefix("+++ ") {
+            if let Some(path) = diff_header_path(path, "b/") {
+                file.path = path;
+            }
+        } else if line.starts_with("Binary files ") {
+            file.hunks = Some(vec![Hunk {
+                old_start: 0,
+                old_count: 0,
+                new_start: 0,
+                new_count: 0,
+                lines: vec![DiffLine {
+                    tag: "binary",
+                    content: "Binary files differ".to_string(),
+                }],
+            }]);
+        } else if let Some(hunk) = hunk_header(line) {
+            old_remaining = hunk.old_count;
+            new_remaining = hunk.new_count;
+            file.hunks.get_or_insert_with(Vec::new).push(hunk);
+        } else if line.starts_with('\\') {
+            // "No newline at end of file" after the last line of a hunk.
+            if let Some(last) = file
+                .hunks
+                .as_mut()
+                .and_then(|hunks| hunks.last_mut())
+                .and_then(|hunk| hunk.lines.last_mut())
+            {
+                last.content.pop();
+            }
+        }
+    }
+    files
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BranchArgs {
+    #[serde(default)]
+    cwd: Option<String>,
+    #[serde(default)]
+    all: bool,
+}
+
+async fn branch(args: Value, paths: &PathResolver) -> Result<Value, String> {
+    let args: BranchArgs = parse_args(args)?;
+    let cwd = repository(paths, args.cwd.as_deref(), Access::Read)?;
+    let mut command = vec![
+        "for-each-ref",
+        "--format=%(HEAD)%1f%(refname)%1f%(objectname)%1f%(upstream:short)%1f%(upstream:track,nobracket)%1f%(subject)",
+        "refs/heads",
+    ];
+    if args.all {
+        command.push("refs/remotes");
+    }
+    let output = git(&cwd, &command).await?;
+    Ok(parse_branches(&output))
+}
+
+fn parse_branches(output: &str) -> Value {
+    let mut current = None;
+    let mut branches = Vec::new();
+    for line in output.lines() {
+        let fields: Vec<&str> = line.splitn(6, '\x1f').collect();
+        let field = |index: usize| fields.get(index).copied().unwrap_or_default();
+        let refname = field(1);
+        let (name, remote) = if let Some(name) = refname.strip_prefix("refs/heads/") {
+            (name, false)
+        } else if let Some(name) = refname.strip_prefix("refs/remotes/") {
+            if name.ends_with("/HEAD") {
+                continue;
+            }
+            (name, true)
+        } else {
+            continue;
+        };
+        let is_current = field(0) == "*";
+        if is_current {
+            current = Some(name.to_string());
+        }
+        let (mut ahead, mut behind) = (0u64, 0u64);
+        for part in field(4).split(", ") {
+            if let Some(count) = part.strip_prefix("ahead ") {
+                ahead = count.parse().unwrap_or(0);
+            } else if let Some(count) = part.strip_prefix("behind ") {
+                behind = count.parse().unwrap_or(0);
+            }
+        }
+        let upstream = Some(field(3)).filter(|upstream| !upstream.is_empty());
+        branches.push(json!({
+            "name": name,
+            "remote": remote,
+            "current": is_current,
+            "commit": field(2),
+            "upstream": upstream,
+            "ahead": ahead,
+            "behind": behind,
+            "upstreamGone": field(4) == "gone",
+            "subject": field(5),
+        }));
+    }
+    json!({
+        "current": current,
+        "detached": current.is_none(),
+        "branches": branches,
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CommitArgs {
+    #[serde(default)]
+    cwd: Option<String>,
+    message: String,
+    #[serde(default)]
+    all: bool,
+    #[serde(default)]
+    paths: Vec<String>,
+    #[serde(default)]
+    amend: bool,
+    #[serde(default)]
+    allow_empty: bool,
+}
+
+async fn commit(args: Value, paths: &PathResolver) -> Result<Value, String> {
+    let args: CommitArgs = parse_args(args)?;
+    if args.message.trim().is_empty() {
+        return Err("message must not be empty".to_string());
+    }
+    let cwd = repository(paths, args.cwd.as_deref(), Access::Write)?;
+    let specs = pathspecs(paths, &cwd, &args.paths, Access::Write)?;
+    if !specs.is_empty() {
+        let mut add = vec!["add", "--all", "--"];
+        add.extend(specs.iter().map(String::as_str));
+        git(&cwd, &add).await?;
+    }
+    let mut command = vec!["commit", "--quiet", "--message", &args.message];
+    if args.all {
+        command.push("--all");
+    }
+    if args.amend {
+        command.push("--amend");
+    }
+    if args.allow_empty {
+        command.push("--allow-empty");
+    }
+    git(&cwd, &command).await?;
+
+    let output = git(
+        &cwd,
+        &[
+            "log",
+            "--no-color",
+            LOG_FORMAT,
+            "--max-count=1",
+            "--name-status",
+        ],
+    )
+    .await?;
+    let commit = parse_log(&output).into_iter().next().unwrap_or(Value::Null);
+    Ok(json!({ "commit": commit }))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckoutArgs {
+    #[serde(default)]
+    cwd: Option<String>,
+    #[serde(default, rename = "ref")]
+    revision: Option<String>,
+    #[serde(default)]
+    create: bool,
+    #[serde(default)]
+    paths: Vec<String>,
+}
+
+async fn checkout(args: Value, paths: &PathResolver) -> Result<Value, String> {
+    let args: CheckoutArgs = parse_args(args)?;
+    let cwd = repository(paths, args.cwd.as_deref(), Access::Write)?;
+    let specs = pathspecs(paths, &cwd, &args.paths, Access::Write)?;
+    let mut command = vec!["checkout", "--quiet"];
+    if args.paths.is_empty() {
+        let revision_arg = args
+            .revision
+            .as_deref()
+            .ok_or_else(|| "git.checkout requires ref or paths".to_string())?;
+        if args.create {
+            command.push("-b");
+        }
+        command.push(revision(revision_arg, "ref")?);
+    } else {
+        if args.create {
+            return Err("git.checkout cannot create a branch while restoring paths".to_string());
+        }
+        if let Some(revision_arg) = args.revision.as_deref() {
+            command.push(revision(revision_arg, "ref")?);
+        }
+        command.push("--");
+        command.extend(specs.iter().map(String::as_str));
+    }
+    git(&cwd, &command).await?;
+
+    let output = git(
+        &cwd,
+        &[
+            "status",
+            "--porcelain=v2",
+            "--branch",
+            "-z",
+            "--untracked-files=no",
+        ],
+    )
+    .await?;
+    let mut result = parse_status(&output);
+    result["restored"] = json!(args.paths);
+    Ok(result)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum StashAction {
+    #[default]
+    List,
+    Push,
+    Pop,
+    Apply,
+    Drop,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StashArgs {
+    #[serde(default)]
+    cwd: Option<String>,
+    #[serde(default)]
+    action: StashAction,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    index: Option<usize>,
+    #[serde(default)]
+    include_untracked: bool,
+    #[serde(default)]
+    paths: Vec<String>,
+}
+
+async fn stash_list(cwd: &Path) -> Result<Vec<Value>, String> {
+    let output = git(cwd, &["stash", "list", "--format=%gd%x1f%H%x1f%gs"]).await?;
+    Ok(output
+        .lines()
+        .enumerate()
+        .map(|(index, line)| {
+            let mut fields = line.splitn(3, '\x1f');
+            json!({
+                "index": index,
+                "ref": fields.next().unwrap_or_default(),
+                "commit": fields.next().unwrap_or_default(),
+                "message": fields.next().unwrap_or_default(),
+            })
+        })
+        .collect())
+}
+
+async fn stash(args: Value, paths: &PathResolver) -> Result<Value, String> {
+    let args: StashArgs = parse_args(args)?;
+    let access = if args.action == StashAction::List {
+        Access::Read
+    } else {
+        Access::Write
+    };
+    let cwd = repository(paths, args.cwd.as_deref(), access)?;
+    let stash_ref = args.index.map(|index| format!("stash@{{{}}}", index));
+    match args.action {
+        StashAction::List => Ok(json!({ "stashes": stash_list(&cwd).await? })),
+        StashAction::Push => {
+            let mut command = vec!["stash", "push", "--quiet"];
+            if args.include_untracked {
+                command.push("--include-untracked");
+            }
+            if let Some(message) = args.message.as_deref() {
+                command.extend(["--message", message]);
+            }
+            let specs = pathspecs(paths, &cwd, &args.paths, Access::Write)?;
+            if !specs.is_empty() {
+                command.push("--");
+                command.extend(specs.iter().map(String::as_str));
+            }
+            let before = stash_list(&cwd).await?.len();
+            git(&cwd, &command).await?;
+            let stashes = stash_list(&cwd).await?;
+            let created = stashes.len() > before;
+            Ok(json!({
+                "created": created,
+                "stash": created.then(|| stashes.first().cloned()).flatten(),
+            }))
+        }
+        StashAction::Pop | StashAction::Apply | StashAction::Drop => {
+            let action = match args.action {
+                StashAction::Pop => "pop",
+                StashAction::Apply => "apply",
+                _ => "drop",
+            };
+            let mut command = vec!["stash", action, "--quiet"];
+            if let Some(stash_ref) = stash_ref.as_deref() {
+                command.push(stash_ref);
+            }
+            git(&cwd, &command).await?;
+            Ok(json!({
+                "action": action,
+                "ref": stash_ref.unwrap_or_else(|| "stash@{0}".to_string()),
+                "stashes": stash_list(&cwd).await?,
+            }))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlameArgs {
+    path: String,
+    #[serde(default, rename = "ref")]
+    revision: Option<String>,
+    #[serde(default)]
+    start_line: Option<usize>,
+    #[serde(default)]
+    end_line: Option<usize>,
+}
+
+async fn blame(args: Value, paths: &PathResolver) -> Result<Value, String> {
+    let args: BlameArgs = parse_args(args)?;
+    let path = paths.resolve(&args.path, Access::Read)?;
+    let (Some(cwd), Some(file_name)) = (path.parent(), path.file_name()) else {
+        return Err(format!("Not a file: '{}'", path.display()));
+    };
+    let file_name = file_name
+        .to_str()
+        .ok_or_else(|| format!("Not a UTF-8 path: '{}'", path.display()))?;
+    let range = match (args.start_line, args.end_line) {
+        (None, None) => None,
+        (start, end) => Some(format!(
+            "-L{},{}",
+            start.unwrap_or(1).max(1),
+            end.map(|end| end.to_string()).unwrap_or_default()
+        )),
+    };
+    let mut command = vec!["blame", "--porcelain"];
+    if let Some(range) = range.as_deref() {
+        command.push(range);
+    }
+    if let Some(revision_arg) = args.revision.as_deref() {
+        command.push(revision(revision_arg, "ref")?);
+    }
+    command.extend(["--", file_name]);
+    let output = git(cwd, &command).await?;
+    Ok(json!({
+        "path": path.display().to_string(),
+        "lines": parse_blame(&output),
+    }))
+}
+
+/// Parse `git blame --porcelain`, where commit details only follow the
+/// first line from each commit.
+fn parse_blame(output: &str) -> Vec<Value> {
+    let mut commits: HashMap<String, HashMap<&str, &str>> = HashMap::new();
+    let mut lines = Vec::new();
+    let mut current: Option<(String, u64)> = None;
+    for line in output.lines() {
+        if let Some(content) = line.strip_prefix('\t') {
+            let Some((hash, number)) = current.take() else {
+                continue;
+            };
+            let details = commits.get(&hash);
+            let detail = |key: &str| details.and_then(|details| details.get(key)).copied();
+            lines.push(json!({
+                "line": number,
+                "commit": hash,
+                "author": detail("author"),
+                "authorMail": detail("author-mail")
+                    .map(|mail| mail.trim_start_matches('<').trim_end_matches('>')),
+                "authorTime": detail("author-time").and_then(|time| time.parse::<i64>().ok()),
+                "summary": detail("summary"),
+                "content": content,
+            }));
+            continue;
+        }
+        let mut fields = line.split(' ');
+        let first = fields.next().unwrap_or_default();
+        if first.len() == 40 && first.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            let number = fields.nth(1).and_then(|number| number.parse().ok());
+            if let Some(number) = number {
+                commits.entry(first.to_string()).or_default();
+                current = Some((first.to_string(), number));
+            }
+            continue;
+        }
+        if let Some((hash, _)) = &current {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            if let Some(details) = commits.get_mut(hash) {
+                details.insert(key, value);
+            }
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        handle_git_syscall, parse_blame, parse_diff, parse_log, parse_status, unquote, DiffStatus,
+    };
+    use gsv::tools::paths::PathResolver;
+    use serde_json::{json, Value};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn status_reports_staged_unstaged_renames_and_untracked() {
+        let output = [
+            "# branch.oid 1234",
+            "# branch.head main",
+            "# branch.upstream origin/main",
+            "# branch.ab +2 -1",
+            "1 M. N... 100644 100644 100644 aaa bbb src/lib.rs",
+            "1 .M N... 100644 100644 100644 aaa bbb with space.txt",
+            "2 R. N... 100644 100644 100644 aaa bbb R100 new name.rs",
+            "old name.rs",
+            "u UU N... 100644 100644 100644 100644 a b c conflict.rs",
+            "? notes/todo.md",
+            "",
+        ]
+        .join("\0");
+        let status = parse_status(&output);
+
+        assert_eq!(status["branch"]["head"], "main");
+        assert_eq!(status["branch"]["upstream"], "origin/main");
+        assert_eq!(status["branch"]["ahead"], 2);
+        assert_eq!(status["branch"]["behind"], 1);
+        assert_eq!(
+            status["staged"],
+            json!([
+                { "path": "src/lib.rs", "status": "modified" },
+                { "path": "new name.rs", "status": "renamed", "origPath": "old name.rs" },
+            ])
+        );
+        assert_eq!(
+            status["unstaged"],
+            json!([{ "path": "with space.txt", "status": "modified" }])
+        );
+        assert_eq!(status["untracked"], json!(["notes/todo.md"]));
+        assert_eq!(status["conflicted"][0]["path"], "conflict.rs");
+        assert_eq!(status["clean"], false);
+
+        let detached = parse_status("# branch.oid (initial)\0# branch.head (detached)\0");
+        assert_eq!(detached["branch"]["detached"], true);
+        assert_eq!(detached["branch"]["oid"], Value::Null);
+        assert_eq!(detached["clean"], true);
+    }
+
+    #[test]
+    fn diffs_match_ripgit_shapes() {
+        let output = "\
+diff --git a/src/a.rs b/src/a.rs
+index 1111111111111111111111111111111111111111..2222222222222222222222222222222222222222 100644
+--- a/src/a.rs
++++ b/src/a.rs
+@@ -1,3 +1,3 @@ fn main
+ keep
+--- removed dashes
++++ added pluses
+ end
+\\ No newline at end of file
+diff --git a/new.txt b/new.txt
+new file mode 100644
+index 0000000000000000000000000000000000000000..3333333333333333333333333333333333333333
+--- /dev/null
++++ b/new.txt
+@@ -0,0 +1 @@
++hello
+diff --git a/img.png b/img.png
+deleted file mode 100644
+index 4444444444444444444444444444444444444444..0000000000000000000000000000000000000000
+Binary files a/img.png and /dev/null differ
+";
+        let files = parse_diff(output);
+        assert_eq!(files.len(), 3);
+
+        assert_eq!(files[0].path, "src/a.rs");
+        assert_eq!(files[0].status, DiffStatus::Modified);
+        let value = serde_json::to_value(&files[0]).unwrap();
+        assert_eq!(value["oldHash"], "1111111111111111111111111111111111111111");
+        assert_eq!(
+            value["hunks"],
+            json!([{
+                "oldStart": 1, "oldCount": 3, "newStart": 1, "newCount": 3,
+                "lines": [
+                    { "tag": "context", "content": "keep\n" },
+                    { "tag": "delete", "content": "-- removed dashes\n" },
+                    { "tag": "add", "content": "++ added pluses\n" },
+                    { "tag": "context", "content": "end" },
+                ],
+            }])
+        );
+
+        let added = serde_json::to_value(&files[1]).unwrap();
+        assert_eq!(added["status"], "added");
+        assert!(added.get("oldHash").is_none());
+        assert_eq!(added["hunks"][0]["oldStart"], 0);
+        assert_eq!(added["hunks"][0]["newStart"], 1);
+
+        let deleted = serde_json::to_value(&files[2]).unwrap();
+        assert_eq!(deleted["status"], "deleted");
+        assert!(deleted.get("newHash").is_none());
+        assert_eq!(deleted["hunks"][0]["lines"][0]["tag"], "binary");
+    }
+
+    #[test]
+    fn log_and_blame_parse() {
+        let output = "\x1eabc\x1fp1 p2\x1fAda\x1fada@example.com\x1f10\x1fBob\x1fbob@example.com\x1f20\x1fSubject\x1fBody line\n\x1f\n\nM\tsrc/a.rs\nR087\told.rs\t\"new\\tname.rs\"\n";
+        let commits = parse_log(output);
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0]["parents"], json!(["p1", "p2"]));
+        assert_eq!(commits[0]["author"]["time"], 10);
+        assert_eq!(commits[0]["body"], "Body line");
+        assert_eq!(
+            commits[0]["files"],
+            json!([
+                { "path": "src/a.rs", "status": "modified" },
+                { "path": "new\tname.rs", "status": "renamed", "origPath": "old.rs" },
+            ])
+        );
+        assert_eq!(unquote("\"caf\\303\\251\""), "café");
+
+        let hash = "a".repeat(40);
+        let blame = format!(
+            "{hash} 1 1 2\nauthor Ada\nauthor-mail <ada@example.com>\nauthor-time 10\nsummary First\nfilename f\n\tone\n{hash} 2 2\n\ttwo\n"
+        );
+        let lines = parse_blame(&blame);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1]["line"], 2);
+        assert_eq!(lines[1]["author"], "Ada");
+        assert_eq!(lines[1]["authorMail"], "ada@example.com");
+        assert_eq!(lines[1]["content"], "two");
+    }
+
+    fn run(cwd: &Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .arg("-C")
+            .arg(cwd)
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    async fn call(name: &str, args: Value, paths: &PathResolver) -> Value {
+        handle_git_syscall(name, args, paths)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn syscalls_drive_a_real_repository() {
+        let workspace: PathBuf =
+            std::env::temp_dir().join(format!("gsv-cli-git-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&workspace).unwrap();
+        run(&workspace, &["init", "--quiet", "--initial-branch=main"]);
+        run(&workspace, &["config", "user.name", "Test"]);
+        run(&workspace, &["config", "user.email", "test@example.com"]);
+        run(&workspace, &["config", "commit.gpgsign", "false"]);
+        std::fs::write(workspace.join("a.txt"), "one\ntwo\n").unwrap();
+        let paths = PathResolver::new(workspace.clone());
+
+        let committed = call(
+            "git.commit",
+            json!({ "message": "Add a", "paths": ["a.txt"] }),
+            &paths,
+        )
+        .await;
+        assert_eq!(committed["commit"]["subject"], "Add a");
+        assert_eq!(committed["commit"]["files"][0]["status"], "added");
+
+        std::fs::write(workspace.join("a.txt"), "one\n2\n").unwrap();
+        std::fs::write(workspace.join("b.txt"), "b\n").unwrap();
+        let status = call("git.status", json!({}), &paths).await;
+        assert_eq!(status["branch"]["head"], "main");
+        assert_eq!(status["unstaged"][0]["path"], "a.txt");
+        assert_eq!(status["untracked"], json!(["b.txt"]));
+
+        let diff = call("git.diff", json!({}), &paths).await;
+        assert_eq!(diff["stats"]["additions"], 1);
+        assert_eq!(diff["files"][0]["hunks"][0]["lines"][2]["content"], "2\n");
+
+        let stashed = call(
+            "git.stash",
+            json!({ "action": "push", "message": "wip", "includeUntracked": true }),
+            &paths,
+        )
+        .await;
+        assert_eq!(stashed["created"], true);
+        assert_eq!(call("git.status", json!({}), &paths).await["clean"], true);
+        call("git.stash", json!({ "action": "pop" }), &paths).await;
+        assert_eq!(
+            call("git.stash", json!({}), &paths).await["stashes"],
+            json!([])
+        );
+
+        let switched = call(
+            "git.checkout",
+            json!({ "ref": "feature", "create": true }),
+            &paths,
+        )
+        .await;
+        assert_eq!(switched["branch"]["head"], "feature");
+        let branches = call("git.branch", json!({}), &paths).await;
+        assert_eq!(branches["current"], "feature");
+        assert_eq!(branches["branches"].as_array().unwrap().len(), 2);
+
+        call("git.checkout", json!({ "paths": ["a.txt"] }), &paths).await;
+        let log = call("git.log", json!({ "paths": ["a.txt"] }), &paths).await;
+        assert_eq!(log["commits"].as_array().unwrap().len(), 1);
+        let blame = call(
+            "git.blame",
+            json!({ "path": "a.txt", "startLine": 2 }),
+            &paths,
+        )
+        .await;
+        assert_eq!(
+            blame["lines"],
+            json!([{
+                "line": 2,
+                "commit": log["commits"][0]["hash"],
+                "author": "Test",
+                "authorMail": "test@example.com",
+                "authorTime": log["commits"][0]["author"]["time"],
+                "summary": "Add a",
+                "content": "two",
+            }])
+        );
+
+        let error = handle_git_syscall("git.log", json!({ "ref": "--output=x" }), &paths)
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(error, "Invalid ref: '--output=x'");
+
+        std::fs::remove_dir_all(workspace).unwrap();
+    }
+
+    #[tokio::test]
+    async fn pathspecs_stay_inside_cwd_and_are_literal() {
+        let workspace: PathBuf =
+            std::env::temp_dir().join(format!("gsv-cli-git-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(workspace.join("sub")).unwrap();
+        run(&workspace, &["init", "--quiet", "--initial-branch=main"]);
+        run(&workspace, &["config", "user.name", "Test"]);
+        run(&workspace, &["config", "user.email", "test@example.com"]);
+        run(&workspace, &["config", "commit.gpgsign", "false"]);
+        std::fs::write(workspace.join("root.txt"), "root\n").unwrap();
+        std::fs::write(workspace.join("sub/a.txt"), "a\n").unwrap();
+        let paths = PathResolver::new(workspace.clone());
+
+        let error = handle_git_syscall(
+            "git.commit",
+            json!({ "cwd": "sub", "message": "Escape", "paths": ["../root.txt"] }),
+            &paths,
+        )
+        .await
+        .unwrap()
+        .unwrap_err();
+        assert_eq!(error, "'../root.txt' is outside the repository directory");
+
+        // `:/` would otherwise mean the whole repository, root.txt included.
+        let error = handle_git_syscall(
+            "git.commit",
+            json!({ "cwd": "sub", "message": "Top", "paths": [":/"] }),
+            &paths,
+        )
+        .await
+        .unwrap()
+        .unwrap_err();
+        assert!(error.contains("did not match any files"), "{}", error);
+        let status = call("git.status", json!({}), &paths).await;
+        assert_eq!(status["staged"], json!([]));
+
+        let committed = call(
+            "git.commit",
+            json!({ "cwd": "sub", "message": "Add a", "paths": ["a.txt"] }),
+            &paths,
+        )
+        .await;
+        assert_eq!(
+            committed["commit"]["files"],
+            json!([{ "path": "sub/a.txt", "status": "added" }])
+        );
+
+        std::fs::remove_dir_all(workspace).unwrap();
+    }
+}
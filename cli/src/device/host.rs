@@ -0,0 +1,700 @@
+//! The host the daemon runs on: `host.info` describes the machine, and
+//! `host.ps`, `host.kill` and `host.ports` cover its processes and listening
+//! sockets beyond the daemon's own shell sessions.
+//!
+//! Processes and sockets are read from Linux `/proc`, so other platforms only
+//! advertise `host.info`. Unless `device.kill_any_user` is set, `host.kill`
+//! only signals processes owned by the daemon's user, even when the daemon
+//! runs as root.
+
+use serde_json::Value;
+
+/// What host syscalls may do, from the identity's configuration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(super) struct HostPolicy {
+    pub(super) kill_any_user: bool,
+}
+
+pub(super) async fn handle_host_syscall(
+    call: &str,
+    args: Value,
+    policy: HostPolicy,
+) -> Option<Result<Value, String>> {
+    if call == "host.info" {
+        return Some(
+            serde_json::to_value(super::system_info::collect().await).map_err(|e| e.to_string()),
+        );
+    }
+    #[cfg(target_os = "linux")]
+    {
+        match call {
+            "host.ps" => Some(procfs::ps(args).await),
+            "host.kill" => Some(procfs::kill(args, policy)),
+            "host.ports" => Some(procfs::ports(args).await),
+            _ => None,
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (args, policy);
+        call.starts_with("host.")
+            .then(|| Err(format!("{} needs Linux /proc", call)))
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod procfs {
+    use super::HostPolicy;
+    use serde::de::DeserializeOwned;
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+    use std::cmp::Reverse;
+    use std::collections::{HashMap, HashSet};
+    use std::fs;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use std::time::{Duration, Instant};
+    use tracing::info;
+
+    /// How long CPU usage is measured over.
+    const CPU_SAMPLE: Duration = Duration::from_millis(250);
+    const DEFAULT_PS_LIMIT: usize = 200;
+    const MAX_PS_LIMIT: usize = 5000;
+
+    const SIGNALS: &[(&str, i32)] = &[
+        ("HUP", libc::SIGHUP),
+        ("INT", libc::SIGINT),
+        ("QUIT", libc::SIGQUIT),
+        ("KILL", libc::SIGKILL),
+        ("USR1", libc::SIGUSR1),
+        ("USR2", libc::SIGUSR2),
+        ("TERM", libc::SIGTERM),
+        ("CONT", libc::SIGCONT),
+        ("STOP", libc::SIGSTOP),
+        ("TSTP", libc::SIGTSTP),
+    ];
+
+    /// Socket tables, and the state a socket has in them while it listens:
+    /// `LISTEN` for TCP, unconnected for UDP.
+    const SOCKET_TABLES: [(&str, &str, &str); 4] = [
+        ("tcp", "/proc/net/tcp", "0A"),
+        ("tcp6", "/proc/net/tcp6", "0A"),
+        ("udp", "/proc/net/udp", "07"),
+        ("udp6", "/proc/net/udp6", "07"),
+    ];
+
+    fn parse_args<T: DeserializeOwned>(args: Value) -> Result<T, String> {
+        let args = if args.is_null() { json!({}) } else { args };
+        serde_json::from_value(args).map_err(|e| format!("Invalid arguments: {}", e))
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct ProcStat {
+        name: String,
+        state: String,
+        ppid: u32,
+        /// User and system time, in clock ticks.
+        cpu_ticks: u64,
+        threads: u64,
+        /// Clock ticks after boot.
+        start_ticks: u64,
+        rss_pages: u64,
+    }
+
+    /// Parse `/proc/<pid>/stat`. The command name is in parentheses and may
+    /// itself contain spaces and parentheses.
+    fn parse_stat(text: &str) -> Option<ProcStat> {
+        let (head, rest) = text.rsplit_once(')')?;
+        let (_, name) = head.split_once('(')?;
+        let fields = rest.split_whitespace().collect::<Vec<_>>();
+        // Indexes count from field 3, the state.
+        let number = |index: usize| fields.get(index)?.parse::<u64>().ok();
+        Some(ProcStat {
+            name: name.to_string(),
+            state: fields.first()?.to_string(),
+            ppid: fields.get(1)?.parse().ok()?,
+            cpu_ticks: number(11)?.saturating_add(number(12)?),
+            threads: number(17)?,
+            start_ticks: number(19)?,
+            rss_pages: fields.get(21)?.parse::<i64>().ok()?.max(0).unsigned_abs(),
+        })
+    }
+
+    fn pids() -> Vec<u32> {
+        fs::read_dir("/proc")
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn read_stat(pid: u32) -> Option<ProcStat> {
+        parse_stat(&fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?)
+    }
+
+    /// The process's real user id.
+    fn process_uid(pid: u32) -> Option<u32> {
+        fs::read_to_string(format!("/proc/{}/status", pid))
+            .ok()?
+            .lines()
+            .find_map(|line| line.strip_prefix("Uid:"))?
+            .split_whitespace()
+            .next()?
+            .parse()
+            .ok()
+    }
+
+    fn cmdline(pid: u32) -> String {
+        fs::read(format!("/proc/{}/cmdline", pid))
+            .map(|bytes| {
+                bytes
+                    .split(|byte| *byte == 0)
+                    .filter(|arg| !arg.is_empty())
+                    .map(String::from_utf8_lossy)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_default()
+    }
+
+    fn user_names() -> HashMap<u32, String> {
+        fs::read_to_string("/etc/passwd")
+            .map(|passwd| {
+                passwd
+                    .lines()
+                    .filter_map(|line| {
+                        let mut fields = line.split(':');
+                        let name = fields.next()?;
+                        let uid = fields.nth(1)?.parse().ok()?;
+                        Some((uid, name.to_string()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn own_uid() -> u32 {
+        // SAFETY: getuid cannot fail and has no side effects.
+        unsafe { libc::getuid() }
+    }
+
+    struct Clock {
+        ticks_per_second: u64,
+        boot_ms: i64,
+        page_size: u64,
+    }
+
+    impl Clock {
+        fn read() -> Self {
+            // SAFETY: sysconf only reads configuration values.
+            let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+            // SAFETY: as above.
+            let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+            let boot_secs = fs::read_to_string("/proc/stat")
+                .ok()
+                .and_then(|stat| {
+                    stat.lines()
+                        .find_map(|line| line.strip_prefix("btime "))?
+                        .trim()
+                        .parse::<i64>()
+                        .ok()
+                })
+                .unwrap_or_default();
+            Self {
+                ticks_per_second: u64::try_from(ticks)
+                    .ok()
+                    .filter(|ticks| *ticks > 0)
+                    .unwrap_or(100),
+                boot_ms: boot_secs.saturating_mul(1000),
+                page_size: u64::try_from(page_size).unwrap_or(4096),
+            }
+        }
+
+        fn started_at(&self, start_ticks: u64) -> i64 {
+            let after_boot = start_ticks.saturating_mul(1000) / self.ticks_per_second;
+            self.boot_ms
+                .saturating_add(i64::try_from(after_boot).unwrap_or(i64::MAX))
+        }
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(rename_all = "camelCase")]
+    struct PsArgs {
+        /// User name or uid.
+        #[serde(default)]
+        user: Option<String>,
+        /// Case-insensitive substring of the name or command line.
+        #[serde(default)]
+        name: Option<String>,
+        #[serde(default)]
+        pids: Option<Vec<u32>>,
+        #[serde(default)]
+        ppid: Option<u32>,
+        /// `cpu` (default), `rss`, `pid` or `start` (newest first).
+        #[serde(default)]
+        sort: Option<String>,
+        #[serde(default)]
+        limit: Option<usize>,
+    }
+
+    pub(super) async fn ps(args: Value) -> Result<Value, String> {
+        let args: PsArgs = parse_args(args)?;
+        let sort = args.sort.clone().unwrap_or_else(|| "cpu".to_string());
+        if !matches!(sort.as_str(), "cpu" | "rss" | "pid" | "start") {
+            return Err(format!(
+                "Unknown sort '{}': expected cpu, rss, pid or start",
+                sort
+            ));
+        }
+        let before = tokio::task::spawn_blocking(|| {
+            pids()
+                .into_iter()
+                .filter_map(|pid| Some((pid, read_stat(pid)?.cpu_ticks)))
+                .collect::<HashMap<_, _>>()
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+        let sampled_from = Instant::now();
+        tokio::time::sleep(CPU_SAMPLE).await;
+        tokio::task::spawn_blocking(move || list_processes(&args, &sort, &before, sampled_from))
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    fn list_processes(
+        args: &PsArgs,
+        sort: &str,
+        before: &HashMap<u32, u64>,
+        sampled_from: Instant,
+    ) -> Value {
+        let clock = Clock::read();
+        let users = user_names();
+        let elapsed = sampled_from.elapsed().as_secs_f64().max(f64::EPSILON);
+        let name_filter = args.name.as_ref().map(|name| name.to_lowercase());
+
+        let mut processes = pids()
+            .into_iter()
+            .filter(|pid| args.pids.as_ref().is_none_or(|pids| pids.contains(pid)))
+            .filter_map(|pid| {
+                let stat = read_stat(pid)?;
+                if args.ppid.is_some_and(|ppid| ppid != stat.ppid) {
+                    return None;
+                }
+                let uid = process_uid(pid)?;
+                let user = users.get(&uid).cloned();
+                if let Some(wanted) = &args.user {
+                    if user.as_ref() != Some(wanted) && uid.to_string() != *wanted {
+                        return None;
+                    }
+                }
+                let cmdline = cmdline(pid);
+                if let Some(name) = &name_filter {
+                    if !stat.name.to_lowercase().contains(name)
+                        && !cmdline.to_lowercase().contains(name)
+                    {
+                        return None;
+                    }
+                }
+                // A process started during the sample has all of its CPU
+                // time in it.
+                let ticks = stat
+                    .cpu_ticks
+                    .saturating_sub(before.get(&pid).copied().unwrap_or_default());
+                let cpu_percent = ticks as f64 / clock.ticks_per_second as f64 / elapsed * 100.0;
+                Some(HostProcess {
+                    pid,
+                    ppid: stat.ppid,
+                    uid,
+                    user,
+                    cmdline: if cmdline.is_empty() {
+                        format!("[{}]", stat.name)
+                    } else {
+                        cmdline
+                    },
+                    name: stat.name,
+                    state: stat.state,
+                    threads: stat.threads,
+                    cpu_percent: (cpu_percent * 10.0).round() / 10.0,
+                    rss_bytes: stat.rss_pages.saturating_mul(clock.page_size),
+                    started_at: clock.started_at(stat.start_ticks),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        match sort {
+            "rss" => processes.sort_by_key(|process| Reverse(process.rss_bytes)),
+            "pid" => processes.sort_by_key(|process| process.pid),
+            "start" => processes.sort_by_key(|process| Reverse(process.started_at)),
+            _ => processes.sort_by(|a, b| b.cpu_percent.total_cmp(&a.cpu_percent)),
+        }
+        let total = processes.len();
+        let limit = args
+            .limit
+            .unwrap_or(DEFAULT_PS_LIMIT)
+            .clamp(1, MAX_PS_LIMIT);
+        processes.truncate(limit);
+        json!({
+            "processes": processes,
+            "total": total,
+            "truncated": total > processes.len(),
+            "sampleMs": CPU_SAMPLE.as_millis(),
+        })
+    }
+
+    #[derive(serde::Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct HostProcess {
+        pid: u32,
+        ppid: u32,
+        uid: u32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        user: Option<String>,
+        name: String,
+        cmdline: String,
+        state: String,
+        threads: u64,
+        cpu_percent: f64,
+        rss_bytes: u64,
+        started_at: i64,
+    }
+
+    #[derive(Deserialize)]
+    struct KillArgs {
+        pid: i32,
+        /// A name such as `TERM` or `SIGKILL`, or a number. Default `TERM`.
+        #[serde(default)]
+        signal: Option<Value>,
+    }
+
+    fn parse_signal(signal: Option<&Value>) -> Result<(&'static str, i32), String> {
+        let found = match signal {
+            None | Some(Value::Null) => SIGNALS.iter().find(|(name, _)| *name == "TERM"),
+            Some(Value::Number(number)) => SIGNALS
+                .iter()
+                .find(|(_, value)| number.as_i64() == Some(i64::from(*value))),
+            Some(Value::String(name)) => {
+                let name = name.trim().to_ascii_uppercase();
+                let name = name.strip_prefix("SIG").unwrap_or(&name);
+                SIGNALS.iter().find(|(known, _)| *known == name)
+            }
+            Some(_) => None,
+        };
+        found.copied().ok_or_else(|| {
+            format!(
+                "Unsupported signal {}: expected one of {}",
+                signal.map(Value::to_string).unwrap_or_default(),
+                SIGNALS
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })
+    }
+
+    fn check_owner(policy: HostPolicy, pid: i32, uid: u32, own_uid: u32) -> Result<(), String> {
+        if policy.kill_any_user || uid == own_uid {
+            return Ok(());
+        }
+        Err(format!(
+            "Process {} belongs to uid {}, not to the daemon's user; set device.kill_any_user to allow this",
+            pid, uid
+        ))
+    }
+
+    pub(super) fn kill(args: Value, policy: HostPolicy) -> Result<Value, String> {
+        let args: KillArgs = parse_args(args)?;
+        let (name, signal) = parse_signal(args.signal.as_ref())?;
+        let pid = args.pid;
+        let process = u32::try_from(pid)
+            .ok()
+            .filter(|process| *process > 1 && *process != std::process::id())
+            .ok_or_else(|| format!("Refusing to signal pid {}", pid))?;
+        let uid = process_uid(process).ok_or_else(|| format!("No such process: {}", pid))?;
+        check_owner(policy, pid, uid, own_uid())?;
+        // SAFETY: `pid` names one process, checked above to be neither init,
+        // the daemon itself, nor a process group.
+        if unsafe { libc::kill(pid, signal) } != 0 {
+            return Err(format!(
+                "Failed to send SIG{} to {}: {}",
+                name,
+                pid,
+                std::io::Error::last_os_error()
+            ));
+        }
+        info!(event = "host.kill", pid, signal = name, uid);
+        Ok(json!({ "pid": pid, "signal": name, "sent": true }))
+    }
+
+    #[derive(Deserialize, Default)]
+    struct PortsArgs {
+        /// `tcp` or `udp`, either IP version.
+        #[serde(default)]
+        protocol: Option<String>,
+        #[serde(default)]
+        port: Option<u16>,
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Socket {
+        protocol: &'static str,
+        address: IpAddr,
+        port: u16,
+        uid: u32,
+        inode: u64,
+    }
+
+    /// Listening sockets in one `/proc/net` table.
+    fn parse_sockets(protocol: &'static str, listening: &str, table: &str) -> Vec<Socket> {
+        table
+            .lines()
+            .skip(1)
+            .filter_map(|line| {
+                let fields = line.split_whitespace().collect::<Vec<_>>();
+                if fields.get(3) != Some(&listening) {
+                    return None;
+                }
+                let (address, port) = fields.get(1)?.split_once(':')?;
+                Some(Socket {
+                    protocol,
+                    address: parse_address(address)?,
+                    port: u16::from_str_radix(port, 16).ok()?,
+                    uid: fields.get(7)?.parse().ok()?,
+                    inode: fields.get(9)?.parse().ok()?,
+                })
+            })
+            .collect()
+    }
+
+    /// Addresses are printed as 32-bit words in host byte order.
+    fn parse_address(hex: &str) -> Option<IpAddr> {
+        let words = (0..hex.len() / 8)
+            .map(|index| {
+                let word = hex.get(index * 8..index * 8 + 8)?;
+                u32::from_str_radix(word, 16).ok().map(u32::to_ne_bytes)
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let bytes = words.concat();
+        match bytes.len() {
+            4 => <[u8; 4]>::try_from(bytes)
+                .ok()
+                .map(|bytes| IpAddr::V4(Ipv4Addr::from(bytes))),
+            16 => <[u8; 16]>::try_from(bytes)
+                .ok()
+                .map(|bytes| IpAddr::V6(Ipv6Addr::from(bytes))),
+            _ => None,
+        }
+    }
+
+    /// The process holding each socket inode. Only processes whose file
+    /// descriptors the daemon may read are found.
+    fn socket_owners(inodes: &HashSet<u64>) -> HashMap<u64, u32> {
+        let mut owners = HashMap::new();
+        for pid in pids() {
+            let Ok(fds) = fs::read_dir(format!("/proc/{}/fd", pid)) else {
+                continue;
+            };
+            for fd in fds.filter_map(Result::ok) {
+                let Ok(target) = fs::read_link(fd.path()) else {
+                    continue;
+                };
+                let inode = target
+                    .to_str()
+                    .and_then(|target| target.strip_prefix("socket:["))
+                    .and_then(|target| target.strip_suffix(']'))
+                    .and_then(|inode| inode.parse::<u64>().ok());
+                if let Some(inode) = inode.filter(|inode| inodes.contains(inode)) {
+                    owners.entry(inode).or_insert(pid);
+                }
+            }
+        }
+        owners
+    }
+
+    pub(super) async fn ports(args: Value) -> Result<Value, String> {
+        let args: PortsArgs = parse_args(args)?;
+        if let Some(protocol) = args.protocol.as_deref() {
+            if !matches!(protocol, "tcp" | "udp") {
+                return Err(format!(
+                    "Unknown protocol '{}': expected tcp or udp",
+                    protocol
+                ));
+            }
+        }
+        tokio::task::spawn_blocking(move || list_ports(&args))
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    fn list_ports(args: &PortsArgs) -> Value {
+        let mut sockets = SOCKET_TABLES
+            .iter()
+            .filter(|(protocol, _, _)| {
+                args.protocol
+                    .as_deref()
+                    .is_none_or(|wanted| protocol.starts_with(wanted))
+            })
+            .flat_map(|(protocol, path, listening)| {
+                parse_sockets(
+                    protocol,
+                    listening,
+                    &fs::read_to_string(path).unwrap_or_default(),
+                )
+            })
+            .filter(|socket| args.port.is_none_or(|port| port == socket.port))
+            .collect::<Vec<_>>();
+        sockets.sort_by_key(|socket| (socket.port, socket.protocol, socket.address));
+
+        let inodes = sockets
+            .iter()
+            .map(|socket| socket.inode)
+            .filter(|inode| *inode != 0)
+            .collect::<HashSet<_>>();
+        let owners = socket_owners(&inodes);
+        let users = user_names();
+        let ports = sockets
+            .iter()
+            .map(|socket| {
+                let pid = owners.get(&socket.inode).copied();
+                json!({
+                    "protocol": socket.protocol,
+                    "address": socket.address.to_string(),
+                    "port": socket.port,
+                    "uid": socket.uid,
+                    "user": users.get(&socket.uid),
+                    "pid": pid,
+                    "process": pid.and_then(read_stat).map(|stat| stat.name),
+                })
+            })
+            .collect::<Vec<_>>();
+        json!({ "ports": ports })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn stat_names_may_contain_parentheses() {
+            let stat = "4242 (tmux: server (1)) S 1 4242 4242 0 -1 4194560 1390 0 0 0 \
+                        120 30 0 0 20 0 3 0 987654 12345678 2048 18446744073709551615";
+            assert_eq!(
+                parse_stat(stat),
+                Some(ProcStat {
+                    name: "tmux: server (1)".to_string(),
+                    state: "S".to_string(),
+                    ppid: 1,
+                    cpu_ticks: 150,
+                    threads: 3,
+                    start_ticks: 987654,
+                    rss_pages: 2048,
+                })
+            );
+        }
+
+        #[test]
+        #[cfg(target_endian = "little")]
+        fn listening_sockets_are_parsed() {
+            let tcp = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n\
+                       0: 00000000:0016 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 1234 1\n\
+                       1: 0100007F:1F90 0100007F:C350 01 00000000:00000000 00:00000000 00000000  1000        0 5678 1\n";
+            assert_eq!(
+                parse_sockets("tcp", "0A", tcp),
+                vec![Socket {
+                    protocol: "tcp",
+                    address: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                    port: 22,
+                    uid: 0,
+                    inode: 1234,
+                }]
+            );
+            assert_eq!(
+                parse_address("00000000000000000000000001000000"),
+                Some(IpAddr::V6(Ipv6Addr::LOCALHOST))
+            );
+        }
+
+        #[test]
+        fn signals_and_owners_are_checked() {
+            assert_eq!(parse_signal(None).unwrap(), ("TERM", libc::SIGTERM));
+            assert_eq!(
+                parse_signal(Some(&json!("sigkill"))).unwrap(),
+                ("KILL", libc::SIGKILL)
+            );
+            assert_eq!(
+                parse_signal(Some(&json!(1))).unwrap(),
+                ("HUP", libc::SIGHUP)
+            );
+            assert!(parse_signal(Some(&json!("SEGV")))
+                .unwrap_err()
+                .contains("Unsupported signal"));
+
+            let policy = HostPolicy::default();
+            assert!(check_owner(policy, 42, 0, 1000)
+                .unwrap_err()
+                .contains("device.kill_any_user"));
+            check_owner(policy, 42, 1000, 1000).unwrap();
+            check_owner(
+                HostPolicy {
+                    kill_any_user: true,
+                },
+                42,
+                0,
+                1000,
+            )
+            .unwrap();
+
+            for pid in [1, 0, -5, std::process::id() as i32] {
+                assert_eq!(
+                    kill(json!({ "pid": pid }), policy).unwrap_err(),
+                    format!("Refusing to signal pid {}", pid)
+                );
+            }
+        }
+
+        #[tokio::test]
+        async fn processes_are_listed_and_signalled() {
+            let mut child = std::process::Command::new("sleep")
+                .arg("30")
+                .spawn()
+                .unwrap();
+            let pid = child.id();
+
+            let listed = ps(json!({ "pids": [pid] })).await.unwrap();
+            let process = &listed["processes"][0];
+            assert_eq!(listed["total"], 1);
+            assert_eq!(process["name"], "sleep");
+            assert_eq!(process["cmdline"], "sleep 30");
+            assert_eq!(process["ppid"], std::process::id());
+            assert_eq!(process["uid"], own_uid());
+            assert!(process["startedAt"].as_i64().unwrap() > 0);
+
+            let sent = kill(
+                json!({ "pid": pid, "signal": "KILL" }),
+                HostPolicy::default(),
+            )
+            .unwrap();
+            assert_eq!(sent["signal"], "KILL");
+            use std::os::unix::process::ExitStatusExt;
+            assert_eq!(child.wait().unwrap().signal(), Some(libc::SIGKILL));
+        }
+
+        #[tokio::test]
+        async fn listening_ports_map_to_their_process() {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let listed = ports(json!({ "port": port, "protocol": "tcp" }))
+                .await
+                .unwrap();
+            let socket = &listed["ports"][0];
+            assert_eq!(socket["address"], "127.0.0.1");
+            assert_eq!(socket["pid"], std::process::id());
+            assert!(ports(json!({ "protocol": "sctp" }))
+                .await
+                .unwrap_err()
+                .contains("expected tcp or udp"));
+        }
+    }
+}
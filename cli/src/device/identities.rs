@@ -0,0 +1,339 @@
+//! Device identities served by one daemon.
+//!
+//! `[device]` describes the default identity. Each `[[devices]]` entry adds
+//! another one with its own gateway, credentials, workspace and mounts;
+//! connection fields it leaves unset fall back to `[gateway]`, and the
+//! workspace, retry ceiling and limits fall back to `[device]`.
+//! `device.kill_any_user`, `device.desktop` and `[device.approval]` apply to
+//! every identity.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use gsv::config::{CliConfig, DeviceIdentityConfig, DeviceLimitsConfig};
+use gsv::kernel_client::GatewayAuth;
+use gsv::tools::paths::PathResolver;
+
+use super::approval::ApprovalPolicy;
+use super::{build_device_paths, resolve_device_id, resolve_device_workspace, stateless};
+use crate::auth_flow::resolve_device_gateway_auth;
+
+/// Everything one identity needs to connect and serve requests.
+#[derive(Clone)]
+pub(crate) struct DeviceIdentity {
+    pub(crate) device_id: String,
+    pub(crate) url: String,
+    pub(crate) auth: GatewayAuth,
+    pub(crate) paths: PathResolver,
+    pub(crate) max_retry_delay: Option<Duration>,
+    pub(crate) limits: DeviceLimitsConfig,
+    /// `host.kill` may signal processes the daemon's user does not own.
+    pub(crate) kill_any_user: bool,
+    /// Serve and advertise `desktop.*`.
+    pub(crate) desktop: bool,
+    /// Requests that wait for approval at the device.
+    pub(crate) approval: ApprovalPolicy,
+}
+
+/// `gsv device run` flags and global connection overrides, kept so a
+/// configuration reload resolves identities the same way startup did.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DeviceRunFlags {
+    pub(crate) id: Option<String>,
+    pub(crate) workspace: Option<PathBuf>,
+    pub(crate) mounts: Vec<String>,
+    pub(crate) url: Option<String>,
+    pub(crate) username: Option<String>,
+    pub(crate) token: Option<String>,
+    /// Configure from the environment and never write files in home; see
+    /// [`super::stateless`].
+    pub(crate) stateless: bool,
+    /// How long requests still running at shutdown may take to finish;
+    /// overrides `device.grace_period_secs`.
+    pub(crate) grace_period: Option<Duration>,
+}
+
+impl DeviceRunFlags {
+    /// The configuration to resolve identities from. Unlike [`CliConfig::load`],
+    /// a file that does not parse is an error.
+    pub(crate) fn load_config(&self) -> Result<CliConfig, String> {
+        if self.stateless {
+            stateless::load_config()
+        } else {
+            CliConfig::try_load()
+        }
+    }
+
+    /// File whose edits trigger a reload.
+    pub(crate) fn config_file(&self) -> Option<PathBuf> {
+        if self.stateless {
+            stateless::config_file()
+        } else {
+            CliConfig::config_path()
+        }
+    }
+
+    /// Whether identities come from `[[devices]]`: entries are configured
+    /// and `--id`, if given, names one of them.
+    pub(crate) fn uses_device_entries(&self, cfg: &CliConfig) -> bool {
+        !cfg.devices.is_empty()
+            && self
+                .id
+                .as_deref()
+                .is_none_or(|id| cfg.devices.iter().any(|entry| entry.id.trim() == id))
+    }
+}
+
+/// Identities for `gsv device run`.
+///
+/// Without `[[devices]]` this is the single `[device]` identity, with flags
+/// and overrides applied. `--id` naming an entry serves just that entry,
+/// with `--workspace` and `--mount` applied to it. Otherwise all entries are
+/// served, plus the `[device]` identity when it has a `device.token`.
+pub(crate) fn resolve_device_identities(
+    cfg: &CliConfig,
+    flags: &DeviceRunFlags,
+) -> Result<Vec<DeviceIdentity>, Box<dyn std::error::Error>> {
+    let default_identity = || -> Result<DeviceIdentity, Box<dyn std::error::Error>> {
+        let workspace = resolve_device_workspace(flags.workspace.clone(), cfg);
+        Ok(DeviceIdentity {
+            device_id: resolve_device_id(flags.id.clone(), cfg),
+            url: flags.url.clone().unwrap_or_else(|| cfg.gateway_url()),
+            auth: resolve_device_gateway_auth(cfg, flags.token.clone(), flags.username.clone())?,
+            paths: build_device_paths(workspace, &cfg.device.mounts, &flags.mounts)?,
+            max_retry_delay: cfg.device_max_retry_delay(),
+            limits: cfg.device.limits.clone(),
+            kill_any_user: cfg.device.kill_any_user.unwrap_or(false),
+            desktop: cfg.device.desktop.unwrap_or(false),
+            approval: ApprovalPolicy::from_config(&cfg.device.approval)?,
+        })
+    };
+    if !flags.uses_device_entries(cfg) {
+        return Ok(vec![default_identity()?]);
+    }
+
+    let mut identities = Vec::new();
+    match flags.id.as_deref() {
+        Some(id) => {
+            if let Some(entry) = cfg.devices.iter().find(|entry| entry.id.trim() == id) {
+                identities.push(identity_from_entry(
+                    cfg,
+                    entry,
+                    flags.workspace.clone(),
+                    &flags.mounts,
+                )?);
+            }
+        }
+        None if flags.workspace.is_some() || !flags.mounts.is_empty() => {
+            return Err(
+                "--workspace and --mount apply to a single device; pick one with --id".into(),
+            );
+        }
+        None => {
+            if cfg.default_device_token().is_some() {
+                identities.push(default_identity()?);
+            }
+            for entry in &cfg.devices {
+                identities.push(identity_from_entry(cfg, entry, None, &[])?);
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for identity in &identities {
+        if !seen.insert(identity.device_id.as_str()) {
+            return Err(format!(
+                "Device '{}' is configured more than once",
+                identity.device_id
+            )
+            .into());
+        }
+    }
+    Ok(identities)
+}
+
+fn identity_from_entry(
+    cfg: &CliConfig,
+    entry: &DeviceIdentityConfig,
+    workspace: Option<PathBuf>,
+    cli_mounts: &[String],
+) -> Result<DeviceIdentity, Box<dyn std::error::Error>> {
+    let device_id = entry.id.trim().to_string();
+    if device_id.is_empty() {
+        return Err("Every [[devices]] entry needs an id".into());
+    }
+    let non_empty = |value: &Option<String>| {
+        value
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+    let token = non_empty(&entry.token)
+        .ok_or_else(|| format!("Device '{}' in [[devices]] has no token", device_id))?;
+    let username = non_empty(&entry.username)
+        .or_else(|| non_empty(&cfg.gateway.username))
+        .ok_or_else(|| {
+            format!(
+                "Device '{}' in [[devices]] has no username (set it or gateway.username)",
+                device_id
+            )
+        })?;
+    let auth = GatewayAuth {
+        username: Some(username),
+        password: None,
+        token: Some(token),
+    };
+    auth.validate()?;
+
+    let workspace = resolve_device_workspace(workspace.or_else(|| entry.workspace.clone()), cfg);
+    Ok(DeviceIdentity {
+        url: non_empty(&entry.url).unwrap_or_else(|| cfg.gateway_url()),
+        auth,
+        paths: build_device_paths(workspace, &entry.mounts, cli_mounts)
+            .map_err(|e| format!("Device '{}': {}", device_id, e))?,
+        max_retry_delay: entry
+            .max_retry_delay_secs
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+            .or_else(|| cfg.device_max_retry_delay()),
+        limits: entry
+            .limits
+            .clone()
+            .unwrap_or_else(|| cfg.device.limits.clone()),
+        kill_any_user: cfg.device.kill_any_user.unwrap_or(false),
+        desktop: cfg.device.desktop.unwrap_or(false),
+        approval: ApprovalPolicy::from_config(&cfg.device.approval)?,
+        device_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, token: Option<&str>) -> DeviceIdentityConfig {
+        DeviceIdentityConfig {
+            id: id.to_string(),
+            token: token.map(str::to_string),
+            workspace: Some(std::env::temp_dir()),
+            ..DeviceIdentityConfig::default()
+        }
+    }
+
+    fn only(id: &str, mounts: &[&str]) -> DeviceRunFlags {
+        DeviceRunFlags {
+            id: Some(id.to_string()),
+            mounts: mounts.iter().map(|mount| mount.to_string()).collect(),
+            ..DeviceRunFlags::default()
+        }
+    }
+
+    fn ids(identities: &[DeviceIdentity]) -> Vec<&str> {
+        identities
+            .iter()
+            .map(|identity| identity.device_id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn without_device_entries_the_default_identity_is_served() {
+        let mut cfg = CliConfig::default();
+        cfg.device.id = Some("laptop".to_string());
+        let flags = DeviceRunFlags {
+            url: Some("ws://override/ws".to_string()),
+            ..DeviceRunFlags::default()
+        };
+        assert!(!flags.uses_device_entries(&cfg));
+        let identities = resolve_device_identities(&cfg, &flags).unwrap();
+        assert_eq!(ids(&identities), ["laptop"]);
+        assert_eq!(identities[0].url, "ws://override/ws");
+    }
+
+    #[test]
+    fn entries_fall_back_to_gateway_and_device_defaults() {
+        let mut cfg = CliConfig::default();
+        cfg.gateway.url = Some("wss://home.example/ws".to_string());
+        cfg.gateway.username = Some("alice".to_string());
+        cfg.device.max_retry_delay_secs = Some(15);
+        cfg.device.limits.queue_size = Some(8);
+        let mut work = entry("work", Some("work-token"));
+        work.url = Some("wss://work.example/ws".to_string());
+        work.username = Some("alice.w".to_string());
+        work.limits = Some(DeviceLimitsConfig {
+            queue_size: Some(2),
+            ..DeviceLimitsConfig::default()
+        });
+        cfg.devices = vec![entry("home", Some("home-token")), work];
+
+        let identities = resolve_device_identities(&cfg, &DeviceRunFlags::default()).unwrap();
+        let [home, work] = identities.as_slice() else {
+            panic!("expected two identities");
+        };
+        assert_eq!(home.device_id, "home");
+        assert_eq!(home.url, "wss://home.example/ws");
+        assert_eq!(home.auth.username.as_deref(), Some("alice"));
+        assert_eq!(home.auth.token.as_deref(), Some("home-token"));
+        assert_eq!(home.max_retry_delay, Some(Duration::from_secs(15)));
+        assert_eq!(home.limits.queue_size, Some(8));
+        assert_eq!(work.url, "wss://work.example/ws");
+        assert_eq!(work.auth.username.as_deref(), Some("alice.w"));
+        assert_eq!(work.limits.queue_size, Some(2));
+    }
+
+    #[test]
+    fn default_identity_joins_when_it_has_a_token() {
+        let mut cfg = CliConfig::default();
+        cfg.gateway.username = Some("alice".to_string());
+        cfg.device.id = Some("laptop".to_string());
+        cfg.device.token = Some("laptop-token".to_string());
+        cfg.devices = vec![entry("work", Some("work-token"))];
+
+        let identities = resolve_device_identities(&cfg, &DeviceRunFlags::default()).unwrap();
+        assert_eq!(ids(&identities), ["laptop", "work"]);
+
+        // `--id` picks a single entry, or falls through to the default path.
+        let work = resolve_device_identities(&cfg, &only("work", &["data=/srv/data:ro"])).unwrap();
+        assert_eq!(ids(&work), ["work"]);
+        assert!(work[0]
+            .paths
+            .mounts()
+            .iter()
+            .any(|mount| mount.name == "data" && mount.read_only));
+        let laptop = only("laptop", &[]);
+        assert!(!laptop.uses_device_entries(&cfg));
+        assert_eq!(
+            ids(&resolve_device_identities(&cfg, &laptop).unwrap()),
+            ["laptop"]
+        );
+
+        let error = resolve_device_identities(
+            &cfg,
+            &DeviceRunFlags {
+                mounts: vec!["data=/srv/data".to_string()],
+                ..DeviceRunFlags::default()
+            },
+        )
+        .err()
+        .unwrap();
+        assert!(error.to_string().contains("pick one with --id"), "{error}");
+    }
+
+    #[test]
+    fn incomplete_or_duplicate_entries_are_rejected() {
+        let mut cfg = CliConfig::default();
+        cfg.gateway.username = Some("alice".to_string());
+        cfg.devices = vec![entry("work", None)];
+        let error = resolve_device_identities(&cfg, &DeviceRunFlags::default())
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("has no token"), "{error}");
+
+        cfg.devices = vec![entry("work", Some("a")), entry("work", Some("b"))];
+        let error = resolve_device_identities(&cfg, &DeviceRunFlags::default())
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("more than once"), "{error}");
+    }
+}
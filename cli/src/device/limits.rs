@@ -0,0 +1,467 @@
+//! Per-family concurrency limits for driver requests.
+//!
+//! Each syscall family (a full call name such as `fs.search`, or a namespace
+//! such as `shell`) runs at most `limit` requests at once. Requests beyond
+//! that wait in a bounded FIFO queue; cheap calls are served from a separate
+//! lane ahead of everything else in their family. Requests that cannot be
+//! queued, or wait longer than the queue timeout, fail with a retryable error
+//! so the gateway can try again later.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use gsv::config::DeviceLimitsConfig;
+use gsv::protocol::{ErrorShape, ResponseFrame};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::oneshot;
+use tokio::time::Duration;
+
+const DEFAULT_QUEUE_SIZE: usize = 64;
+const DEFAULT_QUEUE_TIMEOUT: Duration = Duration::from_secs(30);
+/// Built-in family limits; `device.limits.concurrency` entries override or
+/// extend them.
+const DEFAULT_CONCURRENCY: &[(&str, usize)] =
+    &[("shell", 4), ("fs.search", 2), ("net", 8), ("fs", 16)];
+/// Calls that skip ahead of queued work in their family.
+const PRIORITY_CALLS: &[&str] = &["fs.read"];
+
+const OVERLOADED_ERROR_CODE: i32 = 429;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum QueueError {
+    Full,
+    TimedOut,
+}
+
+/// Occupancy of one family, as reported by `gsv device status`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct QueueDepth {
+    pub(crate) limit: usize,
+    pub(crate) running: usize,
+    pub(crate) queued: usize,
+}
+
+struct Waiter {
+    id: u64,
+    grant: oneshot::Sender<()>,
+}
+
+#[derive(Default)]
+struct FamilyState {
+    running: usize,
+    next_waiter: u64,
+    priority: VecDeque<Waiter>,
+    normal: VecDeque<Waiter>,
+}
+
+impl FamilyState {
+    fn queued(&self) -> usize {
+        self.priority.len() + self.normal.len()
+    }
+
+    /// Drop waiters whose requests went away while queued.
+    fn prune(&mut self) {
+        self.priority.retain(|waiter| !waiter.grant.is_closed());
+        self.normal.retain(|waiter| !waiter.grant.is_closed());
+    }
+
+    fn remove(&mut self, id: u64) {
+        self.priority.retain(|waiter| waiter.id != id);
+        self.normal.retain(|waiter| waiter.id != id);
+    }
+
+    /// Hand a freed slot to the next live waiter, or give it back.
+    fn release(&mut self) {
+        while let Some(waiter) = self
+            .priority
+            .pop_front()
+            .or_else(|| self.normal.pop_front())
+        {
+            if waiter.grant.send(()).is_ok() {
+                return;
+            }
+        }
+        self.running = self.running.saturating_sub(1);
+    }
+}
+
+struct Family {
+    name: String,
+    limit: usize,
+    state: Mutex<FamilyState>,
+}
+
+/// Slot in a family; frees it on drop.
+pub(super) struct Permit {
+    family: Option<Arc<Family>>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if let Some(family) = self.family.take() {
+            if let Ok(mut state) = family.state.lock() {
+                state.release();
+            }
+        }
+    }
+}
+
+/// A queued request. Dropping it before the grant is consumed (the request
+/// was cancelled, or the wait timed out) leaves the queue consistent.
+struct Ticket {
+    family: Arc<Family>,
+    id: u64,
+    grant: oneshot::Receiver<()>,
+    done: bool,
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+        let Ok(mut state) = self.family.state.lock() else {
+            return;
+        };
+        // Grants happen under this lock, so this check cannot race one.
+        if self.grant.try_recv().is_ok() {
+            state.release();
+        } else {
+            state.remove(self.id);
+        }
+    }
+}
+
+/// Current limits. Reloading the configuration swaps the inner table;
+/// requests already admitted or queued finish against the family they
+/// joined.
+#[derive(Clone)]
+pub(super) struct SyscallLimits(Arc<Mutex<Arc<LimitsInner>>>);
+
+struct LimitsInner {
+    /// Longest key first so `fs.search` wins over `fs`.
+    families: Vec<Arc<Family>>,
+    queue_size: usize,
+    queue_timeout: Duration,
+}
+
+impl Default for SyscallLimits {
+    fn default() -> Self {
+        Self::from_config(&DeviceLimitsConfig::default())
+    }
+}
+
+impl LimitsInner {
+    fn from_config(config: &DeviceLimitsConfig) -> Self {
+        let mut limits = DEFAULT_CONCURRENCY
+            .iter()
+            .map(|(family, limit)| (family.to_string(), *limit))
+            .collect::<BTreeMap<_, _>>();
+        for (family, limit) in &config.concurrency {
+            let family = family.trim().trim_end_matches(".*").to_string();
+            if !family.is_empty() {
+                limits.insert(family, *limit);
+            }
+        }
+        let mut families = limits
+            .into_iter()
+            .filter(|(_, limit)| *limit > 0)
+            .map(|(name, limit)| {
+                Arc::new(Family {
+                    name,
+                    limit,
+                    state: Mutex::new(FamilyState::default()),
+                })
+            })
+            .collect::<Vec<_>>();
+        families.sort_by_key(|family| std::cmp::Reverse(family.name.len()));
+
+        Self {
+            families,
+            queue_size: config.queue_size.unwrap_or(DEFAULT_QUEUE_SIZE),
+            queue_timeout: config
+                .queue_timeout_secs
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_QUEUE_TIMEOUT),
+        }
+    }
+
+    fn family_of(&self, call: &str) -> Option<&Arc<Family>> {
+        self.families.iter().find(|family| {
+            call == family.name
+                || call
+                    .strip_prefix(family.name.as_str())
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })
+    }
+}
+
+impl SyscallLimits {
+    pub(super) fn from_config(config: &DeviceLimitsConfig) -> Self {
+        Self(Arc::new(Mutex::new(Arc::new(LimitsInner::from_config(
+            config,
+        )))))
+    }
+
+    fn current(&self) -> Arc<LimitsInner> {
+        self.0.lock().expect("limits mutex poisoned").clone()
+    }
+
+    /// Apply new limits to requests that arrive from now on. Families whose
+    /// limit is unchanged keep their running count and queue; a family with
+    /// a new limit starts counting afresh.
+    pub(super) fn reconfigure(&self, config: &DeviceLimitsConfig) {
+        let previous = self.current();
+        let mut next = LimitsInner::from_config(config);
+        for family in &mut next.families {
+            if let Some(existing) = previous
+                .families
+                .iter()
+                .find(|existing| existing.name == family.name && existing.limit == family.limit)
+            {
+                *family = existing.clone();
+            }
+        }
+        *self.0.lock().expect("limits mutex poisoned") = Arc::new(next);
+    }
+
+    /// Wait for a slot in the family of `call`. Calls outside every
+    /// configured family run unrestricted.
+    pub(super) async fn acquire(&self, call: &str) -> Result<Permit, QueueError> {
+        let limits = self.current();
+        let Some(family) = limits.family_of(call).cloned() else {
+            return Ok(Permit { family: None });
+        };
+        let priority = PRIORITY_CALLS.contains(&call);
+
+        let mut ticket = {
+            let mut state = family.state.lock().expect("queue mutex poisoned");
+            state.prune();
+            let lane_clear = if priority {
+                state.priority.is_empty()
+            } else {
+                state.queued() == 0
+            };
+            if state.running < family.limit && lane_clear {
+                state.running += 1;
+                return Ok(Permit {
+                    family: Some(family.clone()),
+                });
+            }
+            if state.queued() >= limits.queue_size {
+                return Err(QueueError::Full);
+            }
+            let (grant, receiver) = oneshot::channel();
+            state.next_waiter += 1;
+            let id = state.next_waiter;
+            let waiter = Waiter { id, grant };
+            if priority {
+                state.priority.push_back(waiter);
+            } else {
+                state.normal.push_back(waiter);
+            }
+            Ticket {
+                family: family.clone(),
+                id,
+                grant: receiver,
+                done: false,
+            }
+        };
+
+        match tokio::time::timeout(limits.queue_timeout, &mut ticket.grant).await {
+            Ok(Ok(())) => {
+                ticket.done = true;
+                Ok(Permit {
+                    family: Some(family),
+                })
+            }
+            // Dropping the ticket either dequeues it or, if the grant landed
+            // in the meantime, hands the slot on.
+            Ok(Err(_)) | Err(_) => Err(QueueError::TimedOut),
+        }
+    }
+
+    pub(super) fn depths(&self) -> BTreeMap<String, QueueDepth> {
+        self.current()
+            .families
+            .iter()
+            .map(|family| {
+                let mut state = family.state.lock().expect("queue mutex poisoned");
+                state.prune();
+                (
+                    family.name.clone(),
+                    QueueDepth {
+                        limit: family.limit,
+                        running: state.running,
+                        queued: state.queued(),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Retryable error for a request that never got a slot.
+    pub(super) fn rejection(&self, id: &str, call: &str, error: QueueError) -> ResponseFrame {
+        let limits = self.current();
+        let family = limits
+            .family_of(call)
+            .map(|family| family.name.clone())
+            .unwrap_or_else(|| call.to_string());
+        let (reason, message) = match error {
+            QueueError::Full => (
+                "queue_full",
+                format!("Device is busy: {} queue is full", family),
+            ),
+            QueueError::TimedOut => (
+                "queue_timeout",
+                format!(
+                    "Device is busy: {} request waited {}s for a slot",
+                    family,
+                    limits.queue_timeout.as_secs()
+                ),
+            ),
+        };
+        ResponseFrame {
+            id: id.to_string(),
+            ok: false,
+            data: None,
+            error: Some(ErrorShape {
+                code: OVERLOADED_ERROR_CODE,
+                message,
+                details: Some(json!({ "reason": reason, "family": family })),
+                retryable: Some(true),
+            }),
+            body: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn limits(
+        concurrency: &[(&str, usize)],
+        queue_size: usize,
+        timeout_secs: u64,
+    ) -> SyscallLimits {
+        SyscallLimits::from_config(&DeviceLimitsConfig {
+            queue_size: Some(queue_size),
+            queue_timeout_secs: Some(timeout_secs),
+            concurrency: concurrency
+                .iter()
+                .map(|(family, limit)| (family.to_string(), *limit))
+                .collect::<BTreeMap<_, _>>(),
+        })
+    }
+
+    #[test]
+    fn most_specific_family_wins_and_zero_lifts_the_limit() {
+        let limits = limits(&[("net", 0), ("fs.write", 1)], 4, 1).current();
+        assert_eq!(limits.family_of("fs.search").unwrap().name, "fs.search");
+        assert_eq!(limits.family_of("fs.write").unwrap().name, "fs.write");
+        assert_eq!(limits.family_of("fs.read").unwrap().name, "fs");
+        assert_eq!(limits.family_of("shell.exec").unwrap().name, "shell");
+        assert!(limits.family_of("net.fetch").is_none());
+        assert!(limits.family_of("fsck").is_none());
+    }
+
+    #[tokio::test]
+    async fn queued_requests_run_in_order_with_priority_first() {
+        let limits = limits(&[("fs", 1)], 8, 30);
+        let held = limits.acquire("fs.write").await.unwrap();
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut tasks = Vec::new();
+        for call in ["fs.write", "fs.delete", "fs.read"] {
+            let limits = limits.clone();
+            let order = order.clone();
+            tasks.push(tokio::spawn(async move {
+                let _permit = limits.acquire(call).await.unwrap();
+                order.lock().unwrap().push(call);
+            }));
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(limits.depths()["fs"].queued, 3);
+
+        drop(held);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), ["fs.read", "fs.write", "fs.delete"]);
+        let depth = &limits.depths()["fs"];
+        assert_eq!((depth.running, depth.queued), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn full_queue_and_timeouts_are_rejected() {
+        let limits = limits(&[("shell", 1)], 1, 1);
+        let _held = limits.acquire("shell.exec").await.unwrap();
+
+        let waiting = tokio::spawn({
+            let limits = limits.clone();
+            async move { limits.acquire("shell.exec").await.err() }
+        });
+        tokio::task::yield_now().await;
+        assert_eq!(
+            limits.acquire("shell.exec").await.err(),
+            Some(QueueError::Full)
+        );
+
+        assert_eq!(waiting.await.unwrap(), Some(QueueError::TimedOut));
+        assert_eq!(limits.depths()["shell"].queued, 0);
+
+        let response = limits.rejection("req-1", "shell.exec", QueueError::TimedOut);
+        let error = response.error.unwrap();
+        assert_eq!(error.retryable, Some(true));
+        assert_eq!(error.code, OVERLOADED_ERROR_CODE);
+    }
+
+    #[tokio::test]
+    async fn cancelled_waiters_give_up_their_place() {
+        let limits = limits(&[("shell", 1)], 4, 30);
+        let held = limits.acquire("shell.exec").await.unwrap();
+
+        let cancelled = tokio::spawn({
+            let limits = limits.clone();
+            async move { limits.acquire("shell.exec").await.is_ok() }
+        });
+        tokio::task::yield_now().await;
+        assert_eq!(limits.depths()["shell"].queued, 1);
+        cancelled.abort();
+        let _ = cancelled.await;
+        assert_eq!(limits.depths()["shell"].queued, 0);
+
+        drop(held);
+        let depth = &limits.depths()["shell"];
+        assert_eq!((depth.running, depth.queued), (0, 0));
+        limits.acquire("shell.exec").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn reconfigure_keeps_unchanged_families_and_applies_new_limits() {
+        let limits = limits(&[("shell", 1), ("fs", 1)], 4, 30);
+        let shell = limits.acquire("shell.exec").await.unwrap();
+        let _fs = limits.acquire("fs.write").await.unwrap();
+
+        limits.reconfigure(&DeviceLimitsConfig {
+            concurrency: [("shell".to_string(), 1), ("fs".to_string(), 2)]
+                .into_iter()
+                .collect(),
+            ..DeviceLimitsConfig::default()
+        });
+
+        // `shell` kept its limit, so the held slot still counts.
+        assert_eq!(limits.depths()["shell"].running, 1);
+        // `fs` starts afresh under its new limit.
+        let depth = &limits.depths()["fs"];
+        assert_eq!((depth.limit, depth.running), (2, 0));
+        let _second = limits.acquire("fs.read").await.unwrap();
+
+        drop(shell);
+        assert_eq!(limits.depths()["shell"].running, 0);
+    }
+}
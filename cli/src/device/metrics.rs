@@ -0,0 +1,275 @@
+//! Per-syscall counters and latency histograms for the device daemon.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+
+use gsv::protocol::ResponseFrame;
+use serde::{Deserialize, Serialize};
+use tokio::time::Duration;
+
+/// Histogram bucket bounds in seconds. File syscalls land in the first few;
+/// shell and fetch calls spread across the rest.
+const LATENCY_BUCKETS: [f64; 9] = [0.005, 0.025, 0.1, 0.25, 1.0, 5.0, 30.0, 120.0, 600.0];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Outcome {
+    Ok,
+    Error,
+    Cancelled,
+}
+
+impl Outcome {
+    /// Classify a response. `fs.*` failures come back as `ok: true` with an
+    /// `ok: false` payload, so both layers count as errors.
+    pub(super) fn of(response: &ResponseFrame) -> Self {
+        let failed_payload = response
+            .data
+            .as_ref()
+            .and_then(|payload| payload.get("ok"))
+            .and_then(|ok| ok.as_bool())
+            == Some(false);
+        if response.ok && !failed_payload {
+            Self::Ok
+        } else {
+            Self::Error
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Error => "error",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct SyscallStats {
+    ok: u64,
+    error: u64,
+    cancelled: u64,
+    total: Duration,
+    max: Duration,
+    /// Non-cumulative counts per bucket; the last slot is `+Inf`.
+    buckets: [u64; LATENCY_BUCKETS.len() + 1],
+}
+
+impl SyscallStats {
+    fn calls(&self) -> u64 {
+        self.ok + self.error + self.cancelled
+    }
+}
+
+/// Aggregate view of one syscall, as reported by `gsv device status`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SyscallSummary {
+    pub(crate) calls: u64,
+    pub(crate) errors: u64,
+    pub(crate) cancelled: u64,
+    pub(crate) avg_ms: f64,
+    pub(crate) max_ms: f64,
+}
+
+#[derive(Clone, Default)]
+pub(super) struct SyscallMetrics(Arc<Mutex<BTreeMap<String, SyscallStats>>>);
+
+impl SyscallMetrics {
+    pub(super) fn record(&self, call: &str, outcome: Outcome, elapsed: Duration) {
+        let Ok(mut calls) = self.0.lock() else {
+            return;
+        };
+        let stats = calls.entry(call.to_string()).or_default();
+        match outcome {
+            Outcome::Ok => stats.ok += 1,
+            Outcome::Error => stats.error += 1,
+            Outcome::Cancelled => stats.cancelled += 1,
+        }
+        stats.total += elapsed;
+        stats.max = stats.max.max(elapsed);
+        let seconds = elapsed.as_secs_f64();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        if let Some(count) = stats.buckets.get_mut(bucket) {
+            *count += 1;
+        }
+    }
+
+    pub(super) fn summary(&self) -> BTreeMap<String, SyscallSummary> {
+        let Ok(calls) = self.0.lock() else {
+            return BTreeMap::new();
+        };
+        calls
+            .iter()
+            .map(|(call, stats)| {
+                let count = stats.calls();
+                let avg_ms = if count == 0 {
+                    0.0
+                } else {
+                    stats.total.as_secs_f64() * 1000.0 / count as f64
+                };
+                (
+                    call.clone(),
+                    SyscallSummary {
+                        calls: count,
+                        errors: stats.error,
+                        cancelled: stats.cancelled,
+                        avg_ms,
+                        max_ms: stats.max.as_secs_f64() * 1000.0,
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+/// Append the request counters and latency histograms of each device in
+/// Prometheus text exposition format.
+pub(super) fn render_prometheus(devices: &[(&str, &SyscallMetrics)], out: &mut String) {
+    let locked = devices
+        .iter()
+        .filter_map(|(device, metrics)| Some((escape_label(device), metrics.0.lock().ok()?)))
+        .collect::<Vec<_>>();
+
+    out.push_str("# HELP gsv_device_syscall_requests_total Syscalls handled, by outcome.\n");
+    out.push_str("# TYPE gsv_device_syscall_requests_total counter\n");
+    for (device, calls) in &locked {
+        for (call, stats) in calls.iter() {
+            for (outcome, count) in [
+                (Outcome::Ok, stats.ok),
+                (Outcome::Error, stats.error),
+                (Outcome::Cancelled, stats.cancelled),
+            ] {
+                let _ = writeln!(
+                    out,
+                    "gsv_device_syscall_requests_total{{device=\"{}\",call=\"{}\",outcome=\"{}\"}} {}",
+                    device,
+                    escape_label(call),
+                    outcome.as_str(),
+                    count
+                );
+            }
+        }
+    }
+
+    out.push_str("# HELP gsv_device_syscall_duration_seconds Syscall handling time.\n");
+    out.push_str("# TYPE gsv_device_syscall_duration_seconds histogram\n");
+    for (device, calls) in &locked {
+        for (call, stats) in calls.iter() {
+            let labels = format!("device=\"{}\",call=\"{}\"", device, escape_label(call));
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(stats.buckets.iter()) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "gsv_device_syscall_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, bound, cumulative
+                );
+            }
+            let _ = writeln!(
+                out,
+                "gsv_device_syscall_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels,
+                stats.calls()
+            );
+            let _ = writeln!(
+                out,
+                "gsv_device_syscall_duration_seconds_sum{{{}}} {}",
+                labels,
+                stats.total.as_secs_f64()
+            );
+            let _ = writeln!(
+                out,
+                "gsv_device_syscall_duration_seconds_count{{{}}} {}",
+                labels,
+                stats.calls()
+            );
+        }
+    }
+}
+
+pub(super) fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn response(ok: bool, payload: serde_json::Value) -> ResponseFrame {
+        ResponseFrame {
+            id: "req-1".to_string(),
+            ok,
+            data: Some(payload),
+            error: None,
+            body: None,
+        }
+    }
+
+    #[test]
+    fn failed_fs_payloads_count_as_errors() {
+        assert_eq!(
+            Outcome::of(&response(true, json!({ "ok": true }))),
+            Outcome::Ok
+        );
+        assert_eq!(
+            Outcome::of(&response(true, json!({ "ok": false, "error": "nope" }))),
+            Outcome::Error
+        );
+        assert_eq!(Outcome::of(&response(false, json!({}))), Outcome::Error);
+    }
+
+    #[test]
+    fn summary_and_histogram_track_each_call() {
+        let metrics = SyscallMetrics::default();
+        metrics.record("fs.read", Outcome::Ok, Duration::from_millis(2));
+        metrics.record("fs.read", Outcome::Error, Duration::from_millis(200));
+        metrics.record("shell.exec", Outcome::Cancelled, Duration::from_secs(1000));
+
+        let summary = metrics.summary();
+        let read = &summary["fs.read"];
+        assert_eq!((read.calls, read.errors, read.cancelled), (2, 1, 0));
+        assert!((read.avg_ms - 101.0).abs() < 1e-6);
+        assert!((read.max_ms - 200.0).abs() < 1e-6);
+
+        let other = SyscallMetrics::default();
+        other.record("fs.read", Outcome::Ok, Duration::from_millis(1));
+
+        let mut text = String::new();
+        render_prometheus(&[("mac", &metrics), ("work", &other)], &mut text);
+        assert_eq!(
+            text.matches("# TYPE gsv_device_syscall_requests_total")
+                .count(),
+            1
+        );
+        assert!(text.contains(
+            "gsv_device_syscall_requests_total{device=\"mac\",call=\"fs.read\",outcome=\"error\"} 1\n"
+        ));
+        assert!(text.contains(
+            "gsv_device_syscall_duration_seconds_bucket{device=\"mac\",call=\"fs.read\",le=\"0.005\"} 1\n"
+        ));
+        assert!(text.contains(
+            "gsv_device_syscall_duration_seconds_bucket{device=\"mac\",call=\"fs.read\",le=\"0.25\"} 2\n"
+        ));
+        assert!(text.contains(
+            "gsv_device_syscall_duration_seconds_bucket{device=\"mac\",call=\"shell.exec\",le=\"600\"} 0\n"
+        ));
+        assert!(text.contains(
+            "gsv_device_syscall_duration_seconds_bucket{device=\"mac\",call=\"shell.exec\",le=\"+Inf\"} 1\n"
+        ));
+        assert!(text.contains(
+            "gsv_device_syscall_duration_seconds_count{device=\"mac\",call=\"fs.read\"} 2\n"
+        ));
+        assert!(text.contains(
+            "gsv_device_syscall_duration_seconds_count{device=\"work\",call=\"fs.read\"} 1\n"
+        ));
+    }
+}
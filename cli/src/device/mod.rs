@@ -1,19 +1,26 @@
 use std::collections::{HashMap, VecDeque};
 use std::future::Future;
-use std::io;
+use std::io::{self, IsTerminal};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use gsv::config::CliConfig;
+use futures_util::future::LocalBoxFuture;
+use futures_util::stream::{FuturesUnordered, StreamExt};
+use gsv::config::{CliConfig, DeviceMountConfig};
 use gsv::connection::{Connection, GatewayRpcError};
-use gsv::device_service;
+use gsv::device_service::{self, ServiceManagerKind};
 use gsv::kernel_client::{GatewayAuth, KernelClient};
 use gsv::logger;
 use gsv::protocol::{
-    DeviceExecEventParams, ErrorShape, Frame, FrameBodyDescriptor, RequestFrame, ResponseFrame,
-    SignalFrame, REQUEST_CANCEL_SIGNAL,
+    DeviceExecEventParams, DriverInfo, ErrorShape, Frame, FrameBodyDescriptor, RequestFrame,
+    ResponseFrame, SessionResume, SignalFrame, DEVICE_OFFLINE_SIGNAL, REQUEST_CANCEL_SIGNAL,
+};
+use gsv::tools::paths::{Mount, PathResolver};
+use gsv::tools::{
+    adopt_shell_sessions, detach_shell_sessions, set_shell_session_dir, subscribe_exec_events,
+    Tool, ToolOutput,
 };
-use gsv::tools::{all_tools_with_workspace_for_device, subscribe_exec_events, Tool, ToolOutput};
 use serde::Deserialize;
 use serde_json::json;
 use tokio_util::sync::CancellationToken;
@@ -21,15 +28,85 @@ use tracing::{error, info, info_span, warn, Instrument};
 
 use crate::cli::DeviceServiceAction;
 
+pub(crate) use identities::{resolve_device_identities, DeviceIdentity, DeviceRunFlags};
+
+mod approval;
+mod archive;
+mod control;
+mod cron;
+mod data;
+mod desktop;
+mod git;
+mod host;
+mod identities;
+mod limits;
+mod metrics;
+mod reconnect;
+mod reload;
+mod resume;
+mod state;
+mod stateless;
+mod system_info;
 mod transfer;
+mod update;
 
 const MAX_DEVICE_EXEC_EVENT_OUTBOX: usize = 2048;
-const DEVICE_DRIVER_IMPLEMENTS: &[&str] = &["fs.*", "shell.exec", "net.fetch"];
+#[cfg(target_os = "linux")]
+const DEVICE_DRIVER_IMPLEMENTS: &[&str] = &[
+    "fs.*",
+    "shell.exec",
+    "net.fetch",
+    "cron.*",
+    "git.*",
+    "data.query",
+    "host.*",
+];
+/// Beyond `host.info`, `host.*` reads Linux `/proc`.
+#[cfg(not(target_os = "linux"))]
+const DEVICE_DRIVER_IMPLEMENTS: &[&str] = &[
+    "fs.*",
+    "shell.exec",
+    "net.fetch",
+    "cron.*",
+    "git.*",
+    "data.query",
+    "host.info",
+];
+/// Syscalls a device with this tool set advertises.
+fn driver_implements(tools: &reload::ToolSet) -> Vec<String> {
+    let mut implements: Vec<String> = DEVICE_DRIVER_IMPLEMENTS
+        .iter()
+        .map(|item| item.to_string())
+        .collect();
+    if tools.desktop && cfg!(target_os = "linux") {
+        implements.push("desktop.*".to_string());
+    }
+    implements
+}
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Grace period for requests still running at shutdown, unless
+/// `--grace-period` or `device.grace_period_secs` say otherwise; below
+/// Kubernetes' default of 30 seconds.
+const DEFAULT_GRACE_PERIOD_SECS: u64 = 25;
+/// How long the last exec events may take to reach the gateway on shutdown.
+const SHUTDOWN_FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+const SHUTTING_DOWN_ERROR_CODE: i32 = 503;
+
+/// How a device winds down once told to stop: new requests are refused,
+/// requests still running get a grace period, which a second shutdown
+/// signal cuts short.
+#[derive(Clone, Default)]
+struct Drain {
+    grace_period: Duration,
+    interrupt: CancellationToken,
+}
 
 #[derive(Clone, Default)]
 struct ActiveRequests(Arc<Mutex<HashMap<String, ActiveRequest>>>);
 
 struct ActiveRequest {
+    call: String,
+    started_at: tokio::time::Instant,
     cancellation: Arc<CancellationToken>,
     body: Option<FrameBodyDescriptor>,
 }
@@ -53,6 +130,8 @@ impl ActiveRequests {
             requests.insert(
                 request.id.clone(),
                 ActiveRequest {
+                    call: request.call.clone(),
+                    started_at: tokio::time::Instant::now(),
                     cancellation: cancellation.clone(),
                     body: request.body,
                 },
@@ -98,6 +177,86 @@ impl ActiveRequests {
         }
     }
 
+    /// Cancel only the requests still waiting on an incoming body. Their
+    /// streams died with the socket; everything else may keep running and
+    /// answer once the session resumes.
+    fn cancel_streaming(&self, reason: &str, binary_inbox: &transfer::BinaryFrameInbox) {
+        let requests = {
+            let mut requests = self.0.lock().expect("active request mutex poisoned");
+            let ids = requests
+                .iter()
+                .filter(|(_, request)| request.body.is_some())
+                .map(|(id, _)| id.clone())
+                .collect::<Vec<_>>();
+            ids.into_iter()
+                .filter_map(|id| requests.remove(&id))
+                .collect::<Vec<_>>()
+        };
+        for request in requests {
+            Self::stop(request, reason, binary_inbox);
+        }
+    }
+
+    fn ids(&self) -> Vec<String> {
+        self.0
+            .lock()
+            .expect("active request mutex poisoned")
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    fn len(&self) -> usize {
+        self.0.lock().expect("active request mutex poisoned").len()
+    }
+
+    /// Give running requests until the grace period ends, or `drain` is
+    /// interrupted, to finish. Returns how many are still running.
+    async fn drain(&self, drain: &Drain) -> usize {
+        let running = self.len();
+        if running == 0 || drain.grace_period.is_zero() {
+            return running;
+        }
+        info!(
+            event = "shutdown.drain",
+            active = running,
+            grace_seconds = drain.grace_period.as_secs_f64(),
+        );
+        let deadline = tokio::time::Instant::now() + drain.grace_period;
+        loop {
+            let running = self.len();
+            if running == 0 {
+                info!(event = "shutdown.drained");
+                return 0;
+            }
+            tokio::select! {
+                _ = drain.interrupt.cancelled() => {}
+                _ = tokio::time::sleep_until(deadline) => {}
+                _ = tokio::time::sleep(DRAIN_POLL_INTERVAL) => continue,
+            }
+            warn!(event = "shutdown.drain_incomplete", active = running);
+            return running;
+        }
+    }
+
+    /// In-flight requests, longest running first.
+    fn list(&self) -> Vec<control::ActiveRequestInfo> {
+        let mut requests = self
+            .0
+            .lock()
+            .expect("active request mutex poisoned")
+            .iter()
+            .map(|(id, request)| control::ActiveRequestInfo {
+                id: id.clone(),
+                call: request.call.clone(),
+                elapsed_ms: u64::try_from(request.started_at.elapsed().as_millis())
+                    .unwrap_or(u64::MAX),
+            })
+            .collect::<Vec<_>>();
+        requests.sort_by_key(|request| std::cmp::Reverse(request.elapsed_ms));
+        requests
+    }
+
     fn stop(request: ActiveRequest, reason: &str, binary_inbox: &transfer::BinaryFrameInbox) {
         if let Some(body) = request.body {
             binary_inbox.cancel_incoming(body.stream_id, reason);
@@ -152,10 +311,68 @@ pub(crate) fn resolve_device_workspace(cli_workspace: Option<PathBuf>, cfg: &Cli
         .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")))
 }
 
+/// Workspace plus named mounts from config and `--mount` flags; a flag
+/// replaces a configured mount of the same name.
+fn build_device_paths(
+    workspace: PathBuf,
+    configured: &[DeviceMountConfig],
+    cli_mounts: &[String],
+) -> Result<PathResolver, String> {
+    let mut mounts: Vec<Mount> = Vec::new();
+    let configured = configured.iter().map(|mount| {
+        Ok(Mount {
+            name: mount.name.trim().to_string(),
+            root: absolute_mount_root(&mount.path),
+            read_only: mount.read_only,
+        })
+    });
+    for mount in configured.chain(cli_mounts.iter().map(|spec| parse_mount_flag(spec))) {
+        let mount = mount?;
+        mounts.retain(|existing| existing.name != mount.name);
+        mounts.push(mount);
+    }
+    PathResolver::with_mounts(workspace, mounts)
+}
+
+/// Parse `NAME=PATH`, `NAME=PATH:ro` or `NAME=PATH:rw`.
+fn parse_mount_flag(spec: &str) -> Result<Mount, String> {
+    let (name, path) = spec
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid --mount '{}': expected NAME=PATH[:ro]", spec))?;
+    let (path, read_only) = match path.rsplit_once(':') {
+        Some((path, "ro")) => (path, true),
+        Some((path, "rw")) => (path, false),
+        _ => (path, false),
+    };
+    if path.is_empty() {
+        return Err(format!("Invalid --mount '{}': missing path", spec));
+    }
+    Ok(Mount {
+        name: name.trim().to_string(),
+        root: absolute_mount_root(Path::new(path)),
+        read_only,
+    })
+}
+
+fn absolute_mount_root(path: &Path) -> PathBuf {
+    let path = match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    };
+    if path.is_absolute() {
+        path
+    } else {
+        std::env::current_dir()
+            .map(|cwd| cwd.join(&path))
+            .unwrap_or(path)
+    }
+}
+
 fn persist_device_defaults(
     cfg: &CliConfig,
     device_id: Option<String>,
     workspace: Option<PathBuf>,
+    service_manager: ServiceManagerKind,
 ) -> Result<(String, PathBuf, bool), Box<dyn std::error::Error>> {
     let device_id = resolve_device_id(device_id, cfg);
     let workspace = resolve_device_workspace(workspace, cfg);
@@ -174,6 +391,11 @@ fn persist_device_defaults(
         changed = true;
     }
 
+    if local_cfg.device.service_manager != Some(service_manager) {
+        local_cfg.device.service_manager = Some(service_manager);
+        changed = true;
+    }
+
     if changed {
         local_cfg.save()?;
     }
@@ -231,23 +453,34 @@ pub(crate) fn run_device_service(
     gateway_username_override: Option<&str>,
     gateway_token_override: Option<&str>,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let preference = cfg.device.service_manager;
+    let service_home = device_service::device_service_home(preference);
     match action {
-        DeviceServiceAction::Install { id, workspace } => {
+        DeviceServiceAction::Install {
+            id,
+            workspace,
+            service_manager,
+        } => {
+            let service_manager =
+                device_service::resolve_service_manager(service_manager.or(preference))?;
             let gateway_overrides_changed = persist_gateway_overrides(
                 gateway_url_override,
                 gateway_username_override,
                 gateway_token_override,
             )?;
             let (device_id, workspace, device_defaults_changed) =
-                persist_device_defaults(cfg, id, workspace)?;
+                persist_device_defaults(cfg, id, workspace, service_manager)?;
 
-            device_service::install_device_service()?;
+            device_service::install_device_service(Some(service_manager))?;
 
             if gateway_overrides_changed || device_defaults_changed {
-                device_service::restart_device_service()?;
+                device_service::restart_device_service(Some(service_manager))?;
             }
 
-            println!("Device daemon installed and started.");
+            println!(
+                "Device daemon installed and started ({}).",
+                service_manager.as_str()
+            );
             if gateway_overrides_changed {
                 println!("Saved gateway connection overrides to local config.");
             }
@@ -256,13 +489,21 @@ pub(crate) fn run_device_service(
                 device_id,
                 workspace.display()
             );
+            if !cfg.devices.is_empty() {
+                let ids = cfg
+                    .devices
+                    .iter()
+                    .map(|entry| entry.id.as_str())
+                    .collect::<Vec<_>>();
+                println!("Also serving [[devices]]: {}", ids.join(", "));
+            }
             println!("\nCheck status:");
             println!("  gsv device status");
             println!("View logs:");
             println!("  gsv device logs --follow");
         }
         DeviceServiceAction::Uninstall => {
-            device_service::uninstall_device_service()?;
+            device_service::uninstall_device_service(preference)?;
 
             println!("Device daemon uninstalled.");
         }
@@ -274,26 +515,78 @@ pub(crate) fn run_device_service(
             )?;
 
             if gateway_overrides_changed {
-                device_service::restart_device_service()?;
+                device_service::restart_device_service(preference)?;
                 println!("Saved gateway connection overrides to local config.");
                 println!("Device daemon restarted.");
                 return Ok(());
             }
 
-            device_service::start_device_service()?;
+            device_service::start_device_service(preference)?;
 
             println!("Device daemon started.");
         }
         DeviceServiceAction::Stop => {
-            device_service::stop_device_service()?;
+            device_service::stop_device_service(preference)?;
 
             println!("Device daemon stopped.");
         }
-        DeviceServiceAction::Status => {
-            device_service::status_device_service()?;
+        DeviceServiceAction::Reload => {
+            println!(
+                "{}",
+                control::request(service_home.as_deref(), "reload")?.trim_end()
+            );
+        }
+        DeviceServiceAction::Approve { id, deny, json } => {
+            if let Some(id) = id {
+                let command = format!("{} {}", if deny { "deny" } else { "approve" }, id);
+                println!(
+                    "{}",
+                    control::request(service_home.as_deref(), &command)?.trim_end()
+                );
+                return Ok(());
+            }
+            let reply = control::request(service_home.as_deref(), "approvals")?;
+            if json {
+                print!("{}", reply);
+                return Ok(());
+            }
+            let pending = serde_json::from_str::<Vec<approval::PendingApproval>>(&reply)?;
+            if pending.is_empty() {
+                println!("No requests are waiting for approval.");
+            }
+            for pending in &pending {
+                println!("{}", approval::describe_pending(pending));
+            }
+        }
+        DeviceServiceAction::Status { json, prometheus } => {
+            if prometheus {
+                print!("{}", control::request(service_home.as_deref(), "metrics")?);
+                return Ok(());
+            }
+            if json {
+                print!("{}", control::request(service_home.as_deref(), "status")?);
+                return Ok(());
+            }
+            device_service::status_device_service(preference)?;
+            let status = control::request(service_home.as_deref(), "status").and_then(|reply| {
+                serde_json::from_str::<control::DaemonStatus>(&reply).map_err(Into::into)
+            });
+            match status {
+                Ok(status) => println!(
+                    "{}",
+                    control::describe_status(&status, chrono::Utc::now().timestamp_millis())
+                ),
+                Err(e) => println!("Connection: unknown ({})", e),
+            }
+        }
+        DeviceServiceAction::Probe { ready } => {
+            let status = control::request(service_home.as_deref(), "status").and_then(|reply| {
+                serde_json::from_str::<control::DaemonStatus>(&reply).map_err(Into::into)
+            })?;
+            println!("{}", control::probe(&status, ready)?);
         }
         DeviceServiceAction::Logs { lines, follow } => {
-            device_service::show_device_service_logs(lines, follow)?;
+            device_service::show_device_service_logs(preference, lines, follow)?;
         }
     }
 
@@ -413,12 +706,12 @@ async fn flush_exec_event_outbox(
                 seq: None,
             });
 
-            match serde_json::to_string(&frame) {
-                Ok(text) => match conn.send_raw(text).await {
+            match conn.codec().encode(&frame) {
+                Ok(message) => match conn.send_message(message).await {
                     Ok(_) => ExecEventSendOutcome::Sent,
                     Err(error) => ExecEventSendOutcome::Retry(error.to_string()),
                 },
-                Err(error) => ExecEventSendOutcome::Drop(error.to_string()),
+                Err(error) => ExecEventSendOutcome::Drop(error),
             }
         }
     })
@@ -439,15 +732,45 @@ fn syscall_to_tool_name(call: &str) -> Option<&'static str> {
     }
 }
 
+/// Build the error response for a failed syscall. `fs.*` failures are
+/// reported in-band so the kernel can surface them as tool results.
+fn driver_error_response(id: &str, call: &str, message: String) -> ResponseFrame {
+    if call.starts_with("fs.") {
+        ResponseFrame {
+            id: id.to_string(),
+            ok: true,
+            data: Some(json!({
+                "ok": false,
+                "error": message,
+            })),
+            error: None,
+            body: None,
+        }
+    } else {
+        ResponseFrame {
+            id: id.to_string(),
+            ok: false,
+            data: None,
+            error: Some(ErrorShape {
+                code: -1,
+                message,
+                details: None,
+                retryable: None,
+            }),
+            body: None,
+        }
+    }
+}
+
 async fn handle_driver_request(
-    conn: &Arc<Connection>,
-    tools: &[Box<dyn Tool>],
-    workspace: &Path,
+    tool_set: &reload::ToolSet,
+    cron_jobs: &cron::CronJobs,
     req: &RequestFrame,
     binary_inbox: &transfer::BinaryFrameInbox,
     cancellation: &CancellationToken,
-) {
+) -> (ResponseFrame, Option<transfer::OutgoingBody>) {
     let args = req.args.clone().unwrap_or(serde_json::Value::Null);
+    let paths = &tool_set.paths;
 
     let call = req.call.as_str();
     if call == "net.fetch" {
@@ -469,13 +792,49 @@ async fn handle_driver_request(
     }
 
     let result = if let Some(transfer_result) =
-        transfer::handle_transfer_syscall(call, args.clone(), req.body, workspace, binary_inbox)
-            .await
+        transfer::handle_transfer_syscall(call, args.clone(), req.body, paths, binary_inbox).await
     {
         transfer_result
+    } else if let Some(archive_result) =
+        archive::handle_archive_syscall(call, args.clone(), req.body, paths, binary_inbox).await
+    {
+        archive_result
+    } else if let Some(data_result) =
+        data::handle_data_syscall(call, args.clone(), paths, binary_inbox).await
+    {
+        if let Some(body) = req.body {
+            binary_inbox.cancel_incoming(body.stream_id, "Request body not accepted");
+        }
+        data_result
+    } else if let Some(cron_result) =
+        cron::handle_cron_syscall(call, args.clone(), cron_jobs, paths).await
+    {
+        if let Some(body) = req.body {
+            binary_inbox.cancel_incoming(body.stream_id, "Request body not accepted");
+        }
+        cron_result.map(|data| (data, None))
+    } else if let Some(host_result) =
+        host::handle_host_syscall(call, args.clone(), tool_set.host).await
+    {
+        if let Some(body) = req.body {
+            binary_inbox.cancel_incoming(body.stream_id, "Request body not accepted");
+        }
+        host_result.map(|data| (data, None))
+    } else if let Some(desktop_result) =
+        desktop::handle_desktop_syscall(call, args.clone(), tool_set.desktop).await
+    {
+        if let Some(body) = req.body {
+            binary_inbox.cancel_incoming(body.stream_id, "Request body not accepted");
+        }
+        desktop_result.map(|data| (data, None))
+    } else if let Some(git_result) = git::handle_git_syscall(call, args.clone(), paths).await {
+        if let Some(body) = req.body {
+            binary_inbox.cancel_incoming(body.stream_id, "Request body not accepted");
+        }
+        git_result.map(|data| (data, None))
     } else if let Some(tool_name) = syscall_to_tool_name(call) {
         execute_tool_by_name(
-            tools,
+            &tool_set.tools,
             call,
             tool_name,
             args,
@@ -497,8 +856,7 @@ async fn handle_driver_request(
         Err(format!("unknown syscall: {}", call))
     };
 
-    let mut outgoing_body = None;
-    let response = match result {
+    match result {
         Ok((data, body)) => {
             let body_descriptor = body.as_ref().map(|body| body.descriptor());
             if call == "net.fetch" {
@@ -510,14 +868,14 @@ async fn handle_driver_request(
                     body_bytes = ?body_descriptor.and_then(|body| body.length),
                 );
             }
-            outgoing_body = body;
-            Frame::Res(ResponseFrame {
+            let response = ResponseFrame {
                 id: req.id.clone(),
                 ok: true,
                 data: Some(data),
                 error: None,
                 body: body_descriptor,
-            })
+            };
+            (response, body)
         }
         Err(message) => {
             if call == "net.fetch" {
@@ -527,63 +885,7 @@ async fn handle_driver_request(
                     error = %message,
                 );
             }
-            if req.call.starts_with("fs.") {
-                Frame::Res(ResponseFrame {
-                    id: req.id.clone(),
-                    ok: true,
-                    data: Some(json!({
-                        "ok": false,
-                        "error": message,
-                    })),
-                    error: None,
-                    body: None,
-                })
-            } else {
-                Frame::Res(ResponseFrame {
-                    id: req.id.clone(),
-                    ok: false,
-                    data: None,
-                    error: Some(ErrorShape {
-                        code: -1,
-                        message: message.clone(),
-                        details: None,
-                        retryable: None,
-                    }),
-                    body: None,
-                })
-            }
-        }
-    };
-
-    match serde_json::to_string(&response) {
-        Ok(text) => {
-            if let Err(e) = conn.send_raw(text).await {
-                error!(
-                    event = "driver.response.send_failed",
-                    request_id = %req.id,
-                    call = %req.call,
-                    error = %e,
-                );
-                return;
-            }
-            if let Some(body) = outgoing_body {
-                if let Err(e) = body.send(conn).await {
-                    error!(
-                        event = "driver.response.body_send_failed",
-                        request_id = %req.id,
-                        call = %req.call,
-                        error = %e,
-                    );
-                }
-            }
-        }
-        Err(e) => {
-            error!(
-                event = "driver.response.serialize_failed",
-                request_id = %req.id,
-                call = %req.call,
-                error = %e,
-            );
+            (driver_error_response(&req.id, call, message), None)
         }
     }
 }
@@ -717,306 +1019,1080 @@ pub(crate) async fn run_shell(
     Ok(())
 }
 
-pub(crate) async fn run_device(
-    url: &str,
-    auth: GatewayAuth,
-    device_id: String,
-    workspace: PathBuf,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let _logging_guard = logger::init_device_logging()?;
-    let workspace_label = workspace.display().to_string();
-    let device_span = info_span!("device", device_id = %device_id, workspace = %workspace_label);
+/// Tear down requests after losing the gateway link. With a resumable
+/// session only requests mid-upload are lost; without one nothing can be
+/// delivered anymore, so everything is cancelled.
+fn shutting_down_response(id: &str) -> ResponseFrame {
+    ResponseFrame {
+        id: id.to_string(),
+        ok: false,
+        data: None,
+        error: Some(ErrorShape {
+            code: SHUTTING_DOWN_ERROR_CODE,
+            message: "Device is shutting down".to_string(),
+            details: Some(json!({ "reason": "shutting_down" })),
+            retryable: Some(true),
+        }),
+        body: None,
+    }
+}
 
-    let run = async move {
-        let log_pattern = logger::device_log_pattern()?;
+/// Wind down a connected device: tell the gateway it is going offline, let
+/// running requests finish within the grace period, and deliver the exec
+/// events still owed, including those for background sessions left behind,
+/// before the socket closes.
+async fn shut_down_connected(
+    conn: &Arc<Connection>,
+    device_id: &str,
+    active_requests: &ActiveRequests,
+    drain: &Drain,
+    binary_inbox: &transfer::BinaryFrameInbox,
+    exec_event_outbox: &Arc<Mutex<VecDeque<DeviceExecEventParams>>>,
+) {
+    let offline = Frame::Sig(SignalFrame {
+        signal: DEVICE_OFFLINE_SIGNAL.to_string(),
+        payload: Some(json!({
+            "reason": "shutdown",
+            "activeRequests": active_requests.len(),
+            "gracePeriodMs": u64::try_from(drain.grace_period.as_millis()).unwrap_or(u64::MAX),
+        })),
+        seq: None,
+    });
+    match conn.codec().encode(&offline) {
+        Ok(message) => {
+            if let Err(e) = conn.send_message(message).await {
+                warn!(event = "shutdown.offline_signal_failed", error = %e);
+            }
+        }
+        Err(e) => warn!(event = "shutdown.offline_signal_failed", error = %e),
+    }
+
+    active_requests.drain(drain).await;
+    active_requests.cancel_all("Device shutting down", binary_inbox);
+
+    for event in detach_shell_sessions(Some(device_id)).await {
+        info!(event = "shell.session.detached", session_id = %event.session_id);
+        queue_exec_event_for_retry(exec_event_outbox, event);
+    }
+    let flushed = tokio::select! {
+        _ = drain.interrupt.cancelled() => 0,
+        result = tokio::time::timeout(
+            SHUTDOWN_FLUSH_TIMEOUT,
+            flush_exec_event_outbox(conn, exec_event_outbox),
+        ) => result.unwrap_or(0),
+    };
+    if flushed > 0 {
         info!(
-            event = "device.start",
-            url = %url,
-            log_path = %log_pattern,
-            log_rotation = "daily",
+            event = "device.exec.event.flushed",
+            sent = flushed,
+            remaining = exec_event_outbox_len(exec_event_outbox)
+        );
+    }
+    let _ = conn.close().await;
+}
+
+fn detach_requests(
+    active_requests: &ActiveRequests,
+    resumable: bool,
+    reason: &str,
+    binary_inbox: &transfer::BinaryFrameInbox,
+) {
+    if resumable {
+        active_requests.cancel_streaming(reason, binary_inbox);
+    } else {
+        active_requests.cancel_all(reason, binary_inbox);
+    }
+}
+
+/// Identity being served, as tracked by [`Supervisor`].
+struct RunningDevice {
+    /// Tells a finished serve future from one started later under the same id.
+    serial: u64,
+    identity: tokio::sync::watch::Sender<DeviceIdentity>,
+    stop: CancellationToken,
+}
+
+type ServeOutcome = (String, u64, Result<(), Box<dyn std::error::Error>>);
+
+/// Starts, updates and stops identities as the configuration changes.
+struct Supervisor {
+    flags: DeviceRunFlags,
+    devices: Arc<Mutex<Vec<control::DeviceHandles>>>,
+    shutdown: CancellationToken,
+    running: HashMap<String, RunningDevice>,
+    serving: FuturesUnordered<LocalBoxFuture<'static, ServeOutcome>>,
+    next_serial: u64,
+    any_served: bool,
+    first_error: Option<Box<dyn std::error::Error>>,
+    updater: update::Updater,
+    drain: Drain,
+    approvals: approval::Approvals,
+}
+
+impl Supervisor {
+    fn devices(&self) -> std::sync::MutexGuard<'_, Vec<control::DeviceHandles>> {
+        self.devices.lock().expect("device handles mutex poisoned")
+    }
+
+    fn start(&mut self, identity: DeviceIdentity) {
+        let device_id = identity.device_id.clone();
+        let handles = control::DeviceHandles {
+            device_id: device_id.clone(),
+            state: state::DeviceState::new(&redact_url_for_log(&identity.url)),
+            active_requests: ActiveRequests::default(),
+            exec_event_outbox: Arc::new(Mutex::new(VecDeque::new())),
+            response_outbox: resume::ResponseOutbox::default(),
+            metrics: metrics::SyscallMetrics::default(),
+            limits: limits::SyscallLimits::from_config(&identity.limits),
+            cron: if self.flags.stateless {
+                cron::CronJobs::in_memory(&device_id)
+            } else {
+                cron::CronJobs::load(&device_id)
+            },
+        };
+        self.devices().push(handles.clone());
+
+        let span = info_span!(
+            "device",
+            device_id = %device_id,
+            workspace = %identity.paths.workspace().display(),
         );
+        let serial = self.next_serial;
+        self.next_serial += 1;
+        let stop = self.shutdown.child_token();
+        let (identity, identity_rx) = tokio::sync::watch::channel(identity);
+        let serve = serve_device(
+            identity_rx,
+            handles,
+            self.updater.clone(),
+            self.approvals.clone(),
+            stop.clone(),
+            self.drain.clone(),
+        )
+        .instrument(span);
+        let id = device_id.clone();
+        self.serving
+            .push(Box::pin(async move { (id, serial, serve.await) }));
+        self.running.insert(
+            device_id,
+            RunningDevice {
+                serial,
+                identity,
+                stop,
+            },
+        );
+    }
+
+    fn stop(&mut self, device_id: &str) {
+        if let Some(device) = self.running.remove(device_id) {
+            device.stop.cancel();
+        }
+        self.devices()
+            .retain(|handles| handles.device_id != device_id);
+    }
 
-        let shutdown = wait_for_shutdown_signal();
-        tokio::pin!(shutdown);
+    fn finished(&mut self, (device_id, serial, result): ServeOutcome) {
+        if self
+            .running
+            .get(&device_id)
+            .is_some_and(|device| device.serial == serial)
+        {
+            self.stop(&device_id);
+        }
+        match result {
+            Ok(()) => self.any_served = true,
+            Err(e) => {
+                self.first_error.get_or_insert(e);
+            }
+        }
+    }
 
-        let exec_event_outbox: Arc<Mutex<VecDeque<DeviceExecEventParams>>> =
-            Arc::new(Mutex::new(VecDeque::new()));
-        let outbox_for_exec_events = exec_event_outbox.clone();
-        let mut exec_events = subscribe_exec_events();
-        let exec_event_span = tracing::Span::current();
-        let exec_event_collector = tokio::spawn(
-            async move {
-                loop {
-                    match exec_events.recv().await {
-                        Ok(event) => {
-                            queue_exec_event_for_retry(&outbox_for_exec_events, event);
-                        }
-                        Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
-                            warn!(event = "device.exec.event.lagged", skipped);
-                        }
-                        Err(tokio::sync::broadcast::error::RecvError::Closed) => {
-                            break;
-                        }
-                    }
+    /// Re-read the configuration and hand every identity its new settings.
+    /// On failure nothing changes and the daemon keeps its current setup.
+    fn reload(&mut self, request: reload::ReloadRequest) {
+        let result = self
+            .flags
+            .load_config()
+            .and_then(|cfg| resolve_device_identities(&cfg, &self.flags).map_err(|e| e.to_string()))
+            .map(|identities| self.apply(identities));
+        match &result {
+            Ok(changes) => info!(
+                event = "config.reloaded",
+                source = request.source,
+                changes = %changes,
+            ),
+            Err(e) => warn!(
+                event = "config.reload.failed",
+                source = request.source,
+                error = %e,
+            ),
+        }
+        if let Some(reply) = request.reply {
+            let _ = reply.send(result);
+        }
+    }
+
+    fn apply(&mut self, identities: Vec<DeviceIdentity>) -> String {
+        let mut changes = Vec::new();
+        let removed = self
+            .running
+            .keys()
+            .filter(|id| !identities.iter().any(|identity| &identity.device_id == *id))
+            .cloned()
+            .collect::<Vec<_>>();
+        for device_id in removed {
+            self.stop(&device_id);
+            changes.push(format!("stopped {}", device_id));
+        }
+        for identity in identities {
+            match self.running.get(&identity.device_id) {
+                Some(device) => {
+                    let reconnect = reload::gateway_changed(&device.identity.borrow(), &identity);
+                    changes.push(format!(
+                        "{} {}",
+                        if reconnect {
+                            "reconnecting"
+                        } else {
+                            "reloaded"
+                        },
+                        identity.device_id
+                    ));
+                    device.identity.send_replace(identity);
+                }
+                None => {
+                    changes.push(format!("started {}", identity.device_id));
+                    self.start(identity);
                 }
             }
-            .instrument(exec_event_span),
+        }
+        changes.join(", ")
+    }
+}
+
+/// Serve the identities `flags` resolve to from one process. Logging, signal
+/// handling and the control socket are shared; each identity keeps its own
+/// connection, binary frame inbox, tools, queues and metrics.
+///
+/// `SIGHUP`, edits of `config.toml` and the control socket's `reload`
+/// command re-resolve the identities without restarting the process.
+///
+/// An identity that fails for good (for example because its credentials
+/// were revoked) stops on its own; the call only fails when all of them did.
+pub(crate) async fn run_devices(flags: DeviceRunFlags) -> Result<(), Box<dyn std::error::Error>> {
+    let cfg = if flags.stateless {
+        flags.load_config()?
+    } else {
+        CliConfig::load()
+    };
+    let identities = resolve_device_identities(&cfg, &flags)?;
+
+    let _logging_guard = if flags.stateless {
+        logger::init_device_stdout_logging()?;
+        info!(
+            event = "daemon.start",
+            devices = identities.len(),
+            stateless = true,
+        );
+        None
+    } else {
+        let guard = logger::init_device_logging()?;
+        info!(
+            event = "daemon.start",
+            devices = identities.len(),
+            log_path = %logger::device_log_pattern()?,
+            log_rotation = "daily",
         );
+        Some(guard)
+    };
 
-        macro_rules! shutdown_device {
-            ($signal:expr) => {{
-                exec_event_collector.abort();
-                info!(event = "shutdown", signal = %$signal);
-                return Ok(());
-            }};
+    let (reload_requests, mut reloads) = tokio::sync::mpsc::unbounded_channel();
+    let mut supervisor = Supervisor {
+        devices: Arc::new(Mutex::new(Vec::new())),
+        shutdown: CancellationToken::new(),
+        running: HashMap::new(),
+        serving: FuturesUnordered::new(),
+        next_serial: 0,
+        any_served: false,
+        first_error: None,
+        updater: update::Updater::new(&cfg.device),
+        drain: Drain {
+            grace_period: flags.grace_period.unwrap_or(Duration::from_secs(
+                cfg.device
+                    .grace_period_secs
+                    .unwrap_or(DEFAULT_GRACE_PERIOD_SECS),
+            )),
+            interrupt: CancellationToken::new(),
+        },
+        approvals: approval::Approvals::default(),
+        flags,
+    };
+    // A daemon started in a terminal asks there as well.
+    if !supervisor.flags.stateless && std::io::stdin().is_terminal() {
+        supervisor.approvals.prompt_on_terminal();
+    }
+    // A stateless daemon is never updated in place and keeps no state file;
+    // its background sessions end with its container anyway.
+    let probation = if supervisor.flags.stateless {
+        None
+    } else {
+        if let Some(home) = dirs::home_dir() {
+            set_shell_session_dir(home.join(".gsv").join("shell-sessions"));
         }
+        update::begin_probation(supervisor.devices.clone(), cfg.device.service_manager)
+    };
 
-        const CONNECT_TIMEOUT: tokio::time::Duration = tokio::time::Duration::from_secs(30);
-        const INITIAL_RETRY_DELAY: tokio::time::Duration = tokio::time::Duration::from_secs(3);
-        const MAX_RETRY_DELAY: tokio::time::Duration = tokio::time::Duration::from_secs(300);
-        let mut retry_delay = INITIAL_RETRY_DELAY;
+    // Status and metrics stay available while disconnected; failing to
+    // bind only costs `gsv device status` its details.
+    let _control_server = match control::ControlServer::bind(control::DaemonHandles {
+        devices: supervisor.devices.clone(),
+        reload: Some(reload_requests.clone()),
+        approvals: supervisor.approvals.clone(),
+    }) {
+        Ok(server) => {
+            info!(event = "control.listening", path = %server.path().display());
+            Some(server)
+        }
+        Err(e) => {
+            warn!(event = "control.unavailable", error = %e);
+            None
+        }
+    };
+    let _reload_triggers = reload::spawn_triggers(reload_requests, supervisor.flags.config_file());
 
-        loop {
-            info!(event = "connect.attempt", url = %url);
+    let signal_watcher = tokio::spawn({
+        let shutdown = supervisor.shutdown.clone();
+        let interrupt = supervisor.drain.interrupt.clone();
+        async move {
+            let signal = wait_for_shutdown_signal().await;
+            info!(event = "shutdown", signal = %signal);
+            shutdown.cancel();
+            let signal = wait_for_shutdown_signal().await;
+            info!(event = "shutdown.forced", signal = %signal);
+            interrupt.cancel();
+        }
+    });
 
-            let tools_for_handler: Arc<Vec<Box<dyn Tool>>> = Arc::new(
-                all_tools_with_workspace_for_device(workspace.clone(), device_id.clone()),
-            );
+    for identity in identities {
+        supervisor.start(identity);
+    }
+    loop {
+        tokio::select! {
+            outcome = supervisor.serving.next() => match outcome {
+                Some(outcome) => supervisor.finished(outcome),
+                None => break,
+            },
+            Some(request) = reloads.recv(), if !supervisor.shutdown.is_cancelled() => {
+                supervisor.reload(request);
+            }
+        }
+    }
+    signal_watcher.abort();
+    if let Some(probation) = probation {
+        probation.abort();
+    }
 
-            let conn_attempt = tokio::time::timeout(
-                CONNECT_TIMEOUT,
-                KernelClient::connect_driver(
-                    url,
-                    device_id.clone(),
-                    DEVICE_DRIVER_IMPLEMENTS
-                        .iter()
-                        .map(|item| item.to_string())
-                        .collect(),
-                    auth.clone(),
-                    |_frame| {},
-                ),
+    match supervisor.first_error {
+        Some(e) if !supervisor.any_served => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn log_mounts(paths: &PathResolver) {
+    for mount in paths.mounts() {
+        info!(
+            event = "device.mount",
+            name = %mount.name,
+            path = %mount.root.display(),
+            read_only = mount.read_only,
+        );
+        if !mount.root.is_dir() {
+            warn!(
+                event = "device.mount.missing",
+                name = %mount.name,
+                path = %mount.root.display(),
             );
-            let conn_attempt = tokio::select! {
-                signal = &mut shutdown => shutdown_device!(signal),
-                result = conn_attempt => result,
-            };
+        }
+    }
+}
 
-            let conn = match conn_attempt {
-                Ok(Ok(c)) => {
-                    retry_delay = INITIAL_RETRY_DELAY;
-                    c.into_connection()
-                }
-                Ok(Err(e)) => {
-                    if let Some(rpc_error) = e.downcast_ref::<GatewayRpcError>() {
-                        if rpc_error.is_setup_required() {
-                            error!(
-                                event = "connect.setup_required",
-                                error = %rpc_error,
-                            );
-                            return Err(e);
-                        }
+/// Connection loop of one identity, until shutdown or a fatal error.
+/// Settings sent through `identity_rx` apply to the next requests; only
+/// changed gateway credentials or advertised mounts reconnect.
+async fn serve_device(
+    mut identity_rx: tokio::sync::watch::Receiver<DeviceIdentity>,
+    handles: control::DeviceHandles,
+    updater: update::Updater,
+    approvals: approval::Approvals,
+    shutdown: CancellationToken,
+    drain: Drain,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut identity = identity_rx.borrow_and_update().clone();
+    let device_id = identity.device_id.clone();
+    let control::DeviceHandles {
+        state: device_state,
+        active_requests,
+        exec_event_outbox,
+        response_outbox,
+        metrics: syscall_metrics,
+        limits: syscall_limits,
+        cron: cron_jobs,
+        ..
+    } = handles;
+
+    info!(event = "device.start", url = %identity.url);
+    log_mounts(&identity.paths);
+    let tools = reload::CurrentTools::new(&identity);
+
+    let outbox_for_exec_events = exec_event_outbox.clone();
+    let exec_event_owner = device_id.clone();
+    let mut exec_events = subscribe_exec_events();
+    // Subscribed first, so events of sessions that ended while the daemon
+    // was down are not missed.
+    let adopted = adopt_shell_sessions(Some(&device_id)).await;
+    if adopted > 0 {
+        info!(event = "shell.session.adopted", count = adopted);
+    }
+    let exec_event_span = tracing::Span::current();
+    let exec_event_collector = tokio::spawn(
+        async move {
+            loop {
+                match exec_events.recv().await {
+                    Ok(event) if event.owner.as_deref() == Some(exec_event_owner.as_str()) => {
+                        queue_exec_event_for_retry(&outbox_for_exec_events, event.params);
                     }
-                    error!(
-                        event = "connect.failed",
-                        error = %e,
-                        retry_seconds = retry_delay.as_secs(),
-                    );
-                    tokio::select! {
-                        signal = &mut shutdown => shutdown_device!(signal),
-                        _ = tokio::time::sleep(retry_delay) => {}
+                    Ok(_) => {}
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!(event = "device.exec.event.lagged", skipped);
                     }
-                    retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
-                    continue;
-                }
-                Err(_) => {
-                    error!(
-                        event = "connect.timeout",
-                        timeout_seconds = CONNECT_TIMEOUT.as_secs(),
-                        retry_seconds = retry_delay.as_secs(),
-                    );
-                    tokio::select! {
-                        signal = &mut shutdown => shutdown_device!(signal),
-                        _ = tokio::time::sleep(retry_delay) => {}
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => {
+                        break;
                     }
-                    retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
-                    continue;
                 }
-            };
+            }
+        }
+        .instrument(exec_event_span),
+    );
+    // Jobs keep to their schedule while the gateway is unreachable.
+    let cron_scheduler = tokio::spawn(
+        cron::run_scheduler(cron_jobs.clone(), tools.clone(), approvals.clone())
+            .instrument(tracing::Span::current()),
+    );
+
+    macro_rules! shutdown_device {
+        () => {{
+            exec_event_collector.abort();
+            cron_scheduler.abort();
+            for event in detach_shell_sessions(Some(&device_id)).await {
+                queue_exec_event_for_retry(&exec_event_outbox, event);
+            }
+            let undelivered = exec_event_outbox_len(&exec_event_outbox);
+            if undelivered > 0 {
+                warn!(event = "device.exec.event.undelivered", count = undelivered);
+            }
+            info!(event = "device.stop");
+            return Ok(());
+        }};
+    }
 
-            info!(event = "connect.ok", implements = ?DEVICE_DRIVER_IMPLEMENTS);
+    // Requests and undelivered responses (in `handles`) outlive
+    // individual sockets so a resumed session can pick them up again.
+    let current_link = resume::CurrentLink::default();
+    let mut session_token: Option<String> = None;
+    let mut generation = 0u64;
+
+    const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+    let mut backoff = reconnect::Backoff::new(
+        identity
+            .max_retry_delay
+            .unwrap_or(reconnect::DEFAULT_MAX_RETRY_DELAY),
+    );
+    let mut network = reconnect::NetworkWatcher::spawn();
+
+    // Take over reloaded settings. Evaluates to whether the gateway
+    // connection has to be re-established for them.
+    macro_rules! apply_identity {
+        () => {{
+            let next = identity_rx.borrow_and_update().clone();
+            let reconnect = reload::gateway_changed(&identity, &next);
+            if !reload::session_survives(&identity, &next) {
+                session_token = None;
+            }
+            tools.replace(&next);
+            syscall_limits.reconfigure(&next.limits);
+            backoff = reconnect::Backoff::new(
+                next.max_retry_delay
+                    .unwrap_or(reconnect::DEFAULT_MAX_RETRY_DELAY),
+            );
+            device_state.set_gateway_url(&redact_url_for_log(&next.url));
+            log_mounts(&next.paths);
+            info!(event = "config.applied", reconnect);
+            identity = next;
+            reconnect
+        }};
+    }
 
-            let conn = Arc::new(conn);
-            let weak_conn = Arc::downgrade(&conn);
-            let binary_inbox = transfer::BinaryFrameInbox::with_sender(move |frame| {
-                if let Some(conn) = weak_conn.upgrade() {
-                    tokio::spawn(async move {
-                        let _ = conn.send_binary(frame).await;
-                    });
+    // Sleep out the backoff delay, cut short by shutdown, by a network
+    // change that makes an immediate attempt worthwhile, or by new settings.
+    macro_rules! wait_before_retry {
+        ($delay:expr, $error:expr) => {{
+            device_state.waiting($delay, $error);
+            tokio::select! {
+                _ = shutdown.cancelled() => shutdown_device!(),
+                _ = tokio::time::sleep($delay) => {}
+                change = network.changed() => {
+                    info!(event = "connect.retry_now", reason = change.as_str());
+                    backoff.reset();
                 }
-            });
-            let binary_inbox_for_handler = binary_inbox.clone();
-            conn.set_binary_handler(move |data| {
-                binary_inbox_for_handler.push(data);
-            })
-            .await;
-
-            let conn_clone = conn.clone();
-            let tools_clone = tools_for_handler.clone();
-            let workspace_clone = workspace.clone();
-            let binary_inbox_clone = binary_inbox.clone();
-            let active_requests = ActiveRequests::default();
-            let active_requests_for_handler = active_requests.clone();
-            let request_span = tracing::Span::current();
-
-            // In the new OS architecture, the kernel sends req frames directly to
-            // the driver. We dispatch based on `call` and respond with a res frame.
-            conn.set_frame_handler(move |frame| match frame {
-                Frame::Req(req) => {
-                    let cancellation =
-                        active_requests_for_handler.register(&req, &binary_inbox_clone);
-                    let requests = active_requests_for_handler.clone();
-                    let conn = conn_clone.clone();
-                    let tools = tools_clone.clone();
-                    let workspace = workspace_clone.clone();
-                    let binary_inbox = binary_inbox_clone.clone();
-                    let request_span = request_span.clone();
-                    let id = req.id.clone();
-
-                    tokio::spawn(
-                        async move {
-                            tokio::select! {
-                                biased;
-                                _ = cancellation.cancelled() => {}
-                                _ = handle_driver_request(
-                                    &conn,
-                                    &tools,
-                                    &workspace,
-                                    &req,
-                                    &binary_inbox,
-                                    &cancellation,
-                                ) => {}
-                            }
-                            requests.finish(&id, &cancellation);
-                        }
-                        .instrument(request_span),
-                    );
+                Ok(()) = identity_rx.changed() => {
+                    apply_identity!();
+                    info!(event = "connect.retry_now", reason = "config");
+                    backoff.reset();
                 }
-                Frame::Sig(signal) if signal.signal == REQUEST_CANCEL_SIGNAL => {
-                    let cancellation = signal
-                        .payload
-                        .and_then(|payload| serde_json::from_value(payload).ok());
-                    if let Some(cancellation) = cancellation {
-                        active_requests_for_handler.cancel(cancellation, &binary_inbox_clone);
+            }
+        }};
+    }
+
+    loop {
+        info!(event = "connect.attempt", url = %identity.url);
+        device_state.connecting();
+
+        let session = SessionResume {
+            token: session_token.clone(),
+            pending: active_requests
+                .ids()
+                .into_iter()
+                .chain(response_outbox.ids())
+                .collect(),
+        };
+
+        let conn_attempt = tokio::time::timeout(
+            CONNECT_TIMEOUT,
+            KernelClient::connect_driver(
+                &identity.url,
+                device_id.clone(),
+                DriverInfo {
+                    implements: driver_implements(&tools.get()),
+                    mounts: tools.get().paths.advertised(),
+                    system: Some(system_info::collect().await),
+                },
+                identity.auth.clone(),
+                Some(session),
+                |_frame| {},
+            ),
+        );
+        let conn_attempt = tokio::select! {
+            _ = shutdown.cancelled() => shutdown_device!(),
+            result = conn_attempt => result,
+        };
+
+        let conn = match conn_attempt {
+            Ok(Ok(c)) => c.into_connection(),
+            Ok(Err(e)) => {
+                if let Some(rpc_error) = e.downcast_ref::<GatewayRpcError>() {
+                    if let Some(hint) = rpc_error.update_hint() {
+                        updater.offer(&hint);
+                    }
+                    if rpc_error.is_setup_required() {
+                        error!(
+                            event = "connect.setup_required",
+                            error = %rpc_error,
+                        );
+                        return Err(e);
                     }
                 }
-                _ => {}
-            })
-            .await;
+                let delay = backoff.next_delay();
+                error!(
+                    event = "connect.failed",
+                    error = %e,
+                    retry_seconds = delay.as_secs_f64(),
+                );
+                wait_before_retry!(delay, e.to_string());
+                continue;
+            }
+            Err(_) => {
+                let delay = backoff.next_delay();
+                error!(
+                    event = "connect.timeout",
+                    timeout_seconds = CONNECT_TIMEOUT.as_secs(),
+                    retry_seconds = delay.as_secs_f64(),
+                );
+                wait_before_retry!(delay, "connect timed out");
+                continue;
+            }
+        };
+        device_state.connected(
+            conn.connect_result
+                .as_ref()
+                .map(|result| result.server.connection_id.clone()),
+        );
+        network.clear();
+
+        let session_info = conn
+            .connect_result
+            .as_ref()
+            .and_then(|result| result.session.clone());
+        let resumed =
+            session_token.is_some() && session_info.as_ref().is_some_and(|session| session.resumed);
+        info!(
+            event = "connect.ok",
+            implements = ?driver_implements(&tools.get()),
+            resumable = session_info.is_some(),
+            resumed,
+        );
+        if let Some(hint) = conn
+            .connect_result
+            .as_ref()
+            .and_then(|result| result.update.as_ref())
+        {
+            updater.offer(hint);
+        }
+        if !resumed {
+            let dropped = response_outbox.clear();
+            let carried = active_requests.ids().len();
+            if dropped > 0 || carried > 0 {
+                warn!(
+                    event = "session.resume_rejected",
+                    dropped_responses = dropped,
+                    cancelled_requests = carried,
+                );
+            }
+            // Only body-less requests survive a disconnect, so no inbox
+            // streams are touched here.
+            active_requests
+                .cancel_all("Device session expired", &transfer::BinaryFrameInbox::new());
+        }
+        response_outbox.set_session_ttl(
+            session_info
+                .as_ref()
+                .and_then(|session| session.ttl_ms)
+                .map(tokio::time::Duration::from_millis),
+        );
+        session_token = session_info.map(|session| session.token);
+        generation += 1;
+
+        let conn = Arc::new(conn);
+        let weak_conn = Arc::downgrade(&conn);
+        let binary_inbox = transfer::BinaryFrameInbox::with_sender(move |frame| {
+            if let Some(conn) = weak_conn.upgrade() {
+                tokio::spawn(async move {
+                    let _ = conn.send_binary(frame).await;
+                });
+            }
+        });
+        let binary_inbox_for_handler = binary_inbox.clone();
+        conn.set_binary_handler(move |data| {
+            binary_inbox_for_handler.push(data);
+        })
+        .await;
 
-            let flushed = flush_exec_event_outbox(&conn, &exec_event_outbox).await;
-            if flushed > 0 {
-                info!(
-                    event = "device.exec.event.flushed",
-                    sent = flushed,
-                    remaining = exec_event_outbox_len(&exec_event_outbox),
+        let link = resume::DriverLink {
+            generation,
+            conn: conn.clone(),
+            binary_inbox: binary_inbox.clone(),
+        };
+        let link_for_handler = current_link.clone();
+        let outbox_for_handler = response_outbox.clone();
+        let tools_for_handler = tools.clone();
+        let cron_for_handler = cron_jobs.clone();
+        let binary_inbox_clone = binary_inbox.clone();
+        let active_requests_for_handler = active_requests.clone();
+        let metrics_for_handler = syscall_metrics.clone();
+        let limits_for_handler = syscall_limits.clone();
+        let approvals_for_handler = approvals.clone();
+        let device_id_for_handler = device_id.clone();
+        let shutdown_for_handler = shutdown.clone();
+        let request_span = tracing::Span::current();
+
+        // In the new OS architecture, the kernel sends req frames directly to
+        // the driver. We dispatch based on `call` and respond with a res frame.
+        conn.set_frame_handler(move |frame| match frame {
+            // Draining: refuse new work so the gateway can retry elsewhere.
+            Frame::Req(req) if shutdown_for_handler.is_cancelled() => {
+                warn!(
+                    event = "request.rejected",
+                    id = %req.id,
+                    call = %req.call,
+                    reason = "shutting_down",
+                );
+                if let Some(body) = req.body {
+                    binary_inbox_clone.register(Some(body));
+                    binary_inbox_clone.cancel_incoming(body.stream_id, "Device shutting down");
+                }
+                let link = link_for_handler.clone();
+                let outbox = outbox_for_handler.clone();
+                let response = shutting_down_response(&req.id);
+                metrics_for_handler.record(
+                    &req.call,
+                    metrics::Outcome::of(&response),
+                    Duration::ZERO,
+                );
+                tokio::spawn(
+                    async move {
+                        resume::deliver_response(
+                            &link, generation, &outbox, &req.call, response, None,
+                        )
+                        .await;
+                    }
+                    .instrument(request_span.clone()),
+                );
+            }
+            Frame::Req(req) => {
+                let cancellation = active_requests_for_handler.register(&req, &binary_inbox_clone);
+                let requests = active_requests_for_handler.clone();
+                let link = link_for_handler.clone();
+                let outbox = outbox_for_handler.clone();
+                let tools = tools_for_handler.get();
+                let cron_jobs = cron_for_handler.clone();
+                let binary_inbox = binary_inbox_clone.clone();
+                let request_span = request_span.clone();
+                let metrics = metrics_for_handler.clone();
+                let limits = limits_for_handler.clone();
+                let approvals = approvals_for_handler.clone();
+                let device_id = device_id_for_handler.clone();
+                let id = req.id.clone();
+
+                tokio::spawn(
+                    async move {
+                        let started_at = tokio::time::Instant::now();
+                        tokio::select! {
+                            biased;
+                            _ = cancellation.cancelled() => {
+                                metrics.record(
+                                    &req.call,
+                                    metrics::Outcome::Cancelled,
+                                    started_at.elapsed(),
+                                );
+                            }
+                            _ = async {
+                                // Waiting for a person holds no queue slot.
+                                let reviewed = match req.call.as_str() {
+                                    "cron.run" => cron_jobs.run_args_with_command(req.args.as_ref()),
+                                    _ => None,
+                                };
+                                let approval = match tools
+                                    .approval
+                                    .review(&req.call, reviewed.as_ref().or(req.args.as_ref()))
+                                {
+                                    Some(detail) => {
+                                        approvals
+                                            .ask(&device_id, &req.id, &req.call, detail, tools.approval.timeout())
+                                            .await
+                                    }
+                                    None => Ok(()),
+                                };
+                                let (response, body) = match approval {
+                                    Ok(()) => {
+                                        let permit = limits.acquire(&req.call).await;
+                                        match &permit {
+                                            Ok(_) => {
+                                                handle_driver_request(
+                                                    &tools,
+                                                    &cron_jobs,
+                                                    &req,
+                                                    &binary_inbox,
+                                                    &cancellation,
+                                                )
+                                                .await
+                                            }
+                                            Err(error) => {
+                                                warn!(
+                                                    event = "request.rejected",
+                                                    id = %req.id,
+                                                    call = %req.call,
+                                                    reason = ?error,
+                                                );
+                                                (limits.rejection(&req.id, &req.call, *error), None)
+                                            }
+                                        }
+                                    }
+                                    Err(reason) => {
+                                        if let Some(body) = req.body {
+                                            binary_inbox.cancel_incoming(body.stream_id, "Request denied");
+                                        }
+                                        warn!(
+                                            event = "request.denied",
+                                            id = %req.id,
+                                            call = %req.call,
+                                            reason = %reason,
+                                        );
+                                        (driver_error_response(&req.id, &req.call, reason), None)
+                                    }
+                                };
+                                metrics.record(
+                                    &req.call,
+                                    metrics::Outcome::of(&response),
+                                    started_at.elapsed(),
+                                );
+                                resume::deliver_response(
+                                    &link,
+                                    generation,
+                                    &outbox,
+                                    &req.call,
+                                    response,
+                                    body,
+                                )
+                                .await;
+                            } => {}
+                        }
+                        requests.finish(&id, &cancellation);
+                    }
+                    .instrument(request_span),
                 );
             }
+            Frame::Sig(signal) if signal.signal == REQUEST_CANCEL_SIGNAL => {
+                let cancellation = signal
+                    .payload
+                    .and_then(|payload| serde_json::from_value(payload).ok());
+                if let Some(cancellation) = cancellation {
+                    active_requests_for_handler.cancel(cancellation, &binary_inbox_clone);
+                }
+            }
+            _ => {}
+        })
+        .await;
+        current_link.set(link.clone());
+        if resumed {
+            response_outbox.replay(&link).await;
+        }
 
-            let keepalive_interval = tokio::time::Duration::from_secs(240);
-            let keepalive_timeout = tokio::time::Duration::from_secs(10);
+        let flushed = flush_exec_event_outbox(&conn, &exec_event_outbox).await;
+        if flushed > 0 {
             info!(
-                event = "connect.keepalive_configured",
-                keepalive_seconds = keepalive_interval.as_secs(),
+                event = "device.exec.event.flushed",
+                sent = flushed,
+                remaining = exec_event_outbox_len(&exec_event_outbox),
             );
-            let mut next_keepalive_at = tokio::time::Instant::now() + keepalive_interval;
+        }
 
-            // Monitor for disconnection or Ctrl+C
-            loop {
-                tokio::select! {
-                    signal = &mut shutdown => {
-                        active_requests.cancel_all("Device shutting down", &binary_inbox);
-                        shutdown_device!(signal);
+        let mut keepalive = reconnect::KeepaliveSchedule::new();
+        let connected_at = tokio::time::Instant::now();
+
+        // Monitor for disconnection, network changes, reloads, or Ctrl+C
+        let mut reconfigured = false;
+        let lost = loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => {
+                    shut_down_connected(
+                        &conn,
+                        &device_id,
+                        &active_requests,
+                        &drain,
+                        &binary_inbox,
+                        &exec_event_outbox,
+                    )
+                    .await;
+                    shutdown_device!();
+                }
+                change = network.changed() => {
+                    info!(event = "keepalive.probe", reason = change.as_str());
+                    keepalive.probe_now(tokio::time::Instant::now());
+                }
+                Ok(()) = identity_rx.changed() => {
+                    if apply_identity!() {
+                        info!(event = "connect.reconfigure");
+                        reconfigured = true;
+                        break "Gateway credentials changed";
+                    }
+                }
+                _ = tokio::time::sleep(Duration::from_secs(1)) => {
+                    if conn.is_disconnected() {
+                        warn!(event = "connect.lost");
+                        break "Device disconnected";
                     }
-                    _ = tokio::time::sleep(tokio::time::Duration::from_secs(1)) => {
-                        if conn.is_disconnected() {
-                            active_requests.cancel_all("Device disconnected", &binary_inbox);
-                            warn!(
-                                event = "connect.lost",
-                                retry_seconds = 3,
-                            );
-                            tokio::select! {
-                                signal = &mut shutdown => shutdown_device!(signal),
-                                _ = tokio::time::sleep(tokio::time::Duration::from_secs(3)) => {}
-                            }
-                            break; // Break inner loop to reconnect
-                        }
 
-                        let flushed = flush_exec_event_outbox(&conn, &exec_event_outbox).await;
-                        if flushed > 0 {
-                            info!(
-                                event = "device.exec.event.flushed",
-                                sent = flushed,
-                                remaining = exec_event_outbox_len(&exec_event_outbox),
-                            );
-                        }
+                    let flushed = flush_exec_event_outbox(&conn, &exec_event_outbox).await;
+                    if flushed > 0 {
+                        info!(
+                            event = "device.exec.event.flushed",
+                            sent = flushed,
+                            remaining = exec_event_outbox_len(&exec_event_outbox),
+                        );
+                    }
+                }
+            }
 
-                        if tokio::time::Instant::now() >= next_keepalive_at {
-                            let payload = b"gsv-keepalive".to_vec();
-                            let keepalive = tokio::select! {
-                                signal = &mut shutdown => {
-                                    active_requests.cancel_all("Device shutting down", &binary_inbox);
-                                    shutdown_device!(signal)
-                                },
-                                result = tokio::time::timeout(keepalive_timeout, conn.send_ping(payload)) => result,
-                            };
-
-                            match keepalive {
-                                Ok(Ok(())) => {
-                                    next_keepalive_at = tokio::time::Instant::now() + keepalive_interval;
-                                }
-                                Ok(Err(e)) => {
-                                    active_requests.cancel_all("Device disconnected", &binary_inbox);
-                                    warn!(
-                                        event = "keepalive.request_error",
-                                        error = %e,
-                                        retry_seconds = 3,
-                                    );
-                                    tokio::select! {
-                                        signal = &mut shutdown => shutdown_device!(signal),
-                                        _ = tokio::time::sleep(tokio::time::Duration::from_secs(3)) => {}
-                                    }
-                                    break;
-                                }
-                                Err(_) => {
-                                    active_requests.cancel_all("Device keepalive timed out", &binary_inbox);
-                                    warn!(
-                                        event = "keepalive.timeout",
-                                        timeout_seconds = 10,
-                                        retry_seconds = 3,
-                                    );
-                                    tokio::select! {
-                                        signal = &mut shutdown => shutdown_device!(signal),
-                                        _ = tokio::time::sleep(tokio::time::Duration::from_secs(3)) => {}
-                                    }
-                                    break;
-                                }
-                            }
-                        }
+            if keepalive.is_due(tokio::time::Instant::now()) {
+                let ping = tokio::select! {
+                    _ = shutdown.cancelled() => {
+                        shut_down_connected(
+                            &conn,
+                            &device_id,
+                            &active_requests,
+                            &drain,
+                            &binary_inbox,
+                            &exec_event_outbox,
+                        )
+                        .await;
+                        shutdown_device!()
+                    },
+                    result = conn.ping(reconnect::KEEPALIVE_PONG_TIMEOUT) => result,
+                };
+                match ping {
+                    Ok(rtt) => {
+                        keepalive.record_pong(tokio::time::Instant::now());
+                        tracing::debug!(
+                            event = "keepalive.ok",
+                            rtt_ms = rtt.as_secs_f64() * 1000.0,
+                            next_seconds = keepalive.interval().as_secs(),
+                        );
+                    }
+                    Err(e) => {
+                        warn!(
+                            event = "keepalive.failed",
+                            error = %e,
+                            timeout_seconds = reconnect::KEEPALIVE_PONG_TIMEOUT.as_secs(),
+                        );
+                        break "Device keepalive timed out";
                     }
                 }
             }
-            active_requests.cancel_all("Device disconnected", &binary_inbox);
+        };
+        current_link.clear();
+        if reconfigured {
+            let _ = conn.close().await;
         }
-    };
+        detach_requests(
+            &active_requests,
+            session_token.is_some(),
+            lost,
+            &binary_inbox,
+        );
 
-    run.instrument(device_span).await
+        // A connection that held up, or that was closed for new settings,
+        // reconnects right away; one that dropped soon after connecting
+        // keeps backing off.
+        if reconfigured || connected_at.elapsed() >= reconnect::STABLE_CONNECTION {
+            backoff.reset();
+        } else {
+            let delay = backoff.next_delay();
+            info!(
+                event = "connect.unstable",
+                retry_seconds = delay.as_secs_f64()
+            );
+            wait_before_retry!(delay, lost);
+        }
+    }
 }
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use gsv::config::DeviceConfig;
     use gsv::protocol::{parse_binary_frame, BINARY_FRAME_CANCEL, BINARY_FRAME_END};
+    use gsv::tools::all_tools_with_workspace_for_device;
     use std::sync::atomic::{AtomicBool, Ordering};
 
+    fn test_identity(device_id: &str, token: &str) -> DeviceIdentity {
+        DeviceIdentity {
+            device_id: device_id.to_string(),
+            url: "ws://127.0.0.1:1/ws".to_string(),
+            auth: GatewayAuth {
+                username: Some("alice".to_string()),
+                password: None,
+                token: Some(token.to_string()),
+            },
+            paths: PathResolver::new(std::env::temp_dir()),
+            max_retry_delay: None,
+            limits: Default::default(),
+            kill_any_user: false,
+            desktop: false,
+            approval: Default::default(),
+        }
+    }
+
+    #[test]
+    fn reload_starts_updates_and_stops_identities() {
+        let mut supervisor = Supervisor {
+            flags: DeviceRunFlags::default(),
+            devices: Arc::new(Mutex::new(Vec::new())),
+            shutdown: CancellationToken::new(),
+            running: HashMap::new(),
+            serving: FuturesUnordered::new(),
+            next_serial: 0,
+            any_served: false,
+            first_error: None,
+            updater: update::Updater::new(&DeviceConfig {
+                auto_update: Some(false),
+                ..DeviceConfig::default()
+            }),
+            drain: Drain::default(),
+            approvals: approval::Approvals::default(),
+        };
+        supervisor.start(test_identity("home", "t1"));
+        supervisor.start(test_identity("work", "t1"));
+        let work_stop = supervisor.running["work"].stop.clone();
+
+        let changes = supervisor.apply(vec![
+            test_identity("home", "t2"),
+            test_identity("lab", "t1"),
+        ]);
+        assert_eq!(changes, "stopped work, reconnecting home, started lab");
+        assert!(work_stop.is_cancelled());
+        assert_eq!(
+            supervisor.running["home"]
+                .identity
+                .borrow()
+                .auth
+                .token
+                .as_deref(),
+            Some("t2")
+        );
+        let ids = supervisor
+            .devices()
+            .iter()
+            .map(|handles| handles.device_id.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["home", "lab"]);
+
+        // A late outcome of the stopped identity does not touch a successor
+        // that reuses its id.
+        supervisor.start(test_identity("work", "t1"));
+        supervisor.finished(("work".to_string(), 1, Ok(())));
+        assert!(supervisor.running.contains_key("work"));
+        supervisor.finished(("work".to_string(), 3, Ok(())));
+        assert!(!supervisor.running.contains_key("work"));
+        assert_eq!(
+            supervisor.apply(vec![test_identity("home", "t2")]),
+            "stopped lab, reloaded home"
+        );
+    }
+
+    #[test]
+    fn mount_flags_override_configured_mounts() {
+        let mut cfg = CliConfig::default();
+        cfg.device.mounts.push(gsv::config::DeviceMountConfig {
+            name: "data".to_string(),
+            path: PathBuf::from("/data"),
+            read_only: false,
+        });
+        let paths = build_device_paths(
+            PathBuf::from("/srv/work"),
+            &cfg.device.mounts,
+            &["data=/mnt/data:ro".to_string(), "notes=/notes".to_string()],
+        )
+        .unwrap();
+        let mounts = paths
+            .mounts()
+            .iter()
+            .map(|mount| (mount.name.as_str(), mount.root.clone(), mount.read_only))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            mounts,
+            [
+                ("workspace", PathBuf::from("/srv/work"), false),
+                ("data", PathBuf::from("/mnt/data"), true),
+                ("notes", PathBuf::from("/notes"), false),
+            ]
+        );
+        parse_mount_flag("no-equals").unwrap_err();
+        build_device_paths(
+            PathBuf::from("/"),
+            &cfg.device.mounts,
+            &["bad name=/x".to_string()],
+        )
+        .unwrap_err();
+    }
+
     fn test_exec_event(index: usize) -> DeviceExecEventParams {
         DeviceExecEventParams {
             event_id: format!("event-{index}"),
@@ -1028,6 +2104,7 @@ mod tests {
             output_tail: Some("ok".to_string()),
             started_at: Some(1),
             ended_at: Some(2),
+            job_id: None,
         }
     }
 
@@ -1133,6 +2210,46 @@ mod tests {
         assert!(second.is_cancelled());
     }
 
+    #[test]
+    fn requests_refused_while_draining_can_be_retried_elsewhere() {
+        let response = shutting_down_response("req-1");
+        assert_eq!(response.id, "req-1");
+        assert!(!response.ok);
+        let error = response.error.unwrap();
+        assert_eq!(error.code, SHUTTING_DOWN_ERROR_CODE);
+        assert_eq!(error.retryable, Some(true));
+        assert_eq!(error.details, Some(json!({ "reason": "shutting_down" })));
+    }
+
+    #[tokio::test]
+    async fn drain_waits_for_running_requests_until_the_grace_period_ends() {
+        let requests = ActiveRequests::default();
+        let inbox = transfer::BinaryFrameInbox::new();
+        let request = RequestFrame::new("fs.write", None);
+        let cancellation = requests.register(&request, &inbox);
+        let drain = Drain {
+            grace_period: Duration::from_secs(5),
+            interrupt: CancellationToken::new(),
+        };
+
+        let finishing = requests.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            finishing.finish(&request.id, &cancellation);
+        });
+        assert_eq!(requests.drain(&drain).await, 0);
+
+        let stuck = requests.register(&RequestFrame::new("fs.transfer", None), &inbox);
+        drain.interrupt.cancel();
+        assert_eq!(requests.drain(&drain).await, 1);
+        let short = Drain {
+            grace_period: Duration::from_millis(50),
+            interrupt: CancellationToken::new(),
+        };
+        assert_eq!(requests.drain(&short).await, 1);
+        assert!(!stuck.is_cancelled());
+    }
+
     #[test]
     fn connection_teardown_cancels_all_requests() {
         let requests = ActiveRequests::default();
@@ -0,0 +1,234 @@
+//! Reconnect pacing for the device daemon: jittered backoff between connect
+//! attempts, an adaptive keepalive interval, and a watcher that notices
+//! network changes (interfaces coming and going, or the machine waking from
+//! sleep) so the daemon can reconnect without waiting out its backoff.
+
+use std::collections::BTreeSet;
+use std::time::SystemTime;
+
+use tokio::sync::watch;
+use tokio::time::{Duration, Instant};
+use tracing::info;
+
+pub(super) const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(1);
+pub(super) const DEFAULT_MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+/// Connections that stay up at least this long reset the backoff.
+pub(super) const STABLE_CONNECTION: Duration = Duration::from_secs(30);
+
+const KEEPALIVE_MIN_INTERVAL: Duration = Duration::from_secs(15);
+const KEEPALIVE_MAX_INTERVAL: Duration = Duration::from_secs(60);
+pub(super) const KEEPALIVE_PONG_TIMEOUT: Duration = Duration::from_secs(10);
+
+const NETWORK_POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Wall-clock time advancing this much further than the monotonic clock
+/// between two polls means the machine was suspended.
+const SLEEP_DETECTION_SLACK: Duration = Duration::from_secs(5);
+
+/// Exponential backoff with "equal jitter": each delay is half the current
+/// step plus a random share of the other half, so a fleet of devices that
+/// lost the gateway together does not reconnect in lockstep.
+pub(super) struct Backoff {
+    step: Duration,
+    max: Duration,
+}
+
+impl Backoff {
+    pub(super) fn new(max: Duration) -> Self {
+        Self {
+            step: INITIAL_RETRY_DELAY.min(max),
+            max,
+        }
+    }
+
+    pub(super) fn reset(&mut self) {
+        self.step = INITIAL_RETRY_DELAY.min(self.max);
+    }
+
+    /// Delay before the next attempt; advances the backoff step.
+    pub(super) fn next_delay(&mut self) -> Duration {
+        let half = self.step / 2;
+        let jitter = half.mul_f64(fastrand::f64());
+        self.step = (self.step * 2).min(self.max);
+        half + jitter
+    }
+}
+
+/// Ping interval that starts short after (re)connecting or a network change
+/// and stretches while pongs keep arriving.
+pub(super) struct KeepaliveSchedule {
+    interval: Duration,
+    next_at: Instant,
+}
+
+impl KeepaliveSchedule {
+    pub(super) fn new() -> Self {
+        Self {
+            interval: KEEPALIVE_MIN_INTERVAL,
+            next_at: Instant::now() + KEEPALIVE_MIN_INTERVAL,
+        }
+    }
+
+    pub(super) fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub(super) fn is_due(&self, now: Instant) -> bool {
+        now >= self.next_at
+    }
+
+    pub(super) fn record_pong(&mut self, now: Instant) {
+        self.interval = (self.interval * 2).min(KEEPALIVE_MAX_INTERVAL);
+        self.next_at = now + self.interval;
+    }
+
+    /// Probe right away and fall back to the shortest interval.
+    pub(super) fn probe_now(&mut self, now: Instant) {
+        self.interval = KEEPALIVE_MIN_INTERVAL;
+        self.next_at = now;
+    }
+}
+
+/// Why the network watcher fired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum NetworkChange {
+    Interfaces,
+    Resumed,
+}
+
+impl NetworkChange {
+    pub(super) fn as_str(self) -> &'static str {
+        match self {
+            Self::Interfaces => "interfaces",
+            Self::Resumed => "resumed_from_sleep",
+        }
+    }
+}
+
+/// Handle to the background network watcher.
+pub(super) struct NetworkWatcher {
+    changes: watch::Receiver<Option<NetworkChange>>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl NetworkWatcher {
+    pub(super) fn spawn() -> Self {
+        let (sender, changes) = watch::channel(None);
+        let task = tokio::spawn(async move {
+            let mut fingerprint = interface_fingerprint();
+            let mut last_wall = SystemTime::now();
+            let mut last_mono = Instant::now();
+            let mut ticker = tokio::time::interval(NETWORK_POLL_INTERVAL);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                let wall = SystemTime::now();
+                let mono = Instant::now();
+                let wall_elapsed = wall.duration_since(last_wall).unwrap_or_default();
+                let slept = wall_elapsed > mono.duration_since(last_mono) + SLEEP_DETECTION_SLACK;
+                last_wall = wall;
+                last_mono = mono;
+
+                let current = interface_fingerprint();
+                let change = if slept {
+                    Some(NetworkChange::Resumed)
+                } else if current != fingerprint {
+                    Some(NetworkChange::Interfaces)
+                } else {
+                    None
+                };
+                fingerprint = current;
+                if let Some(change) = change {
+                    info!(event = "network.changed", reason = change.as_str());
+                    if sender.send(Some(change)).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        Self { changes, task }
+    }
+
+    /// Wait for the next network change.
+    pub(super) async fn changed(&mut self) -> NetworkChange {
+        loop {
+            if self.changes.changed().await.is_err() {
+                std::future::pending::<()>().await;
+            }
+            if let Some(change) = *self.changes.borrow_and_update() {
+                return change;
+            }
+        }
+    }
+
+    /// Forget changes that happened while nobody was waiting.
+    pub(super) fn clear(&mut self) {
+        self.changes.borrow_and_update();
+    }
+}
+
+impl Drop for NetworkWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Non-loopback interface names and addresses. Any difference between two
+/// snapshots counts as a network change.
+fn interface_fingerprint() -> BTreeSet<String> {
+    if_addrs::get_if_addrs()
+        .map(|interfaces| {
+            interfaces
+                .into_iter()
+                .filter(|interface| !interface.is_loopback())
+                .map(|interface| format!("{}={}", interface.name, interface.ip()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_with_jitter_up_to_max() {
+        let mut backoff = Backoff::new(Duration::from_secs(8));
+        let mut steps = Vec::new();
+        for _ in 0..6 {
+            steps.push(backoff.step);
+            let delay = backoff.next_delay();
+            let step = *steps.last().unwrap();
+            assert!(
+                delay >= step / 2 && delay <= step,
+                "{delay:?} outside {step:?}"
+            );
+        }
+        assert_eq!(steps, [1, 2, 4, 8, 8, 8].map(Duration::from_secs).to_vec());
+
+        backoff.reset();
+        assert_eq!(backoff.step, INITIAL_RETRY_DELAY);
+    }
+
+    #[test]
+    fn backoff_respects_a_max_below_the_initial_step() {
+        let mut backoff = Backoff::new(Duration::from_millis(200));
+        assert!(backoff.next_delay() <= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn keepalive_interval_stretches_and_resets() {
+        let now = Instant::now();
+        let mut schedule = KeepaliveSchedule::new();
+        assert!(!schedule.is_due(now));
+
+        schedule.record_pong(now);
+        schedule.record_pong(now);
+        schedule.record_pong(now);
+        assert_eq!(schedule.interval(), KEEPALIVE_MAX_INTERVAL);
+        assert!(!schedule.is_due(now + Duration::from_secs(59)));
+
+        schedule.probe_now(now);
+        assert!(schedule.is_due(now));
+        assert_eq!(schedule.interval(), KEEPALIVE_MIN_INTERVAL);
+    }
+}
@@ -0,0 +1,280 @@
+//! Configuration reload for a running daemon.
+//!
+//! A reload is requested by a change to `config.toml` (the `GSV_CONFIG` file
+//! of a stateless daemon), by `SIGHUP`, or by the
+//! `reload` control-socket command. The daemon re-reads the configuration,
+//! resolves its identities again and hands each running identity its new
+//! settings: tools and limits apply to the next requests, and only changed
+//! gateway credentials or advertised mounts and syscalls cost a reconnect. A configuration that does not
+//! resolve is reported and the daemon keeps running on the previous one.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use gsv::tools::paths::PathResolver;
+use gsv::tools::{all_tools_with_paths_for_device, Tool};
+use tokio::sync::{mpsc, oneshot};
+use tracing::warn;
+
+use super::approval::ApprovalPolicy;
+use super::host::HostPolicy;
+use super::identities::DeviceIdentity;
+
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Tools and path resolver used for new requests. Requests already running
+/// keep the set they started with.
+pub(super) struct ToolSet {
+    pub(super) paths: PathResolver,
+    pub(super) tools: Vec<Box<dyn Tool>>,
+    pub(super) host: HostPolicy,
+    pub(super) desktop: bool,
+    pub(super) approval: ApprovalPolicy,
+}
+
+impl ToolSet {
+    fn new(identity: &DeviceIdentity) -> Self {
+        Self {
+            tools: all_tools_with_paths_for_device(
+                identity.paths.clone(),
+                identity.device_id.clone(),
+            ),
+            paths: identity.paths.clone(),
+            host: HostPolicy {
+                kill_any_user: identity.kill_any_user,
+            },
+            desktop: identity.desktop,
+            approval: identity.approval.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(super) struct CurrentTools(Arc<Mutex<Arc<ToolSet>>>);
+
+impl CurrentTools {
+    pub(super) fn new(identity: &DeviceIdentity) -> Self {
+        Self(Arc::new(Mutex::new(Arc::new(ToolSet::new(identity)))))
+    }
+
+    pub(super) fn get(&self) -> Arc<ToolSet> {
+        self.0.lock().expect("tool set mutex poisoned").clone()
+    }
+
+    pub(super) fn replace(&self, identity: &DeviceIdentity) {
+        let next = Arc::new(ToolSet::new(identity));
+        *self.0.lock().expect("tool set mutex poisoned") = next;
+    }
+}
+
+/// Whether switching from `current` to `next` needs a new gateway connection:
+/// other credentials, or other mounts or syscalls to advertise.
+pub(super) fn gateway_changed(current: &DeviceIdentity, next: &DeviceIdentity) -> bool {
+    current.url != next.url
+        || current.auth.username != next.auth.username
+        || current.auth.password != next.auth.password
+        || current.auth.token != next.auth.token
+        || current.desktop != next.desktop
+        || current.paths.advertised() != next.paths.advertised()
+}
+
+/// A session can only resume on the same gateway, as the same user.
+pub(super) fn session_survives(current: &DeviceIdentity, next: &DeviceIdentity) -> bool {
+    current.url == next.url && current.auth.username == next.auth.username
+}
+
+pub(super) struct ReloadRequest {
+    pub(super) source: &'static str,
+    /// Receives a summary of the applied change, or why it was rejected.
+    pub(super) reply: Option<oneshot::Sender<Result<String, String>>>,
+}
+
+/// Background tasks that turn `SIGHUP` and config edits into reload
+/// requests; stopped when dropped.
+pub(super) struct ReloadTriggers(Vec<tokio::task::JoinHandle<()>>);
+
+impl Drop for ReloadTriggers {
+    fn drop(&mut self) {
+        for task in &self.0 {
+            task.abort();
+        }
+    }
+}
+
+/// Forward `SIGHUP` and edits of `config_file` as reload requests until the
+/// receiver goes away.
+pub(super) fn spawn_triggers(
+    requests: mpsc::UnboundedSender<ReloadRequest>,
+    config_file: Option<PathBuf>,
+) -> ReloadTriggers {
+    let mut tasks = Vec::new();
+    #[cfg(unix)]
+    {
+        let requests = requests.clone();
+        tasks.push(tokio::spawn(async move {
+            use tokio::signal::unix::{signal, SignalKind};
+            let mut hangup = match signal(SignalKind::hangup()) {
+                Ok(hangup) => hangup,
+                Err(e) => {
+                    warn!(event = "config.sighup_unavailable", error = %e);
+                    return;
+                }
+            };
+            while hangup.recv().await.is_some() {
+                let request = ReloadRequest {
+                    source: "SIGHUP",
+                    reply: None,
+                };
+                if requests.send(request).is_err() {
+                    return;
+                }
+            }
+        }));
+    }
+
+    if let Some(path) = config_file {
+        tasks.push(tokio::spawn(watch_config_file(path, requests)));
+    }
+    ReloadTriggers(tasks)
+}
+
+/// Poll the file's size and modification time; cheap, and it also notices
+/// editors that replace the file instead of writing it in place.
+async fn watch_config_file(path: PathBuf, requests: mpsc::UnboundedSender<ReloadRequest>) {
+    let fingerprint = |path: &PathBuf| -> Option<(SystemTime, u64)> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some((metadata.modified().ok()?, metadata.len()))
+    };
+    let mut last = fingerprint(&path);
+    loop {
+        tokio::time::sleep(CONFIG_POLL_INTERVAL).await;
+        let current = fingerprint(&path);
+        if current == last {
+            continue;
+        }
+        last = current;
+        let request = ReloadRequest {
+            source: "config file",
+            reply: None,
+        };
+        if requests.send(request).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gsv::kernel_client::GatewayAuth;
+    use gsv::tools::paths::Mount;
+
+    fn identity(url: &str, username: &str, token: &str) -> DeviceIdentity {
+        DeviceIdentity {
+            device_id: "laptop".to_string(),
+            url: url.to_string(),
+            auth: GatewayAuth {
+                username: Some(username.to_string()),
+                password: None,
+                token: Some(token.to_string()),
+            },
+            paths: PathResolver::new(std::env::temp_dir()),
+            max_retry_delay: None,
+            limits: Default::default(),
+            kill_any_user: false,
+            desktop: false,
+            approval: Default::default(),
+        }
+    }
+
+    #[test]
+    fn only_gateway_settings_require_a_reconnect() {
+        let current = identity("wss://a/ws", "alice", "t1");
+
+        let mut limits_only = current.clone();
+        limits_only.limits.queue_size = Some(1);
+        limits_only.kill_any_user = true;
+        assert!(!gateway_changed(&current, &limits_only));
+
+        let rotated = identity("wss://a/ws", "alice", "t2");
+        assert!(gateway_changed(&current, &rotated));
+        assert!(session_survives(&current, &rotated));
+
+        let moved = identity("wss://b/ws", "alice", "t1");
+        assert!(gateway_changed(&current, &moved));
+        assert!(!session_survives(&current, &moved));
+
+        // Enabling desktop syscalls changes what the device advertises.
+        let mut desktop = current.clone();
+        desktop.desktop = true;
+        assert!(gateway_changed(&current, &desktop));
+        assert!(session_survives(&current, &desktop));
+    }
+
+    #[test]
+    fn changed_mounts_are_advertised_again() {
+        let mount = |name: &str, root: &str, read_only: bool| Mount {
+            name: name.to_string(),
+            root: PathBuf::from(root),
+            read_only,
+        };
+        let mut current = identity("wss://a/ws", "alice", "t1");
+        current.paths = PathResolver::with_mounts(
+            PathBuf::from("/srv/work"),
+            vec![mount("docs", "/srv/docs", false)],
+        )
+        .unwrap();
+
+        let mut same = current.clone();
+        same.paths = PathResolver::with_mounts(
+            PathBuf::from("/srv/work"),
+            vec![mount("docs", "/srv/docs", false)],
+        )
+        .unwrap();
+        assert!(!gateway_changed(&current, &same));
+
+        let mut read_only = current.clone();
+        read_only.paths = PathResolver::with_mounts(
+            PathBuf::from("/srv/work"),
+            vec![mount("docs", "/srv/docs", true)],
+        )
+        .unwrap();
+        assert!(gateway_changed(&current, &read_only));
+
+        let mut added = current.clone();
+        added.paths = PathResolver::with_mounts(
+            PathBuf::from("/srv/work"),
+            vec![
+                mount("docs", "/srv/docs", false),
+                mount("data", "/srv/data", true),
+            ],
+        )
+        .unwrap();
+        assert!(gateway_changed(&current, &added));
+        assert!(session_survives(&current, &added));
+
+        let mut moved = current.clone();
+        moved.paths = PathResolver::with_mounts(
+            PathBuf::from("/srv/other"),
+            vec![mount("docs", "/srv/docs", false)],
+        )
+        .unwrap();
+        assert!(gateway_changed(&current, &moved));
+    }
+
+    #[test]
+    fn replaced_tools_serve_new_requests_only() {
+        let mut identity = identity("wss://a/ws", "alice", "t1");
+        identity.paths = PathResolver::new(PathBuf::from("/srv/a"));
+        let tools = CurrentTools::new(&identity);
+        let before = tools.get();
+        identity.paths = PathResolver::new(PathBuf::from("/srv/b"));
+        identity.kill_any_user = true;
+        tools.replace(&identity);
+        assert_eq!(before.paths.workspace(), PathBuf::from("/srv/a"));
+        assert!(!before.host.kill_any_user);
+        assert_eq!(tools.get().paths.workspace(), PathBuf::from("/srv/b"));
+        assert!(tools.get().host.kill_any_user);
+    }
+}
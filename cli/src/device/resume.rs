@@ -0,0 +1,495 @@
+//! Driver session resumption.
+//!
+//! Requests keep running when the gateway socket drops. Responses that
+//! complete while no link is up are parked in a bounded outbox and replayed
+//! once `sys.connect` resumes the previous session.
+
+use std::collections::VecDeque;
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+
+use gsv::connection::Connection;
+use gsv::protocol::{Frame, ResponseFrame};
+use tokio::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+use super::transfer::{BinaryFrameInbox, OutgoingBody};
+
+const MAX_BUFFERED_RESPONSES: usize = 256;
+const MAX_BUFFERED_RESPONSE_BYTES: usize = 16 * 1024 * 1024;
+const MAX_BUFFERED_RESPONSE_AGE: Duration = Duration::from_secs(120);
+
+/// A gateway connection together with the binary inbox bound to it.
+/// `generation` increases on every reconnect so responses can tell whether
+/// their body streams were allocated on the socket they are about to use.
+#[derive(Clone)]
+pub(super) struct DriverLink {
+    pub(super) generation: u64,
+    pub(super) conn: Arc<Connection>,
+    pub(super) binary_inbox: BinaryFrameInbox,
+}
+
+#[derive(Clone, Default)]
+pub(super) struct CurrentLink(Arc<Mutex<Option<DriverLink>>>);
+
+impl CurrentLink {
+    pub(super) fn set(&self, link: DriverLink) {
+        *self.0.lock().expect("driver link mutex poisoned") = Some(link);
+    }
+
+    pub(super) fn clear(&self) {
+        self.0.lock().expect("driver link mutex poisoned").take();
+    }
+
+    fn live(&self) -> Option<DriverLink> {
+        self.0
+            .lock()
+            .expect("driver link mutex poisoned")
+            .clone()
+            .filter(|link| !link.conn.is_disconnected())
+    }
+}
+
+struct BufferedResponse {
+    call: String,
+    response: ResponseFrame,
+    body: Option<BufferedBody>,
+    queued_at: Instant,
+}
+
+struct BufferedBody {
+    bytes: Vec<u8>,
+    source: String,
+}
+
+impl BufferedResponse {
+    fn body_len(&self) -> usize {
+        self.body.as_ref().map_or(0, |body| body.bytes.len())
+    }
+}
+
+#[derive(Clone, Default)]
+pub(super) struct ResponseOutbox(Arc<Mutex<ResponseOutboxState>>);
+
+#[derive(Default)]
+struct ResponseOutboxState {
+    queue: VecDeque<BufferedResponse>,
+    body_bytes: usize,
+    max_age: Option<Duration>,
+}
+
+impl ResponseOutboxState {
+    fn pop_front(&mut self) -> Option<BufferedResponse> {
+        let entry = self.queue.pop_front()?;
+        self.body_bytes -= entry.body_len();
+        Some(entry)
+    }
+
+    fn max_age(&self) -> Duration {
+        self.max_age.unwrap_or(MAX_BUFFERED_RESPONSE_AGE)
+    }
+}
+
+impl ResponseOutbox {
+    fn lock(&self) -> std::sync::MutexGuard<'_, ResponseOutboxState> {
+        self.0.lock().expect("response outbox mutex poisoned")
+    }
+
+    /// Cap buffering at the session TTL the gateway advertised, since
+    /// anything older could never be replayed.
+    pub(super) fn set_session_ttl(&self, ttl: Option<Duration>) {
+        self.lock().max_age = ttl.map(|ttl| ttl.min(MAX_BUFFERED_RESPONSE_AGE));
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.lock().queue.len()
+    }
+
+    pub(super) fn ids(&self) -> Vec<String> {
+        self.lock()
+            .queue
+            .iter()
+            .map(|entry| entry.response.id.clone())
+            .collect()
+    }
+
+    fn push(&self, entry: BufferedResponse) {
+        let mut state = self.lock();
+        let mut dropped = Vec::new();
+        while !state.queue.is_empty()
+            && (state.queue.len() >= MAX_BUFFERED_RESPONSES
+                || state.body_bytes + entry.body_len() > MAX_BUFFERED_RESPONSE_BYTES)
+        {
+            if let Some(oldest) = state.pop_front() {
+                dropped.push(oldest.response.id);
+            }
+        }
+        state.body_bytes += entry.body_len();
+        state.queue.push_back(entry);
+        drop(state);
+
+        for request_id in dropped {
+            warn!(
+                event = "driver.response.buffer_dropped",
+                request_id = %request_id,
+                reason = "outbox full",
+            );
+        }
+    }
+
+    /// Drop responses older than the buffering window. Returns how many
+    /// were discarded.
+    pub(super) fn expire(&self, now: Instant) -> usize {
+        let mut state = self.lock();
+        let max_age = state.max_age();
+        let mut expired = 0;
+        while state
+            .queue
+            .front()
+            .is_some_and(|entry| now.saturating_duration_since(entry.queued_at) > max_age)
+        {
+            if let Some(entry) = state.pop_front() {
+                warn!(
+                    event = "driver.response.buffer_dropped",
+                    request_id = %entry.response.id,
+                    call = %entry.call,
+                    reason = "expired",
+                );
+                expired += 1;
+            }
+        }
+        expired
+    }
+
+    /// Discard everything, e.g. when the gateway refused to resume.
+    pub(super) fn clear(&self) -> usize {
+        let mut state = self.lock();
+        let cleared = state.queue.len();
+        state.queue.clear();
+        state.body_bytes = 0;
+        cleared
+    }
+
+    fn drain(&self) -> VecDeque<BufferedResponse> {
+        let mut state = self.lock();
+        state.body_bytes = 0;
+        std::mem::take(&mut state.queue)
+    }
+
+    fn requeue_front(&self, entries: VecDeque<BufferedResponse>) {
+        let mut state = self.lock();
+        for entry in entries.into_iter().rev() {
+            state.body_bytes += entry.body_len();
+            state.queue.push_front(entry);
+        }
+    }
+
+    /// Send everything buffered over a freshly resumed link, oldest first.
+    /// Stops at the first failed send and keeps the remainder queued.
+    pub(super) async fn replay(&self, link: &DriverLink) -> usize {
+        self.expire(Instant::now());
+        let mut entries = self.drain();
+        let mut sent = 0;
+        while let Some(entry) = entries.pop_front() {
+            let mut response = entry.response.clone();
+            let body = entry.body.as_ref().map(|body| {
+                let outgoing = OutgoingBody::new(
+                    &link.binary_inbox,
+                    Some(body.bytes.len() as u64),
+                    None,
+                    Cursor::new(body.bytes.clone()),
+                    body.source.clone(),
+                );
+                response.body = Some(outgoing.descriptor());
+                outgoing
+            });
+            match send_response(&link.conn, &entry.call, response, body).await {
+                Ok(()) => sent += 1,
+                Err(_unsent) => {
+                    entries.push_front(entry);
+                    self.requeue_front(entries);
+                    break;
+                }
+            }
+        }
+        if sent > 0 {
+            info!(
+                event = "driver.response.replayed",
+                sent,
+                remaining = self.len(),
+            );
+        }
+        sent
+    }
+}
+
+/// Deliver a finished request's response over whichever link is live now,
+/// or park it in the outbox until the session resumes.
+pub(super) async fn deliver_response(
+    link: &CurrentLink,
+    origin_generation: u64,
+    outbox: &ResponseOutbox,
+    call: &str,
+    mut response: ResponseFrame,
+    mut body: Option<OutgoingBody>,
+) {
+    if let Some(current) = link.live() {
+        if current.generation != origin_generation {
+            // Stream ids belong to the socket that allocated them, so the
+            // body has to be re-announced on the resumed connection.
+            match rebind_body(body.take(), &current.binary_inbox).await {
+                Ok(rebound) => {
+                    response.body = rebound.as_ref().map(OutgoingBody::descriptor);
+                    body = rebound;
+                }
+                Err(message) => {
+                    response = super::driver_error_response(&response.id, call, message);
+                }
+            }
+        }
+        match send_response(&current.conn, call, response, body).await {
+            Ok(()) => return,
+            Err(Unsent {
+                response: unsent,
+                body: unsent_body,
+            }) => {
+                response = unsent;
+                body = unsent_body;
+            }
+        }
+    }
+
+    let buffered_body = match body {
+        Some(body) => {
+            let source = body.source().to_string();
+            match body.into_bytes(MAX_BUFFERED_RESPONSE_BYTES).await {
+                Ok(bytes) => Some(BufferedBody { bytes, source }),
+                Err(message) => {
+                    response = super::driver_error_response(&response.id, call, message);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+    info!(
+        event = "driver.response.buffered",
+        request_id = %response.id,
+        call = %call,
+        body_bytes = buffered_body.as_ref().map_or(0, |body| body.bytes.len()),
+    );
+    outbox.push(BufferedResponse {
+        call: call.to_string(),
+        response,
+        body: buffered_body,
+        queued_at: Instant::now(),
+    });
+}
+
+async fn rebind_body(
+    body: Option<OutgoingBody>,
+    binary_inbox: &BinaryFrameInbox,
+) -> Result<Option<OutgoingBody>, String> {
+    let Some(body) = body else {
+        return Ok(None);
+    };
+    let source = body.source().to_string();
+    let bytes = body.into_bytes(MAX_BUFFERED_RESPONSE_BYTES).await?;
+    Ok(Some(OutgoingBody::new(
+        binary_inbox,
+        Some(bytes.len() as u64),
+        None,
+        Cursor::new(bytes),
+        source,
+    )))
+}
+
+/// A response whose text frame never reached the socket.
+struct Unsent {
+    response: ResponseFrame,
+    body: Option<OutgoingBody>,
+}
+
+async fn send_response(
+    conn: &Connection,
+    call: &str,
+    response: ResponseFrame,
+    body: Option<OutgoingBody>,
+) -> Result<(), Unsent> {
+    let message = match conn.codec().encode(&Frame::Res(response.clone())) {
+        Ok(message) => message,
+        Err(e) => {
+            error!(
+                event = "driver.response.serialize_failed",
+                request_id = %response.id,
+                call = %call,
+                error = %e,
+            );
+            return Ok(());
+        }
+    };
+    if let Err(e) = conn.send_message(message).await {
+        warn!(
+            event = "driver.response.send_failed",
+            request_id = %response.id,
+            call = %call,
+            error = %e,
+        );
+        return Err(Unsent { response, body });
+    }
+    if let Some(body) = body {
+        if let Err(e) = body.send(conn).await {
+            error!(
+                event = "driver.response.body_send_failed",
+                request_id = %response.id,
+                call = %call,
+                error = %e,
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffered(id: &str, body_len: usize, queued_at: Instant) -> BufferedResponse {
+        BufferedResponse {
+            call: "fs.read".to_string(),
+            response: ResponseFrame {
+                id: id.to_string(),
+                ok: true,
+                data: None,
+                error: None,
+                body: None,
+            },
+            body: (body_len > 0).then(|| BufferedBody {
+                bytes: vec![0; body_len],
+                source: id.to_string(),
+            }),
+            queued_at,
+        }
+    }
+
+    #[test]
+    fn outbox_drops_oldest_when_count_limit_reached() {
+        let outbox = ResponseOutbox::default();
+        let now = Instant::now();
+        for index in 0..=MAX_BUFFERED_RESPONSES {
+            outbox.push(buffered(&format!("req-{index}"), 0, now));
+        }
+
+        let ids = outbox.ids();
+        assert_eq!(ids.len(), MAX_BUFFERED_RESPONSES);
+        assert_eq!(ids.first().map(String::as_str), Some("req-1"));
+    }
+
+    #[test]
+    fn outbox_drops_oldest_when_byte_limit_reached() {
+        let outbox = ResponseOutbox::default();
+        let now = Instant::now();
+        let half = MAX_BUFFERED_RESPONSE_BYTES / 2;
+        outbox.push(buffered("a", half, now));
+        outbox.push(buffered("b", half, now));
+        outbox.push(buffered("c", 1, now));
+
+        assert_eq!(outbox.ids(), vec!["b".to_string(), "c".to_string()]);
+        assert_eq!(outbox.lock().body_bytes, half + 1);
+    }
+
+    #[test]
+    fn outbox_expires_entries_past_session_ttl() {
+        let outbox = ResponseOutbox::default();
+        outbox.set_session_ttl(Some(Duration::from_secs(10)));
+        let start = Instant::now();
+        outbox.push(buffered("old", 4, start));
+        outbox.push(buffered("new", 4, start + Duration::from_secs(8)));
+
+        assert_eq!(outbox.expire(start + Duration::from_secs(11)), 1);
+        assert_eq!(outbox.ids(), vec!["new".to_string()]);
+        assert_eq!(outbox.lock().body_bytes, 4);
+        assert_eq!(outbox.clear(), 1);
+        assert_eq!(outbox.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn responses_are_buffered_with_their_body_while_disconnected() {
+        let outbox = ResponseOutbox::default();
+        let inbox = BinaryFrameInbox::new();
+        let body = OutgoingBody::new(
+            &inbox,
+            Some(5),
+            None,
+            Cursor::new(b"hello".to_vec()),
+            "greeting.txt".to_string(),
+        );
+        let response = ResponseFrame {
+            id: "req-1".to_string(),
+            ok: true,
+            data: Some(serde_json::json!({ "ok": true })),
+            error: None,
+            body: Some(body.descriptor()),
+        };
+
+        deliver_response(
+            &CurrentLink::default(),
+            0,
+            &outbox,
+            "fs.read",
+            response,
+            Some(body),
+        )
+        .await;
+
+        let entries = outbox.drain();
+        assert_eq!(entries.len(), 1);
+        let entry = &entries[0];
+        assert_eq!(entry.response.id, "req-1");
+        assert_eq!(
+            entry.body.as_ref().map(|body| body.bytes.as_slice()),
+            Some(&b"hello"[..])
+        );
+    }
+
+    #[tokio::test]
+    async fn oversized_bodies_are_buffered_as_errors() {
+        let outbox = ResponseOutbox::default();
+        let inbox = BinaryFrameInbox::new();
+        let length = MAX_BUFFERED_RESPONSE_BYTES as u64 + 1;
+        let body = OutgoingBody::new(
+            &inbox,
+            Some(length),
+            None,
+            tokio::io::empty(),
+            "huge.bin".to_string(),
+        );
+        let response = ResponseFrame {
+            id: "req-2".to_string(),
+            ok: true,
+            data: None,
+            error: None,
+            body: Some(body.descriptor()),
+        };
+
+        deliver_response(
+            &CurrentLink::default(),
+            0,
+            &outbox,
+            "net.fetch",
+            response,
+            Some(body),
+        )
+        .await;
+
+        let entries = outbox.drain();
+        let entry = &entries[0];
+        assert!(entry.body.is_none());
+        assert!(!entry.response.ok);
+        assert!(entry
+            .response
+            .error
+            .as_ref()
+            .is_some_and(|error| error.message.contains("buffer limit")));
+    }
+}
@@ -0,0 +1,178 @@
+//! Connection state of a running daemon, reported through the control
+//! socket to `gsv device status`.
+
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tokio::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ConnectionState {
+    Connecting,
+    Connected,
+    Waiting,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DeviceStateSnapshot {
+    pub(crate) state: ConnectionState,
+    pub(crate) gateway_url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) connection_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) connected_since_ms: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) next_retry_at_ms: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) last_error: Option<String>,
+    /// Successful connects since the daemon started, the first included.
+    #[serde(default)]
+    pub(crate) connects: u64,
+}
+
+/// Writer side, owned by `serve_device` and shared with the control socket.
+#[derive(Clone)]
+pub(super) struct DeviceState(Arc<Mutex<DeviceStateSnapshot>>);
+
+impl DeviceState {
+    pub(super) fn new(gateway_url: &str) -> Self {
+        Self(Arc::new(Mutex::new(DeviceStateSnapshot {
+            state: ConnectionState::Connecting,
+            gateway_url: gateway_url.to_string(),
+            connection_id: None,
+            connected_since_ms: None,
+            next_retry_at_ms: None,
+            last_error: None,
+            connects: 0,
+        })))
+    }
+
+    fn update(&self, apply: impl FnOnce(&mut DeviceStateSnapshot)) {
+        if let Ok(mut snapshot) = self.0.lock() {
+            apply(&mut snapshot);
+        }
+    }
+
+    pub(super) fn set_gateway_url(&self, gateway_url: &str) {
+        self.update(|snapshot| snapshot.gateway_url = gateway_url.to_string());
+    }
+
+    pub(super) fn connecting(&self) {
+        self.update(|snapshot| {
+            snapshot.state = ConnectionState::Connecting;
+            snapshot.next_retry_at_ms = None;
+        });
+    }
+
+    pub(super) fn connected(&self, connection_id: Option<String>) {
+        self.update(|snapshot| {
+            snapshot.state = ConnectionState::Connected;
+            snapshot.connection_id = connection_id;
+            snapshot.connected_since_ms = Some(now_ms());
+            snapshot.next_retry_at_ms = None;
+            snapshot.connects += 1;
+        });
+    }
+
+    pub(super) fn waiting(&self, delay: Duration, error: impl Into<String>) {
+        let error = error.into();
+        self.update(|snapshot| {
+            snapshot.state = ConnectionState::Waiting;
+            snapshot.connection_id = None;
+            snapshot.connected_since_ms = None;
+            snapshot.next_retry_at_ms =
+                Some(now_ms().saturating_add(i64::try_from(delay.as_millis()).unwrap_or(i64::MAX)));
+            snapshot.last_error = Some(error);
+        });
+    }
+
+    pub(super) fn snapshot(&self) -> Option<DeviceStateSnapshot> {
+        self.0.lock().ok().map(|snapshot| snapshot.clone())
+    }
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// One-line summary for `gsv device status`.
+pub(crate) fn describe_device_state(snapshot: &DeviceStateSnapshot, now_ms: i64) -> String {
+    match snapshot.state {
+        ConnectionState::Connected => format!("Connection: connected to {}", snapshot.gateway_url),
+        ConnectionState::Connecting => {
+            format!("Connection: connecting to {}", snapshot.gateway_url)
+        }
+        ConnectionState::Waiting => {
+            let retry = snapshot
+                .next_retry_at_ms
+                .map(|at| {
+                    let seconds = (at.saturating_sub(now_ms).max(0) + 999) / 1000;
+                    format!("next retry in {}s", seconds)
+                })
+                .unwrap_or_else(|| "retry pending".to_string());
+            match &snapshot.last_error {
+                Some(error) => format!(
+                    "Connection: disconnected ({}; last error: {})",
+                    retry, error
+                ),
+                None => format!("Connection: disconnected ({})", retry),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(state: ConnectionState) -> DeviceStateSnapshot {
+        DeviceStateSnapshot {
+            state,
+            gateway_url: "wss://gateway.example/ws".to_string(),
+            connection_id: None,
+            connected_since_ms: None,
+            next_retry_at_ms: Some(10_500),
+            last_error: Some("connection refused".to_string()),
+            connects: 0,
+        }
+    }
+
+    #[test]
+    fn waiting_state_reports_time_until_next_retry() {
+        assert_eq!(
+            describe_device_state(&snapshot(ConnectionState::Waiting), 1_000),
+            "Connection: disconnected (next retry in 10s; last error: connection refused)"
+        );
+        assert_eq!(
+            describe_device_state(&snapshot(ConnectionState::Waiting), 20_000),
+            "Connection: disconnected (next retry in 0s; last error: connection refused)"
+        );
+    }
+
+    #[test]
+    fn reconnecting_clears_the_previous_connection() {
+        let state = DeviceState::new("wss://gateway.example/ws");
+        state.connected(Some("conn-1".to_string()));
+        state.waiting(Duration::from_secs(5), "Device disconnected");
+        let snapshot = state.snapshot().unwrap();
+        assert_eq!(snapshot.state, ConnectionState::Waiting);
+        assert_eq!(snapshot.connection_id, None);
+        assert_eq!(snapshot.connects, 1);
+        assert_eq!(snapshot.last_error.as_deref(), Some("Device disconnected"));
+
+        state.connected(Some("conn-2".to_string()));
+        let snapshot = state.snapshot().unwrap();
+        assert_eq!(snapshot.connection_id.as_deref(), Some("conn-2"));
+        assert_eq!(snapshot.connects, 2);
+    }
+
+    #[test]
+    fn connected_state_names_the_gateway() {
+        assert_eq!(
+            describe_device_state(&snapshot(ConnectionState::Connected), 0),
+            "Connection: connected to wss://gateway.example/ws"
+        );
+    }
+}
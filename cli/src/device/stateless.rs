@@ -0,0 +1,188 @@
+//! Configuration for `gsv device run --stateless`.
+//!
+//! Containers configure the daemon through their environment and read-only
+//! mounts, so this mode never touches `config.toml`: settings start from the
+//! file named by `GSV_CONFIG`, if any, and environment variables override
+//! them. The global `GSV_URL` and `GSV_TOKEN` apply as usual. Secrets can
+//! also be passed as files, as Kubernetes and Docker mount them.
+
+use std::path::{Path, PathBuf};
+
+use gsv::config::{CliConfig, DeviceMountConfig};
+
+use super::parse_mount_flag;
+
+pub(super) fn load_config() -> Result<CliConfig, String> {
+    config_from_env(|name| std::env::var(name).ok())
+}
+
+/// The file named by `GSV_CONFIG`, which a reload re-reads.
+pub(super) fn config_file() -> Option<PathBuf> {
+    std::env::var_os("GSV_CONFIG")
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+fn config_from_env(env: impl Fn(&str) -> Option<String>) -> Result<CliConfig, String> {
+    let value = |name: &str| {
+        env(name)
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    // `NAME`, or the contents of the file named by `NAME_FILE`.
+    let secret = |name: &str| -> Result<Option<String>, String> {
+        if let Some(secret) = value(name) {
+            return Ok(Some(secret));
+        }
+        let Some(path) = value(&format!("{}_FILE", name)) else {
+            return Ok(None);
+        };
+        let secret = std::fs::read_to_string(&path)
+            .map_err(|e| format!("{}_FILE: cannot read {}: {}", name, path, e))?;
+        Ok(Some(secret.trim().to_string()).filter(|secret| !secret.is_empty()))
+    };
+    let number = |name: &str| -> Result<Option<u64>, String> {
+        value(name)
+            .map(|raw| {
+                raw.parse::<u64>()
+                    .map_err(|e| format!("{} must be a whole number: {}", name, e))
+            })
+            .transpose()
+    };
+
+    let mut cfg = match value("GSV_CONFIG") {
+        Some(path) => CliConfig::try_load_from(Path::new(&path))
+            .map_err(|e| format!("GSV_CONFIG ({}): {}", path, e))?,
+        None => CliConfig::default(),
+    };
+
+    if let Some(username) = value("GSV_USERNAME") {
+        cfg.gateway.username = Some(username);
+    }
+    if let Some(id) = value("GSV_DEVICE_ID") {
+        cfg.device.id = Some(id);
+    }
+    if let Some(token) = secret("GSV_DEVICE_TOKEN")? {
+        cfg.device.token = Some(token);
+    }
+    if let Some(workspace) = value("GSV_DEVICE_WORKSPACE") {
+        cfg.device.workspace = Some(PathBuf::from(workspace));
+    }
+    if let Some(mounts) = value("GSV_DEVICE_MOUNTS") {
+        cfg.device.mounts = mounts
+            .split(',')
+            .map(str::trim)
+            .filter(|spec| !spec.is_empty())
+            .map(|spec| {
+                let mount =
+                    parse_mount_flag(spec).map_err(|e| format!("GSV_DEVICE_MOUNTS: {}", e))?;
+                Ok(DeviceMountConfig {
+                    name: mount.name,
+                    path: mount.root,
+                    read_only: mount.read_only,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+    }
+    if let Some(secs) = number("GSV_DEVICE_MAX_RETRY_DELAY_SECS")? {
+        cfg.device.max_retry_delay_secs = Some(secs);
+    }
+    if let Some(size) = number("GSV_DEVICE_QUEUE_SIZE")? {
+        cfg.device.limits.queue_size = Some(usize::try_from(size).unwrap_or(usize::MAX));
+    }
+    if let Some(secs) = number("GSV_DEVICE_QUEUE_TIMEOUT_SECS")? {
+        cfg.device.limits.queue_timeout_secs = Some(secs);
+    }
+    if let Some(secs) = number("GSV_DEVICE_GRACE_PERIOD_SECS")? {
+        cfg.device.grace_period_secs = Some(secs);
+    }
+    if let Some(enabled) = value("GSV_DEVICE_KILL_ANY_USER") {
+        cfg.device.kill_any_user = Some(
+            enabled
+                .parse::<bool>()
+                .map_err(|e| format!("GSV_DEVICE_KILL_ANY_USER must be true or false: {}", e))?,
+        );
+    }
+    // Images are replaced by redeploying them, not by updating in place.
+    cfg.device.auto_update = Some(false);
+    Ok(cfg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<HashMap<_, _>>();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn environment_overrides_the_mounted_config_file() {
+        let dir = std::env::temp_dir().join(format!("gsv-stateless-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("config.toml");
+        std::fs::write(
+            &config,
+            "[gateway]\nusername = \"alice\"\n\n[device]\nid = \"from-file\"\nauto_update = true\n\n[device.limits]\nqueue_size = 4\n",
+        )
+        .unwrap();
+        let token = dir.join("token");
+        std::fs::write(&token, "secret-token\n").unwrap();
+
+        let cfg = config_from_env(env(&[
+            ("GSV_CONFIG", config.to_str().unwrap()),
+            ("GSV_DEVICE_ID", "ci-runner"),
+            ("GSV_DEVICE_TOKEN_FILE", token.to_str().unwrap()),
+            ("GSV_DEVICE_MOUNTS", "cache=/cache:ro, out=/out"),
+            ("GSV_DEVICE_MAX_RETRY_DELAY_SECS", "15"),
+            ("GSV_DEVICE_GRACE_PERIOD_SECS", "20"),
+            ("GSV_DEVICE_KILL_ANY_USER", "true"),
+        ]))
+        .unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(cfg.gateway.username.as_deref(), Some("alice"));
+        assert_eq!(cfg.device.id.as_deref(), Some("ci-runner"));
+        assert_eq!(cfg.device.token.as_deref(), Some("secret-token"));
+        assert_eq!(cfg.device.max_retry_delay_secs, Some(15));
+        assert_eq!(cfg.device.grace_period_secs, Some(20));
+        assert_eq!(cfg.device.kill_any_user, Some(true));
+        assert_eq!(cfg.device.limits.queue_size, Some(4));
+        assert_eq!(cfg.device.auto_update, Some(false));
+        let mounts = cfg
+            .device
+            .mounts
+            .iter()
+            .map(|mount| (mount.name.as_str(), mount.path.clone(), mount.read_only))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            mounts,
+            [
+                ("cache", PathBuf::from("/cache"), true),
+                ("out", PathBuf::from("/out"), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_values_are_reported_by_variable() {
+        let error = config_from_env(env(&[("GSV_DEVICE_QUEUE_SIZE", "lots")]))
+            .err()
+            .unwrap();
+        assert!(error.starts_with("GSV_DEVICE_QUEUE_SIZE"), "{error}");
+
+        let error = config_from_env(env(&[("GSV_DEVICE_TOKEN_FILE", "/nonexistent/token")]))
+            .err()
+            .unwrap();
+        assert!(error.starts_with("GSV_DEVICE_TOKEN_FILE"), "{error}");
+
+        // Without any variables the defaults apply, and nothing is read from home.
+        let cfg = config_from_env(env(&[])).unwrap();
+        assert_eq!(cfg.device.id, None);
+    }
+}
@@ -0,0 +1,804 @@
+//! What a device runs on, for `host.info` and the driver descriptor sent at
+//! connect.
+//!
+//! Linux is read from `/proc` and `/sys`, macOS from `sw_vers`, `sysctl`,
+//! `vm_stat`, `df`, `pmset` and `system_profiler`. The OS, CPU, GPUs and
+//! toolchains are probed once per daemon; memory, disks, battery and network
+//! interfaces on every call.
+
+use gsv::protocol::{
+    BatteryInfo, CpuInfo, DiskInfo, GpuInfo, MemoryInfo, NetworkInterface, OsInfo, SystemInfo,
+    Toolchain,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Toolchain name, the binaries that provide it, and the arguments that
+/// print its version.
+const TOOLCHAINS: &[(&str, &[&str], &[&str])] = &[
+    ("python", &["python3", "python"], &["--version"]),
+    ("node", &["node"], &["--version"]),
+    ("docker", &["docker"], &["--version"]),
+    ("cargo", &["cargo"], &["--version"]),
+    ("rustc", &["rustc"], &["--version"]),
+    ("go", &["go"], &["version"]),
+];
+
+/// The parts that do not change while the daemon runs.
+struct Fixed {
+    os: OsInfo,
+    cpu: CpuInfo,
+    gpus: Vec<GpuInfo>,
+    toolchains: Vec<Toolchain>,
+}
+
+static FIXED: OnceCell<Fixed> = OnceCell::const_new();
+
+pub(super) async fn collect() -> SystemInfo {
+    let fixed = FIXED
+        .get_or_init(|| async {
+            let (os, cpu, gpus, toolchains) =
+                tokio::join!(os_info(), cpu_info(), gpus(), toolchains());
+            Fixed {
+                os,
+                cpu,
+                gpus,
+                toolchains,
+            }
+        })
+        .await;
+    let (memory, disks, battery) = tokio::join!(memory(), disks(), battery());
+    SystemInfo {
+        os: fixed.os.clone(),
+        cpu: fixed.cpu.clone(),
+        memory,
+        disks,
+        gpus: fixed.gpus.clone(),
+        battery,
+        network: network_interfaces(),
+        toolchains: fixed.toolchains.clone(),
+    }
+}
+
+/// Standard output and error of a probe command that succeeded.
+async fn run(program: &str, args: &[&str]) -> Option<(String, String)> {
+    let output = tokio::time::timeout(
+        PROBE_TIMEOUT,
+        tokio::process::Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .ok()?
+    .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some((
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    ))
+}
+
+async fn run_stdout(program: &str, args: &[&str]) -> Option<String> {
+    run(program, args).await.map(|(stdout, _)| stdout)
+}
+
+#[cfg(target_os = "macos")]
+async fn run_line(program: &str, args: &[&str]) -> Option<String> {
+    first_line(&run_stdout(program, args).await?)
+}
+
+fn first_line(text: &str) -> Option<String> {
+    text.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+fn read(path: impl AsRef<Path>) -> Option<String> {
+    std::fs::read_to_string(path).ok()
+}
+
+fn read_line(path: impl AsRef<Path>) -> Option<String> {
+    first_line(&read(path)?)
+}
+
+fn base_os() -> OsInfo {
+    OsInfo {
+        family: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        hostname: hostname::get()
+            .ok()
+            .map(|name| name.to_string_lossy().into_owned()),
+        ..OsInfo::default()
+    }
+}
+
+#[cfg(target_os = "linux")]
+async fn os_info() -> OsInfo {
+    let release = read("/etc/os-release")
+        .or_else(|| read("/usr/lib/os-release"))
+        .map(|text| parse_os_release(&text))
+        .unwrap_or_default();
+    OsInfo {
+        name: release
+            .get("PRETTY_NAME")
+            .or_else(|| release.get("NAME"))
+            .cloned(),
+        version: release.get("VERSION_ID").cloned(),
+        kernel: read_line("/proc/sys/kernel/osrelease"),
+        ..base_os()
+    }
+}
+
+#[cfg(target_os = "macos")]
+async fn os_info() -> OsInfo {
+    let (name, version, kernel) = tokio::join!(
+        run_line("sw_vers", &["-productName"]),
+        run_line("sw_vers", &["-productVersion"]),
+        run_line("sysctl", &["-n", "kern.osrelease"]),
+    );
+    OsInfo {
+        name,
+        version,
+        kernel,
+        ..base_os()
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+async fn os_info() -> OsInfo {
+    base_os()
+}
+
+fn parse_os_release(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| {
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .unwrap_or(value);
+            (key.trim().to_string(), value.to_string())
+        })
+        .collect()
+}
+
+fn logical_cores() -> usize {
+    std::thread::available_parallelism()
+        .map(|cores| cores.get())
+        .unwrap_or(1)
+}
+
+#[cfg(target_os = "linux")]
+async fn cpu_info() -> CpuInfo {
+    let (model, physical_cores) = read("/proc/cpuinfo")
+        .map(|text| parse_cpuinfo(&text))
+        .unwrap_or_default();
+    CpuInfo {
+        model,
+        cores: logical_cores(),
+        physical_cores,
+    }
+}
+
+#[cfg(target_os = "macos")]
+async fn cpu_info() -> CpuInfo {
+    let (model, physical_cores) = tokio::join!(
+        run_line("sysctl", &["-n", "machdep.cpu.brand_string"]),
+        run_line("sysctl", &["-n", "hw.physicalcpu"]),
+    );
+    CpuInfo {
+        model,
+        cores: logical_cores(),
+        physical_cores: physical_cores.and_then(|cores| cores.parse().ok()),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+async fn cpu_info() -> CpuInfo {
+    CpuInfo {
+        cores: logical_cores(),
+        ..CpuInfo::default()
+    }
+}
+
+/// The CPU model, and the number of distinct cores where the kernel
+/// reports core ids.
+fn parse_cpuinfo(text: &str) -> (Option<String>, Option<usize>) {
+    let mut model = None;
+    let mut cores = std::collections::HashSet::new();
+    for processor in text.split("\n\n") {
+        let fields = processor
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .collect::<HashMap<_, _>>();
+        if model.is_none() {
+            model = ["model name", "Hardware", "Model", "cpu model"]
+                .iter()
+                .find_map(|key| fields.get(key).filter(|value| !value.is_empty()))
+                .map(|value| value.to_string());
+        }
+        if let Some(core) = fields.get("core id") {
+            let package = fields.get("physical id").copied().unwrap_or("0");
+            cores.insert((package.to_string(), core.to_string()));
+        }
+    }
+    (model, (!cores.is_empty()).then_some(cores.len()))
+}
+
+#[cfg(target_os = "linux")]
+async fn memory() -> Option<MemoryInfo> {
+    parse_meminfo(&read("/proc/meminfo")?)
+}
+
+#[cfg(target_os = "macos")]
+async fn memory() -> Option<MemoryInfo> {
+    let (total, vm_stat) = tokio::join!(
+        run_line("sysctl", &["-n", "hw.memsize"]),
+        run_stdout("vm_stat", &[]),
+    );
+    Some(MemoryInfo {
+        total_bytes: total?.parse().ok()?,
+        available_bytes: parse_vm_stat(&vm_stat?)?,
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+async fn memory() -> Option<MemoryInfo> {
+    None
+}
+
+fn parse_meminfo(text: &str) -> Option<MemoryInfo> {
+    let kib = |key: &str| -> Option<u64> {
+        text.lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))?
+            .split_whitespace()
+            .next()?
+            .parse::<u64>()
+            .ok()
+            .map(|kib| kib.saturating_mul(1024))
+    };
+    Some(MemoryInfo {
+        total_bytes: kib("MemTotal")?,
+        available_bytes: kib("MemAvailable").or_else(|| kib("MemFree"))?,
+    })
+}
+
+/// Free, inactive and speculative pages, which macOS hands out on demand.
+#[cfg(any(target_os = "macos", test))]
+fn parse_vm_stat(text: &str) -> Option<u64> {
+    let page_size = text
+        .lines()
+        .next()?
+        .split("page size of ")
+        .nth(1)?
+        .split_whitespace()
+        .next()?
+        .parse::<u64>()
+        .ok()?;
+    let pages = ["Pages free", "Pages inactive", "Pages speculative"]
+        .iter()
+        .filter_map(|key| {
+            text.lines()
+                .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))?
+                .trim()
+                .trim_end_matches('.')
+                .parse::<u64>()
+                .ok()
+        })
+        .sum::<u64>();
+    Some(pages.saturating_mul(page_size))
+}
+
+#[cfg(target_os = "linux")]
+async fn disks() -> Vec<DiskInfo> {
+    let Some(mounts) = read("/proc/mounts") else {
+        return Vec::new();
+    };
+    parse_mounts(&mounts)
+        .into_iter()
+        .filter_map(|(mount, filesystem)| {
+            let (total_bytes, available_bytes) = disk_space(&mount)?;
+            Some(DiskInfo {
+                mount,
+                filesystem: Some(filesystem),
+                total_bytes,
+                available_bytes,
+            })
+        })
+        .filter(|disk| disk.total_bytes > 0)
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn disks() -> Vec<DiskInfo> {
+    run_stdout("df", &["-kPl"])
+        .await
+        .map(|text| parse_df(&text))
+        .unwrap_or_default()
+}
+
+/// Mount points and filesystem types of block devices, once per device.
+#[cfg(any(target_os = "linux", test))]
+fn parse_mounts(text: &str) -> Vec<(String, String)> {
+    let mut seen = std::collections::HashSet::new();
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (device, mount, filesystem) = (fields.next()?, fields.next()?, fields.next()?);
+            if !device.starts_with("/dev/") || device.starts_with("/dev/loop") {
+                return None;
+            }
+            if !seen.insert(device.to_string()) {
+                return None;
+            }
+            Some((mount.replace("\\040", " "), filesystem.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+#[expect(
+    clippy::useless_conversion,
+    reason = "statvfs fields are narrower than u64 on 32-bit targets"
+)]
+fn disk_space(mount: &str) -> Option<(u64, u64)> {
+    let path = std::ffi::CString::new(mount).ok()?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is a valid C string and statvfs only writes to `stat`.
+    if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    // SAFETY: statvfs succeeded, so it filled in `stat`.
+    let stat = unsafe { stat.assume_init() };
+    let block = u64::from(stat.f_frsize);
+    Some((
+        u64::from(stat.f_blocks).saturating_mul(block),
+        u64::from(stat.f_bavail).saturating_mul(block),
+    ))
+}
+
+/// Local volumes from `df -kP`, leaving out macOS system volumes other than
+/// the data volume.
+#[cfg(any(not(target_os = "linux"), test))]
+fn parse_df(text: &str) -> Vec<DiskInfo> {
+    text.lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let device = fields.first()?;
+            let mount = fields.get(5..)?.join(" ");
+            if !device.starts_with("/dev/")
+                || (mount.starts_with("/System/Volumes/") && mount != "/System/Volumes/Data")
+            {
+                return None;
+            }
+            let kib = |index: usize| -> Option<u64> {
+                Some(fields.get(index)?.parse::<u64>().ok()?.saturating_mul(1024))
+            };
+            Some(DiskInfo {
+                mount,
+                filesystem: None,
+                total_bytes: kib(1)?,
+                available_bytes: kib(3)?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+async fn gpus() -> Vec<GpuInfo> {
+    let mut gpus = drm_gpus(Path::new("/sys/class/drm"));
+    if let Some(nvidia) = run_stdout(
+        "nvidia-smi",
+        &[
+            "--query-gpu=name,memory.total",
+            "--format=csv,noheader,nounits",
+        ],
+    )
+    .await
+    {
+        gpus.retain(|gpu| gpu.vendor.as_deref() != Some("NVIDIA"));
+        gpus.extend(nvidia.lines().filter_map(|line| {
+            let (name, memory) = line.rsplit_once(',')?;
+            Some(GpuInfo {
+                name: name.trim().to_string(),
+                vendor: Some("NVIDIA".to_string()),
+                memory_bytes: memory
+                    .trim()
+                    .parse::<u64>()
+                    .ok()
+                    .map(|mib| mib.saturating_mul(1024 * 1024)),
+            })
+        }));
+    }
+    gpus
+}
+
+#[cfg(target_os = "macos")]
+async fn gpus() -> Vec<GpuInfo> {
+    run_stdout("system_profiler", &["SPDisplaysDataType", "-json"])
+        .await
+        .map(|text| parse_system_profiler_gpus(&text))
+        .unwrap_or_default()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+async fn gpus() -> Vec<GpuInfo> {
+    Vec::new()
+}
+
+/// PCI display devices under `/sys/class/drm`, named by their PCI ids
+/// unless the driver reports a product name.
+#[cfg(any(target_os = "linux", test))]
+fn drm_gpus(drm: &Path) -> Vec<GpuInfo> {
+    let Ok(entries) = std::fs::read_dir(drm) else {
+        return Vec::new();
+    };
+    let mut cards = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| {
+            name.strip_prefix("card")
+                .is_some_and(|index| !index.is_empty() && index.chars().all(|c| c.is_ascii_digit()))
+        })
+        .collect::<Vec<_>>();
+    cards.sort();
+    cards
+        .into_iter()
+        .filter_map(|card| {
+            let device = drm.join(card).join("device");
+            let vendor_id = read_line(device.join("vendor"))?;
+            let device_id = read_line(device.join("device")).unwrap_or_default();
+            let vendor = match vendor_id.as_str() {
+                "0x10de" => Some("NVIDIA"),
+                "0x1002" => Some("AMD"),
+                "0x8086" => Some("Intel"),
+                _ => None,
+            };
+            let name = read_line(device.join("product_name")).unwrap_or_else(|| {
+                format!(
+                    "PCI {}:{}",
+                    vendor_id.trim_start_matches("0x"),
+                    device_id.trim_start_matches("0x")
+                )
+            });
+            Some(GpuInfo {
+                name,
+                vendor: vendor.map(str::to_string),
+                memory_bytes: read_line(device.join("mem_info_vram_total"))
+                    .and_then(|bytes| bytes.parse().ok()),
+            })
+        })
+        .collect()
+}
+
+#[cfg(any(target_os = "macos", test))]
+fn parse_system_profiler_gpus(text: &str) -> Vec<GpuInfo> {
+    let Ok(report) = serde_json::from_str::<serde_json::Value>(text) else {
+        return Vec::new();
+    };
+    let Some(displays) = report
+        .get("SPDisplaysDataType")
+        .and_then(|value| value.as_array())
+    else {
+        return Vec::new();
+    };
+    displays
+        .iter()
+        .filter_map(|display| {
+            let field = |key: &str| display.get(key).and_then(|value| value.as_str());
+            let name = field("sppci_model").or_else(|| field("_name"))?;
+            let vendor = field("spdisplays_vendor").map(|vendor| {
+                let vendor = vendor.strip_prefix("sppci_vendor_").unwrap_or(vendor);
+                vendor.split(" (").next().unwrap_or(vendor).to_string()
+            });
+            let memory_bytes = field("spdisplays_vram")
+                .or_else(|| field("spdisplays_vram_shared"))
+                .and_then(parse_size);
+            Some(GpuInfo {
+                name: name.to_string(),
+                vendor,
+                memory_bytes,
+            })
+        })
+        .collect()
+}
+
+/// Sizes such as `1536 MB` or `8 GB`.
+#[cfg(any(target_os = "macos", test))]
+fn parse_size(text: &str) -> Option<u64> {
+    let mut parts = text.split_whitespace();
+    let value = parts.next()?.parse::<u64>().ok()?;
+    let unit = match parts.next()? {
+        "KB" => 1 << 10,
+        "MB" => 1 << 20,
+        "GB" => 1 << 30,
+        "TB" => 1 << 40,
+        _ => return None,
+    };
+    Some(value.saturating_mul(unit))
+}
+
+#[cfg(target_os = "linux")]
+async fn battery() -> Option<BatteryInfo> {
+    linux_battery(Path::new("/sys/class/power_supply"))
+}
+
+#[cfg(target_os = "macos")]
+async fn battery() -> Option<BatteryInfo> {
+    parse_pmset(&run_stdout("pmset", &["-g", "batt"]).await?)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+async fn battery() -> Option<BatteryInfo> {
+    None
+}
+
+/// The first system battery; batteries of peripherals have `scope` set to
+/// `Device`.
+#[cfg(any(target_os = "linux", test))]
+fn linux_battery(power_supply: &Path) -> Option<BatteryInfo> {
+    let mut supplies = std::fs::read_dir(power_supply)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    supplies.sort();
+    let supply = supplies.into_iter().find(|supply| {
+        read_line(supply.join("type")).as_deref() == Some("Battery")
+            && read_line(supply.join("scope")).as_deref() != Some("Device")
+    })?;
+    let state = match read_line(supply.join("status")).as_deref() {
+        Some("Charging") => "charging",
+        Some("Discharging") => "discharging",
+        Some("Full") => "full",
+        Some("Not charging") => "not_charging",
+        _ => "unknown",
+    };
+    Some(BatteryInfo {
+        percent: read_line(supply.join("capacity")).and_then(|capacity| capacity.parse().ok()),
+        state: state.to_string(),
+    })
+}
+
+/// The internal battery line of `pmset -g batt`, e.g.
+/// `-InternalBattery-0 (id=1234)`, a tab, then `85%; discharging; 4:12 remaining`.
+#[cfg(any(target_os = "macos", test))]
+fn parse_pmset(text: &str) -> Option<BatteryInfo> {
+    let line = text.lines().find(|line| line.contains("InternalBattery"))?;
+    let (_, details) = line.split_once('\t')?;
+    let mut fields = details.split(';').map(str::trim);
+    let percent = fields
+        .next()?
+        .strip_suffix('%')
+        .and_then(|percent| percent.parse().ok());
+    let state = match fields.next().unwrap_or_default() {
+        "charging" | "finishing charge" => "charging",
+        "discharging" => "discharging",
+        "charged" => "full",
+        "AC attached" => "not_charging",
+        _ => "unknown",
+    };
+    Some(BatteryInfo {
+        percent,
+        state: state.to_string(),
+    })
+}
+
+fn network_interfaces() -> Vec<NetworkInterface> {
+    let mut interfaces = BTreeMap::<String, Vec<String>>::new();
+    for interface in if_addrs::get_if_addrs().unwrap_or_default() {
+        if interface.is_loopback() {
+            continue;
+        }
+        interfaces
+            .entry(interface.name.clone())
+            .or_default()
+            .push(interface.ip().to_string());
+    }
+    interfaces
+        .into_iter()
+        .map(|(name, addresses)| NetworkInterface { name, addresses })
+        .collect()
+}
+
+async fn toolchains() -> Vec<Toolchain> {
+    let probes = TOOLCHAINS.iter().filter_map(|(name, binaries, args)| {
+        let path = binaries.iter().find_map(|binary| find_on_path(binary))?;
+        Some(async move {
+            let program = path.to_string_lossy().into_owned();
+            // Python 2 prints its version to standard error.
+            let version = run(&program, args)
+                .await
+                .and_then(|(stdout, stderr)| first_line(&stdout).or_else(|| first_line(&stderr)));
+            Toolchain {
+                name: name.to_string(),
+                path: program,
+                version,
+            }
+        })
+    });
+    futures_util::future::join_all(probes).await
+}
+
+fn find_on_path(binary: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(binary))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path)
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.with_extension("exe").is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proc_files_are_parsed() {
+        let release = parse_os_release(
+            "NAME=\"Ubuntu\"\nVERSION_ID=\"24.04\"\nPRETTY_NAME=\"Ubuntu 24.04.1 LTS\"\n",
+        );
+        assert_eq!(release["PRETTY_NAME"], "Ubuntu 24.04.1 LTS");
+        assert_eq!(release["VERSION_ID"], "24.04");
+
+        let cpuinfo = "processor\t: 0\nmodel name\t: AMD Ryzen 9 7950X\nphysical id\t: 0\ncore id\t: 0\n\n\
+                       processor\t: 1\nmodel name\t: AMD Ryzen 9 7950X\nphysical id\t: 0\ncore id\t: 0\n\n\
+                       processor\t: 2\nmodel name\t: AMD Ryzen 9 7950X\nphysical id\t: 0\ncore id\t: 1\n";
+        assert_eq!(
+            parse_cpuinfo(cpuinfo),
+            (Some("AMD Ryzen 9 7950X".to_string()), Some(2))
+        );
+        assert_eq!(
+            parse_cpuinfo("processor\t: 0\nBogoMIPS\t: 108.00\n\nModel\t: Raspberry Pi 5\n"),
+            (Some("Raspberry Pi 5".to_string()), None)
+        );
+
+        assert_eq!(
+            parse_meminfo(
+                "MemTotal:       16318480 kB\nMemFree:  1000 kB\nMemAvailable:   8159240 kB\n"
+            ),
+            Some(MemoryInfo {
+                total_bytes: 16318480 * 1024,
+                available_bytes: 8159240 * 1024,
+            })
+        );
+
+        assert_eq!(
+            parse_mounts(
+                "/dev/nvme0n1p2 / ext4 rw 0 0\nproc /proc proc rw 0 0\n/dev/loop3 /snap/core squashfs ro 0 0\n\
+                 /dev/nvme0n1p1 /boot/efi vfat rw 0 0\n/dev/nvme0n1p2 /var/lib/docker ext4 rw 0 0\n\
+                 /dev/sdb1 /mnt/My\\040Drive exfat rw 0 0\n"
+            ),
+            vec![
+                ("/".to_string(), "ext4".to_string()),
+                ("/boot/efi".to_string(), "vfat".to_string()),
+                ("/mnt/My Drive".to_string(), "exfat".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn sysfs_gpus_and_batteries_are_found() {
+        let root = std::env::temp_dir().join(format!("gsv-sysinfo-{}", uuid::Uuid::new_v4()));
+        let card = root.join("drm/card0/device");
+        std::fs::create_dir_all(&card).unwrap();
+        std::fs::create_dir_all(root.join("drm/card0-DP-1")).unwrap();
+        std::fs::write(card.join("vendor"), "0x1002\n").unwrap();
+        std::fs::write(card.join("device"), "0x744c\n").unwrap();
+        std::fs::write(card.join("mem_info_vram_total"), "25753026560\n").unwrap();
+        assert_eq!(
+            drm_gpus(&root.join("drm")),
+            vec![GpuInfo {
+                name: "PCI 1002:744c".to_string(),
+                vendor: Some("AMD".to_string()),
+                memory_bytes: Some(25753026560),
+            }]
+        );
+
+        let supplies = root.join("power_supply");
+        for (name, kind, scope, capacity, status) in [
+            ("AC", "Mains", None, None, None),
+            ("BAT0", "Battery", None, Some("81"), Some("Discharging")),
+            (
+                "hidpp_battery_0",
+                "Battery",
+                Some("Device"),
+                Some("40"),
+                Some("Discharging"),
+            ),
+        ] {
+            let supply = supplies.join(name);
+            std::fs::create_dir_all(&supply).unwrap();
+            std::fs::write(supply.join("type"), kind).unwrap();
+            for (file, value) in [("scope", scope), ("capacity", capacity), ("status", status)] {
+                if let Some(value) = value {
+                    std::fs::write(supply.join(file), value).unwrap();
+                }
+            }
+        }
+        assert_eq!(
+            linux_battery(&supplies),
+            Some(BatteryInfo {
+                percent: Some(81),
+                state: "discharging".to_string(),
+            })
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn macos_tool_output_is_parsed() {
+        let vm_stat = "Mach Virtual Memory Statistics: (page size of 16384 bytes)\n\
+                       Pages free:                               10000.\n\
+                       Pages active:                            200000.\n\
+                       Pages inactive:                           30000.\n\
+                       Pages speculative:                         2000.\n";
+        assert_eq!(parse_vm_stat(vm_stat), Some(42000 * 16384));
+
+        let df = "Filesystem 1024-blocks Used Available Capacity Mounted on\n\
+                  /dev/disk3s1s1 482797652 10405300 231020140 5% /\n\
+                  devfs 206 206 0 100% /dev\n\
+                  /dev/disk3s6 482797652 2097172 231020140 1% /System/Volumes/VM\n\
+                  /dev/disk3s5 482797652 238393256 231020140 51% /System/Volumes/Data\n\
+                  /dev/disk5s1 976486400 1024 976485376 1% /Volumes/Backup Drive\n";
+        let mounts = parse_df(df)
+            .into_iter()
+            .map(|disk| disk.mount)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            mounts,
+            ["/", "/System/Volumes/Data", "/Volumes/Backup Drive"]
+        );
+
+        let gpus = parse_system_profiler_gpus(
+            r#"{"SPDisplaysDataType":[{"_name":"kHW_AppleM2ProItem","sppci_model":"Apple M2 Pro","spdisplays_vendor":"sppci_vendor_Apple"},
+                {"sppci_model":"AMD Radeon Pro 5500M","spdisplays_vendor":"AMD (0x1002)","spdisplays_vram":"8 GB"}]}"#,
+        );
+        assert_eq!(gpus[0].vendor.as_deref(), Some("Apple"));
+        assert_eq!(gpus[1].vendor.as_deref(), Some("AMD"));
+        assert_eq!(gpus[1].memory_bytes, Some(8 << 30));
+
+        assert_eq!(
+            parse_pmset(
+                "Now drawing from 'Battery Power'\n -InternalBattery-0 (id=4587619)\t85%; discharging; 4:12 remaining present: true\n"
+            ),
+            Some(BatteryInfo {
+                percent: Some(85),
+                state: "discharging".to_string(),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn collect_describes_this_machine() {
+        let info = collect().await;
+        assert_eq!(info.os.family, std::env::consts::OS);
+        assert!(info.cpu.cores > 0);
+        let value = serde_json::to_value(&info).unwrap();
+        assert!(value["cpu"]["cores"].is_u64());
+    }
+}
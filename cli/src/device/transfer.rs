@@ -3,6 +3,9 @@ use gsv::protocol::{
     build_binary_frame, parse_binary_frame, FrameBodyDescriptor, BINARY_FRAME_CANCEL,
     BINARY_FRAME_DATA, BINARY_FRAME_END, BINARY_FRAME_ERROR,
 };
+use gsv::tools::digest::{file_digest, same_digest};
+use gsv::tools::paths::{Access, PathResolver};
+use gsv::tools::preview;
 use gsv::tools::ToolBody;
 use serde::Deserialize;
 use serde_json::{json, Value};
@@ -286,14 +289,14 @@ impl BinaryFrameInbox {
     }
 }
 
-struct IncomingStreamGuard<'a> {
+pub(super) struct IncomingStreamGuard<'a> {
     inbox: &'a BinaryFrameInbox,
     stream_id: u32,
     complete: bool,
 }
 
 impl<'a> IncomingStreamGuard<'a> {
-    fn new(inbox: &'a BinaryFrameInbox, stream_id: u32) -> Self {
+    pub(super) fn new(inbox: &'a BinaryFrameInbox, stream_id: u32) -> Self {
         Self {
             inbox,
             stream_id,
@@ -301,7 +304,7 @@ impl<'a> IncomingStreamGuard<'a> {
         }
     }
 
-    fn complete(&mut self) {
+    pub(super) fn complete(&mut self) {
         self.complete = true;
     }
 }
@@ -328,7 +331,7 @@ pub(super) struct OutgoingBody {
 }
 
 impl OutgoingBody {
-    fn new(
+    pub(super) fn new(
         binary_inbox: &BinaryFrameInbox,
         length: Option<u64>,
         max_length: Option<u64>,
@@ -364,6 +367,44 @@ impl OutgoingBody {
         }
     }
 
+    pub(super) fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Drain the body into memory so it can outlive the connection it was
+    /// prepared for. Fails once more than `max_bytes` have been read.
+    pub(super) async fn into_bytes(mut self, max_bytes: usize) -> Result<Vec<u8>, String> {
+        self.finished = true;
+        if self.length.is_some_and(|length| length > max_bytes as u64) {
+            return Err(format!(
+                "Body from '{}' exceeds buffer limit (max {} bytes)",
+                self.source, max_bytes
+            ));
+        }
+        let mut bytes = Vec::with_capacity(self.length.unwrap_or(0) as usize);
+        let limit = (max_bytes as u64).saturating_add(1);
+        (&mut self.reader)
+            .take(limit)
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(|e| format!("Failed to read '{}': {}", self.source, e))?;
+        if bytes.len() > max_bytes {
+            return Err(format!(
+                "Body from '{}' exceeds buffer limit (max {} bytes)",
+                self.source, max_bytes
+            ));
+        }
+        if let Some(length) = self.length.filter(|length| bytes.len() as u64 != *length) {
+            return Err(format!(
+                "Transfer size changed for '{}': expected {}, got {}",
+                self.source,
+                length,
+                bytes.len()
+            ));
+        }
+        Ok(bytes)
+    }
+
     pub(super) fn descriptor(&self) -> FrameBodyDescriptor {
         FrameBodyDescriptor {
             stream_id: self.stream_id,
@@ -495,7 +536,7 @@ pub async fn handle_transfer_syscall(
     call: &str,
     args: Value,
     request_body: Option<FrameBodyDescriptor>,
-    workspace: &Path,
+    paths: &PathResolver,
     binary_inbox: &BinaryFrameInbox,
 ) -> Option<Result<(Value, Option<OutgoingBody>), String>> {
     if matches!(call, "fs.transfer.stat" | "fs.transfer.send") {
@@ -506,10 +547,10 @@ pub async fn handle_transfer_syscall(
     }
 
     match call {
-        "fs.transfer.stat" => Some(handle_stat(args, workspace).await.map(|data| (data, None))),
-        "fs.transfer.send" => Some(handle_send(args, workspace, binary_inbox).await),
+        "fs.transfer.stat" => Some(handle_stat(args, paths).await.map(|data| (data, None))),
+        "fs.transfer.send" => Some(handle_send(args, paths, binary_inbox).await),
         "fs.transfer.receive" => Some(
-            handle_receive(args, request_body, workspace, binary_inbox)
+            handle_receive(args, request_body, paths, binary_inbox)
                 .await
                 .map(|data| (data, None)),
         ),
@@ -520,11 +561,18 @@ pub async fn handle_transfer_syscall(
 #[derive(Deserialize)]
 struct TransferStatArgs {
     path: String,
+    #[serde(default)]
+    digest: bool,
 }
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct TransferSendArgs {
     path: String,
+    #[serde(default)]
+    digest: bool,
+    #[serde(default)]
+    if_none_match: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -535,10 +583,10 @@ struct TransferReceiveArgs {
     content_type: Option<String>,
 }
 
-async fn handle_stat(args: Value, workspace: &Path) -> Result<Value, String> {
+async fn handle_stat(args: Value, paths: &PathResolver) -> Result<Value, String> {
     let args: TransferStatArgs =
         serde_json::from_value(args).map_err(|e| format!("Invalid arguments: {}", e))?;
-    let path = resolve_path(&args.path, workspace);
+    let path = paths.resolve(&args.path, Access::Read)?;
     let metadata = tokio::fs::metadata(&path)
         .await
         .map_err(|e| format!("Failed to stat '{}': {}", path.display(), e))?;
@@ -550,24 +598,28 @@ async fn handle_stat(args: Value, workspace: &Path) -> Result<Value, String> {
         None
     };
 
-    Ok(json!({
+    let mut data = json!({
         "ok": true,
         "path": path.display().to_string(),
         "size": metadata.len(),
         "isFile": metadata.is_file(),
         "isDirectory": metadata.is_dir(),
         "contentType": content_type
-    }))
+    });
+    if args.digest && metadata.is_file() {
+        data["digest"] = json!(file_digest(&path).await?);
+    }
+    Ok(data)
 }
 
 async fn handle_send(
     args: Value,
-    workspace: &Path,
+    paths: &PathResolver,
     binary_inbox: &BinaryFrameInbox,
 ) -> Result<(Value, Option<OutgoingBody>), String> {
     let args: TransferSendArgs =
         serde_json::from_value(args).map_err(|e| format!("Invalid arguments: {}", e))?;
-    let path = resolve_path(&args.path, workspace);
+    let path = paths.resolve(&args.path, Access::Read)?;
     let file = tokio::fs::File::open(&path)
         .await
         .map_err(|e| format!("Failed to open '{}': {}", path.display(), e))?;
@@ -583,14 +635,31 @@ async fn handle_send(
         .first()
         .map(|mime| mime.essence_str().to_string());
     let length = metadata.len();
+    let digest = if args.digest || args.if_none_match.is_some() {
+        Some(file_digest(&path).await?)
+    } else {
+        None
+    };
+    let mut data = json!({
+        "ok": true,
+        "path": path.display().to_string(),
+        "size": length,
+        "contentType": content_type
+    });
+    if let Some(digest) = digest {
+        let unchanged = args
+            .if_none_match
+            .as_deref()
+            .is_some_and(|known| same_digest(known, &digest));
+        data["digest"] = json!(digest);
+        if unchanged {
+            data["unchanged"] = json!(true);
+            return Ok((data, None));
+        }
+    }
 
     Ok((
-        json!({
-            "ok": true,
-            "path": path.display().to_string(),
-            "size": length,
-            "contentType": content_type
-        }),
+        data,
         Some(OutgoingBody::new(
             binary_inbox,
             Some(length),
@@ -604,9 +673,26 @@ async fn handle_send(
 async fn handle_receive(
     args: Value,
     request_body: Option<FrameBodyDescriptor>,
-    workspace: &Path,
+    paths: &PathResolver,
     binary_inbox: &BinaryFrameInbox,
 ) -> Result<Value, String> {
+    if preview::is_dry_run(&args) {
+        if let Some(body) = request_body {
+            binary_inbox.cancel_incoming(body.stream_id, "Request body not needed for a dry run");
+        }
+        let args: TransferReceiveArgs =
+            serde_json::from_value(args).map_err(|e| format!("Invalid arguments: {}", e))?;
+        let path = receive_destination(&args, paths).await?;
+        return Ok(json!({
+            "ok": true,
+            "dryRun": true,
+            "path": path.display().to_string(),
+            "size": request_body.and_then(|body| body.length),
+            "contentType": args.content_type,
+            "conflicts": preview::overwrite_conflicts(&path)
+        }));
+    }
+
     let body =
         request_body.ok_or_else(|| "fs.transfer.receive requires a request body".to_string())?;
     if body.stream_id == 0 {
@@ -619,98 +705,113 @@ async fn handle_receive(
     let args: TransferReceiveArgs =
         serde_json::from_value(args).map_err(|e| format!("Invalid arguments: {}", e))?;
 
-    let path = resolve_path(&args.path, workspace);
+    let path = receive_destination(&args, paths).await?;
     if let Some(parent) = path.parent() {
         tokio::fs::create_dir_all(parent)
             .await
             .map_err(|e| format!("Failed to create '{}': {}", parent.display(), e))?;
     }
+
+    let temp_path = transfer_temp_path(&path, body.stream_id);
+    let _temp_file = TempFileGuard(temp_path.clone());
+    let bytes_written = receive_to_file(
+        binary_inbox,
+        &mut stream_guard,
+        expected_length,
+        &temp_path,
+        &path,
+    )
+    .await?;
+    if let Err(error) = tokio::fs::rename(&temp_path, &path).await {
+        return Err(format!("Failed to replace '{}': {}", path.display(), error));
+    }
+    stream_guard.complete();
+
+    Ok(json!({
+        "ok": true,
+        "path": path.display().to_string(),
+        "bytesWritten": bytes_written,
+        "contentType": args.content_type
+    }))
+}
+
+/// Resolve where `fs.transfer.receive` writes, for both real and dry runs.
+async fn receive_destination(
+    args: &TransferReceiveArgs,
+    paths: &PathResolver,
+) -> Result<PathBuf, String> {
+    let path = paths.resolve(&args.path, Access::Write)?;
     if let Ok(metadata) = tokio::fs::metadata(&path).await {
         if metadata.is_dir() {
             return Err(format!("Destination is a directory: '{}'", path.display()));
         }
     }
+    Ok(path)
+}
 
-    let temp_path = transfer_temp_path(&path, body.stream_id);
-    let _temp_file = TempFileGuard(temp_path.clone());
+/// Write the body `stream_guard` watches to a new file at `temp_path`, for
+/// the destination `path`. The body has to be exactly `expected_length`
+/// bytes long.
+pub(super) async fn receive_to_file(
+    binary_inbox: &BinaryFrameInbox,
+    stream_guard: &mut IncomingStreamGuard<'_>,
+    expected_length: u64,
+    temp_path: &Path,
+    path: &Path,
+) -> Result<u64, String> {
+    let stream_id = stream_guard.stream_id;
     let mut file = tokio::fs::OpenOptions::new()
         .create_new(true)
         .write(true)
-        .open(&temp_path)
+        .open(temp_path)
         .await
         .map_err(|e| format!("Failed to open '{}': {}", temp_path.display(), e))?;
 
     let mut bytes_written: u64 = 0;
-    let receive_result: Result<(), String> = async {
-        loop {
-            let frame = binary_inbox.take(body.stream_id).await?;
-            if frame.flags & BINARY_FRAME_ERROR != 0 {
-                binary_inbox.discard(body.stream_id);
-                stream_guard.complete();
-                return Err(String::from_utf8(frame.payload)
-                    .unwrap_or_else(|_| "Binary transfer failed".to_string()));
-            }
-            if frame.flags & BINARY_FRAME_DATA != 0 {
-                bytes_written = bytes_written
-                    .checked_add(frame.payload.len() as u64)
-                    .ok_or_else(|| format!("Transfer size overflow for '{}'", path.display()))?;
-                if bytes_written > expected_length {
-                    return Err(format!(
-                        "Transfer size mismatch for '{}': expected {}, got more than {}",
-                        path.display(),
-                        expected_length,
-                        bytes_written
-                    ));
-                }
-                file.write_all(&frame.payload)
-                    .await
-                    .map_err(|e| format!("Failed to write '{}': {}", temp_path.display(), e))?;
-            }
-            if frame.flags & BINARY_FRAME_END != 0 {
-                break;
+    loop {
+        let frame = binary_inbox.take(stream_id).await?;
+        if frame.flags & BINARY_FRAME_ERROR != 0 {
+            binary_inbox.discard(stream_id);
+            stream_guard.complete();
+            return Err(String::from_utf8(frame.payload)
+                .unwrap_or_else(|_| "Binary transfer failed".to_string()));
+        }
+        if frame.flags & BINARY_FRAME_DATA != 0 {
+            bytes_written = bytes_written
+                .checked_add(frame.payload.len() as u64)
+                .ok_or_else(|| format!("Transfer size overflow for '{}'", path.display()))?;
+            if bytes_written > expected_length {
+                return Err(format!(
+                    "Transfer size mismatch for '{}': expected {}, got more than {}",
+                    path.display(),
+                    expected_length,
+                    bytes_written
+                ));
             }
+            file.write_all(&frame.payload)
+                .await
+                .map_err(|e| format!("Failed to write '{}': {}", temp_path.display(), e))?;
         }
-
-        file.flush()
-            .await
-            .map_err(|e| format!("Failed to flush '{}': {}", temp_path.display(), e))?;
-        if bytes_written != expected_length {
-            return Err(format!(
-                "Transfer size mismatch for '{}': expected {}, got {}",
-                path.display(),
-                expected_length,
-                bytes_written
-            ));
+        if frame.flags & BINARY_FRAME_END != 0 {
+            break;
         }
-        Ok(())
-    }
-    .await;
-
-    drop(file);
-    receive_result?;
-    if let Err(error) = tokio::fs::rename(&temp_path, &path).await {
-        return Err(format!("Failed to replace '{}': {}", path.display(), error));
     }
-    stream_guard.complete();
-
-    Ok(json!({
-        "ok": true,
-        "path": path.display().to_string(),
-        "bytesWritten": bytes_written,
-        "contentType": args.content_type
-    }))
-}
 
-fn resolve_path(path: &str, workspace: &Path) -> PathBuf {
-    let path = PathBuf::from(path);
-    if path.is_absolute() {
-        path
-    } else {
-        workspace.join(path)
+    file.flush()
+        .await
+        .map_err(|e| format!("Failed to flush '{}': {}", temp_path.display(), e))?;
+    if bytes_written != expected_length {
+        return Err(format!(
+            "Transfer size mismatch for '{}': expected {}, got {}",
+            path.display(),
+            expected_length,
+            bytes_written
+        ));
     }
+    Ok(bytes_written)
 }
 
-fn transfer_temp_path(path: &Path, stream_id: u32) -> PathBuf {
+pub(super) fn transfer_temp_path(path: &Path, stream_id: u32) -> PathBuf {
     let parent = path.parent().unwrap_or_else(|| Path::new("."));
     let file_name = path
         .file_name()
@@ -723,7 +824,7 @@ fn transfer_temp_path(path: &Path, stream_id: u32) -> PathBuf {
     parent.join(format!(".{}.gsv-transfer-{}-{}", file_name, stream_id, now))
 }
 
-struct TempFileGuard(PathBuf);
+pub(super) struct TempFileGuard(pub(super) PathBuf);
 
 impl Drop for TempFileGuard {
     fn drop(&mut self) {
@@ -734,9 +835,10 @@ impl Drop for TempFileGuard {
 #[cfg(test)]
 mod tests {
     use super::{
-        build_binary_frame, handle_receive, handle_send, parse_binary_frame, BinaryFrameInbox,
-        FrameBodyDescriptor, OutgoingBody, TransferReceiveArgs, TransferSendArgs,
-        BINARY_FRAME_CANCEL, BINARY_FRAME_DATA, BINARY_FRAME_END, BINARY_FRAME_ERROR,
+        build_binary_frame, handle_receive, handle_send, handle_stat, parse_binary_frame,
+        BinaryFrameInbox, FrameBodyDescriptor, OutgoingBody, PathResolver, TransferReceiveArgs,
+        TransferSendArgs, BINARY_FRAME_CANCEL, BINARY_FRAME_DATA, BINARY_FRAME_END,
+        BINARY_FRAME_ERROR,
     };
     use serde_json::json;
     use std::io::Cursor;
@@ -893,9 +995,13 @@ mod tests {
             .unwrap();
 
         let inbox = BinaryFrameInbox::new();
-        let (data, body) = handle_send(json!({ "path": "source.bin" }), &workspace, &inbox)
-            .await
-            .unwrap();
+        let (data, body) = handle_send(
+            json!({ "path": "source.bin" }),
+            &PathResolver::new(workspace.clone()),
+            &inbox,
+        )
+        .await
+        .unwrap();
         let descriptor = body.as_ref().unwrap().descriptor();
 
         assert_eq!(descriptor.stream_id, 1);
@@ -907,6 +1013,57 @@ mod tests {
         tokio::fs::remove_dir_all(workspace).await.unwrap();
     }
 
+    #[tokio::test]
+    async fn send_skips_the_body_when_the_digest_matches() {
+        let workspace = test_workspace("send-digest");
+        tokio::fs::create_dir_all(&workspace).await.unwrap();
+        tokio::fs::write(workspace.join("artifact.bin"), b"build output")
+            .await
+            .unwrap();
+        let paths = PathResolver::new(workspace.clone());
+        let inbox = BinaryFrameInbox::new();
+        let digest = blake3::hash(b"build output").to_hex().to_string();
+
+        let (data, body) = handle_send(
+            json!({ "path": "artifact.bin", "digest": true }),
+            &paths,
+            &inbox,
+        )
+        .await
+        .unwrap();
+        assert_eq!(data["digest"], digest);
+        assert!(data.get("unchanged").is_none());
+        drop(body.unwrap());
+
+        let (data, body) = handle_send(
+            json!({ "path": "artifact.bin", "ifNoneMatch": digest }),
+            &paths,
+            &inbox,
+        )
+        .await
+        .unwrap();
+        assert_eq!(data["unchanged"], true);
+        assert_eq!(data["size"], 12);
+        assert!(body.is_none());
+
+        let (data, body) = handle_send(
+            json!({ "path": "artifact.bin", "ifNoneMatch": blake3::hash(b"old").to_hex().to_string() }),
+            &paths,
+            &inbox,
+        )
+        .await
+        .unwrap();
+        assert_eq!(data["digest"], digest);
+        assert_eq!(body.unwrap().into_bytes(64).await.unwrap(), b"build output");
+
+        let stat = handle_stat(json!({ "path": "artifact.bin", "digest": true }), &paths)
+            .await
+            .unwrap();
+        assert_eq!(stat["digest"], digest);
+
+        tokio::fs::remove_dir_all(workspace).await.unwrap();
+    }
+
     #[tokio::test]
     async fn outgoing_pump_stops_on_cancel_without_an_end_frame() {
         let inbox = BinaryFrameInbox::new();
@@ -1033,7 +1190,7 @@ mod tests {
                 "contentType": "application/octet-stream"
             }),
             Some(body),
-            &workspace,
+            &PathResolver::new(workspace.clone()),
             &inbox,
         )
         .await
@@ -1070,7 +1227,7 @@ mod tests {
         let error = handle_receive(
             json!({ "path": "destination.bin" }),
             Some(body),
-            &workspace,
+            &PathResolver::new(workspace.clone()),
             &inbox,
         )
         .await
@@ -1106,7 +1263,7 @@ mod tests {
             handle_receive(
                 json!({ "path": "destination.bin" }),
                 Some(body),
-                &receive_workspace,
+                &PathResolver::new(receive_workspace),
                 &receive_inbox,
             )
             .await
@@ -1141,6 +1298,45 @@ mod tests {
         tokio::fs::remove_dir_all(workspace).await.unwrap();
     }
 
+    #[tokio::test]
+    async fn dry_run_receive_reports_conflicts_and_cancels_body() {
+        let workspace = test_workspace("dry-run");
+        tokio::fs::create_dir_all(&workspace).await.unwrap();
+        tokio::fs::write(workspace.join("destination.bin"), [9, 9])
+            .await
+            .unwrap();
+        let (inbox, sent) = recording_inbox();
+        let body = FrameBodyDescriptor {
+            stream_id: 32,
+            length: Some(4),
+        };
+        inbox.register(Some(body));
+
+        let result = handle_receive(
+            json!({ "path": "destination.bin", "dryRun": true }),
+            Some(body),
+            &PathResolver::new(workspace.clone()),
+            &inbox,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result["dryRun"], true);
+        assert_eq!(result["size"], 4);
+        assert_eq!(result["conflicts"][0]["size"], 2);
+        let (stream_id, flags, _) = parse_binary_frame(&sent.lock().unwrap()[0]).unwrap();
+        assert_eq!(stream_id, 32);
+        assert_eq!(flags, BINARY_FRAME_CANCEL | BINARY_FRAME_END);
+        assert_eq!(
+            tokio::fs::read(workspace.join("destination.bin"))
+                .await
+                .unwrap(),
+            vec![9, 9]
+        );
+
+        tokio::fs::remove_dir_all(workspace).await.unwrap();
+    }
+
     #[tokio::test]
     async fn receive_requires_length_on_request_body() {
         let workspace = test_workspace("missing-length");
@@ -1150,7 +1346,7 @@ mod tests {
                 stream_id: 31,
                 length: None,
             }),
-            &workspace,
+            &PathResolver::new(workspace.clone()),
             &BinaryFrameInbox::new(),
         )
         .await
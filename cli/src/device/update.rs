@@ -0,0 +1,675 @@
+//! Self-update of the device daemon.
+//!
+//! The gateway can answer `sys.connect` (or reject it) with an `update` hint
+//! naming the minimum and recommended client versions. When the running build
+//! is older, the daemon downloads the release binary for its platform from
+//! the same release URLs `gsv deploy` uses, checks it against the release
+//! checksums and its reported version against the hint, swaps it in next to
+//! a backup of the current binary and restarts through the service manager.
+//! Stable gateway releases recommend their own version.
+//!
+//! The new binary is on probation until one of its identities connects. If
+//! none does within [`PROBATION`], or it keeps failing to start, the backup is
+//! restored and the daemon restarts on the previous release, which then skips
+//! the rejected version.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use gsv::build_info;
+use gsv::config::DeviceConfig;
+use gsv::deploy::{parse_checksums, release_download_url, sha256_hex};
+use gsv::device_service::{self, ServiceManagerKind};
+use gsv::protocol::UpdateHint;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use super::control::DeviceHandles;
+
+const RELEASE_CHECKSUMS: &str = "checksums.txt";
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(15);
+/// How long a freshly installed binary has to connect one identity.
+const PROBATION: Duration = Duration::from_secs(180);
+/// Starts of a new binary that did not confirm before it is rolled back.
+const MAX_PROBATION_STARTS: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Urgency {
+    /// Below `minimumVersion`; the gateway may refuse this build.
+    Required,
+    /// Below `recommendedVersion` only.
+    Recommended,
+}
+
+impl Urgency {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Required => "required",
+            Self::Recommended => "recommended",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct UpdateTarget {
+    pub(super) version: Version,
+    pub(super) release_tag: String,
+    pub(super) urgency: Urgency,
+}
+
+fn parse_version(raw: &str) -> Option<Version> {
+    Version::parse(raw.trim().trim_start_matches('v')).ok()
+}
+
+/// The release to move to, if `current` is older than the hint asks for.
+/// Prefers the recommended version; versions that do not parse are ignored.
+pub(super) fn update_target(current: &str, hint: &UpdateHint) -> Option<UpdateTarget> {
+    let current = parse_version(current)?;
+    let newer = |raw: &Option<String>| {
+        raw.as_deref()
+            .and_then(parse_version)
+            .filter(|version| version.cmp_precedence(&current).is_gt())
+    };
+    let minimum = newer(&hint.minimum_version);
+    let version = newer(&hint.recommended_version)
+        .into_iter()
+        .chain(minimum.clone())
+        .max_by(|a, b| a.cmp_precedence(b))?;
+    Some(UpdateTarget {
+        release_tag: hint
+            .release_tag
+            .clone()
+            .filter(|tag| !tag.trim().is_empty())
+            .unwrap_or_else(|| format!("v{}", version)),
+        urgency: if minimum.is_some() {
+            Urgency::Required
+        } else {
+            Urgency::Recommended
+        },
+        version,
+    })
+}
+
+/// Record of the last installed update, kept next to the config.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateState {
+    previous_version: String,
+    /// Version reported by the installed binary.
+    version: String,
+    executable: PathBuf,
+    backup: PathBuf,
+    /// Starts of `version` that have not connected yet.
+    #[serde(default)]
+    starts: u32,
+    /// `version` failed its probation and must not be installed again.
+    #[serde(default)]
+    rolled_back: bool,
+}
+
+fn state_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".gsv").join("device-update.json"))
+}
+
+fn read_state(path: &Path) -> Option<UpdateState> {
+    serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()
+}
+
+fn write_state(path: &Path, state: &UpdateState) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let json = serde_json::to_string_pretty(state).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Downloads and installs hinted releases, at most one at a time.
+#[derive(Clone)]
+pub(super) struct Updater(Arc<UpdaterInner>);
+
+struct UpdaterInner {
+    enabled: bool,
+    service_manager: Option<ServiceManagerKind>,
+    installing: AtomicBool,
+    /// Last version acted on, so every connect does not repeat it.
+    handled: Mutex<Option<Version>>,
+    /// Version that was rolled back before.
+    rejected: Option<Version>,
+}
+
+impl Updater {
+    /// Updates need a service manager to restart the daemon, so they only run
+    /// for an installed service with `device.auto_update` left on. A
+    /// system-wide service runs as an account that can neither replace the
+    /// binary nor restart itself.
+    pub(super) fn new(device: &DeviceConfig) -> Self {
+        let service_manager = device.service_manager;
+        let enabled = device.auto_update.unwrap_or(true)
+            && device_service::device_service_is_installed(service_manager).unwrap_or(false)
+            && device_service::device_service_home(service_manager).is_none();
+        let rejected = state_path()
+            .as_deref()
+            .and_then(read_state)
+            .filter(|state| state.rolled_back)
+            .and_then(|state| parse_version(&state.version));
+        Self(Arc::new(UpdaterInner {
+            enabled,
+            service_manager,
+            installing: AtomicBool::new(false),
+            handled: Mutex::new(None),
+            rejected,
+        }))
+    }
+
+    /// React to a hint from the gateway.
+    pub(super) fn offer(&self, hint: &UpdateHint) {
+        let Some(target) = update_target(build_info::BUILD_VERSION, hint) else {
+            return;
+        };
+        {
+            let mut handled = self.0.handled.lock().expect("update mutex poisoned");
+            if handled.as_ref() == Some(&target.version) || self.0.installing.load(Ordering::SeqCst)
+            {
+                return;
+            }
+            *handled = Some(target.version.clone());
+        }
+        if self.0.rejected.as_ref() == Some(&target.version) {
+            warn!(
+                event = "update.skipped",
+                version = %target.version,
+                reason = "rolled back before",
+            );
+            return;
+        }
+        if !self.0.enabled {
+            warn!(
+                event = "update.available",
+                version = %target.version,
+                urgency = target.urgency.as_str(),
+                current = build_info::BUILD_VERSION,
+            );
+            return;
+        }
+
+        self.0.installing.store(true, Ordering::SeqCst);
+        let updater = self.clone();
+        tokio::spawn(async move {
+            info!(
+                event = "update.start",
+                version = %target.version,
+                release = %target.release_tag,
+                urgency = target.urgency.as_str(),
+            );
+            if let Err(e) = install(&target, updater.0.service_manager).await {
+                error!(event = "update.failed", version = %target.version, error = %e);
+                // Try again on a later connect.
+                if let Ok(mut handled) = updater.0.handled.lock() {
+                    *handled = None;
+                }
+                updater.0.installing.store(false, Ordering::SeqCst);
+            }
+        });
+    }
+}
+
+async fn install(
+    target: &UpdateTarget,
+    service_manager: Option<ServiceManagerKind>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let asset = build_info::release_asset_name().ok_or("No release binary for this platform")?;
+    let client = reqwest::Client::builder()
+        .timeout(DOWNLOAD_TIMEOUT)
+        .build()?;
+    let fetch = |file: &str| {
+        client
+            .get(release_download_url(&target.release_tag, file))
+            .header("User-Agent", "gsv-cli")
+            .send()
+    };
+    let checksums = fetch(RELEASE_CHECKSUMS)
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let binary = fetch(&asset).await?.error_for_status()?.bytes().await?;
+    verify_checksum(&binary, &checksums, &asset)?;
+
+    let executable = std::env::current_exe()?;
+    let executable = executable.canonicalize().unwrap_or(executable);
+    let staged = sibling(&executable, "new");
+    write_executable(&staged, &binary)?;
+    let version = match installed_version(&staged).await.and_then(|version| {
+        check_staged_version(&version, &target.version, build_info::PACKAGE_VERSION)?;
+        Ok(version)
+    }) {
+        Ok(version) => version,
+        Err(e) => {
+            let _ = std::fs::remove_file(&staged);
+            return Err(e);
+        }
+    };
+
+    let backup = sibling(&executable, "previous");
+    swap_in(&executable, &staged, &backup)?;
+    let path = state_path().ok_or("Could not determine home directory")?;
+    write_state(
+        &path,
+        &UpdateState {
+            previous_version: build_info::BUILD_VERSION.to_string(),
+            version: version.clone(),
+            executable,
+            backup,
+            starts: 0,
+            rolled_back: false,
+        },
+    )?;
+    info!(event = "update.installed", version = %version, restarting = true);
+    Ok(restart_service(service_manager).await?)
+}
+
+async fn restart_service(service_manager: Option<ServiceManagerKind>) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || {
+        device_service::restart_device_service(service_manager).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn verify_checksum(binary: &[u8], checksums: &str, asset: &str) -> Result<(), String> {
+    let checksums = parse_checksums(checksums);
+    let expected = checksums
+        .get(asset)
+        .ok_or_else(|| format!("Missing checksum entry for '{}'", asset))?;
+    let actual = sha256_hex(binary);
+    if actual != *expected {
+        return Err(format!(
+            "Checksum mismatch for {}: expected {}, got {}",
+            asset, expected, actual
+        ));
+    }
+    Ok(())
+}
+
+fn sibling(executable: &Path, suffix: &str) -> PathBuf {
+    let mut name = executable.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    executable.with_file_name(name)
+}
+
+fn write_executable(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    std::fs::write(path, bytes)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("Failed to make {} executable: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Run `gsv version` on the downloaded binary: it must start on this machine
+/// and tells which version probation has to wait for.
+async fn installed_version(binary: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    let output = tokio::time::timeout(
+        VERIFY_TIMEOUT,
+        tokio::process::Command::new(binary)
+            .arg("version")
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .map_err(|_elapsed| "Downloaded binary did not answer `version`")??;
+    if !output.status.success() {
+        return Err(format!("Downloaded binary failed to run ({})", output.status).into());
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("gsv "))
+        .map(|version| version.trim().to_string())
+        .ok_or_else(|| "Downloaded binary did not report a version".into())
+}
+
+/// The downloaded binary must be the hinted release and newer than this
+/// package, so a mislabelled or stale asset is never swapped in.
+fn check_staged_version(reported: &str, target: &Version, current: &str) -> Result<(), String> {
+    let staged = parse_version(reported)
+        .ok_or_else(|| format!("Downloaded binary reports version '{}'", reported))?;
+    if staged != *target {
+        return Err(format!(
+            "Downloaded binary is version {}, expected {}",
+            staged, target
+        ));
+    }
+    let current = parse_version(current)
+        .ok_or_else(|| format!("Cannot compare with this build's version '{}'", current))?;
+    if staged.cmp_precedence(&current).is_le() {
+        return Err(format!(
+            "Downloaded version {} is not newer than {}",
+            staged, current
+        ));
+    }
+    Ok(())
+}
+
+/// Replace `executable` with `staged`, keeping the current binary at `backup`.
+#[cfg(not(windows))]
+fn swap_in(executable: &Path, staged: &Path, backup: &Path) -> Result<(), String> {
+    std::fs::copy(executable, backup)
+        .map_err(|e| format!("Failed to back up {}: {}", executable.display(), e))?;
+    // Same directory, so the rename replaces the binary atomically.
+    std::fs::rename(staged, executable)
+        .map_err(|e| format!("Failed to replace {}: {}", executable.display(), e))
+}
+
+/// A running binary cannot be overwritten on Windows, but it can be renamed.
+#[cfg(windows)]
+fn swap_in(executable: &Path, staged: &Path, backup: &Path) -> Result<(), String> {
+    let _ = std::fs::remove_file(backup);
+    std::fs::rename(executable, backup)
+        .map_err(|e| format!("Failed to move {} aside: {}", executable.display(), e))?;
+    std::fs::rename(staged, executable).map_err(|e| {
+        let _ = std::fs::rename(backup, executable);
+        format!("Failed to replace {}: {}", executable.display(), e)
+    })
+}
+
+#[cfg(not(windows))]
+fn restore(executable: &Path, backup: &Path) -> Result<(), String> {
+    std::fs::rename(backup, executable)
+        .map_err(|e| format!("Failed to restore {}: {}", executable.display(), e))
+}
+
+#[cfg(windows)]
+fn restore(executable: &Path, backup: &Path) -> Result<(), String> {
+    let failed = sibling(executable, "failed");
+    let _ = std::fs::remove_file(&failed);
+    std::fs::rename(executable, &failed)
+        .map_err(|e| format!("Failed to move {} aside: {}", executable.display(), e))?;
+    std::fs::rename(backup, executable)
+        .map_err(|e| format!("Failed to restore {}: {}", executable.display(), e))
+}
+
+/// What a starting daemon has to do about the last update.
+#[derive(Debug, PartialEq, Eq)]
+enum StartCheck {
+    /// Nothing pending for this binary.
+    Settled,
+    /// This binary was just installed and must connect to stay.
+    Probation,
+    /// This binary kept failing to start; go back to the previous one.
+    RollBack,
+}
+
+/// Count this start against a pending update of `current`.
+fn check_start(path: &Path, current: &str) -> Result<StartCheck, String> {
+    let Some(mut state) = read_state(path) else {
+        return Ok(StartCheck::Settled);
+    };
+    if state.rolled_back || state.version != current {
+        return Ok(StartCheck::Settled);
+    }
+    state.starts += 1;
+    write_state(path, &state)?;
+    if state.starts > MAX_PROBATION_STARTS {
+        Ok(StartCheck::RollBack)
+    } else {
+        Ok(StartCheck::Probation)
+    }
+}
+
+/// The update is good: drop the backup and the pending record.
+fn confirm(path: &Path) -> Result<(), String> {
+    if let Some(state) = read_state(path) {
+        let _ = std::fs::remove_file(&state.backup);
+    }
+    std::fs::remove_file(path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))
+}
+
+/// Put the previous binary back and remember the rejected version.
+fn roll_back(path: &Path) -> Result<String, String> {
+    let mut state = read_state(path).ok_or("No update to roll back")?;
+    restore(&state.executable, &state.backup)?;
+    state.rolled_back = true;
+    write_state(path, &state)?;
+    Ok(state.previous_version)
+}
+
+/// Start the probation of a freshly installed binary, if this is one. The
+/// returned task confirms the update once an identity in `devices` connects,
+/// and rolls back when none does in time.
+pub(super) fn begin_probation(
+    devices: Arc<Mutex<Vec<DeviceHandles>>>,
+    service_manager: Option<ServiceManagerKind>,
+) -> Option<tokio::task::JoinHandle<()>> {
+    let path = state_path()?;
+    match check_start(&path, build_info::BUILD_VERSION) {
+        Ok(StartCheck::Settled) => None,
+        Ok(StartCheck::RollBack) => {
+            warn!(
+                event = "update.unstable",
+                version = build_info::BUILD_VERSION
+            );
+            Some(tokio::spawn(async move {
+                roll_back_and_restart(&path, service_manager).await
+            }))
+        }
+        Ok(StartCheck::Probation) => {
+            info!(
+                event = "update.probation",
+                version = build_info::BUILD_VERSION,
+                seconds = PROBATION.as_secs(),
+            );
+            Some(tokio::spawn(async move {
+                let deadline = tokio::time::Instant::now() + PROBATION;
+                while tokio::time::Instant::now() < deadline {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    if any_connected(&devices) {
+                        match confirm(&path) {
+                            Ok(()) => info!(
+                                event = "update.confirmed",
+                                version = build_info::BUILD_VERSION
+                            ),
+                            Err(e) => warn!(event = "update.confirm_failed", error = %e),
+                        }
+                        return;
+                    }
+                }
+                warn!(
+                    event = "update.probation_failed",
+                    version = build_info::BUILD_VERSION,
+                );
+                roll_back_and_restart(&path, service_manager).await;
+            }))
+        }
+        Err(e) => {
+            warn!(event = "update.state_unavailable", error = %e);
+            None
+        }
+    }
+}
+
+fn any_connected(devices: &Mutex<Vec<DeviceHandles>>) -> bool {
+    let devices = devices
+        .lock()
+        .expect("device handles mutex poisoned")
+        .clone();
+    devices.iter().any(|device| {
+        device
+            .state
+            .snapshot()
+            .is_some_and(|snapshot| snapshot.connects > 0)
+    })
+}
+
+async fn roll_back_and_restart(path: &Path, service_manager: Option<ServiceManagerKind>) {
+    match roll_back(path) {
+        Ok(previous) => {
+            warn!(event = "update.rolled_back", version = %previous, restarting = true);
+            if let Err(e) = restart_service(service_manager).await {
+                error!(event = "update.restart_failed", error = %e);
+            }
+        }
+        Err(e) => error!(event = "update.rollback_failed", error = %e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hint(minimum: Option<&str>, recommended: Option<&str>) -> UpdateHint {
+        UpdateHint {
+            minimum_version: minimum.map(str::to_string),
+            recommended_version: recommended.map(str::to_string),
+            release_tag: None,
+        }
+    }
+
+    #[test]
+    fn update_target_prefers_the_recommended_release() {
+        let target = update_target("0.4.1", &hint(Some("0.4.0"), Some("0.5.2"))).unwrap();
+        assert_eq!(target.version, Version::new(0, 5, 2));
+        assert_eq!(target.release_tag, "v0.5.2");
+        assert_eq!(target.urgency, Urgency::Recommended);
+
+        let target = update_target("0.4.1", &hint(Some("v0.5.0"), None)).unwrap();
+        assert_eq!(target.version, Version::new(0, 5, 0));
+        assert_eq!(target.urgency, Urgency::Required);
+
+        let mut tagged = hint(None, Some("0.5.0"));
+        tagged.release_tag = Some("dev".to_string());
+        assert_eq!(update_target("0.4.1", &tagged).unwrap().release_tag, "dev");
+    }
+
+    #[test]
+    fn current_or_unparsable_versions_do_not_update() {
+        assert_eq!(
+            update_target("0.5.2", &hint(Some("0.4.0"), Some("0.5.2"))),
+            None
+        );
+        assert_eq!(update_target("0.4.1", &hint(None, Some("soon"))), None);
+        assert_eq!(
+            update_target("local-build", &hint(None, Some("0.5.0"))),
+            None
+        );
+        // Build metadata does not make a dev build newer than its release.
+        assert_eq!(
+            update_target("0.5.0-dev.12+abc123", &hint(None, Some("0.5.0")))
+                .unwrap()
+                .version,
+            Version::new(0, 5, 0)
+        );
+        assert_eq!(
+            update_target("0.5.0+abc123", &hint(None, Some("0.5.0"))),
+            None
+        );
+    }
+
+    #[test]
+    fn staged_binary_must_be_the_newer_target_version() {
+        let target = Version::new(0, 5, 2);
+        assert_eq!(check_staged_version("0.5.2", &target, "0.4.1"), Ok(()));
+        assert_eq!(
+            check_staged_version("0.5.1", &target, "0.4.1").unwrap_err(),
+            "Downloaded binary is version 0.5.1, expected 0.5.2"
+        );
+        assert_eq!(
+            check_staged_version("0.5.2", &target, "0.5.2").unwrap_err(),
+            "Downloaded version 0.5.2 is not newer than 0.5.2"
+        );
+        assert_eq!(
+            check_staged_version("0.5.2", &target, "0.6.0").unwrap_err(),
+            "Downloaded version 0.5.2 is not newer than 0.6.0"
+        );
+        assert!(check_staged_version("local", &target, "0.4.1").is_err());
+    }
+
+    #[test]
+    fn checksums_must_match_the_platform_asset() {
+        let binary = b"new gsv";
+        let checksums = format!("{}  gsv-linux-x64\n", sha256_hex(binary));
+        verify_checksum(binary, &checksums, "gsv-linux-x64").unwrap();
+        let error = verify_checksum(b"tampered", &checksums, "gsv-linux-x64").unwrap_err();
+        assert!(error.contains("Checksum mismatch"), "{error}");
+        let error = verify_checksum(binary, &checksums, "gsv-darwin-arm64").unwrap_err();
+        assert!(error.contains("Missing checksum"), "{error}");
+    }
+
+    #[test]
+    fn swapped_binary_rolls_back_after_repeated_failed_starts() {
+        let dir = std::env::temp_dir().join(format!("gsv-update-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let executable = dir.join("gsv");
+        std::fs::write(&executable, "old").unwrap();
+        let staged = sibling(&executable, "new");
+        write_executable(&staged, b"new").unwrap();
+        let backup = sibling(&executable, "previous");
+        swap_in(&executable, &staged, &backup).unwrap();
+        assert_eq!(std::fs::read_to_string(&executable).unwrap(), "new");
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), "old");
+
+        let path = dir.join("device-update.json");
+        write_state(
+            &path,
+            &UpdateState {
+                previous_version: "0.4.1".to_string(),
+                version: "0.5.0".to_string(),
+                executable: executable.clone(),
+                backup: backup.clone(),
+                starts: 0,
+                rolled_back: false,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(check_start(&path, "0.4.1").unwrap(), StartCheck::Settled);
+        for _ in 0..MAX_PROBATION_STARTS {
+            assert_eq!(check_start(&path, "0.5.0").unwrap(), StartCheck::Probation);
+        }
+        assert_eq!(check_start(&path, "0.5.0").unwrap(), StartCheck::RollBack);
+
+        assert_eq!(roll_back(&path).unwrap(), "0.4.1");
+        assert_eq!(std::fs::read_to_string(&executable).unwrap(), "old");
+        let state = read_state(&path).unwrap();
+        assert!(state.rolled_back);
+        // The restored binary does not treat the rejected version as pending.
+        assert_eq!(check_start(&path, "0.5.0").unwrap(), StartCheck::Settled);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn confirming_drops_backup_and_record() {
+        let dir = std::env::temp_dir().join(format!("gsv-update-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let backup = dir.join("gsv.previous");
+        std::fs::write(&backup, "old").unwrap();
+        let path = dir.join("device-update.json");
+        write_state(
+            &path,
+            &UpdateState {
+                previous_version: "0.4.1".to_string(),
+                version: "0.5.0".to_string(),
+                executable: dir.join("gsv"),
+                backup: backup.clone(),
+                starts: 1,
+                rolled_back: false,
+            },
+        )
+        .unwrap();
+        confirm(&path).unwrap();
+        assert!(!backup.exists());
+        assert!(!path.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,6 +1,9 @@
+#[cfg(target_os = "linux")]
+use crate::config::CliConfig;
 use crate::logger;
 #[cfg(any(test, target_os = "windows"))]
 use base64::Engine;
+use serde::{Deserialize, Serialize};
 use std::ffi::OsString;
 use std::fs::{self, File};
 use std::io::{Read, Seek, SeekFrom, Write};
@@ -16,7 +19,51 @@ const DEVICE_SYSTEMD_UNIT_NAME: &str = "gsvd.service";
 const DEVICE_LAUNCHD_LABEL: &str = "gsvd";
 #[cfg(target_os = "windows")]
 const DEVICE_WINDOWS_TASK_NAME: &str = "gsvd";
+#[cfg(any(test, target_os = "linux"))]
+const DEVICE_OPENRC_SERVICE_NAME: &str = "gsvd";
+#[cfg(any(test, target_os = "linux"))]
+const DEVICE_RUNIT_SERVICE_NAME: &str = "gsvd";
+/// Account that system-wide services run as.
+#[cfg(any(test, target_os = "linux"))]
+const DEVICE_SERVICE_USER: &str = "gsvd";
+/// Home of the service account; holds its config, logs and control socket.
+const DEVICE_SERVICE_HOME: &str = "/var/lib/gsvd";
 const LOG_POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Seconds a stopping daemon gets before it is killed, where the service
+/// manager's default is shorter than the daemon's drain grace period.
+#[cfg(any(test, target_os = "linux", target_os = "macos"))]
+const DEVICE_SERVICE_STOP_TIMEOUT_SECS: u64 = 30;
+
+/// Service manager that installs and supervises the device daemon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ServiceManagerKind {
+    /// systemd user unit of the installing user
+    SystemdUser,
+    /// systemd system unit running as a dedicated service user
+    SystemdSystem,
+    /// OpenRC init script running as a dedicated service user
+    Openrc,
+    /// runit service running as a dedicated service user
+    Runit,
+    /// launchd agent of the installing user
+    Launchd,
+    /// Task Scheduler logon task of the installing user
+    WindowsTask,
+}
+
+impl ServiceManagerKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::SystemdUser => "systemd-user",
+            Self::SystemdSystem => "systemd-system",
+            Self::Openrc => "openrc",
+            Self::Runit => "runit",
+            Self::Launchd => "launchd",
+            Self::WindowsTask => "windows-task",
+        }
+    }
+}
 
 struct DeviceServiceInstallSpec {
     description: &'static str,
@@ -39,6 +86,11 @@ impl DeviceServiceInstallSpec {
 }
 
 trait DeviceServiceManager {
+    /// System-wide services run as [`DEVICE_SERVICE_USER`] with
+    /// [`DEVICE_SERVICE_HOME`] as home instead of as the installing user.
+    fn system_wide(&self) -> bool {
+        false
+    }
     fn is_installed(&self) -> Result<bool, DynError>;
     fn install(&self, spec: &DeviceServiceInstallSpec) -> Result<(), DynError>;
     fn uninstall(&self) -> Result<(), DynError>;
@@ -49,40 +101,67 @@ trait DeviceServiceManager {
 }
 
 pub fn device_service_management_supported() -> bool {
-    platform_service_manager().is_some()
+    detect_service_manager().is_some()
 }
 
-pub fn device_service_is_installed() -> Result<bool, DynError> {
-    require_platform_service_manager()?.is_installed()
+/// The backend used for `preference`, or the one detected for this host.
+pub fn resolve_service_manager(
+    preference: Option<ServiceManagerKind>,
+) -> Result<ServiceManagerKind, DynError> {
+    preference
+        .or_else(detect_service_manager)
+        .ok_or_else(|| unsupported_message().into())
 }
 
-pub fn install_device_service() -> Result<(), DynError> {
+pub fn device_service_is_installed(
+    preference: Option<ServiceManagerKind>,
+) -> Result<bool, DynError> {
+    service_manager(preference)?.is_installed()
+}
+
+/// Home directory of a system-wide service's account; `None` when the
+/// service runs as the installing user.
+pub fn device_service_home(preference: Option<ServiceManagerKind>) -> Option<PathBuf> {
+    service_manager(preference)
+        .ok()
+        .filter(|manager| manager.system_wide())
+        .map(|_| PathBuf::from(DEVICE_SERVICE_HOME))
+}
+
+pub fn install_device_service(preference: Option<ServiceManagerKind>) -> Result<(), DynError> {
     let spec = DeviceServiceInstallSpec::current()?;
-    require_platform_service_manager()?.install(&spec)
+    service_manager(preference)?.install(&spec)
 }
 
-pub fn uninstall_device_service() -> Result<(), DynError> {
-    require_platform_service_manager()?.uninstall()
+pub fn uninstall_device_service(preference: Option<ServiceManagerKind>) -> Result<(), DynError> {
+    service_manager(preference)?.uninstall()
 }
 
-pub fn start_device_service() -> Result<(), DynError> {
-    require_platform_service_manager()?.start()
+pub fn start_device_service(preference: Option<ServiceManagerKind>) -> Result<(), DynError> {
+    service_manager(preference)?.start()
 }
 
-pub fn restart_device_service() -> Result<(), DynError> {
-    require_platform_service_manager()?.restart()
+pub fn restart_device_service(preference: Option<ServiceManagerKind>) -> Result<(), DynError> {
+    service_manager(preference)?.restart()
 }
 
-pub fn stop_device_service() -> Result<(), DynError> {
-    require_platform_service_manager()?.stop()
+pub fn stop_device_service(preference: Option<ServiceManagerKind>) -> Result<(), DynError> {
+    service_manager(preference)?.stop()
 }
 
-pub fn status_device_service() -> Result<(), DynError> {
-    require_platform_service_manager()?.status()
+pub fn status_device_service(preference: Option<ServiceManagerKind>) -> Result<(), DynError> {
+    service_manager(preference)?.status()
 }
 
-pub fn show_device_service_logs(lines: usize, follow: bool) -> Result<(), DynError> {
-    let log_path = logger::device_log_path()?;
+pub fn show_device_service_logs(
+    preference: Option<ServiceManagerKind>,
+    lines: usize,
+    follow: bool,
+) -> Result<(), DynError> {
+    let log_path = match device_service_home(preference) {
+        Some(home) => logger::device_log_path_in(&logger::device_log_dir_under(&home))?,
+        None => logger::device_log_path()?,
+    };
     if !log_path.exists() {
         return Err(format!("Log file not found: {}", log_path.display()).into());
     }
@@ -96,24 +175,44 @@ pub fn show_device_service_logs(lines: usize, follow: bool) -> Result<(), DynErr
     follow_log_file(&log_path)
 }
 
-fn require_platform_service_manager() -> Result<Box<dyn DeviceServiceManager>, DynError> {
-    platform_service_manager().ok_or_else(|| unsupported_message().into())
+fn service_manager(
+    preference: Option<ServiceManagerKind>,
+) -> Result<Box<dyn DeviceServiceManager>, DynError> {
+    match resolve_service_manager(preference)? {
+        #[cfg(target_os = "linux")]
+        ServiceManagerKind::SystemdUser => Ok(Box::new(SystemdUserServiceManager)),
+        #[cfg(target_os = "linux")]
+        ServiceManagerKind::SystemdSystem => Ok(Box::new(SystemdSystemServiceManager)),
+        #[cfg(target_os = "linux")]
+        ServiceManagerKind::Openrc => Ok(Box::new(OpenRcServiceManager)),
+        #[cfg(target_os = "linux")]
+        ServiceManagerKind::Runit => Ok(Box::new(RunitServiceManager)),
+        #[cfg(target_os = "macos")]
+        ServiceManagerKind::Launchd => Ok(Box::new(LaunchdUserServiceManager)),
+        #[cfg(target_os = "windows")]
+        ServiceManagerKind::WindowsTask => Ok(Box::new(WindowsTaskServiceManager)),
+        other => Err(format!(
+            "the {} service manager is not available on this OS",
+            other.as_str()
+        )
+        .into()),
+    }
 }
 
-fn platform_service_manager() -> Option<Box<dyn DeviceServiceManager>> {
+fn detect_service_manager() -> Option<ServiceManagerKind> {
     #[cfg(target_os = "linux")]
     {
-        Some(Box::new(SystemdUserServiceManager))
+        Some(select_linux_service_manager(&InitProbe::current()))
     }
 
     #[cfg(target_os = "macos")]
     {
-        Some(Box::new(LaunchdUserServiceManager))
+        Some(ServiceManagerKind::Launchd)
     }
 
     #[cfg(target_os = "windows")]
     {
-        Some(Box::new(WindowsTaskServiceManager))
+        Some(ServiceManagerKind::WindowsTask)
     }
 
     #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
@@ -122,6 +221,47 @@ fn platform_service_manager() -> Option<Box<dyn DeviceServiceManager>> {
     }
 }
 
+/// What a Linux host runs as init, and whether we are root.
+#[cfg(any(test, target_os = "linux"))]
+struct InitProbe {
+    systemd: bool,
+    openrc: bool,
+    runit: bool,
+    root: bool,
+}
+
+#[cfg(target_os = "linux")]
+impl InitProbe {
+    fn current() -> Self {
+        let exists = |path: &str| Path::new(path).exists();
+        Self {
+            systemd: Path::new("/run/systemd/system").is_dir(),
+            openrc: exists("/run/openrc") || exists("/sbin/openrc-run"),
+            runit: exists("/run/runit") || exists("/etc/runit"),
+            root: running_as_root(),
+        }
+    }
+}
+
+/// systemd hosts get a user unit, or a system unit when installing as root;
+/// hosts without systemd use OpenRC or runit.
+#[cfg(any(test, target_os = "linux"))]
+fn select_linux_service_manager(probe: &InitProbe) -> ServiceManagerKind {
+    if probe.systemd {
+        if probe.root {
+            ServiceManagerKind::SystemdSystem
+        } else {
+            ServiceManagerKind::SystemdUser
+        }
+    } else if probe.openrc {
+        ServiceManagerKind::Openrc
+    } else if probe.runit {
+        ServiceManagerKind::Runit
+    } else {
+        ServiceManagerKind::SystemdUser
+    }
+}
+
 fn unsupported_message() -> &'static str {
     "device daemon management is currently supported on Linux, macOS, and Windows only"
 }
@@ -328,6 +468,81 @@ fn systemd_exec_start(spec: &DeviceServiceInstallSpec) -> String {
     parts.join(" ")
 }
 
+#[cfg(any(test, target_os = "linux"))]
+fn systemd_system_unit_contents(spec: &DeviceServiceInstallSpec, user: &str, home: &str) -> String {
+    format!(
+        "[Unit]\nDescription={}\nAfter=network-online.target\nWants=network-online.target\n\n[Service]\nType=simple\nUser={user}\nGroup={user}\nWorkingDirectory={home}\nEnvironment=\"HOME={}\"\n{}ExecStart={}\nRestart=always\nRestartSec=3\nKillSignal=SIGTERM\nKillMode=process\nNoNewPrivileges=true\n\n[Install]\nWantedBy=multi-user.target\n",
+        spec.description,
+        systemd_escape_environment_value(home),
+        systemd_path_environment_line(spec.path_env.as_deref()),
+        systemd_exec_start(spec),
+    )
+}
+
+#[cfg(any(test, target_os = "linux"))]
+fn shell_single_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+#[cfg(any(test, target_os = "linux"))]
+fn shell_double_quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for ch in value.chars() {
+        if matches!(ch, '\\' | '"' | '$' | '`') {
+            quoted.push('\\');
+        }
+        quoted.push(ch);
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(any(test, target_os = "linux"))]
+fn shell_arguments(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| shell_single_quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(any(test, target_os = "linux"))]
+fn shell_export_lines(home: &str, path: Option<&str>) -> String {
+    let mut lines = format!("export HOME={}\n", shell_single_quote(home));
+    if let Some(path) = path {
+        lines.push_str(&format!("export PATH={}\n", shell_single_quote(path)));
+    }
+    lines
+}
+
+/// OpenRC runscript supervised by `supervise-daemon`, which restarts the
+/// daemon when it exits.
+#[cfg(any(test, target_os = "linux"))]
+fn openrc_init_script(spec: &DeviceServiceInstallSpec, user: &str, home: &str) -> String {
+    format!(
+        "#!/sbin/openrc-run\n\ndescription={}\nsupervisor=supervise-daemon\ncommand={}\ncommand_args={}\ncommand_user={}\ndirectory={}\nrespawn_delay=3\nrespawn_max=0\nretry=TERM/{}/KILL/5\n\n{}\ndepend() {{\n\tneed net\n\tuse dns logger\n}}\n",
+        shell_single_quote(spec.description),
+        shell_single_quote(&spec.exe_path.display().to_string()),
+        shell_double_quote(&shell_arguments(&spec.args)),
+        shell_single_quote(&format!("{user}:{user}")),
+        shell_single_quote(home),
+        DEVICE_SERVICE_STOP_TIMEOUT_SECS,
+        shell_export_lines(home, spec.path_env.as_deref()),
+    )
+}
+
+/// runit `run` script; `runsv` restarts the daemon when it exits.
+#[cfg(any(test, target_os = "linux"))]
+fn runit_run_script(spec: &DeviceServiceInstallSpec, user: &str, home: &str) -> String {
+    format!(
+        "#!/bin/sh\nexec 2>&1\n{}cd {} || exit 1\nexec chpst -u {} {} {}\n",
+        shell_export_lines(home, spec.path_env.as_deref()),
+        shell_single_quote(home),
+        shell_single_quote(&format!("{user}:{user}")),
+        shell_single_quote(&spec.exe_path.display().to_string()),
+        shell_arguments(&spec.args),
+    )
+}
+
 #[cfg(any(test, target_os = "macos"))]
 fn launchd_path_environment_block(path: Option<&str>) -> String {
     path.map(|value| {
@@ -358,10 +573,11 @@ fn launchd_plist_contents(
     path_env_block: &str,
 ) -> String {
     format!(
-        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n<plist version=\"1.0\">\n<dict>\n  <key>Label</key>\n  <string>{}</string>\n  <key>ProgramArguments</key>\n  <array>\n{}\n  </array>\n{}  <key>RunAtLoad</key>\n  <true/>\n  <key>KeepAlive</key>\n  <true/>\n</dict>\n</plist>\n",
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n<plist version=\"1.0\">\n<dict>\n  <key>Label</key>\n  <string>{}</string>\n  <key>ProgramArguments</key>\n  <array>\n{}\n  </array>\n{}  <key>RunAtLoad</key>\n  <true/>\n  <key>KeepAlive</key>\n  <true/>\n  <key>ExitTimeOut</key>\n  <integer>{}</integer>\n</dict>\n</plist>\n",
         label,
         launchd_program_arguments_block(spec),
         path_env_block,
+        DEVICE_SERVICE_STOP_TIMEOUT_SECS,
     )
 }
 
@@ -496,7 +712,7 @@ impl DeviceServiceManager for SystemdUserServiceManager {
 
         let path_env_line = systemd_path_environment_line(spec.path_env.as_deref());
         let unit = format!(
-            "[Unit]\nDescription={}\nAfter=network-online.target\nWants=network-online.target\n\n[Service]\nType=simple\nExecStart={}\n{}Restart=always\nRestartSec=3\nKillSignal=SIGTERM\n\n[Install]\nWantedBy=default.target\n",
+            "[Unit]\nDescription={}\nAfter=network-online.target\nWants=network-online.target\n\n[Service]\nType=simple\nExecStart={}\n{}Restart=always\nRestartSec=3\nKillSignal=SIGTERM\nKillMode=process\n\n[Install]\nWantedBy=default.target\n",
             spec.description,
             systemd_exec_start(spec),
             path_env_line,
@@ -641,6 +857,423 @@ fn try_enable_linger() -> Result<(), DynError> {
     }
 }
 
+#[cfg(target_os = "linux")]
+fn running_as_root() -> bool {
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(target_os = "linux")]
+fn require_root(kind: ServiceManagerKind) -> Result<(), DynError> {
+    if running_as_root() {
+        return Ok(());
+    }
+    Err(format!(
+        "{} services are managed system-wide; run this command with sudo",
+        kind.as_str()
+    )
+    .into())
+}
+
+/// Create the service account, hand it the installing user's config and
+/// make its home its own.
+#[cfg(target_os = "linux")]
+fn prepare_service_account(kind: ServiceManagerKind) -> Result<(), DynError> {
+    require_root(kind)?;
+    ensure_service_user()?;
+
+    let home = Path::new(DEVICE_SERVICE_HOME);
+    let config_dir = home.join(".config").join("gsv");
+    fs::create_dir_all(&config_dir)?;
+    if let Some(source) = CliConfig::config_path().filter(|path| path.exists()) {
+        use std::os::unix::fs::PermissionsExt;
+        let target = config_dir.join("config.toml");
+        fs::copy(&source, &target)?;
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o600))?;
+    }
+    run_command_capture(
+        Command::new("chown")
+            .arg("-R")
+            .arg(format!("{0}:{0}", DEVICE_SERVICE_USER))
+            .arg(home),
+        "Failed to give the service user its home directory",
+    )
+}
+
+#[cfg(target_os = "linux")]
+fn ensure_service_user() -> Result<(), DynError> {
+    let exists = Command::new("id")
+        .arg("-u")
+        .arg(DEVICE_SERVICE_USER)
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false);
+    if exists {
+        return Ok(());
+    }
+
+    let shell = ["/usr/sbin/nologin", "/sbin/nologin"]
+        .into_iter()
+        .find(|path| Path::new(path).exists())
+        .unwrap_or("/bin/false");
+    let useradd = Command::new("useradd")
+        .arg("--system")
+        .arg("--user-group")
+        .arg("--home-dir")
+        .arg(DEVICE_SERVICE_HOME)
+        .arg("--create-home")
+        .arg("--shell")
+        .arg(shell)
+        .arg(DEVICE_SERVICE_USER)
+        .output();
+    if useradd.is_ok_and(|output| output.status.success()) {
+        return Ok(());
+    }
+
+    // BusyBox (Alpine) has adduser/addgroup instead of useradd.
+    let _ = run_command_capture(
+        Command::new("addgroup").arg("-S").arg(DEVICE_SERVICE_USER),
+        "Failed to create service group",
+    );
+    run_command_capture(
+        Command::new("adduser")
+            .arg("-S")
+            .arg("-D")
+            .arg("-h")
+            .arg(DEVICE_SERVICE_HOME)
+            .arg("-s")
+            .arg(shell)
+            .arg("-G")
+            .arg(DEVICE_SERVICE_USER)
+            .arg(DEVICE_SERVICE_USER),
+        "Failed to create service user",
+    )
+}
+
+#[cfg(target_os = "linux")]
+fn print_system_service_notes() {
+    let log_dir = logger::device_log_dir_under(Path::new(DEVICE_SERVICE_HOME));
+    println!(
+        "Runs as user '{}' with home {}.",
+        DEVICE_SERVICE_USER, DEVICE_SERVICE_HOME
+    );
+    println!(
+        "Config copied to {}/.config/gsv/config.toml; re-run install after changing yours.",
+        DEVICE_SERVICE_HOME
+    );
+    println!("Make sure the service user can access the device workspace.");
+    println!("Logs: {}", logger::device_log_pattern_in(&log_dir));
+}
+
+#[cfg(target_os = "linux")]
+struct SystemdSystemServiceManager;
+
+#[cfg(target_os = "linux")]
+impl SystemdSystemServiceManager {
+    fn unit_path() -> PathBuf {
+        Path::new("/etc/systemd/system").join(DEVICE_SYSTEMD_UNIT_NAME)
+    }
+
+    fn systemctl(action: &str, context: &str) -> Result<(), DynError> {
+        run_command_capture(
+            Command::new("systemctl")
+                .arg(action)
+                .arg(DEVICE_SYSTEMD_UNIT_NAME),
+            context,
+        )
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl DeviceServiceManager for SystemdSystemServiceManager {
+    fn system_wide(&self) -> bool {
+        true
+    }
+
+    fn is_installed(&self) -> Result<bool, DynError> {
+        Ok(Self::unit_path().exists())
+    }
+
+    fn install(&self, spec: &DeviceServiceInstallSpec) -> Result<(), DynError> {
+        prepare_service_account(ServiceManagerKind::SystemdSystem)?;
+        let unit_path = Self::unit_path();
+        fs::write(
+            &unit_path,
+            systemd_system_unit_contents(spec, DEVICE_SERVICE_USER, DEVICE_SERVICE_HOME),
+        )?;
+
+        run_command_capture(
+            Command::new("systemctl").arg("daemon-reload"),
+            "Failed to reload systemd",
+        )?;
+        run_command_capture(
+            Command::new("systemctl")
+                .arg("enable")
+                .arg("--now")
+                .arg(DEVICE_SYSTEMD_UNIT_NAME),
+            "Failed to enable/start device service",
+        )?;
+
+        println!("Installed systemd unit: {}", unit_path.display());
+        print_system_service_notes();
+        Ok(())
+    }
+
+    fn uninstall(&self) -> Result<(), DynError> {
+        require_root(ServiceManagerKind::SystemdSystem)?;
+        let _ = run_command_capture(
+            Command::new("systemctl")
+                .arg("disable")
+                .arg("--now")
+                .arg(DEVICE_SYSTEMD_UNIT_NAME),
+            "Failed to disable/stop device service",
+        );
+
+        let unit_path = Self::unit_path();
+        if unit_path.exists() {
+            fs::remove_file(&unit_path)?;
+        }
+
+        run_command_capture(
+            Command::new("systemctl").arg("daemon-reload"),
+            "Failed to reload systemd",
+        )
+    }
+
+    fn start(&self) -> Result<(), DynError> {
+        Self::systemctl("start", "Failed to start device service")
+    }
+
+    fn restart(&self) -> Result<(), DynError> {
+        Self::systemctl("restart", "Failed to restart device service")
+    }
+
+    fn stop(&self) -> Result<(), DynError> {
+        Self::systemctl("stop", "Failed to stop device service")
+    }
+
+    fn status(&self) -> Result<(), DynError> {
+        run_command_passthrough(
+            Command::new("systemctl")
+                .arg("status")
+                .arg("--no-pager")
+                .arg(DEVICE_SYSTEMD_UNIT_NAME),
+            "Failed to read device service status",
+        )
+    }
+}
+
+#[cfg(target_os = "linux")]
+struct OpenRcServiceManager;
+
+#[cfg(target_os = "linux")]
+impl OpenRcServiceManager {
+    fn script_path() -> PathBuf {
+        Path::new("/etc/init.d").join(DEVICE_OPENRC_SERVICE_NAME)
+    }
+
+    fn rc_service(action: &str, context: &str) -> Result<(), DynError> {
+        run_command_capture(
+            Command::new("rc-service")
+                .arg(DEVICE_OPENRC_SERVICE_NAME)
+                .arg(action),
+            context,
+        )
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl DeviceServiceManager for OpenRcServiceManager {
+    fn system_wide(&self) -> bool {
+        true
+    }
+
+    fn is_installed(&self) -> Result<bool, DynError> {
+        Ok(Self::script_path().exists())
+    }
+
+    fn install(&self, spec: &DeviceServiceInstallSpec) -> Result<(), DynError> {
+        use std::os::unix::fs::PermissionsExt;
+
+        prepare_service_account(ServiceManagerKind::Openrc)?;
+        let script_path = Self::script_path();
+        fs::write(
+            &script_path,
+            openrc_init_script(spec, DEVICE_SERVICE_USER, DEVICE_SERVICE_HOME),
+        )?;
+        fs::set_permissions(&script_path, fs::Permissions::from_mode(0o755))?;
+
+        run_command_capture(
+            Command::new("rc-update")
+                .arg("add")
+                .arg(DEVICE_OPENRC_SERVICE_NAME)
+                .arg("default"),
+            "Failed to add device service to the default runlevel",
+        )?;
+        Self::rc_service("start", "Failed to start device service")?;
+
+        println!("Installed OpenRC service: {}", script_path.display());
+        print_system_service_notes();
+        Ok(())
+    }
+
+    fn uninstall(&self) -> Result<(), DynError> {
+        require_root(ServiceManagerKind::Openrc)?;
+        let _ = Self::rc_service("stop", "Failed to stop device service");
+        let _ = run_command_capture(
+            Command::new("rc-update")
+                .arg("del")
+                .arg(DEVICE_OPENRC_SERVICE_NAME)
+                .arg("default"),
+            "Failed to remove device service from the default runlevel",
+        );
+
+        let script_path = Self::script_path();
+        if script_path.exists() {
+            fs::remove_file(&script_path)?;
+        }
+        Ok(())
+    }
+
+    fn start(&self) -> Result<(), DynError> {
+        Self::rc_service("start", "Failed to start device service")
+    }
+
+    fn restart(&self) -> Result<(), DynError> {
+        Self::rc_service("restart", "Failed to restart device service")
+    }
+
+    fn stop(&self) -> Result<(), DynError> {
+        Self::rc_service("stop", "Failed to stop device service")
+    }
+
+    fn status(&self) -> Result<(), DynError> {
+        run_command_passthrough(
+            Command::new("rc-service")
+                .arg(DEVICE_OPENRC_SERVICE_NAME)
+                .arg("status"),
+            "Failed to read device service status",
+        )
+    }
+}
+
+/// runsvdir scan directories used by common distributions, most specific
+/// first.
+#[cfg(target_os = "linux")]
+const RUNIT_SCAN_DIRS: &[&str] = &[
+    "/var/service",
+    "/etc/service",
+    "/service",
+    "/etc/runit/runsvdir/default",
+];
+
+#[cfg(target_os = "linux")]
+struct RunitServiceManager;
+
+#[cfg(target_os = "linux")]
+impl RunitServiceManager {
+    fn service_dir() -> PathBuf {
+        Path::new("/etc/sv").join(DEVICE_RUNIT_SERVICE_NAME)
+    }
+
+    fn scan_dir() -> Result<&'static Path, DynError> {
+        RUNIT_SCAN_DIRS
+            .iter()
+            .map(Path::new)
+            .find(|dir| dir.is_dir())
+            .ok_or_else(|| {
+                format!(
+                    "No runit service directory found (looked for {})",
+                    RUNIT_SCAN_DIRS.join(", ")
+                )
+                .into()
+            })
+    }
+
+    /// The enabled service, as `sv` addresses it.
+    fn link_path() -> Result<PathBuf, DynError> {
+        Ok(Self::scan_dir()?.join(DEVICE_RUNIT_SERVICE_NAME))
+    }
+
+    fn sv(action: &str, context: &str) -> Result<(), DynError> {
+        run_command_capture(
+            Command::new("sv").arg(action).arg(Self::link_path()?),
+            context,
+        )
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl DeviceServiceManager for RunitServiceManager {
+    fn system_wide(&self) -> bool {
+        true
+    }
+
+    fn is_installed(&self) -> Result<bool, DynError> {
+        Ok(Self::service_dir().join("run").exists())
+    }
+
+    fn install(&self, spec: &DeviceServiceInstallSpec) -> Result<(), DynError> {
+        use std::os::unix::fs::PermissionsExt;
+
+        prepare_service_account(ServiceManagerKind::Runit)?;
+        let service_dir = Self::service_dir();
+        fs::create_dir_all(&service_dir)?;
+        let run_path = service_dir.join("run");
+        fs::write(
+            &run_path,
+            runit_run_script(spec, DEVICE_SERVICE_USER, DEVICE_SERVICE_HOME),
+        )?;
+        fs::set_permissions(&run_path, fs::Permissions::from_mode(0o755))?;
+
+        // runsvdir notices the link within a few seconds and starts the service.
+        let link_path = Self::link_path()?;
+        if fs::symlink_metadata(&link_path).is_err() {
+            std::os::unix::fs::symlink(&service_dir, &link_path)?;
+        }
+
+        println!("Installed runit service: {}", service_dir.display());
+        println!("Enabled via {}", link_path.display());
+        print_system_service_notes();
+        Ok(())
+    }
+
+    fn uninstall(&self) -> Result<(), DynError> {
+        require_root(ServiceManagerKind::Runit)?;
+        if let Ok(link_path) = Self::link_path() {
+            let _ = Self::sv("down", "Failed to stop device service");
+            if fs::symlink_metadata(&link_path).is_ok() {
+                fs::remove_file(&link_path)?;
+            }
+        }
+
+        let service_dir = Self::service_dir();
+        if service_dir.exists() {
+            fs::remove_dir_all(&service_dir)?;
+        }
+        Ok(())
+    }
+
+    fn start(&self) -> Result<(), DynError> {
+        Self::sv("start", "Failed to start device service")
+    }
+
+    fn restart(&self) -> Result<(), DynError> {
+        Self::sv("restart", "Failed to restart device service")
+    }
+
+    fn stop(&self) -> Result<(), DynError> {
+        Self::sv("stop", "Failed to stop device service")
+    }
+
+    fn status(&self) -> Result<(), DynError> {
+        run_command_passthrough(
+            Command::new("sv").arg("status").arg(Self::link_path()?),
+            "Failed to read device service status",
+        )
+    }
+}
+
 #[cfg(target_os = "macos")]
 struct LaunchdUserServiceManager;
 
@@ -967,6 +1600,93 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_select_linux_service_manager_follows_init_system() {
+        let probe = |systemd, openrc, runit, root| InitProbe {
+            systemd,
+            openrc,
+            runit,
+            root,
+        };
+        assert_eq!(
+            select_linux_service_manager(&probe(true, false, false, false)),
+            ServiceManagerKind::SystemdUser
+        );
+        assert_eq!(
+            select_linux_service_manager(&probe(true, true, false, true)),
+            ServiceManagerKind::SystemdSystem
+        );
+        assert_eq!(
+            select_linux_service_manager(&probe(false, true, true, true)),
+            ServiceManagerKind::Openrc
+        );
+        assert_eq!(
+            select_linux_service_manager(&probe(false, false, true, false)),
+            ServiceManagerKind::Runit
+        );
+    }
+
+    #[test]
+    fn test_service_manager_kind_names_match_flag_and_config() {
+        use clap::ValueEnum;
+        for kind in ServiceManagerKind::value_variants() {
+            let flag = kind.to_possible_value().expect("flag name");
+            assert_eq!(flag.get_name(), kind.as_str());
+            assert_eq!(
+                serde_json::to_value(kind).expect("serialize"),
+                serde_json::Value::String(kind.as_str().to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn test_systemd_system_unit_runs_as_service_user() {
+        let unit = systemd_system_unit_contents(&test_spec(), "gsvd", "/var/lib/gsvd");
+        assert!(unit.contains("User=gsvd\nGroup=gsvd\n"));
+        assert!(unit.contains("WorkingDirectory=/var/lib/gsvd\n"));
+        assert!(unit.contains("Environment=\"HOME=/var/lib/gsvd\"\n"));
+        assert!(unit.contains("Environment=\"PATH=/opt/bin:/usr/bin\"\n"));
+        assert!(unit.contains("ExecStart=\"/Applications/GSV/gsv\" \"device\" \"run\"\n"));
+        assert!(unit.contains("NoNewPrivileges=true\n"));
+        // Background shell sessions outlive daemon restarts.
+        assert!(unit.contains("KillMode=process\n"));
+        assert!(unit.ends_with("[Install]\nWantedBy=multi-user.target\n"));
+    }
+
+    #[test]
+    fn test_shell_quoting_survives_quotes_and_expansions() {
+        assert_eq!(shell_single_quote("it's"), "'it'\\''s'");
+        assert_eq!(
+            shell_double_quote("'a' $HOME \"`"),
+            "\"'a' \\$HOME \\\"\\`\""
+        );
+    }
+
+    #[test]
+    fn test_openrc_init_script_supervises_device_run() {
+        let script = openrc_init_script(&test_spec(), "gsvd", "/var/lib/gsvd");
+        assert!(script.starts_with("#!/sbin/openrc-run\n"));
+        assert!(script.contains("supervisor=supervise-daemon\n"));
+        assert!(script.contains("command='/Applications/GSV/gsv'\n"));
+        assert!(script.contains("command_args=\"'device' 'run'\"\n"));
+        assert!(script.contains("command_user='gsvd:gsvd'\n"));
+        assert!(script.contains("directory='/var/lib/gsvd'\n"));
+        assert!(script.contains("retry=TERM/30/KILL/5\n"));
+        assert!(script.contains("export HOME='/var/lib/gsvd'\nexport PATH='/opt/bin:/usr/bin'\n"));
+        assert!(script.contains("depend() {\n\tneed net\n"));
+    }
+
+    #[test]
+    fn test_runit_run_script_drops_privileges() {
+        let mut spec = test_spec();
+        spec.path_env = None;
+        let script = runit_run_script(&spec, "gsvd", "/var/lib/gsvd");
+        assert_eq!(
+            script,
+            "#!/bin/sh\nexec 2>&1\nexport HOME='/var/lib/gsvd'\ncd '/var/lib/gsvd' || exit 1\nexec chpst -u 'gsvd:gsvd' '/Applications/GSV/gsv' 'device' 'run'\n"
+        );
+    }
+
     #[test]
     fn test_launchd_path_environment_block_escapes_xml() {
         let block = launchd_path_environment_block(Some("/opt/bin:&\"'<>"));
@@ -981,6 +1701,7 @@ mod tests {
         assert!(plist.contains("<string>run</string>"));
         assert!(!plist.contains("<string>node</string>"));
         assert!(!plist.contains("<string>--foreground</string>"));
+        assert!(plist.contains("<key>ExitTimeOut</key>\n  <integer>30</integer>"));
     }
 
     #[test]
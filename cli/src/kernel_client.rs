@@ -1,5 +1,6 @@
+use crate::codec;
 use crate::connection::{ConnectOptions, Connection, GatewayRpcError};
-use crate::protocol::Frame;
+use crate::protocol::{DriverInfo, Frame, SessionResume};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
@@ -51,9 +52,13 @@ impl KernelClient {
                 role: "user".to_string(),
                 client_id: None,
                 implements: None,
+                mounts: None,
+                system: None,
                 auth_username: auth.username,
                 auth_password: auth.password,
                 auth_token: auth.token,
+                session: None,
+                codec: Some(codec::default_offer()),
             },
             on_frame,
         )
@@ -65,8 +70,9 @@ impl KernelClient {
     pub async fn connect_driver(
         url: &str,
         device_id: String,
-        implements: Vec<String>,
+        driver: DriverInfo,
         auth: GatewayAuth,
+        session: Option<SessionResume>,
         on_frame: impl Fn(Frame) + Send + Sync + 'static,
     ) -> Result<Self, Box<dyn std::error::Error>> {
         auth.validate()?;
@@ -75,10 +81,14 @@ impl KernelClient {
                 url: url.to_string(),
                 role: "driver".to_string(),
                 client_id: Some(device_id),
-                implements: Some(implements),
+                implements: Some(driver.implements),
+                mounts: Some(driver.mounts),
+                system: driver.system,
                 auth_username: auth.username,
                 auth_password: auth.password,
                 auth_token: auth.token,
+                session,
+                codec: Some(codec::default_offer()),
             },
             on_frame,
         )
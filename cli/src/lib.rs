@@ -1,4 +1,5 @@
 pub mod build_info;
+pub mod codec;
 pub mod config;
 pub mod connection;
 pub mod deploy;
@@ -1,10 +1,13 @@
 use std::path::PathBuf;
 
 use gsv::config::{self, CliConfig};
+use gsv::device_service::ServiceManagerKind;
 
 use crate::auth_flow::format_unix_ms;
 use crate::cli::LocalConfigAction;
 
+const CONCURRENCY_KEY_PREFIX: &str = "device.limits.concurrency.";
+
 fn mask_secret_edges(value: &str, prefix_chars: usize, suffix_chars: usize) -> String {
     let chars = value.chars().collect::<Vec<_>>();
     if chars.len() <= prefix_chars + suffix_chars {
@@ -67,6 +70,35 @@ pub(crate) fn run_local_config(
                 "device.workspace" | "node.workspace" => {
                     cfg.device.workspace.map(|path| path.display().to_string())
                 }
+                "device.max_retry_delay_secs" => {
+                    cfg.device.max_retry_delay_secs.map(|secs| secs.to_string())
+                }
+                "device.auto_update" => cfg.device.auto_update.map(|enabled| enabled.to_string()),
+                "device.grace_period_secs" => {
+                    cfg.device.grace_period_secs.map(|secs| secs.to_string())
+                }
+                "device.kill_any_user" => {
+                    cfg.device.kill_any_user.map(|enabled| enabled.to_string())
+                }
+                "device.desktop" => cfg.device.desktop.map(|enabled| enabled.to_string()),
+                "device.service_manager" => cfg
+                    .device
+                    .service_manager
+                    .map(|kind| kind.as_str().to_string()),
+                "device.limits.queue_size" => {
+                    cfg.device.limits.queue_size.map(|size| size.to_string())
+                }
+                "device.limits.queue_timeout_secs" => cfg
+                    .device
+                    .limits
+                    .queue_timeout_secs
+                    .map(|secs| secs.to_string()),
+                family if family.starts_with(CONCURRENCY_KEY_PREFIX) => cfg
+                    .device
+                    .limits
+                    .concurrency
+                    .get(family.trim_start_matches(CONCURRENCY_KEY_PREFIX))
+                    .map(|limit| limit.to_string()),
                 _ => {
                     eprintln!("Unknown config key: {}", key);
                     eprintln!("\nValid keys:");
@@ -76,7 +108,15 @@ pub(crate) fn run_local_config(
                     eprintln!("  release.channel");
                     eprintln!("  r2.account_id, r2.access_key_id, r2.bucket");
                     eprintln!("  session.default_key");
-                    eprintln!("  device.id, device.token, device.workspace");
+                    eprintln!(
+                        "  device.id, device.token, device.workspace, device.max_retry_delay_secs"
+                    );
+                    eprintln!(
+                        "  device.auto_update, device.grace_period_secs, device.service_manager"
+                    );
+                    eprintln!("  device.kill_any_user, device.desktop");
+                    eprintln!("  device.limits.queue_size, device.limits.queue_timeout_secs");
+                    eprintln!("  device.limits.concurrency.<family>");
                     return Ok(());
                 }
             };
@@ -127,6 +167,72 @@ pub(crate) fn run_local_config(
                 "device.workspace" | "node.workspace" => {
                     cfg.device.workspace = Some(PathBuf::from(value.clone()))
                 }
+                "device.max_retry_delay_secs" => {
+                    let parsed = value.trim().parse::<u64>().map_err(|error| {
+                        format!("device.max_retry_delay_secs must be seconds: {}", error)
+                    })?;
+                    cfg.device.max_retry_delay_secs = Some(parsed);
+                }
+                "device.auto_update" => {
+                    let parsed = value.trim().parse::<bool>().map_err(|error| {
+                        format!("device.auto_update must be true or false: {}", error)
+                    })?;
+                    cfg.device.auto_update = Some(parsed);
+                }
+                "device.grace_period_secs" => {
+                    let parsed = value.trim().parse::<u64>().map_err(|error| {
+                        format!("device.grace_period_secs must be seconds: {}", error)
+                    })?;
+                    cfg.device.grace_period_secs = Some(parsed);
+                }
+                "device.kill_any_user" => {
+                    let parsed = value.trim().parse::<bool>().map_err(|error| {
+                        format!("device.kill_any_user must be true or false: {}", error)
+                    })?;
+                    cfg.device.kill_any_user = Some(parsed);
+                }
+                "device.desktop" => {
+                    let parsed = value.trim().parse::<bool>().map_err(|error| {
+                        format!("device.desktop must be true or false: {}", error)
+                    })?;
+                    cfg.device.desktop = Some(parsed);
+                }
+                "device.service_manager" => {
+                    let parsed =
+                        <ServiceManagerKind as clap::ValueEnum>::from_str(value.trim(), true)
+                            .map_err(|error| format!("device.service_manager: {}", error))?;
+                    cfg.device.service_manager = Some(parsed);
+                }
+                "device.limits.queue_size" => {
+                    let parsed = value.trim().parse::<usize>().map_err(|error| {
+                        format!("device.limits.queue_size must be a count: {}", error)
+                    })?;
+                    cfg.device.limits.queue_size = Some(parsed);
+                }
+                "device.limits.queue_timeout_secs" => {
+                    let parsed = value.trim().parse::<u64>().map_err(|error| {
+                        format!(
+                            "device.limits.queue_timeout_secs must be seconds: {}",
+                            error
+                        )
+                    })?;
+                    cfg.device.limits.queue_timeout_secs = Some(parsed);
+                }
+                family if family.starts_with(CONCURRENCY_KEY_PREFIX) => {
+                    let family = family.trim_start_matches(CONCURRENCY_KEY_PREFIX);
+                    if family.is_empty() {
+                        eprintln!("Missing syscall family, e.g. device.limits.concurrency.shell");
+                        return Ok(());
+                    }
+                    let parsed = value
+                        .trim()
+                        .parse::<usize>()
+                        .map_err(|error| format!("{} must be a count: {}", key, error))?;
+                    cfg.device
+                        .limits
+                        .concurrency
+                        .insert(family.to_string(), parsed);
+                }
                 _ => {
                     eprintln!("Unknown config key: {}", key);
                     return Ok(());
@@ -24,23 +24,36 @@ pub struct DeviceLoggingGuard {
 
 pub fn device_log_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
     let home = dirs::home_dir().ok_or("Could not determine home directory")?;
-    Ok(home.join(".gsv").join("logs"))
+    Ok(device_log_dir_under(&home))
+}
+
+/// Log directory of a daemon running with `home` as its home directory.
+pub fn device_log_dir_under(home: &Path) -> PathBuf {
+    home.join(".gsv").join("logs")
 }
 
 pub fn device_log_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
-    if let Some(path) = latest_device_log_path()? {
+    device_log_path_in(&device_log_dir()?)
+}
+
+pub fn device_log_path_in(log_dir: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if let Some(path) = latest_device_log_path(log_dir)? {
         return Ok(path);
     }
-    Ok(device_log_dir()?.join(DEVICE_LOG_FILE_PREFIX))
+    Ok(log_dir.join(DEVICE_LOG_FILE_PREFIX))
 }
 
 pub fn device_log_pattern() -> Result<String, Box<dyn std::error::Error>> {
-    Ok(format!(
+    Ok(device_log_pattern_in(&device_log_dir()?))
+}
+
+pub fn device_log_pattern_in(log_dir: &Path) -> String {
+    format!(
         "{}{}{}*",
-        device_log_dir()?.display(),
+        log_dir.display(),
         std::path::MAIN_SEPARATOR,
         DEVICE_LOG_FILE_PREFIX,
-    ))
+    )
 }
 
 pub fn init_device_logging() -> Result<DeviceLoggingGuard, Box<dyn std::error::Error>> {
@@ -91,6 +104,23 @@ pub fn init_device_logging() -> Result<DeviceLoggingGuard, Box<dyn std::error::E
     })
 }
 
+/// Logging for `gsv device run --stateless`: JSON lines on stdout only, for
+/// the container runtime to collect.
+pub fn init_device_stdout_logging() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::registry()
+        .with(tracing_env_filter())
+        .with(
+            fmt::layer()
+                .json()
+                .flatten_event(true)
+                .with_current_span(true)
+                .with_span_list(true)
+                .with_ansi(false),
+        )
+        .try_init()?;
+    Ok(())
+}
+
 fn tracing_env_filter() -> EnvFilter {
     EnvFilter::try_from_env("GSV_DEVICE_LOG")
         .or_else(|_| EnvFilter::try_from_env("GSV_NODE_LOG"))
@@ -113,8 +143,7 @@ fn device_console_log_format() -> ConsoleLogFormat {
     }
 }
 
-fn latest_device_log_path() -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
-    let log_dir = device_log_dir()?;
+fn latest_device_log_path(log_dir: &Path) -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
     if !log_dir.exists() {
         return Ok(None);
     }
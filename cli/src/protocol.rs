@@ -4,10 +4,18 @@ use serde_json::Value;
 pub const BINARY_FRAME_HEADER_BYTES: usize = 5;
 pub const PROTOCOL_VERSION: u32 = 2;
 pub const REQUEST_CANCEL_SIGNAL: &str = "request.cancel";
+/// Sent by a driver that is shutting down and takes no further requests.
+pub const DEVICE_OFFLINE_SIGNAL: &str = "device.offline";
 pub const BINARY_FRAME_DATA: u8 = 1 << 0;
 pub const BINARY_FRAME_END: u8 = 1 << 1;
 pub const BINARY_FRAME_ERROR: u8 = 1 << 2;
 pub const BINARY_FRAME_CANCEL: u8 = 1 << 3;
+/// Payload is raw-deflate compressed (negotiated via `ConnectArgs::codec`).
+pub const BINARY_FRAME_DEFLATE: u8 = 1 << 4;
+/// Stream-0 payload is a MessagePack control frame rather than JSON.
+pub const BINARY_FRAME_MSGPACK: u8 = 1 << 5;
+/// Binary frames on stream 0 carry encoded control frames, not bodies.
+pub const CONTROL_FRAME_STREAM_ID: u32 = 0;
 
 // ---------------------------------------------------------------------------
 //  Core frame types — mirrors gateway/src/protocol/frames.ts
@@ -83,6 +91,10 @@ pub struct ConnectArgs {
     pub driver: Option<DriverInfo>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub auth: Option<AuthInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<SessionResume>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub codec: Option<CodecOffer>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -93,11 +105,150 @@ pub struct ClientInfo {
     pub role: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub channel: Option<String>,
+    /// Details of the running build. Older gateways ignore it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<ClientBuild>,
+}
+
+/// Build metadata from `build_info`, so the gateway can tell which release a
+/// client runs and which release asset would replace it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientBuild {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+    /// Release asset platform, for example `linux-x64`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DriverInfo {
     pub implements: Vec<String>,
+    /// Named roots reachable as `@name/...` paths. Older gateways ignore it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mounts: Vec<DriverMount>,
+    /// What the device runs on, as `host.info` reports it. Older gateways
+    /// ignore it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<SystemInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DriverMount {
+    pub name: String,
+    pub path: String,
+    pub read_only: bool,
+}
+
+/// A device's hardware and software, so agents can pick a device for a job
+/// without probing it first.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemInfo {
+    pub os: OsInfo,
+    pub cpu: CpuInfo,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<MemoryInfo>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disks: Vec<DiskInfo>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gpus: Vec<GpuInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub battery: Option<BatteryInfo>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub network: Vec<NetworkInterface>,
+    /// Toolchains found on the daemon's `PATH`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub toolchains: Vec<Toolchain>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OsInfo {
+    /// `std::env::consts::OS`, e.g. `linux` or `macos`.
+    pub family: String,
+    pub arch: String,
+    /// Distribution or product name, e.g. `Ubuntu 24.04.1 LTS`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kernel: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CpuInfo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Logical CPUs available to the daemon.
+    pub cores: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub physical_cores: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryInfo {
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskInfo {
+    pub mount: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filesystem: Option<String>,
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GpuInfo {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vendor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatteryInfo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percent: Option<u8>,
+    /// `charging`, `discharging`, `full`, `not_charging` or `unknown`.
+    pub state: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkInterface {
+    pub name: String,
+    pub addresses: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Toolchain {
+    /// `python`, `node`, `docker`, `cargo`, `rustc` or `go`.
+    pub name: String,
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -109,6 +260,53 @@ pub struct AuthInfo {
     pub token: Option<String>,
 }
 
+/// Resumable driver session offered during `sys.connect`.
+///
+/// A driver without a token asks for a new session. A driver reconnecting after
+/// a dropped socket presents the previous token together with the request ids
+/// it still owes responses for.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionResume {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pending: Vec<String>,
+}
+
+/// Control-frame encoding. JSON text frames are always understood.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameEncoding {
+    #[default]
+    Json,
+    Msgpack,
+}
+
+/// Per-message compression applied to control frames and body chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameCompression {
+    Deflate,
+}
+
+/// Frame codecs the client supports, in order of preference.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CodecOffer {
+    pub encodings: Vec<FrameEncoding>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub compression: Vec<FrameCompression>,
+}
+
+/// Codec the gateway picked from the client's offer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NegotiatedCodec {
+    #[serde(default)]
+    pub encoding: FrameEncoding,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<FrameCompression>,
+}
+
 // ---------------------------------------------------------------------------
 //  sys.connect result
 // ---------------------------------------------------------------------------
@@ -120,6 +318,39 @@ pub struct ConnectResult {
     pub identity: Value,
     pub syscalls: Vec<String>,
     pub signals: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<SessionInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub codec: Option<NegotiatedCodec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update: Option<UpdateHint>,
+}
+
+/// Client versions the gateway expects. Below `minimumVersion` the gateway
+/// may refuse the connection (with the hint in the error details under
+/// `update`); below `recommendedVersion` it keeps working.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateHint {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minimum_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recommended_version: Option<String>,
+    /// Release to download; defaults to `v<version>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_tag: Option<String>,
+}
+
+/// Session granted by the gateway. `resumed` is true only when the presented
+/// token was still valid and pending request ids were re-attached.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    pub token: String,
+    #[serde(default)]
+    pub resumed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_ms: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -151,6 +382,9 @@ pub struct DeviceExecEventParams {
     pub started_at: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ended_at: Option<i64>,
+    /// Device cron job that started the session.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<String>,
 }
 
 // ---------------------------------------------------------------------------
@@ -205,7 +439,9 @@ pub fn parse_binary_frame(data: &[u8]) -> Option<(u32, u8, Vec<u8>)> {
 
 #[cfg(test)]
 mod tests {
-    use super::{Frame, FrameBodyDescriptor, RequestFrame, ResponseFrame};
+    use super::{
+        ConnectResult, Frame, FrameBodyDescriptor, RequestFrame, ResponseFrame, SessionResume,
+    };
     use serde_json::json;
 
     #[test]
@@ -255,4 +491,25 @@ mod tests {
 
         assert!(value.get("body").is_none());
     }
+
+    #[test]
+    fn connect_session_fields_are_optional() {
+        let args = serde_json::to_value(SessionResume::default())
+            .expect("session resume should serialize");
+        assert_eq!(args, json!({}));
+
+        let result: ConnectResult = serde_json::from_value(json!({
+            "protocol": 2,
+            "server": { "version": "0.4.0", "connectionId": "conn-1" },
+            "identity": {},
+            "syscalls": [],
+            "signals": [],
+            "session": { "token": "tok", "resumed": true, "ttlMs": 1000 }
+        }))
+        .expect("connect result should deserialize");
+        let session = result.session.expect("session should be present");
+        assert_eq!(session.token, "tok");
+        assert!(session.resumed);
+        assert_eq!(session.ttl_ms, Some(1000));
+    }
 }
@@ -1,4 +1,6 @@
 use crate::protocol::ToolDefinition;
+use crate::tools::paths::{Access, PathResolver};
+use crate::tools::preview;
 use crate::tools::{Tool, ToolOutput};
 use async_trait::async_trait;
 use serde::Deserialize;
@@ -6,25 +8,17 @@ use serde_json::{json, Value};
 use std::path::{Path, PathBuf};
 
 pub struct CopyTool {
-    workspace: PathBuf,
+    paths: PathResolver,
     device_id: String,
 }
 
 impl CopyTool {
     pub fn new(workspace: PathBuf, device_id: String) -> Self {
-        Self {
-            workspace,
-            device_id,
-        }
+        Self::with_paths(PathResolver::new(workspace), device_id)
     }
 
-    fn resolve_path(&self, path: &str) -> PathBuf {
-        let path = PathBuf::from(path);
-        if path.is_absolute() {
-            path
-        } else {
-            self.workspace.join(path)
-        }
+    pub fn with_paths(paths: PathResolver, device_id: String) -> Self {
+        Self { paths, device_id }
     }
 
     fn validate_endpoint(&self, endpoint: &CopyEndpoint) -> Result<(), String> {
@@ -38,6 +32,44 @@ impl CopyTool {
         }
         Ok(())
     }
+
+    /// Resolve both endpoints into the copy a real or dry run performs.
+    async fn plan(&self, args: &CopyArgs) -> Result<CopyPlan, String> {
+        self.validate_endpoint(&args.source)?;
+        self.validate_endpoint(&args.destination)?;
+
+        let source = self.paths.resolve(&args.source.path, Access::Read)?;
+        let mut destination = self.paths.resolve(&args.destination.path, Access::Write)?;
+
+        let source_metadata = tokio::fs::metadata(&source)
+            .await
+            .map_err(|e| format!("Failed to stat source '{}': {}", source.display(), e))?;
+        if source_metadata.is_dir() {
+            return Err(format!(
+                "Failed to copy '{}': directories are not supported yet",
+                source.display()
+            ));
+        }
+
+        if let Ok(destination_metadata) = tokio::fs::metadata(&destination).await {
+            if destination_metadata.is_dir() {
+                let file_name = source.file_name().ok_or_else(|| {
+                    format!("Failed to resolve basename for '{}'", source.display())
+                })?;
+                destination = destination.join(file_name);
+            }
+        }
+
+        let content_type = mime_guess::from_path(&source)
+            .first()
+            .map(|mime| mime.essence_str().to_string());
+        Ok(CopyPlan {
+            source,
+            destination,
+            size: source_metadata.len(),
+            content_type,
+        })
+    }
 }
 
 #[derive(Deserialize)]
@@ -45,6 +77,8 @@ impl CopyTool {
 struct CopyArgs {
     source: CopyEndpoint,
     destination: CopyEndpoint,
+    #[serde(default)]
+    dry_run: bool,
 }
 
 #[derive(Deserialize)]
@@ -60,7 +94,7 @@ impl Tool for CopyTool {
         ToolDefinition {
             name: "Copy".to_string(),
             description:
-                "Copy a file on this target. Paths are relative to the workspace unless absolute."
+                "Copy a file on this target. Paths are relative to the workspace unless absolute; @name/... paths address named mounts."
                     .to_string(),
             input_schema: json!({
                 "type": "object",
@@ -80,6 +114,10 @@ impl Tool for CopyTool {
                             "path": { "type": "string" }
                         },
                         "required": ["path"]
+                    },
+                    "dryRun": {
+                        "type": "boolean",
+                        "description": "Report the destination and any file it would overwrite without copying (default: false)"
                     }
                 },
                 "required": ["source", "destination"]
@@ -91,62 +129,61 @@ impl Tool for CopyTool {
         let args: CopyArgs =
             serde_json::from_value(args).map_err(|e| format!("Invalid arguments: {}", e))?;
 
-        self.validate_endpoint(&args.source)?;
-        self.validate_endpoint(&args.destination)?;
-
-        let source = self.resolve_path(&args.source.path);
-        let mut destination = self.resolve_path(&args.destination.path);
-
-        let source_metadata = tokio::fs::metadata(&source)
-            .await
-            .map_err(|e| format!("Failed to stat source '{}': {}", source.display(), e))?;
-        if source_metadata.is_dir() {
-            return Err(format!(
-                "Failed to copy '{}': directories are not supported yet",
-                source.display()
-            ));
+        let plan = self.plan(&args).await?;
+        if args.dry_run {
+            let mut preview = plan.describe(&self.device_id, plan.size);
+            preview["dryRun"] = json!(true);
+            preview["conflicts"] = json!(preview::overwrite_conflicts(&plan.destination));
+            return Ok(ToolOutput::json(preview));
         }
+        let bytes = plan.apply().await?;
 
-        if let Ok(destination_metadata) = tokio::fs::metadata(&destination).await {
-            if destination_metadata.is_dir() {
-                let file_name = source.file_name().ok_or_else(|| {
-                    format!("Failed to resolve basename for '{}'", source.display())
-                })?;
-                destination = destination.join(file_name);
-            }
-        }
+        Ok(ToolOutput::json(plan.describe(&self.device_id, bytes)))
+    }
+}
+
+/// Copying one file to its resolved destination, for real and dry runs.
+struct CopyPlan {
+    source: PathBuf,
+    destination: PathBuf,
+    size: u64,
+    content_type: Option<String>,
+}
 
-        if let Some(parent) = destination.parent() {
+impl CopyPlan {
+    async fn apply(&self) -> Result<u64, String> {
+        if let Some(parent) = self.destination.parent() {
             tokio::fs::create_dir_all(parent)
                 .await
                 .map_err(|e| format!("Failed to create '{}': {}", parent.display(), e))?;
         }
 
-        let bytes = tokio::fs::copy(&source, &destination).await.map_err(|e| {
-            format!(
-                "Failed to copy '{}' to '{}': {}",
-                source.display(),
-                destination.display(),
-                e
-            )
-        })?;
-        let content_type = mime_guess::from_path(&source)
-            .first()
-            .map(|mime| mime.essence_str().to_string());
+        tokio::fs::copy(&self.source, &self.destination)
+            .await
+            .map_err(|e| {
+                format!(
+                    "Failed to copy '{}' to '{}': {}",
+                    self.source.display(),
+                    self.destination.display(),
+                    e
+                )
+            })
+    }
 
-        Ok(ToolOutput::json(json!({
+    fn describe(&self, device_id: &str, size: u64) -> Value {
+        json!({
             "ok": true,
             "source": {
-                "target": self.device_id,
-                "path": display_path(&source)
+                "target": device_id,
+                "path": display_path(&self.source)
             },
             "destination": {
-                "target": self.device_id,
-                "path": display_path(&destination)
+                "target": device_id,
+                "path": display_path(&self.destination)
             },
-            "size": bytes,
-            "contentType": content_type
-        })))
+            "size": size,
+            "contentType": self.content_type
+        })
     }
 }
 
@@ -219,6 +256,44 @@ mod tests {
         tokio::fs::remove_dir_all(root).await.unwrap();
     }
 
+    #[tokio::test]
+    async fn dry_run_reports_overwrite_without_copying() {
+        let root = test_root();
+        tokio::fs::create_dir_all(root.join("dest")).await.unwrap();
+        tokio::fs::write(root.join("source.txt"), "hello")
+            .await
+            .unwrap();
+        tokio::fs::write(root.join("dest/source.txt"), "old")
+            .await
+            .unwrap();
+
+        let tool = CopyTool::new(root.clone(), "device-a".to_string());
+        let result = tool
+            .execute(json!({
+                "source": { "path": "source.txt" },
+                "destination": { "path": "dest" },
+                "dryRun": true
+            }))
+            .await
+            .unwrap();
+
+        assert_eq!(result.data["dryRun"], true);
+        assert_eq!(result.data["size"], 5);
+        assert_eq!(
+            result.data["conflicts"][0]["path"],
+            root.join("dest/source.txt").display().to_string()
+        );
+        assert_eq!(result.data["conflicts"][0]["size"], 3);
+        assert_eq!(
+            tokio::fs::read_to_string(root.join("dest/source.txt"))
+                .await
+                .unwrap(),
+            "old"
+        );
+
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+
     #[tokio::test]
     async fn rejects_non_local_endpoints() {
         let root = test_root();
@@ -1,33 +1,32 @@
 use crate::protocol::ToolDefinition;
+use crate::tools::paths::{Access, PathResolver};
+use crate::tools::preview::DeletePlan;
 use crate::tools::{Tool, ToolOutput};
 use async_trait::async_trait;
 use serde::Deserialize;
 use serde_json::{json, Value};
-use std::fs;
 use std::path::PathBuf;
 
 pub struct DeleteTool {
-    workspace: PathBuf,
+    paths: PathResolver,
 }
 
 impl DeleteTool {
     pub fn new(workspace: PathBuf) -> Self {
-        Self { workspace }
+        Self::with_paths(PathResolver::new(workspace))
     }
 
-    fn resolve_path(&self, path: &str) -> PathBuf {
-        let path = PathBuf::from(path);
-        if path.is_absolute() {
-            path
-        } else {
-            self.workspace.join(path)
-        }
+    pub fn with_paths(paths: PathResolver) -> Self {
+        Self { paths }
     }
 }
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct DeleteArgs {
     path: String,
+    #[serde(default)]
+    dry_run: bool,
 }
 
 #[async_trait]
@@ -36,7 +35,7 @@ impl Tool for DeleteTool {
         ToolDefinition {
             name: "Delete".to_string(),
             description:
-                "Delete a file or directory. Paths are relative to the workspace unless absolute."
+                "Delete a file or directory. Paths are relative to the workspace unless absolute; @name/... paths address named mounts."
                     .to_string(),
             input_schema: json!({
                 "type": "object",
@@ -44,6 +43,10 @@ impl Tool for DeleteTool {
                     "path": {
                         "type": "string",
                         "description": "Path to the file or directory to delete"
+                    },
+                    "dryRun": {
+                        "type": "boolean",
+                        "description": "List the paths that would be removed without deleting (default: false)"
                     }
                 },
                 "required": ["path"]
@@ -55,21 +58,16 @@ impl Tool for DeleteTool {
         let args: DeleteArgs =
             serde_json::from_value(args).map_err(|e| format!("Invalid arguments: {}", e))?;
 
-        let resolved = self.resolve_path(&args.path);
-        let metadata = fs::metadata(&resolved)
-            .map_err(|e| format!("Failed to delete '{}': {}", resolved.display(), e))?;
-
-        if metadata.is_dir() {
-            fs::remove_dir_all(&resolved)
-                .map_err(|e| format!("Failed to delete '{}': {}", resolved.display(), e))?;
-        } else {
-            fs::remove_file(&resolved)
-                .map_err(|e| format!("Failed to delete '{}': {}", resolved.display(), e))?;
+        let resolved = self.paths.resolve(&args.path, Access::Write)?;
+        let plan = DeletePlan::new(resolved)?;
+        if args.dry_run {
+            return Ok(ToolOutput::json(plan.preview()?));
         }
+        plan.apply()?;
 
         Ok(ToolOutput::json(json!({
             "ok": true,
-            "path": resolved.display().to_string()
+            "path": plan.path().display().to_string()
         })))
     }
 }
@@ -0,0 +1,168 @@
+//! BLAKE3 digests of files, remembered per path for as long as the file's
+//! size and timestamps stay the same, so an unchanged file is hashed once.
+//!
+//! `fs.read` and `fs.transfer.send` return them on request, and skip the body
+//! when the requester already holds the file's current digest.
+
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::SystemTime;
+
+/// Least recently used digests are forgotten beyond this many files.
+const MAX_CACHED_DIGESTS: usize = 4096;
+
+/// What has to stay the same for a cached digest to still be valid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    size: u64,
+    modified: Option<SystemTime>,
+    /// Inode and change time, which also catch a restored modification time
+    /// or a file replaced by another.
+    #[cfg(unix)]
+    inode: (u64, u64),
+    #[cfg(unix)]
+    changed: (i64, i64),
+}
+
+impl Stamp {
+    fn of(metadata: &Metadata) -> Self {
+        #[cfg(unix)]
+        use std::os::unix::fs::MetadataExt;
+
+        Self {
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+            #[cfg(unix)]
+            inode: (metadata.dev(), metadata.ino()),
+            #[cfg(unix)]
+            changed: (metadata.ctime(), metadata.ctime_nsec()),
+        }
+    }
+}
+
+struct CachedDigest {
+    stamp: Stamp,
+    digest: String,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct DigestCache {
+    entries: HashMap<PathBuf, CachedDigest>,
+    clock: u64,
+}
+
+impl DigestCache {
+    fn get(&mut self, path: &Path, stamp: Stamp) -> Option<String> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(path)?;
+        if entry.stamp != stamp {
+            return None;
+        }
+        entry.last_used = self.clock;
+        Some(entry.digest.clone())
+    }
+
+    fn insert(&mut self, path: PathBuf, stamp: Stamp, digest: String) {
+        if self.entries.len() >= MAX_CACHED_DIGESTS && !self.entries.contains_key(&path) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(path, _)| path.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.clock += 1;
+        self.entries.insert(
+            path,
+            CachedDigest {
+                stamp,
+                digest,
+                last_used: self.clock,
+            },
+        );
+    }
+}
+
+static DIGEST_CACHE: OnceLock<Mutex<DigestCache>> = OnceLock::new();
+
+fn digest_cache() -> MutexGuard<'static, DigestCache> {
+    DIGEST_CACHE
+        .get_or_init(Default::default)
+        .lock()
+        .expect("digest cache mutex poisoned")
+}
+
+async fn stamp(path: &Path) -> Result<Stamp, String> {
+    let metadata = tokio::fs::metadata(path)
+        .await
+        .map_err(|e| format!("Failed to stat '{}': {}", path.display(), e))?;
+    if !metadata.is_file() {
+        return Err(format!("Not a file: '{}'", path.display()));
+    }
+    Ok(Stamp::of(&metadata))
+}
+
+/// The BLAKE3 digest of the file at `path`, as lowercase hex.
+pub async fn file_digest(path: &Path) -> Result<String, String> {
+    let before = stamp(path).await?;
+    if let Some(digest) = digest_cache().get(path, before) {
+        return Ok(digest);
+    }
+
+    let source = path.to_path_buf();
+    let digest = tokio::task::spawn_blocking(move || {
+        let file = std::fs::File::open(&source)?;
+        let mut hasher = blake3::Hasher::new();
+        hasher.update_reader(file)?;
+        Ok::<_, std::io::Error>(hasher.finalize().to_hex().to_string())
+    })
+    .await
+    .map_err(|e| format!("Failed to hash '{}': {}", path.display(), e))?
+    .map_err(|e| format!("Failed to hash '{}': {}", path.display(), e))?;
+
+    // A file written to while it was hashed may not match its digest.
+    if stamp(path).await? == before {
+        digest_cache().insert(path.to_path_buf(), before, digest.clone());
+    }
+    Ok(digest)
+}
+
+/// Whether a digest the requester holds is the file's current `digest`.
+pub fn same_digest(known: &str, digest: &str) -> bool {
+    known.trim().eq_ignore_ascii_case(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{digest_cache, file_digest, same_digest};
+
+    #[tokio::test]
+    async fn digests_are_cached_until_the_file_changes() {
+        let root = std::env::temp_dir().join(format!("gsv-digest-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        let path = root.join("artifact.bin");
+        std::fs::write(&path, b"first").unwrap();
+
+        let first = file_digest(&path).await.unwrap();
+        assert_eq!(first, blake3::hash(b"first").to_hex().to_string());
+        assert!(digest_cache().entries.contains_key(&path));
+        assert_eq!(file_digest(&path).await.unwrap(), first);
+
+        std::fs::write(&path, b"second, longer").unwrap();
+        let second = file_digest(&path).await.unwrap();
+        assert_eq!(second, blake3::hash(b"second, longer").to_hex().to_string());
+        assert!(same_digest(
+            &format!(" {} ", second.to_uppercase()),
+            &second
+        ));
+        assert!(!same_digest(&first, &second));
+
+        file_digest(&root).await.unwrap_err();
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
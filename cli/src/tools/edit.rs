@@ -1,4 +1,6 @@
 use crate::protocol::ToolDefinition;
+use crate::tools::paths::{Access, PathResolver};
+use crate::tools::preview::ReplacePlan;
 use crate::tools::{Tool, ToolOutput};
 use async_trait::async_trait;
 use serde::Deserialize;
@@ -7,21 +9,16 @@ use std::fs;
 use std::path::PathBuf;
 
 pub struct EditTool {
-    workspace: PathBuf,
+    paths: PathResolver,
 }
 
 impl EditTool {
     pub fn new(workspace: PathBuf) -> Self {
-        Self { workspace }
+        Self::with_paths(PathResolver::new(workspace))
     }
 
-    fn resolve_path(&self, path: &str) -> PathBuf {
-        let path = PathBuf::from(path);
-        if path.is_absolute() {
-            path
-        } else {
-            self.workspace.join(path)
-        }
+    pub fn with_paths(paths: PathResolver) -> Self {
+        Self { paths }
     }
 }
 
@@ -33,6 +30,8 @@ struct EditArgs {
     new_string: String,
     #[serde(default)]
     replace_all: bool,
+    #[serde(default)]
+    dry_run: bool,
 }
 
 #[async_trait]
@@ -40,7 +39,7 @@ impl Tool for EditTool {
     fn definition(&self) -> ToolDefinition {
         ToolDefinition {
             name: "Edit".to_string(),
-            description: "Edit a file by replacing text. Paths are relative to the workspace unless absolute.".to_string(),
+            description: "Edit a file by replacing text. Paths are relative to the workspace unless absolute; @name/... paths address named mounts.".to_string(),
             input_schema: json!({
                 "type": "object",
                 "properties": {
@@ -59,6 +58,10 @@ impl Tool for EditTool {
                     "replaceAll": {
                         "type": "boolean",
                         "description": "Replace all occurrences (default: false, replace first only)"
+                    },
+                    "dryRun": {
+                        "type": "boolean",
+                        "description": "Return a unified diff of the edit without writing (default: false)"
                     }
                 },
                 "required": ["path", "oldString", "newString"]
@@ -70,7 +73,7 @@ impl Tool for EditTool {
         let args: EditArgs =
             serde_json::from_value(args).map_err(|e| format!("Invalid arguments: {}", e))?;
 
-        let resolved = self.resolve_path(&args.path);
+        let resolved = self.paths.resolve(&args.path, Access::Write)?;
 
         let content = fs::read_to_string(&resolved)
             .map_err(|e| format!("Failed to read '{}': {}", resolved.display(), e))?;
@@ -95,13 +98,19 @@ impl Tool for EditTool {
             content.replacen(&args.old_string, &args.new_string, 1)
         };
 
-        fs::write(&resolved, &new_content)
-            .map_err(|e| format!("Failed to write '{}': {}", resolved.display(), e))?;
+        let replacements = if args.replace_all { count } else { 1 };
+        let plan = ReplacePlan::replacing(resolved, content, new_content);
+        if args.dry_run {
+            let mut preview = plan.preview();
+            preview["replacements"] = json!(replacements);
+            return Ok(ToolOutput::json(preview));
+        }
+        plan.apply()?;
 
         Ok(ToolOutput::json(json!({
             "ok": true,
-            "path": resolved.display().to_string(),
-            "replacements": if args.replace_all { count } else { 1 }
+            "path": plan.path().display().to_string(),
+            "replacements": replacements
         })))
     }
 }
@@ -1,7 +1,10 @@
 mod copy;
 mod delete;
+pub mod digest;
 mod edit;
 mod net;
+pub mod paths;
+pub mod preview;
 mod read;
 mod search;
 mod shell;
@@ -13,10 +16,14 @@ pub use edit::EditTool;
 pub use net::NetFetchTool;
 pub use read::ReadTool;
 pub use search::SearchTool;
-pub use shell::{subscribe_exec_events, ShellTool};
+pub use shell::{
+    adopt_shell_sessions, detach_shell_sessions, list_shell_sessions, set_shell_session_dir,
+    spawn_background_shell, subscribe_exec_events, ExecEvent, ShellSessionSummary, ShellTool,
+};
 pub use write::WriteTool;
 
 use crate::protocol::ToolDefinition;
+use crate::tools::paths::PathResolver;
 use async_trait::async_trait;
 use serde_json::Value;
 use std::fmt;
@@ -141,15 +148,23 @@ pub fn all_tools_with_workspace(workspace: PathBuf) -> Vec<Box<dyn Tool>> {
 pub fn all_tools_with_workspace_for_device(
     workspace: PathBuf,
     device_id: String,
+) -> Vec<Box<dyn Tool>> {
+    all_tools_with_paths_for_device(PathResolver::new(workspace), device_id)
+}
+
+/// Create all tools for a device driver that exposes named mounts.
+pub fn all_tools_with_paths_for_device(
+    paths: PathResolver,
+    device_id: String,
 ) -> Vec<Box<dyn Tool>> {
     vec![
-        Box::new(ShellTool::new(workspace.clone())),
-        Box::new(ReadTool::new(workspace.clone())),
-        Box::new(WriteTool::new(workspace.clone())),
-        Box::new(DeleteTool::new(workspace.clone())),
-        Box::new(EditTool::new(workspace.clone())),
-        Box::new(CopyTool::new(workspace.clone(), device_id)),
+        Box::new(ShellTool::with_paths(paths.clone()).owned_by(device_id.clone())),
+        Box::new(ReadTool::with_paths(paths.clone())),
+        Box::new(WriteTool::with_paths(paths.clone())),
+        Box::new(DeleteTool::with_paths(paths.clone())),
+        Box::new(EditTool::with_paths(paths.clone())),
+        Box::new(CopyTool::with_paths(paths.clone(), device_id)),
         Box::new(NetFetchTool::new()),
-        Box::new(SearchTool::new(workspace)),
+        Box::new(SearchTool::with_paths(paths)),
     ]
 }
@@ -0,0 +1,391 @@
+//! Path resolution shared by the file tools and device transfers.
+//!
+//! Plain paths are relative to the workspace unless absolute. Paths starting
+//! with `@name/` resolve inside the named mount and may not leave it, neither
+//! through `..` nor through symlinks. Writes into a read-only mount are
+//! refused, whichever way the path was spelled.
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use crate::protocol::DriverMount;
+
+/// Name under which the workspace is always reachable.
+pub const WORKSPACE_MOUNT: &str = "workspace";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    pub name: String,
+    pub root: PathBuf,
+    pub read_only: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+#[derive(Debug, Clone)]
+pub struct PathResolver {
+    workspace: PathBuf,
+    mounts: Arc<Vec<Mount>>,
+}
+
+impl PathResolver {
+    pub fn new(workspace: PathBuf) -> Self {
+        let mounts = vec![Mount {
+            name: WORKSPACE_MOUNT.to_string(),
+            root: workspace.clone(),
+            read_only: false,
+        }];
+        Self {
+            workspace,
+            mounts: Arc::new(mounts),
+        }
+    }
+
+    /// Workspace plus named mounts. Unless a mount claims the name, the
+    /// workspace itself is exposed as `@workspace`.
+    pub fn with_mounts(workspace: PathBuf, mounts: Vec<Mount>) -> Result<Self, String> {
+        let mut all: Vec<Mount> = Vec::with_capacity(mounts.len() + 1);
+        for mount in mounts {
+            validate_mount_name(&mount.name)?;
+            if !mount.root.is_absolute() {
+                return Err(format!(
+                    "Mount '@{}' root must be absolute: {}",
+                    mount.name,
+                    mount.root.display()
+                ));
+            }
+            if all.iter().any(|existing| existing.name == mount.name) {
+                return Err(format!("Mount '@{}' is defined twice", mount.name));
+            }
+            all.push(mount);
+        }
+        if !all.iter().any(|mount| mount.name == WORKSPACE_MOUNT) {
+            all.insert(
+                0,
+                Mount {
+                    name: WORKSPACE_MOUNT.to_string(),
+                    root: workspace.clone(),
+                    read_only: false,
+                },
+            );
+        }
+        Ok(Self {
+            workspace,
+            mounts: Arc::new(all),
+        })
+    }
+
+    pub fn workspace(&self) -> &Path {
+        &self.workspace
+    }
+
+    pub fn mounts(&self) -> &[Mount] {
+        &self.mounts
+    }
+
+    /// Mounts as advertised to the gateway during `sys.connect`.
+    pub fn advertised(&self) -> Vec<DriverMount> {
+        self.mounts
+            .iter()
+            .map(|mount| DriverMount {
+                name: mount.name.clone(),
+                path: mount.root.display().to_string(),
+                read_only: mount.read_only,
+            })
+            .collect()
+    }
+
+    pub fn resolve(&self, path: &str, access: Access) -> Result<PathBuf, String> {
+        if let Some(spec) = path.strip_prefix('@') {
+            let (name, rest) = spec.split_once('/').unwrap_or((spec, ""));
+            let mount = self
+                .mounts
+                .iter()
+                .find(|mount| mount.name == name)
+                .ok_or_else(|| {
+                    let known = self
+                        .mounts
+                        .iter()
+                        .map(|mount| format!("@{}", mount.name))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    format!("Unknown mount '@{}' (available: {})", name, known)
+                })?;
+            if access == Access::Write && mount.read_only {
+                return Err(format!("Mount '@{}' is read-only", mount.name));
+            }
+            return confine(mount, rest);
+        }
+
+        let candidate = PathBuf::from(path);
+        let resolved = if candidate.is_absolute() {
+            candidate
+        } else {
+            self.workspace.join(candidate)
+        };
+        if access == Access::Write {
+            if let Some(mount) = self.read_only_mount_containing(&resolved) {
+                return Err(format!(
+                    "'{}' is inside read-only mount '@{}'",
+                    resolved.display(),
+                    mount.name
+                ));
+            }
+        }
+        Ok(resolved)
+    }
+
+    fn read_only_mount_containing(&self, path: &Path) -> Option<&Mount> {
+        let lexical = normalize_lexically(path);
+        let real = real_path(path);
+        self.mounts
+            .iter()
+            .filter(|mount| mount.read_only)
+            .find(|mount| {
+                lexical
+                    .as_ref()
+                    .is_some_and(|lexical| lexical.starts_with(&mount.root))
+                    || match (&real, mount.root.canonicalize()) {
+                        (Some(real), Ok(root)) => real.starts_with(root),
+                        _ => false,
+                    }
+            })
+    }
+}
+
+fn validate_mount_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid mount name '{}': use letters, digits, '-' or '_'",
+            name
+        ))
+    }
+}
+
+/// Join `rest` onto the mount root, refusing anything that ends up outside.
+fn confine(mount: &Mount, rest: &str) -> Result<PathBuf, String> {
+    let mut relative = PathBuf::new();
+    for component in Path::new(rest.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !relative.pop() {
+                    return Err(format!("'@{}/{}' escapes its mount", mount.name, rest));
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(format!("'@{}/{}' escapes its mount", mount.name, rest));
+            }
+        }
+    }
+
+    let resolved = mount.root.join(&relative);
+    let root = mount.root.canonicalize().map_err(|e| {
+        format!(
+            "Mount '@{}' is unavailable ({}): {}",
+            mount.name,
+            mount.root.display(),
+            e
+        )
+    })?;
+    if let Some(real) = real_path(&resolved) {
+        if !real.starts_with(&root) {
+            return Err(format!(
+                "'@{}/{}' resolves outside its mount",
+                mount.name, rest
+            ));
+        }
+    }
+    Ok(resolved)
+}
+
+/// Resolve `.` and `..` without touching the filesystem. `None` when the
+/// path climbs above its root.
+fn normalize_lexically(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            Component::CurDir => {}
+            other => normalized.push(other),
+        }
+    }
+    Some(normalized)
+}
+
+/// Canonical form of the deepest existing ancestor, with the missing tail
+/// appended, so symlinks are followed even for paths about to be created.
+fn real_path(path: &Path) -> Option<PathBuf> {
+    let mut existing = path;
+    let mut tail = Vec::new();
+    loop {
+        if let Ok(real) = existing.canonicalize() {
+            let mut real = real;
+            for part in tail.iter().rev() {
+                real.push(part);
+            }
+            return Some(real);
+        }
+        tail.push(existing.file_name()?.to_os_string());
+        existing = existing.parent()?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixture {
+        root: PathBuf,
+        paths: PathResolver,
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.root);
+        }
+    }
+
+    fn fixture() -> Fixture {
+        let root = std::env::temp_dir().join(format!("gsv-paths-{}", uuid::Uuid::new_v4()));
+        for dir in ["workspace", "data", "notes", "outside"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        let root = root.canonicalize().unwrap();
+        let paths = PathResolver::with_mounts(
+            root.join("workspace"),
+            vec![
+                Mount {
+                    name: "data".to_string(),
+                    root: root.join("data"),
+                    read_only: true,
+                },
+                Mount {
+                    name: "notes".to_string(),
+                    root: root.join("notes"),
+                    read_only: false,
+                },
+            ],
+        )
+        .unwrap();
+        Fixture { root, paths }
+    }
+
+    #[test]
+    fn mount_paths_resolve_inside_their_root() {
+        let fixture = fixture();
+        let paths = &fixture.paths;
+        assert_eq!(
+            paths.resolve("@data/train.csv", Access::Read).unwrap(),
+            fixture.root.join("data/train.csv")
+        );
+        assert_eq!(
+            paths.resolve("@notes/a/../b.md", Access::Write).unwrap(),
+            fixture.root.join("notes/b.md")
+        );
+        assert_eq!(
+            paths.resolve("@workspace", Access::Read).unwrap(),
+            fixture.root.join("workspace")
+        );
+        assert_eq!(
+            paths.resolve("src/main.rs", Access::Read).unwrap(),
+            fixture.root.join("workspace/src/main.rs")
+        );
+    }
+
+    #[test]
+    fn escapes_unknown_mounts_and_read_only_writes_are_refused() {
+        let fixture = fixture();
+        let paths = &fixture.paths;
+        assert!(paths
+            .resolve("@data/../outside/x", Access::Read)
+            .unwrap_err()
+            .contains("escapes"));
+        assert!(paths
+            .resolve("@music/a.mp3", Access::Read)
+            .unwrap_err()
+            .contains("available: @workspace, @data, @notes"));
+        assert_eq!(
+            paths.resolve("@data/new.csv", Access::Write).unwrap_err(),
+            "Mount '@data' is read-only"
+        );
+        let absolute = fixture.root.join("data/new.csv");
+        assert!(paths
+            .resolve(absolute.to_str().unwrap(), Access::Write)
+            .unwrap_err()
+            .contains("read-only mount '@data'"));
+        paths
+            .resolve(absolute.to_str().unwrap(), Access::Read)
+            .unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_cannot_leave_a_mount() {
+        let fixture = fixture();
+        std::os::unix::fs::symlink(
+            fixture.root.join("outside"),
+            fixture.root.join("notes/link"),
+        )
+        .unwrap();
+        assert!(fixture
+            .paths
+            .resolve("@notes/link/secret.txt", Access::Read)
+            .unwrap_err()
+            .contains("outside its mount"));
+        // Writing through a symlink into a read-only mount is caught too.
+        std::os::unix::fs::symlink(
+            fixture.root.join("data"),
+            fixture.root.join("workspace/data-link"),
+        )
+        .unwrap();
+        assert!(fixture
+            .paths
+            .resolve("data-link/x.csv", Access::Write)
+            .unwrap_err()
+            .contains("read-only mount '@data'"));
+    }
+
+    #[test]
+    fn invalid_mount_definitions_are_rejected() {
+        let workspace = std::env::temp_dir();
+        let mount = |name: &str, root: &str| Mount {
+            name: name.to_string(),
+            root: PathBuf::from(root),
+            read_only: false,
+        };
+        assert!(
+            PathResolver::with_mounts(workspace.clone(), vec![mount("a/b", "/tmp")])
+                .unwrap_err()
+                .contains("Invalid mount name")
+        );
+        assert!(
+            PathResolver::with_mounts(workspace.clone(), vec![mount("rel", "tmp")])
+                .unwrap_err()
+                .contains("must be absolute")
+        );
+        assert_eq!(
+            PathResolver::with_mounts(
+                workspace,
+                vec![mount("twice", "/tmp"), mount("twice", "/var")]
+            )
+            .unwrap_err(),
+            "Mount '@twice' is defined twice"
+        );
+    }
+}
@@ -0,0 +1,233 @@
+//! Shared pieces of `dryRun: true` for the mutating file syscalls.
+//!
+//! Each tool first builds a plan of what it is about to do: Write and Edit a
+//! [`ReplacePlan`], Delete a [`DeletePlan`], Copy its own `CopyPlan`. A dry
+//! run describes the plan; a real run applies that same plan, so the preview
+//! cannot disagree with the operation.
+
+use serde_json::{json, Value};
+use similar::TextDiff;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Most paths a delete preview lists before it reports `truncated`.
+const MAX_PREVIEW_PATHS: usize = 1000;
+
+/// Whether `args` asks for a dry run.
+pub fn is_dry_run(args: &Value) -> bool {
+    args.get("dryRun").and_then(Value::as_bool).unwrap_or(false)
+}
+
+/// Unified diff from `old` to `new` for `path`, empty when nothing changes.
+pub(crate) fn unified_diff(path: &Path, old: &str, new: &str) -> String {
+    let path = path.display().to_string();
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("a/{}", path), &format!("b/{}", path))
+        .to_string()
+}
+
+/// Replacing the whole file at `path` with `content`, for Write and Edit.
+pub(crate) struct ReplacePlan {
+    path: PathBuf,
+    content: String,
+    existing: Option<String>,
+}
+
+impl ReplacePlan {
+    pub(crate) fn new(path: PathBuf, content: String) -> Result<Self, String> {
+        if path.is_dir() {
+            return Err(format!(
+                "Failed to write '{}': is a directory",
+                path.display()
+            ));
+        }
+        Ok(Self {
+            path,
+            content,
+            existing: None,
+        })
+    }
+
+    /// A plan for a file whose current text the caller already read.
+    pub(crate) fn replacing(path: PathBuf, existing: String, content: String) -> Self {
+        Self {
+            path,
+            content,
+            existing: Some(existing),
+        }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        self.content.len()
+    }
+
+    /// What the write would change. Files that are not UTF-8 text report
+    /// `binary` instead of a diff.
+    pub(crate) fn preview(&self) -> Value {
+        let existing = match &self.existing {
+            Some(existing) => Some(existing.as_bytes().to_vec()),
+            None => fs::read(&self.path).ok(),
+        };
+        let mut preview = json!({
+            "ok": true,
+            "dryRun": true,
+            "path": self.path.display().to_string(),
+            "size": self.size(),
+            "exists": existing.is_some()
+        });
+        match std::str::from_utf8(existing.as_deref().unwrap_or_default()) {
+            Ok(old) => preview["diff"] = json!(unified_diff(&self.path, old, &self.content)),
+            Err(_) => preview["binary"] = json!(true),
+        }
+        preview
+    }
+
+    /// Write the file, creating parent directories if needed.
+    pub(crate) fn apply(&self) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directories: {}", e))?;
+        }
+        fs::write(&self.path, &self.content)
+            .map_err(|e| format!("Failed to write '{}': {}", self.path.display(), e))
+    }
+}
+
+/// A file the operation would replace.
+pub fn overwrite_conflicts(path: &Path) -> Vec<Value> {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.is_file() => vec![json!({
+            "path": path.display().to_string(),
+            "size": metadata.len()
+        })],
+        _ => Vec::new(),
+    }
+}
+
+/// Deleting `root`, recursively for a directory. Symlinks are removed
+/// themselves and never followed.
+pub(crate) struct DeletePlan {
+    root: PathBuf,
+    is_dir: bool,
+}
+
+impl DeletePlan {
+    pub(crate) fn new(root: PathBuf) -> Result<Self, String> {
+        let metadata = fs::symlink_metadata(&root)
+            .map_err(|e| format!("Failed to delete '{}': {}", root.display(), e))?;
+        Ok(Self {
+            is_dir: metadata.is_dir(),
+            root,
+        })
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// Every path the delete would remove, parents before their children.
+    pub(crate) fn preview(&self) -> Result<Value, String> {
+        let mut paths = Vec::new();
+        let mut count = 0;
+        for entry in WalkDir::new(&self.root).follow_links(false) {
+            let entry =
+                entry.map_err(|e| format!("Failed to delete '{}': {}", self.root.display(), e))?;
+            if paths.len() < MAX_PREVIEW_PATHS {
+                paths.push(entry.path().display().to_string());
+            }
+            count += 1;
+        }
+        Ok(json!({
+            "ok": true,
+            "dryRun": true,
+            "path": self.root.display().to_string(),
+            "paths": paths,
+            "count": count,
+            "truncated": count > MAX_PREVIEW_PATHS
+        }))
+    }
+
+    pub(crate) fn apply(&self) -> Result<(), String> {
+        let result = if self.is_dir {
+            fs::remove_dir_all(&self.root)
+        } else {
+            fs::remove_file(&self.root)
+        };
+        result.map_err(|e| format!("Failed to delete '{}': {}", self.root.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_dry_run, unified_diff, DeletePlan, ReplacePlan};
+    use serde_json::json;
+    use std::path::Path;
+
+    #[test]
+    fn unified_diff_marks_changed_lines() {
+        let diff = unified_diff(Path::new("notes.txt"), "one\ntwo\n", "one\n2\n");
+
+        assert!(diff.starts_with("--- a/notes.txt\n+++ b/notes.txt\n"));
+        assert!(diff.contains("-two\n"));
+        assert!(diff.contains("+2\n"));
+        assert!(unified_diff(Path::new("notes.txt"), "same\n", "same\n").is_empty());
+    }
+
+    #[test]
+    fn dry_run_flag_defaults_off() {
+        assert!(is_dry_run(&json!({ "path": "a", "dryRun": true })));
+        assert!(!is_dry_run(&json!({ "path": "a" })));
+        assert!(!is_dry_run(&json!({ "dryRun": "yes" })));
+    }
+
+    #[test]
+    fn replace_plan_previews_then_writes_the_same_content() {
+        let root = std::env::temp_dir().join(format!("gsv-preview-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("notes.txt"), "one\n").unwrap();
+
+        let plan = ReplacePlan::new(root.join("notes.txt"), "two\n".to_string()).unwrap();
+        let preview = plan.preview();
+        assert_eq!(preview["exists"], true);
+        assert!(preview["diff"].as_str().unwrap().contains("-one\n+two\n"));
+        assert_eq!(
+            std::fs::read_to_string(root.join("notes.txt")).unwrap(),
+            "one\n"
+        );
+
+        plan.apply().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(root.join("notes.txt")).unwrap(),
+            "two\n"
+        );
+        assert!(ReplacePlan::new(root.clone(), String::new())
+            .err()
+            .unwrap()
+            .contains("is a directory"));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn delete_plan_lists_tree_and_removes_it() {
+        let root = std::env::temp_dir().join(format!("gsv-preview-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("a/b")).unwrap();
+        std::fs::write(root.join("a/b/file.txt"), "x").unwrap();
+        std::fs::write(root.join("top.txt"), "y").unwrap();
+
+        let plan = DeletePlan::new(root.clone()).unwrap();
+        let preview = plan.preview().unwrap();
+        assert_eq!(preview["count"], 5);
+        assert_eq!(preview["paths"][0], root.display().to_string());
+        assert!(root.exists());
+
+        plan.apply().unwrap();
+        assert!(!root.exists());
+    }
+}
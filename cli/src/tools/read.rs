@@ -1,4 +1,6 @@
 use crate::protocol::ToolDefinition;
+use crate::tools::digest::{file_digest, same_digest};
+use crate::tools::paths::{Access, PathResolver};
 use crate::tools::{Tool, ToolBody, ToolOutput};
 use async_trait::async_trait;
 use serde::Deserialize;
@@ -7,34 +9,90 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use tokio::io::{AsyncReadExt, AsyncSeekExt};
 
+mod document;
+mod thumbnail;
+
+use document::{DocumentKind, Selection};
+use thumbnail::{ImageOptions, ImageOutput};
+
 const MIME_SNIFF_BYTES: u64 = 8192;
 
 pub struct ReadTool {
-    workspace: PathBuf,
+    paths: PathResolver,
 }
 
 impl ReadTool {
     pub fn new(workspace: PathBuf) -> Self {
-        Self { workspace }
+        Self::with_paths(PathResolver::new(workspace))
     }
 
-    fn resolve_path(&self, path: &str) -> PathBuf {
-        let path = PathBuf::from(path);
-        if path.is_absolute() {
-            path
-        } else {
-            self.workspace.join(path)
-        }
+    pub fn with_paths(paths: PathResolver) -> Self {
+        Self { paths }
     }
 }
 
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(untagged)]
+enum ReadFormat {
+    Text(TextFormat),
+    Image(ImageOutput),
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum TextFormat {
+    Text,
+}
+
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct ReadArgs {
     path: String,
     #[serde(default)]
     offset: Option<usize>,
     #[serde(default)]
     limit: Option<usize>,
+    #[serde(default)]
+    format: Option<ReadFormat>,
+    #[serde(default)]
+    pages: Option<String>,
+    #[serde(default)]
+    sheets: Option<Vec<String>>,
+    #[serde(default)]
+    range: Option<String>,
+    #[serde(default)]
+    cells: Option<String>,
+    #[serde(default)]
+    max_dimension: Option<u32>,
+    #[serde(default)]
+    max_bytes: Option<u64>,
+    #[serde(default)]
+    quality: Option<u8>,
+    #[serde(default)]
+    digest: bool,
+    #[serde(default)]
+    if_none_match: Option<String>,
+}
+
+impl ReadArgs {
+    /// Whether the result is the file as is. The digest only describes such
+    /// reads, so `ifNoneMatch` is ignored for any other.
+    fn whole_file(&self) -> bool {
+        self.offset.is_none()
+            && self.limit.is_none()
+            && self.format.is_none()
+            && self.max_dimension.is_none()
+            && self.max_bytes.is_none()
+            && self.quality.is_none()
+    }
+}
+
+fn select_lines(content: &str, offset: Option<usize>, limit: Option<usize>) -> Vec<&str> {
+    content
+        .split('\n')
+        .skip(offset.unwrap_or(0))
+        .take(limit.unwrap_or(usize::MAX))
+        .collect()
 }
 
 fn format_byte_size(bytes: u64) -> String {
@@ -84,7 +142,7 @@ impl Tool for ReadTool {
     fn definition(&self) -> ToolDefinition {
         ToolDefinition {
             name: "Read".to_string(),
-            description: "Read file contents. Paths are relative to the workspace unless absolute."
+            description: "Read file contents. Paths are relative to the workspace unless absolute; @name/... paths address named mounts."
                 .to_string(),
             input_schema: json!({
                 "type": "object",
@@ -100,6 +158,48 @@ impl Tool for ReadTool {
                     "limit": {
                         "type": "number",
                         "description": "Maximum number of lines to read (optional)"
+                    },
+                    "format": {
+                        "type": "string",
+                        "enum": ["text", "png", "jpeg", "webp"],
+                        "description": "text: extract the text of a PDF, Word, Excel, PowerPoint or Jupyter notebook file; png, jpeg or webp: re-encode an image (optional)"
+                    },
+                    "pages": {
+                        "type": "string",
+                        "description": "With format text: PDF pages or slides to extract, 1-based, like \"1-3,7\" (optional)"
+                    },
+                    "sheets": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "With format text: spreadsheet sheets to extract by name (optional)"
+                    },
+                    "range": {
+                        "type": "string",
+                        "description": "With format text: spreadsheet cells to extract, like \"A1:D20\" (optional)"
+                    },
+                    "cells": {
+                        "type": "string",
+                        "description": "With format text: notebook cells to extract, 1-based, like \"1-3,7\" (optional)"
+                    },
+                    "maxDimension": {
+                        "type": "number",
+                        "description": "Scale an image down so neither side exceeds this many pixels (optional)"
+                    },
+                    "maxBytes": {
+                        "type": "number",
+                        "description": "Shrink an image until its encoding fits in this many bytes (optional)"
+                    },
+                    "quality": {
+                        "type": "number",
+                        "description": "JPEG quality, 1-100, when re-encoding an image (default 85, optional)"
+                    },
+                    "digest": {
+                        "type": "boolean",
+                        "description": "Include the file's BLAKE3 digest in the result (optional)"
+                    },
+                    "ifNoneMatch": {
+                        "type": "string",
+                        "description": "A BLAKE3 digest from an earlier read; if the file still has it and no offset, limit, format or image option is set, only metadata is returned (optional)"
                     }
                 },
                 "required": ["path"]
@@ -111,7 +211,16 @@ impl Tool for ReadTool {
         let args: ReadArgs =
             serde_json::from_value(args).map_err(|e| format!("Invalid arguments: {}", e))?;
 
-        let resolved = self.resolve_path(&args.path);
+        let resolved = self.paths.resolve(&args.path, Access::Read)?;
+        let selection = Selection {
+            pages: args.pages.clone(),
+            sheets: args.sheets.clone(),
+            range: args.range.clone(),
+            cells: args.cells.clone(),
+        };
+        if args.format != Some(ReadFormat::Text(TextFormat::Text)) && !selection.is_empty() {
+            return Err("pages, sheets, range and cells require format: \"text\"".to_string());
+        }
         let metadata = tokio::fs::metadata(&resolved)
             .await
             .map_err(|e| format!("Failed to read '{}': {}", resolved.display(), e))?;
@@ -120,71 +229,228 @@ impl Tool for ReadTool {
             return read_directory(&resolved);
         }
 
-        let size = metadata.len();
-        let mut file = tokio::fs::File::open(&resolved)
-            .await
-            .map_err(|e| format!("Failed to read '{}': {}", resolved.display(), e))?;
-        let mut header = Vec::new();
-        (&mut file)
-            .take(MIME_SNIFF_BYTES)
-            .read_to_end(&mut header)
-            .await
-            .map_err(|e| format!("Failed to read '{}': {}", resolved.display(), e))?;
-        file.rewind()
-            .await
-            .map_err(|e| format!("Failed to read '{}': {}", resolved.display(), e))?;
-        let content_type = infer::get(&header)
-            .map(|kind| kind.mime_type())
-            .unwrap_or_else(|| infer_content_type(&resolved));
-
-        if content_type.starts_with("image/") && !is_text_content_type(content_type) {
-            return Ok(ToolOutput::with_body(
-                json!({
+        let digest = if args.digest || args.if_none_match.is_some() {
+            Some(file_digest(&resolved).await?)
+        } else {
+            None
+        };
+        if let (Some(digest), Some(known)) = (&digest, &args.if_none_match) {
+            if args.whole_file() && same_digest(known, digest) {
+                return Ok(ToolOutput::json(json!({
                     "ok": true,
                     "path": resolved.display().to_string(),
-                    "size": size,
-                    "kind": "image",
-                    "contentType": content_type,
-                }),
-                ToolBody::reader(file, Some(size), None, resolved.display().to_string()),
+                    "size": metadata.len(),
+                    "digest": digest,
+                    "unchanged": true,
+                })));
+            }
+        }
+
+        let mut output = read_file(&resolved, metadata.len(), &args, selection).await?;
+        if let Some(digest) = digest {
+            output.data["digest"] = json!(digest);
+        }
+        Ok(output)
+    }
+}
+
+async fn read_file(
+    resolved: &Path,
+    size: u64,
+    args: &ReadArgs,
+    selection: Selection,
+) -> Result<ToolOutput, String> {
+    let mut file = tokio::fs::File::open(resolved)
+        .await
+        .map_err(|e| format!("Failed to read '{}': {}", resolved.display(), e))?;
+    let mut header = Vec::new();
+    (&mut file)
+        .take(MIME_SNIFF_BYTES)
+        .read_to_end(&mut header)
+        .await
+        .map_err(|e| format!("Failed to read '{}': {}", resolved.display(), e))?;
+    file.rewind()
+        .await
+        .map_err(|e| format!("Failed to read '{}': {}", resolved.display(), e))?;
+    let content_type = infer::get(&header)
+        .map(|kind| kind.mime_type())
+        .unwrap_or_else(|| infer_content_type(resolved));
+    let document = DocumentKind::detect(resolved, content_type);
+
+    if args.format == Some(ReadFormat::Text(TextFormat::Text)) {
+        if let Some(kind) = document {
+            return read_document(resolved, file, size, content_type, kind, selection, args).await;
+        }
+        if !selection.is_empty() {
+            return Err(format!(
+                "pages, sheets, range and cells apply to documents, not {}",
+                content_type
             ));
         }
+    }
 
-        let binary_error = || {
-            format!(
-                "Binary file ({}, {}) - not a text file",
-                content_type,
-                format_byte_size(size)
-            )
-        };
-        if !is_text_content_type(content_type) {
-            return Err(binary_error());
+    let image_options = ImageOptions {
+        max_dimension: args.max_dimension,
+        max_bytes: args.max_bytes,
+        format: match args.format {
+            Some(ReadFormat::Image(format)) => Some(format),
+            _ => None,
+        },
+        quality: args.quality,
+    };
+    let is_image = content_type.starts_with("image/") && !is_text_content_type(content_type);
+    if !image_options.is_empty() {
+        if !is_image {
+            return Err(format!(
+                "maxDimension, maxBytes, quality and image formats apply to images, not {}",
+                content_type
+            ));
         }
-        let mut bytes = Vec::new();
-        file.read_to_end(&mut bytes)
-            .await
-            .map_err(|e| format!("Failed to read '{}': {}", resolved.display(), e))?;
-        let content = String::from_utf8(bytes).map_err(|_error| binary_error())?;
-        let offset = args.offset.unwrap_or(0);
-        let selected = content
-            .split('\n')
-            .skip(offset)
-            .take(args.limit.unwrap_or(usize::MAX))
-            .collect::<Vec<_>>();
-        let body = selected.join("\n").into_bytes();
-
-        Ok(ToolOutput::with_body(
+        return read_image(resolved, file, size, content_type, image_options).await;
+    }
+
+    if is_image {
+        return Ok(ToolOutput::with_body(
             json!({
                 "ok": true,
                 "path": resolved.display().to_string(),
                 "size": size,
-                "kind": "text",
+                "kind": "image",
                 "contentType": content_type,
-                "lines": selected.len(),
             }),
-            ToolBody::bytes(body, resolved.display().to_string()),
-        ))
+            ToolBody::reader(file, Some(size), None, resolved.display().to_string()),
+        ));
+    }
+
+    let binary_error = || {
+        let hint = if document.is_some() {
+            "; read it with format: \"text\" to extract its text"
+        } else {
+            ""
+        };
+        format!(
+            "Binary file ({}, {}) - not a text file{}",
+            content_type,
+            format_byte_size(size),
+            hint
+        )
+    };
+    if !is_text_content_type(content_type) {
+        return Err(binary_error());
+    }
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)
+        .await
+        .map_err(|e| format!("Failed to read '{}': {}", resolved.display(), e))?;
+    let content = String::from_utf8(bytes).map_err(|_error| binary_error())?;
+    let selected = select_lines(&content, args.offset, args.limit);
+    let body = selected.join("\n").into_bytes();
+
+    Ok(ToolOutput::with_body(
+        json!({
+            "ok": true,
+            "path": resolved.display().to_string(),
+            "size": size,
+            "kind": "text",
+            "contentType": content_type,
+            "lines": selected.len(),
+        }),
+        ToolBody::bytes(body, resolved.display().to_string()),
+    ))
+}
+
+async fn read_image(
+    path: &Path,
+    mut file: tokio::fs::File,
+    size: u64,
+    content_type: &str,
+    options: ImageOptions,
+) -> Result<ToolOutput, String> {
+    if size > thumbnail::MAX_SOURCE_IMAGE_BYTES {
+        return Err(format!(
+            "Image too large to convert ({}; the limit is {})",
+            format_byte_size(size),
+            format_byte_size(thumbnail::MAX_SOURCE_IMAGE_BYTES)
+        ));
+    }
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)
+        .await
+        .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+    let converted = tokio::task::spawn_blocking(move || thumbnail::convert(bytes, &options))
+        .await
+        .map_err(|e| format!("Failed to convert '{}': {}", path.display(), e))?
+        .map_err(|e| format!("Failed to convert '{}': {}", path.display(), e))?;
+
+    let mut data = json!({
+        "ok": true,
+        "path": path.display().to_string(),
+        "size": size,
+        "kind": "image",
+        "contentType": converted.content_type,
+        "width": converted.width,
+        "height": converted.height,
+        "originalContentType": content_type,
+        "originalWidth": converted.original_width,
+        "originalHeight": converted.original_height,
+        "transformed": converted.transformed,
+    });
+    if let Some(exif) = converted.exif {
+        data["exif"] = json!(exif);
     }
+    Ok(ToolOutput::with_body(
+        data,
+        ToolBody::bytes(converted.bytes, path.display().to_string()),
+    ))
+}
+
+async fn read_document(
+    path: &Path,
+    mut file: tokio::fs::File,
+    size: u64,
+    content_type: &str,
+    kind: DocumentKind,
+    selection: Selection,
+    args: &ReadArgs,
+) -> Result<ToolOutput, String> {
+    if size > document::MAX_DOCUMENT_BYTES {
+        return Err(format!(
+            "Document too large to extract ({}; the limit is {})",
+            format_byte_size(size),
+            format_byte_size(document::MAX_DOCUMENT_BYTES)
+        ));
+    }
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)
+        .await
+        .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+    // The parsers are synchronous, and a malformed file may make one panic.
+    let extracted =
+        tokio::task::spawn_blocking(move || document::extract(kind, &bytes, &selection))
+            .await
+            .map_err(|e| format!("Failed to extract text from '{}': {}", path.display(), e))?
+            .map_err(|e| format!("Failed to extract text from '{}': {}", path.display(), e))?;
+
+    let selected = select_lines(&extracted.text, args.offset, args.limit);
+    let body = selected.join("\n").into_bytes();
+    let mut data = json!({
+        "ok": true,
+        "path": path.display().to_string(),
+        "size": size,
+        "kind": "document",
+        "contentType": content_type,
+        "document": kind.as_str(),
+        "metadata": extracted.metadata,
+        "lines": selected.len(),
+    });
+    if let Some((unit, total, parts)) = extracted.parts {
+        data[unit] = json!(total);
+        data["selected"] = json!(parts);
+    }
+    Ok(ToolOutput::with_body(
+        data,
+        ToolBody::bytes(body, path.display().to_string()),
+    ))
 }
 
 fn infer_content_type(path: &Path) -> &'static str {
@@ -222,6 +488,12 @@ fn infer_content_type(path: &Path) -> &'static str {
         Some("mp4") => "video/mp4",
         Some("mov") => "video/quicktime",
         Some("pdf") => "application/pdf",
+        Some("docx") => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        Some("xlsx") => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        Some("pptx") => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        Some("xls") => "application/vnd.ms-excel",
+        Some("ods") => "application/vnd.oasis.opendocument.spreadsheet",
+        Some("ipynb") => "application/x-ipynb+json",
         _ => "text/plain",
     }
 }
@@ -296,4 +568,230 @@ mod tests {
 
         fs::remove_dir_all(root).unwrap();
     }
+
+    #[tokio::test]
+    async fn resizes_images_on_request() {
+        let root = std::env::temp_dir().join(format!("gsv-read-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&root).unwrap();
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::new_rgb8(300, 120)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        fs::write(root.join("shot.png"), png.into_inner()).unwrap();
+        fs::write(root.join("notes.txt"), "hello").unwrap();
+        let tool = ReadTool::new(root.clone());
+
+        let result = tool
+            .execute(json!({ "path": "shot.png", "maxDimension": 60, "format": "jpg" }))
+            .await
+            .unwrap();
+        assert_eq!(result.data["contentType"], "image/jpeg");
+        assert_eq!(result.data["originalContentType"], "image/png");
+        assert_eq!(
+            (result.data["width"].clone(), result.data["height"].clone()),
+            (json!(60), json!(24))
+        );
+        let mut body = result.body.unwrap();
+        let mut actual = Vec::new();
+        body.reader.read_to_end(&mut actual).await.unwrap();
+        assert_eq!(
+            image::guess_format(&actual).unwrap(),
+            image::ImageFormat::Jpeg
+        );
+
+        let error = tool
+            .execute(json!({ "path": "notes.txt", "maxDimension": 60 }))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(
+            error,
+            "maxDimension, maxBytes, quality and image formats apply to images, not text/plain"
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn skips_unchanged_files_by_digest() {
+        let root = std::env::temp_dir().join(format!("gsv-read-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("notes.txt"), "one\ntwo").unwrap();
+        let tool = ReadTool::new(root.clone());
+
+        let first = tool
+            .execute(json!({ "path": "notes.txt", "digest": true }))
+            .await
+            .unwrap();
+        let digest = first.data["digest"].as_str().unwrap().to_string();
+        assert_eq!(digest, blake3::hash(b"one\ntwo").to_hex().to_string());
+        assert!(first.body.is_some());
+
+        let again = tool
+            .execute(json!({ "path": "notes.txt", "ifNoneMatch": digest }))
+            .await
+            .unwrap();
+        assert_eq!(again.data["unchanged"], true);
+        assert!(again.body.is_none());
+
+        fs::write(root.join("notes.txt"), "one\ntwo\nthree").unwrap();
+        let changed = tool
+            .execute(json!({ "path": "notes.txt", "ifNoneMatch": digest }))
+            .await
+            .unwrap();
+        assert!(changed.data.get("unchanged").is_none());
+        assert_ne!(changed.data["digest"], json!(digest));
+        assert_eq!(changed.data["lines"], 3);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn returns_the_body_of_other_ranges_despite_a_matching_digest() {
+        let root = std::env::temp_dir().join(format!("gsv-read-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("notes.txt"), "one\ntwo\nthree").unwrap();
+        let tool = ReadTool::new(root.clone());
+
+        let first = tool
+            .execute(json!({ "path": "notes.txt", "digest": true, "offset": 0, "limit": 1 }))
+            .await
+            .unwrap();
+        let digest = first.data["digest"].as_str().unwrap().to_string();
+
+        let next = tool
+            .execute(json!({
+                "path": "notes.txt",
+                "offset": 1,
+                "limit": 1,
+                "ifNoneMatch": digest,
+            }))
+            .await
+            .unwrap();
+        assert!(next.data.get("unchanged").is_none());
+        assert_eq!(next.data["digest"], json!(digest));
+        let mut body = next.body.unwrap();
+        let mut actual = String::new();
+        body.reader.read_to_string(&mut actual).await.unwrap();
+        assert_eq!(actual, "two");
+
+        let same = tool
+            .execute(json!({
+                "path": "notes.txt",
+                "offset": 0,
+                "limit": 1,
+                "ifNoneMatch": digest,
+            }))
+            .await
+            .unwrap();
+        assert!(same.data.get("unchanged").is_none());
+        assert!(same.body.is_some());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    fn pdf(pages: &[&str]) -> Vec<u8> {
+        use lopdf::content::{Content, Operation};
+        use lopdf::{dictionary, Document, Object, Stream};
+
+        let mut document = Document::with_version("1.5");
+        let pages_id = document.new_object_id();
+        let font_id = document.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+        });
+        let resources_id = document.add_object(dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+        });
+        let kids: Vec<Object> = pages
+            .iter()
+            .map(|text| {
+                let content = Content {
+                    operations: vec![
+                        Operation::new("BT", vec![]),
+                        Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                        Operation::new("Td", vec![72.into(), 720.into()]),
+                        Operation::new("Tj", vec![Object::string_literal(*text)]),
+                        Operation::new("ET", vec![]),
+                    ],
+                };
+                let content_id =
+                    document.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+                document
+                    .add_object(dictionary! {
+                        "Type" => "Page",
+                        "Parent" => pages_id,
+                        "Contents" => content_id,
+                    })
+                    .into()
+            })
+            .collect();
+        document.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Count" => kids.len() as i64,
+                "Kids" => kids,
+                "Resources" => resources_id,
+                "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+            }),
+        );
+        let catalog_id = document.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        let info_id = document.add_object(dictionary! {
+            "Title" => Object::string_literal("Quarterly report"),
+        });
+        document.trailer.set("Root", catalog_id);
+        document.trailer.set("Info", info_id);
+        let mut bytes = Vec::new();
+        document.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[tokio::test]
+    async fn extracts_document_text_with_page_selection() {
+        let root = std::env::temp_dir().join(format!("gsv-read-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("report.pdf"), pdf(&["First page", "Second page"])).unwrap();
+        let tool = ReadTool::new(root.clone());
+
+        let error = tool
+            .execute(json!({ "path": "report.pdf" }))
+            .await
+            .err()
+            .unwrap();
+        assert!(error.ends_with("to extract its text"), "{}", error);
+
+        let result = tool
+            .execute(json!({ "path": "report.pdf", "format": "text", "pages": "2" }))
+            .await
+            .unwrap();
+        assert_eq!(result.data["kind"], "document");
+        assert_eq!(result.data["document"], "pdf");
+        assert_eq!(result.data["pages"], 2);
+        assert_eq!(result.data["selected"], json!([2]));
+        assert_eq!(result.data["metadata"]["title"], "Quarterly report");
+        let mut body = result.body.unwrap();
+        let mut actual = String::new();
+        body.reader.read_to_string(&mut actual).await.unwrap();
+        assert!(actual.starts_with("--- Page 2 ---\n"), "{}", actual);
+        assert!(actual.contains("Second page"), "{}", actual);
+        assert!(!actual.contains("First page"), "{}", actual);
+
+        let error = tool
+            .execute(json!({ "path": "report.pdf", "format": "text", "cells": "1" }))
+            .await
+            .err()
+            .unwrap();
+        assert!(
+            error.ends_with("cells does not apply to a pdf document"),
+            "{}",
+            error
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
 }
@@ -0,0 +1,810 @@
+//! Text extraction for `Read` with `format: "text"`.
+//!
+//! PDFs, Word documents, spreadsheets, PowerPoint decks and Jupyter notebooks
+//! are parsed on the device with pure-Rust readers, so only the extracted text
+//! leaves it. Each page, slide, sheet or cell starts with a `--- ... ---`
+//! marker line so that `offset` and `limit` still address lines.
+
+use calamine::{open_workbook_auto_from_rs, Data, Reader};
+use quick_xml::events::{BytesRef, Event};
+use serde_json::{json, Map, Value};
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+/// Documents are parsed in memory, so larger ones are refused.
+pub(super) const MAX_DOCUMENT_BYTES: u64 = 128 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum DocumentKind {
+    Pdf,
+    Word,
+    Spreadsheet,
+    Presentation,
+    Notebook,
+}
+
+impl DocumentKind {
+    pub(super) fn detect(path: &Path, content_type: &str) -> Option<Self> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("pdf") => Some(Self::Pdf),
+            Some("docx" | "docm") => Some(Self::Word),
+            Some("xlsx" | "xlsm" | "xlsb" | "xls" | "ods") => Some(Self::Spreadsheet),
+            Some("pptx" | "pptm") => Some(Self::Presentation),
+            Some("ipynb") => Some(Self::Notebook),
+            _ => match content_type {
+                "application/pdf" => Some(Self::Pdf),
+                "application/vnd.openxmlformats-officedocument.wordprocessingml.document" => {
+                    Some(Self::Word)
+                }
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+                | "application/vnd.ms-excel"
+                | "application/vnd.oasis.opendocument.spreadsheet" => Some(Self::Spreadsheet),
+                "application/vnd.openxmlformats-officedocument.presentationml.presentation" => {
+                    Some(Self::Presentation)
+                }
+                "application/x-ipynb+json" => Some(Self::Notebook),
+                _ => None,
+            },
+        }
+    }
+
+    pub(super) fn as_str(self) -> &'static str {
+        match self {
+            Self::Pdf => "pdf",
+            Self::Word => "word",
+            Self::Spreadsheet => "spreadsheet",
+            Self::Presentation => "presentation",
+            Self::Notebook => "notebook",
+        }
+    }
+}
+
+/// Which parts of a document to extract; everything when unset.
+#[derive(Debug, Default)]
+pub(super) struct Selection {
+    /// PDF pages or presentation slides, 1-based: `"1-3,7,10-"`.
+    pub(super) pages: Option<String>,
+    /// Spreadsheet sheets by name.
+    pub(super) sheets: Option<Vec<String>>,
+    /// Spreadsheet cells in A1 notation: `"A1:D20"`.
+    pub(super) range: Option<String>,
+    /// Notebook cells, 1-based, like `pages`.
+    pub(super) cells: Option<String>,
+}
+
+impl Selection {
+    pub(super) fn is_empty(&self) -> bool {
+        self.pages.is_none()
+            && self.sheets.is_none()
+            && self.range.is_none()
+            && self.cells.is_none()
+    }
+
+    fn check(&self, kind: DocumentKind) -> Result<(), String> {
+        let given = [
+            (
+                "pages",
+                self.pages.is_some(),
+                &[DocumentKind::Pdf, DocumentKind::Presentation][..],
+            ),
+            (
+                "sheets",
+                self.sheets.is_some(),
+                &[DocumentKind::Spreadsheet][..],
+            ),
+            (
+                "range",
+                self.range.is_some(),
+                &[DocumentKind::Spreadsheet][..],
+            ),
+            ("cells", self.cells.is_some(), &[DocumentKind::Notebook][..]),
+        ];
+        match given
+            .iter()
+            .find(|(_, set, kinds)| *set && !kinds.contains(&kind))
+        {
+            Some((name, _, _)) => Err(format!(
+                "{} does not apply to a {} document",
+                name,
+                kind.as_str()
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+pub(super) struct Extracted {
+    pub(super) text: String,
+    pub(super) metadata: Map<String, Value>,
+    /// What the document is divided into (`"pages"`, `"sheets"`, ...), how
+    /// many it has, and which were extracted.
+    pub(super) parts: Option<(&'static str, usize, Vec<Value>)>,
+}
+
+pub(super) fn extract(
+    kind: DocumentKind,
+    bytes: &[u8],
+    selection: &Selection,
+) -> Result<Extracted, String> {
+    selection.check(kind)?;
+    match kind {
+        DocumentKind::Pdf => extract_pdf(bytes, selection),
+        DocumentKind::Word => extract_word(bytes),
+        DocumentKind::Spreadsheet => extract_spreadsheet(bytes, selection),
+        DocumentKind::Presentation => extract_presentation(bytes, selection),
+        DocumentKind::Notebook => extract_notebook(bytes, selection),
+    }
+}
+
+/// 1-based numbers from a selection like `"1-3,7,10-"`, in the order given.
+fn select(spec: Option<&str>, total: usize, unit: &str) -> Result<Vec<usize>, String> {
+    let Some(spec) = spec else {
+        return Ok((1..=total).collect());
+    };
+    let invalid = |part: &str| {
+        format!(
+            "Invalid {} selection '{}': the document has {} {}",
+            unit, part, total, unit
+        )
+    };
+    let mut selected = Vec::new();
+    for part in spec
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+    {
+        let number = |text: &str| text.trim().parse::<usize>().map_err(|_error| invalid(part));
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) if end.trim().is_empty() => (number(start)?, total),
+            Some((start, end)) => (number(start)?, number(end)?),
+            None => (number(part)?, number(part)?),
+        };
+        if start == 0 || start > end || end > total {
+            return Err(invalid(part));
+        }
+        for index in start..=end {
+            if !selected.contains(&index) {
+                selected.push(index);
+            }
+        }
+    }
+    if selected.is_empty() {
+        return Err(invalid(spec));
+    }
+    Ok(selected)
+}
+
+fn push_section(text: &mut String, title: &str, content: &str) {
+    text.push_str("--- ");
+    text.push_str(title);
+    text.push_str(" ---\n");
+    let content = content.trim_matches('\n');
+    if !content.is_empty() {
+        text.push_str(content);
+        text.push('\n');
+    }
+}
+
+/// A PDF text string: UTF-16 with a byte order mark, or PDFDocEncoding,
+/// which matches Latin-1 for the characters that matter here.
+fn pdf_string(object: &lopdf::Object) -> Option<String> {
+    let lopdf::Object::String(bytes, _) = object else {
+        return None;
+    };
+    Some(match bytes.strip_prefix(&[0xfe, 0xff]) {
+        Some(utf16) => String::from_utf16_lossy(
+            &utf16
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect::<Vec<_>>(),
+        ),
+        None => bytes.iter().copied().map(char::from).collect(),
+    })
+}
+
+fn extract_pdf(bytes: &[u8], selection: &Selection) -> Result<Extracted, String> {
+    let mut document =
+        lopdf::Document::load_mem(bytes).map_err(|e| format!("Invalid PDF: {}", e))?;
+    let encrypted = document.is_encrypted();
+    if encrypted {
+        document
+            .decrypt("")
+            .map_err(|_error| "PDF is encrypted with a password".to_string())?;
+    }
+    let pages = document.get_pages();
+    let selected = select(selection.pages.as_deref(), pages.len(), "pages")?;
+
+    let mut text = String::new();
+    for &page in &selected {
+        let number = u32::try_from(page).map_err(|e| e.to_string())?;
+        let mut page_text = String::new();
+        pdf_extract::output_doc_page(
+            &document,
+            &mut pdf_extract::PlainTextOutput::new(&mut page_text),
+            number,
+        )
+        .map_err(|e| format!("Failed to extract page {}: {}", page, e))?;
+        push_section(&mut text, &format!("Page {}", page), &page_text);
+    }
+
+    let mut metadata = Map::new();
+    metadata.insert("version".to_string(), json!(document.version));
+    metadata.insert("encrypted".to_string(), json!(encrypted));
+    let info = document
+        .trailer
+        .get(b"Info")
+        .ok()
+        .and_then(|info| document.dereference(info).ok())
+        .and_then(|(_, info)| info.as_dict().ok());
+    if let Some(info) = info {
+        for (key, name) in [
+            (&b"Title"[..], "title"),
+            (b"Author", "author"),
+            (b"Subject", "subject"),
+            (b"Keywords", "keywords"),
+            (b"Creator", "creator"),
+            (b"Producer", "producer"),
+            (b"CreationDate", "created"),
+            (b"ModDate", "modified"),
+        ] {
+            if let Some(value) = info.get(key).ok().and_then(pdf_string) {
+                metadata.insert(name.to_string(), json!(value));
+            }
+        }
+    }
+    Ok(Extracted {
+        text,
+        metadata,
+        parts: Some((
+            "pages",
+            pages.len(),
+            selected.into_iter().map(Value::from).collect(),
+        )),
+    })
+}
+
+type Package<'a> = zip::ZipArchive<Cursor<&'a [u8]>>;
+
+fn open_package(bytes: &[u8]) -> Result<Package<'_>, String> {
+    zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| format!("Invalid document: {}", e))
+}
+
+/// A part of an Office Open XML package, if present.
+fn package_part(package: &mut Package<'_>, name: &str) -> Result<Option<String>, String> {
+    let part = match package.by_name(name) {
+        Ok(part) => part,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(format!("Failed to read {}: {}", name, e)),
+    };
+    if part.size() > MAX_DOCUMENT_BYTES {
+        return Err(format!("{} is too large", name));
+    }
+    let mut xml = String::new();
+    part.take(MAX_DOCUMENT_BYTES)
+        .read_to_string(&mut xml)
+        .map_err(|e| format!("Failed to read {}: {}", name, e))?;
+    Ok(Some(xml))
+}
+
+fn xml_error(error: impl std::fmt::Display) -> String {
+    format!("Invalid XML: {}", error)
+}
+
+fn resolve_reference(reference: &BytesRef<'_>) -> Result<String, String> {
+    if let Some(character) = reference.resolve_char_ref().map_err(xml_error)? {
+        return Ok(character.to_string());
+    }
+    let name = reference.decode().map_err(xml_error)?;
+    Ok(quick_xml::escape::resolve_predefined_entity(&name)
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("&{};", name)))
+}
+
+/// The text of a WordprocessingML or DrawingML part: `t` runs, with `p`
+/// paragraphs on their own lines and table rows as tab-separated cells.
+fn markup_text(xml: &str) -> Result<String, String> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut text = String::new();
+    let mut in_run = false;
+    let mut in_tab_stops = false;
+    let mut cell_depth = 0usize;
+    loop {
+        match reader.read_event().map_err(xml_error)? {
+            Event::Start(element) => match element.local_name().as_ref() {
+                b"t" => in_run = true,
+                b"tabs" => in_tab_stops = true,
+                b"tc" => cell_depth += 1,
+                _ => {}
+            },
+            Event::Empty(element) => match element.local_name().as_ref() {
+                b"tab" if !in_tab_stops => text.push('\t'),
+                b"br" | b"cr" => text.push('\n'),
+                _ => {}
+            },
+            Event::Text(content) if in_run => text.push_str(&content.decode().map_err(xml_error)?),
+            Event::GeneralRef(reference) if in_run => {
+                text.push_str(&resolve_reference(&reference)?)
+            }
+            Event::End(element) => match element.local_name().as_ref() {
+                b"t" => in_run = false,
+                b"tabs" => in_tab_stops = false,
+                b"p" if cell_depth > 0 => text.push(' '),
+                b"p" => text.push('\n'),
+                b"tc" => {
+                    cell_depth = cell_depth.saturating_sub(1);
+                    text.truncate(text.trim_end_matches(' ').len());
+                    text.push('\t');
+                }
+                b"tr" => {
+                    text.truncate(text.trim_end_matches('\t').len());
+                    text.push('\n');
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(text)
+}
+
+/// Title, author and dates from `docProps/core.xml`.
+fn core_properties(package: &mut Package<'_>) -> Result<Map<String, Value>, String> {
+    let mut metadata = Map::new();
+    let Some(xml) = package_part(package, "docProps/core.xml")? else {
+        return Ok(metadata);
+    };
+    let mut reader = quick_xml::Reader::from_str(&xml);
+    let mut current: Option<&'static str> = None;
+    let mut value = String::new();
+    loop {
+        match reader.read_event().map_err(xml_error)? {
+            Event::Start(element) => {
+                current = match element.local_name().as_ref() {
+                    b"title" => Some("title"),
+                    b"subject" => Some("subject"),
+                    b"creator" => Some("author"),
+                    b"keywords" => Some("keywords"),
+                    b"description" => Some("description"),
+                    b"lastModifiedBy" => Some("lastModifiedBy"),
+                    b"created" => Some("created"),
+                    b"modified" => Some("modified"),
+                    _ => None,
+                };
+                value.clear();
+            }
+            Event::Text(content) if current.is_some() => {
+                value.push_str(&content.decode().map_err(xml_error)?)
+            }
+            Event::GeneralRef(reference) if current.is_some() => {
+                value.push_str(&resolve_reference(&reference)?)
+            }
+            Event::End(_) => {
+                if let Some(name) = current.take() {
+                    if !value.trim().is_empty() {
+                        metadata.insert(name.to_string(), json!(value.trim()));
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(metadata)
+}
+
+fn extract_word(bytes: &[u8]) -> Result<Extracted, String> {
+    let mut package = open_package(bytes)?;
+    let body = package_part(&mut package, "word/document.xml")?
+        .ok_or_else(|| "Not a Word document: word/document.xml is missing".to_string())?;
+    Ok(Extracted {
+        text: markup_text(&body)?,
+        metadata: core_properties(&mut package)?,
+        parts: None,
+    })
+}
+
+fn extract_presentation(bytes: &[u8], selection: &Selection) -> Result<Extracted, String> {
+    let mut package = open_package(bytes)?;
+    let mut slides: Vec<(usize, String)> = package
+        .file_names()
+        .filter_map(|name| {
+            let number = name
+                .strip_prefix("ppt/slides/slide")?
+                .strip_suffix(".xml")?
+                .parse()
+                .ok()?;
+            Some((number, name.to_string()))
+        })
+        .collect();
+    slides.sort();
+    let selected = select(selection.pages.as_deref(), slides.len(), "slides")?;
+
+    let mut text = String::new();
+    for &index in &selected {
+        let Some((_, name)) = slides.get(index - 1) else {
+            continue;
+        };
+        let xml = package_part(&mut package, name)?.unwrap_or_default();
+        push_section(&mut text, &format!("Slide {}", index), &markup_text(&xml)?);
+    }
+    Ok(Extracted {
+        text,
+        metadata: core_properties(&mut package)?,
+        parts: Some((
+            "slides",
+            slides.len(),
+            selected.into_iter().map(Value::from).collect(),
+        )),
+    })
+}
+
+/// A 0-based (row, column), as calamine addresses cells.
+type CellPosition = (u32, u32);
+
+/// A cell reference like `B12` as a position.
+fn cell_position(reference: &str) -> Option<CellPosition> {
+    let split = reference.find(|c: char| c.is_ascii_digit())?;
+    let (letters, digits) = reference.split_at(split);
+    if letters.is_empty() {
+        return None;
+    }
+    let mut column = 0u32;
+    for letter in letters.chars() {
+        if !letter.is_ascii_alphabetic() {
+            return None;
+        }
+        let value = u32::from(letter.to_ascii_uppercase()) - u32::from('A') + 1;
+        column = column.checked_mul(26)?.checked_add(value)?;
+    }
+    let row: u32 = digits.parse().ok()?;
+    Some((row.checked_sub(1)?, column - 1))
+}
+
+fn cell_range(range: &str) -> Result<(CellPosition, CellPosition), String> {
+    let invalid = || {
+        format!(
+            "Invalid range '{}': expected A1 notation like A1:D20",
+            range
+        )
+    };
+    let (start, end) = range.split_once(':').unwrap_or((range, range));
+    let start = cell_position(start.trim()).ok_or_else(invalid)?;
+    let end = cell_position(end.trim()).ok_or_else(invalid)?;
+    if start.0 > end.0 || start.1 > end.1 {
+        return Err(invalid());
+    }
+    Ok((start, end))
+}
+
+fn cell_text(cell: &Data) -> String {
+    cell.to_string().replace(['\t', '\n', '\r'], " ")
+}
+
+fn extract_spreadsheet(bytes: &[u8], selection: &Selection) -> Result<Extracted, String> {
+    let mut workbook = open_workbook_auto_from_rs(Cursor::new(bytes))
+        .map_err(|e| format!("Invalid spreadsheet: {}", e))?;
+    let names = workbook.sheet_names();
+    let selected = match &selection.sheets {
+        Some(sheets) => {
+            if let Some(missing) = sheets.iter().find(|sheet| !names.contains(sheet)) {
+                return Err(format!(
+                    "No sheet named '{}'; sheets: {}",
+                    missing,
+                    names.join(", ")
+                ));
+            }
+            sheets.clone()
+        }
+        None => names.clone(),
+    };
+    let bounds = selection.range.as_deref().map(cell_range).transpose()?;
+
+    let mut text = String::new();
+    for name in &selected {
+        let sheet = workbook
+            .worksheet_range(name)
+            .map_err(|e| format!("Failed to read sheet '{}': {}", name, e))?;
+        // Only ever allocate within the sheet's used area.
+        let sheet = match (bounds, sheet.end()) {
+            (Some((start, end)), Some(last)) => {
+                let end = (end.0.min(last.0), end.1.min(last.1));
+                if start.0 > end.0 || start.1 > end.1 {
+                    calamine::Range::empty()
+                } else {
+                    sheet.range(start, end)
+                }
+            }
+            _ => sheet,
+        };
+        let rows = sheet
+            .rows()
+            .map(|row| {
+                let cells: Vec<String> = row.iter().map(cell_text).collect();
+                cells.join("\t").trim_end_matches('\t').to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        push_section(&mut text, &format!("Sheet: {}", name), &rows);
+    }
+
+    let mut metadata = Map::new();
+    metadata.insert("sheetNames".to_string(), json!(names));
+    Ok(Extracted {
+        text,
+        metadata,
+        parts: Some((
+            "sheets",
+            names.len(),
+            selected.into_iter().map(Value::from).collect(),
+        )),
+    })
+}
+
+/// Notebook text fields are a string or a list of lines.
+fn multiline(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+fn notebook_output(output: &Value) -> String {
+    match output["output_type"].as_str() {
+        Some("stream") => multiline(&output["text"]),
+        Some("error") => format!(
+            "{}: {}",
+            output["ename"].as_str().unwrap_or("Error"),
+            output["evalue"].as_str().unwrap_or_default()
+        ),
+        _ => {
+            let data = &output["data"];
+            if let Some(text) = data.get("text/plain") {
+                multiline(text)
+            } else {
+                data.as_object()
+                    .and_then(|data| data.keys().next())
+                    .map(|mime| format!("[{} output]", mime))
+                    .unwrap_or_default()
+            }
+        }
+    }
+}
+
+fn extract_notebook(bytes: &[u8], selection: &Selection) -> Result<Extracted, String> {
+    let notebook: Value =
+        serde_json::from_slice(bytes).map_err(|e| format!("Invalid notebook: {}", e))?;
+    let cells = notebook["cells"]
+        .as_array()
+        .ok_or_else(|| "Invalid notebook: no cells".to_string())?;
+    let selected = select(selection.cells.as_deref(), cells.len(), "cells")?;
+
+    let mut text = String::new();
+    for &index in &selected {
+        let Some(cell) = cells.get(index - 1) else {
+            continue;
+        };
+        let cell_type = cell["cell_type"].as_str().unwrap_or("code");
+        push_section(
+            &mut text,
+            &format!("Cell {} ({})", index, cell_type),
+            &multiline(&cell["source"]),
+        );
+        let outputs: Vec<String> = cell["outputs"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|output| notebook_output(output).trim_end_matches('\n').to_string())
+            .filter(|output| !output.is_empty())
+            .collect();
+        if !outputs.is_empty() {
+            push_section(&mut text, "Output", &outputs.join("\n"));
+        }
+    }
+
+    let mut metadata = Map::new();
+    let info = &notebook["metadata"];
+    for (name, value) in [
+        ("nbformat", &notebook["nbformat"]),
+        ("kernel", &info["kernelspec"]["display_name"]),
+        ("language", &info["language_info"]["name"]),
+    ] {
+        if !value.is_null() {
+            metadata.insert(name.to_string(), value.clone());
+        }
+    }
+    Ok(Extracted {
+        text,
+        metadata,
+        parts: Some((
+            "cells",
+            cells.len(),
+            selected.into_iter().map(Value::from).collect(),
+        )),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cell_range, extract, markup_text, select, DocumentKind, Selection};
+    use serde_json::json;
+    use std::io::Write;
+
+    fn package(parts: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, content) in parts {
+            writer
+                .start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn selections_parse_ranges_in_order() {
+        assert_eq!(select(None, 3, "pages").unwrap(), [1, 2, 3]);
+        assert_eq!(
+            select(Some("4, 1-2, 2, 5-"), 6, "pages").unwrap(),
+            [4, 1, 2, 5, 6]
+        );
+        let error = select(Some("2-9"), 3, "pages").unwrap_err();
+        assert_eq!(
+            error,
+            "Invalid pages selection '2-9': the document has 3 pages"
+        );
+        assert_eq!(cell_range("b2:AA10").unwrap(), ((1, 1), (9, 26)));
+        assert_eq!(cell_range("C3").unwrap(), ((2, 2), (2, 2)));
+        cell_range("A0:B2").unwrap_err();
+    }
+
+    #[test]
+    fn word_markup_keeps_paragraphs_and_tables() {
+        let xml = r#"<w:document xmlns:w="w"><w:body>
+            <w:p><w:pPr><w:tabs><w:tab w:val="left"/></w:tabs></w:pPr><w:r><w:t>Fish &amp; chips</w:t></w:r><w:r><w:tab/><w:t xml:space="preserve">x&#233;</w:t></w:r></w:p>
+            <w:tbl><w:tr><w:tc><w:p><w:r><w:t>a</w:t></w:r></w:p></w:tc><w:tc><w:p><w:r><w:t>b</w:t></w:r></w:p><w:p><w:r><w:t>c</w:t></w:r></w:p></w:tc></w:tr></w:tbl>
+            <w:p><w:r><w:delText>gone</w:delText><w:t>end</w:t><w:br/></w:r></w:p>
+        </w:body></w:document>"#;
+        assert_eq!(
+            markup_text(xml).unwrap(),
+            "Fish & chips\txé\na\tb c\nend\n\n"
+        );
+    }
+
+    #[test]
+    fn office_documents_extract_text_and_properties() {
+        let core = r#"<cp:coreProperties xmlns:cp="cp" xmlns:dc="dc"><dc:title>Plan</dc:title><dc:creator>Sam</dc:creator></cp:coreProperties>"#;
+        let word = package(&[
+            ("docProps/core.xml", core),
+            (
+                "word/document.xml",
+                r#"<w:document xmlns:w="w"><w:body><w:p><w:r><w:t>Hello</w:t></w:r></w:p></w:body></w:document>"#,
+            ),
+        ]);
+        let extracted = extract(DocumentKind::Word, &word, &Selection::default()).unwrap();
+        assert_eq!(extracted.text, "Hello\n");
+        assert_eq!(
+            json!(extracted.metadata),
+            json!({ "title": "Plan", "author": "Sam" })
+        );
+
+        let slide = |text: &str| {
+            format!(
+                r#"<p:sld xmlns:p="p" xmlns:a="a"><p:cSld><p:spTree><p:sp><p:txBody><a:p><a:r><a:t>{}</a:t></a:r></a:p></p:txBody></p:sp></p:spTree></p:cSld></p:sld>"#,
+                text
+            )
+        };
+        let (first, second, tenth) = (slide("One"), slide("Two"), slide("Ten"));
+        let deck = package(&[
+            ("ppt/slides/slide10.xml", &tenth),
+            ("ppt/slides/slide2.xml", &second),
+            ("ppt/slides/slide1.xml", &first),
+        ]);
+        let selection = Selection {
+            pages: Some("2-".to_string()),
+            ..Selection::default()
+        };
+        let extracted = extract(DocumentKind::Presentation, &deck, &selection).unwrap();
+        assert_eq!(
+            extracted.text,
+            "--- Slide 2 ---\nTwo\n--- Slide 3 ---\nTen\n"
+        );
+        assert_eq!(extracted.parts.unwrap().1, 3);
+
+        let selection = Selection {
+            sheets: Some(vec!["Sheet1".to_string()]),
+            ..Selection::default()
+        };
+        let error = extract(DocumentKind::Word, &word, &selection)
+            .err()
+            .unwrap();
+        assert_eq!(error, "sheets does not apply to a word document");
+    }
+
+    #[test]
+    fn spreadsheets_extract_selected_sheets_and_ranges() {
+        let workbook = package(&[
+            (
+                "[Content_Types].xml",
+                r#"<?xml version="1.0" encoding="UTF-8"?><Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/></Types>"#,
+            ),
+            (
+                "_rels/.rels",
+                r#"<?xml version="1.0" encoding="UTF-8"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#,
+            ),
+            (
+                "xl/workbook.xml",
+                r#"<?xml version="1.0" encoding="UTF-8"?><workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="Budget" sheetId="1" r:id="rId1"/></sheets></workbook>"#,
+            ),
+            (
+                "xl/_rels/workbook.xml.rels",
+                r#"<?xml version="1.0" encoding="UTF-8"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/></Relationships>"#,
+            ),
+            (
+                "xl/worksheets/sheet1.xml",
+                r#"<?xml version="1.0" encoding="UTF-8"?><worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData><row r="1"><c r="A1" t="inlineStr"><is><t>Item</t></is></c><c r="B1" t="inlineStr"><is><t>Cost</t></is></c></row><row r="2"><c r="A2" t="inlineStr"><is><t>Rent</t></is></c><c r="B2"><v>1200.5</v></c></row></sheetData></worksheet>"#,
+            ),
+        ]);
+        let extracted =
+            extract(DocumentKind::Spreadsheet, &workbook, &Selection::default()).unwrap();
+        assert_eq!(
+            extracted.text,
+            "--- Sheet: Budget ---\nItem\tCost\nRent\t1200.5\n"
+        );
+
+        let selection = Selection {
+            range: Some("B2:Z999".to_string()),
+            ..Selection::default()
+        };
+        let extracted = extract(DocumentKind::Spreadsheet, &workbook, &selection).unwrap();
+        assert_eq!(extracted.text, "--- Sheet: Budget ---\n1200.5\n");
+
+        let selection = Selection {
+            sheets: Some(vec!["Costs".to_string()]),
+            ..Selection::default()
+        };
+        let error = extract(DocumentKind::Spreadsheet, &workbook, &selection)
+            .err()
+            .unwrap();
+        assert_eq!(error, "No sheet named 'Costs'; sheets: Budget");
+    }
+
+    #[test]
+    fn notebooks_extract_sources_and_outputs() {
+        let notebook = json!({
+            "nbformat": 4,
+            "metadata": { "kernelspec": { "display_name": "Python 3" }, "language_info": { "name": "python" } },
+            "cells": [
+                { "cell_type": "markdown", "source": ["# Title\n", "Intro"] },
+                {
+                    "cell_type": "code",
+                    "source": "print(1)\n1 + 1",
+                    "outputs": [
+                        { "output_type": "stream", "name": "stdout", "text": ["1\n"] },
+                        { "output_type": "execute_result", "data": { "text/plain": "2" } },
+                        { "output_type": "display_data", "data": { "image/png": "..." } },
+                    ],
+                },
+            ],
+        });
+        let bytes = serde_json::to_vec(&notebook).unwrap();
+        let selection = Selection {
+            cells: Some("2".to_string()),
+            ..Selection::default()
+        };
+        let extracted = extract(DocumentKind::Notebook, &bytes, &selection).unwrap();
+        assert_eq!(
+            extracted.text,
+            "--- Cell 2 (code) ---\nprint(1)\n1 + 1\n--- Output ---\n1\n2\n[image/png output]\n"
+        );
+        assert_eq!(
+            json!(extracted.metadata),
+            json!({ "nbformat": 4, "kernel": "Python 3", "language": "python" })
+        );
+    }
+}
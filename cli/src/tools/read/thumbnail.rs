@@ -0,0 +1,332 @@
+//! Image downscaling for `Read` with `maxDimension`, `maxBytes` or an image
+//! `format`.
+//!
+//! The image is decoded, turned upright from its EXIF orientation, scaled to
+//! fit and re-encoded on the device, so only the smaller copy crosses the
+//! device link. Re-encoded images carry no EXIF; the fields are returned in
+//! `data` instead.
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::io::Cursor;
+
+/// Images are decoded in memory, so larger files are refused.
+pub(super) const MAX_SOURCE_IMAGE_BYTES: u64 = 256 * 1024 * 1024;
+const DEFAULT_JPEG_QUALITY: u8 = 85;
+/// `maxBytes` lowers JPEG quality down to this before shrinking the image.
+const MIN_JPEG_QUALITY: u8 = 40;
+/// Each attempt to meet `maxBytes` by shrinking scales by 3/4.
+const SHRINK_NUMERATOR: u32 = 3;
+const SHRINK_DENOMINATOR: u32 = 4;
+/// `maxBytes` gives up rather than shrink an image below this.
+const MIN_DIMENSION: u32 = 16;
+/// Longer EXIF values are mostly binary blobs and are left out.
+const MAX_EXIF_VALUE_CHARS: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum ImageOutput {
+    Png,
+    #[serde(alias = "jpg")]
+    Jpeg,
+    Webp,
+}
+
+impl ImageOutput {
+    fn of(format: ImageFormat) -> Option<Self> {
+        match format {
+            ImageFormat::Png => Some(Self::Png),
+            ImageFormat::Jpeg => Some(Self::Jpeg),
+            ImageFormat::WebP => Some(Self::Webp),
+            _ => None,
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+            Self::Webp => "image/webp",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct ImageOptions {
+    /// Longest side, in pixels.
+    pub(super) max_dimension: Option<u32>,
+    pub(super) max_bytes: Option<u64>,
+    pub(super) format: Option<ImageOutput>,
+    /// JPEG quality, 1-100.
+    pub(super) quality: Option<u8>,
+}
+
+impl ImageOptions {
+    pub(super) fn is_empty(&self) -> bool {
+        self.max_dimension.is_none()
+            && self.max_bytes.is_none()
+            && self.format.is_none()
+            && self.quality.is_none()
+    }
+}
+
+pub(super) struct Converted {
+    pub(super) bytes: Vec<u8>,
+    pub(super) content_type: &'static str,
+    pub(super) width: u32,
+    pub(super) height: u32,
+    pub(super) original_width: u32,
+    pub(super) original_height: u32,
+    /// Whether `bytes` is a new encoding rather than the original file.
+    pub(super) transformed: bool,
+    pub(super) exif: Option<Map<String, Value>>,
+}
+
+/// EXIF fields by tag name, as display strings with units.
+fn exif_fields(raw: &[u8]) -> Option<Map<String, Value>> {
+    let exif = exif::Reader::new().read_raw(raw.to_vec()).ok()?;
+    let mut fields = Map::new();
+    for field in exif.fields() {
+        if field.ifd_num != exif::In::PRIMARY || field.tag == exif::Tag::MakerNote {
+            continue;
+        }
+        let value = field.display_value().with_unit(&exif).to_string();
+        if value.len() <= MAX_EXIF_VALUE_CHARS {
+            fields.insert(field.tag.to_string(), json!(value.trim_matches('"')));
+        }
+    }
+    (!fields.is_empty()).then_some(fields)
+}
+
+fn encode(image: &DynamicImage, format: ImageOutput, quality: u8) -> Result<Vec<u8>, String> {
+    let mut out = Cursor::new(Vec::new());
+    match format {
+        ImageOutput::Png => image.write_to(&mut out, ImageFormat::Png),
+        ImageOutput::Jpeg => {
+            JpegEncoder::new_with_quality(&mut out, quality).encode_image(&image.to_rgb8())
+        }
+        ImageOutput::Webp => {
+            DynamicImage::ImageRgba8(image.to_rgba8()).write_to(&mut out, ImageFormat::WebP)
+        }
+    }
+    .map_err(|e| format!("Failed to encode image: {}", e))?;
+    Ok(out.into_inner())
+}
+
+pub(super) fn convert(bytes: Vec<u8>, options: &ImageOptions) -> Result<Converted, String> {
+    let reader = ImageReader::new(Cursor::new(&bytes))
+        .with_guessed_format()
+        .map_err(|e| format!("Failed to read image: {}", e))?;
+    let source = reader
+        .format()
+        .ok_or_else(|| "Unrecognized image format".to_string())?;
+    let mut decoder = reader
+        .into_decoder()
+        .map_err(|e| format!("Failed to decode image: {}", e))?;
+    let raw_exif = decoder
+        .exif_metadata()
+        .map_err(|e| format!("Failed to decode image: {}", e))?
+        .map(|raw| match raw.strip_prefix(b"Exif\0\0") {
+            Some(tiff) => tiff.to_vec(),
+            None => raw,
+        });
+    let orientation = raw_exif
+        .as_deref()
+        .and_then(Orientation::from_exif_chunk)
+        .unwrap_or(Orientation::NoTransforms);
+    let exif = raw_exif.as_deref().and_then(exif_fields);
+
+    let mut image = DynamicImage::from_decoder(decoder)
+        .map_err(|e| format!("Failed to decode image: {}", e))?;
+    image.apply_orientation(orientation);
+    let (original_width, original_height) = (image.width(), image.height());
+    if let Some(max) = options.max_dimension.map(|max| max.max(1)) {
+        if image.width() > max || image.height() > max {
+            image = image.resize(max, max, FilterType::CatmullRom);
+        }
+    }
+
+    // Send the file as it is when nothing about it would change.
+    let fits = |length: usize| {
+        options
+            .max_bytes
+            .is_none_or(|max| u64::try_from(length).is_ok_and(|length| length <= max))
+    };
+    if orientation == Orientation::NoTransforms
+        && (image.width(), image.height()) == (original_width, original_height)
+        && options
+            .format
+            .is_none_or(|format| ImageOutput::of(source) == Some(format))
+        && fits(bytes.len())
+    {
+        return Ok(Converted {
+            bytes,
+            content_type: source.to_mime_type(),
+            width: original_width,
+            height: original_height,
+            original_width,
+            original_height,
+            transformed: false,
+            exif,
+        });
+    }
+
+    let format = options
+        .format
+        .or_else(|| ImageOutput::of(source))
+        .unwrap_or(ImageOutput::Png);
+    let mut quality = options
+        .quality
+        .unwrap_or(DEFAULT_JPEG_QUALITY)
+        .clamp(1, 100);
+    let encoded = loop {
+        let encoded = encode(&image, format, quality)?;
+        if fits(encoded.len()) {
+            break encoded;
+        }
+        if format == ImageOutput::Jpeg && quality > MIN_JPEG_QUALITY {
+            quality = quality.saturating_sub(15).max(MIN_JPEG_QUALITY);
+            continue;
+        }
+        let width = image.width() * SHRINK_NUMERATOR / SHRINK_DENOMINATOR;
+        let height = image.height() * SHRINK_NUMERATOR / SHRINK_DENOMINATOR;
+        if width.max(height) < MIN_DIMENSION {
+            return Err(format!(
+                "Cannot fit the image in {} bytes",
+                options.max_bytes.unwrap_or_default()
+            ));
+        }
+        image = image.resize(width, height, FilterType::CatmullRom);
+    };
+    Ok(Converted {
+        bytes: encoded,
+        content_type: format.content_type(),
+        width: image.width(),
+        height: image.height(),
+        original_width,
+        original_height,
+        transformed: true,
+        exif,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{convert, ImageOptions, ImageOutput};
+    use image::{DynamicImage, ImageFormat, RgbImage};
+    use std::io::Cursor;
+
+    fn gradient(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            image::Rgb([
+                (x * 7 % 256) as u8,
+                (y * 13 % 256) as u8,
+                ((x ^ y) % 256) as u8,
+            ])
+        }))
+    }
+
+    fn encoded(image: &DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut out = Cursor::new(Vec::new());
+        image.write_to(&mut out, format).unwrap();
+        out.into_inner()
+    }
+
+    /// A JPEG with an APP1 segment holding `Make` and `Orientation`.
+    fn jpeg_with_exif(image: &DynamicImage, orientation: u16) -> Vec<u8> {
+        let mut tiff = b"II*\0\x08\0\0\0\x02\0".to_vec();
+        tiff.extend_from_slice(&[0x0f, 0x01, 2, 0, 4, 0, 0, 0]);
+        tiff.extend_from_slice(b"Cam\0");
+        tiff.extend_from_slice(&[0x12, 0x01, 3, 0, 1, 0, 0, 0]);
+        tiff.extend_from_slice(&orientation.to_le_bytes());
+        tiff.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        let mut segment = b"Exif\0\0".to_vec();
+        segment.extend_from_slice(&tiff);
+
+        let jpeg = encoded(image, ImageFormat::Jpeg);
+        let mut out = jpeg[..2].to_vec();
+        out.extend_from_slice(&[0xff, 0xe1]);
+        out.extend_from_slice(&u16::try_from(segment.len() + 2).unwrap().to_be_bytes());
+        out.extend_from_slice(&segment);
+        out.extend_from_slice(&jpeg[2..]);
+        out
+    }
+
+    fn dimensions(bytes: &[u8]) -> (u32, u32) {
+        let image = image::load_from_memory(bytes).unwrap();
+        (image.width(), image.height())
+    }
+
+    #[test]
+    fn downscales_and_reencodes() {
+        let png = encoded(&gradient(400, 200), ImageFormat::Png);
+        let options = ImageOptions {
+            max_dimension: Some(100),
+            format: Some(ImageOutput::Webp),
+            ..ImageOptions::default()
+        };
+        let converted = convert(png.clone(), &options).unwrap();
+        assert!(converted.transformed);
+        assert_eq!(converted.content_type, "image/webp");
+        assert_eq!((converted.width, converted.height), (100, 50));
+        assert_eq!(
+            (converted.original_width, converted.original_height),
+            (400, 200)
+        );
+        assert_eq!(dimensions(&converted.bytes), (100, 50));
+
+        // Already small enough and in the requested format: sent as is.
+        let options = ImageOptions {
+            max_dimension: Some(1000),
+            format: Some(ImageOutput::Png),
+            ..ImageOptions::default()
+        };
+        let converted = convert(png.clone(), &options).unwrap();
+        assert!(!converted.transformed);
+        assert_eq!(converted.bytes, png);
+    }
+
+    #[test]
+    fn shrinks_to_fit_max_bytes() {
+        let png = encoded(&gradient(300, 300), ImageFormat::Png);
+        let options = ImageOptions {
+            max_bytes: Some(20_000),
+            format: Some(ImageOutput::Jpeg),
+            ..ImageOptions::default()
+        };
+        let converted = convert(png.clone(), &options).unwrap();
+        assert!(converted.bytes.len() <= 20_000);
+        assert_eq!(converted.content_type, "image/jpeg");
+
+        let options = ImageOptions {
+            max_bytes: Some(10),
+            ..ImageOptions::default()
+        };
+        let error = convert(png, &options).err().unwrap();
+        assert_eq!(error, "Cannot fit the image in 10 bytes");
+    }
+
+    #[test]
+    fn applies_exif_orientation_and_reports_fields() {
+        // Orientation 6: stored landscape, displayed rotated 90° clockwise.
+        let jpeg = jpeg_with_exif(&gradient(64, 32), 6);
+        let converted = convert(
+            jpeg,
+            &ImageOptions {
+                max_dimension: Some(1000),
+                ..ImageOptions::default()
+            },
+        )
+        .unwrap();
+        assert!(converted.transformed);
+        assert_eq!((converted.width, converted.height), (32, 64));
+        assert_eq!(dimensions(&converted.bytes), (32, 64));
+        let exif = converted.exif.unwrap();
+        assert_eq!(exif["Make"], "Cam");
+        assert_eq!(exif["Orientation"], "row 0 at right and column 0 at top");
+    }
+}
@@ -1,4 +1,5 @@
 use crate::protocol::ToolDefinition;
+use crate::tools::paths::{Access, PathResolver};
 use crate::tools::{Tool, ToolOutput};
 use async_trait::async_trait;
 use serde::Deserialize;
@@ -10,21 +11,16 @@ use tokio_util::sync::CancellationToken;
 use walkdir::WalkDir;
 
 pub struct SearchTool {
-    workspace: PathBuf,
+    paths: PathResolver,
 }
 
 impl SearchTool {
     pub fn new(workspace: PathBuf) -> Self {
-        Self { workspace }
+        Self::with_paths(PathResolver::new(workspace))
     }
 
-    fn resolve_path(&self, path: &str) -> PathBuf {
-        let path = PathBuf::from(path);
-        if path.is_absolute() {
-            path
-        } else {
-            self.workspace.join(path)
-        }
+    pub fn with_paths(paths: PathResolver) -> Self {
+        Self { paths }
     }
 
     async fn search(
@@ -32,9 +28,9 @@ impl SearchTool {
         args: Value,
         cancellation: &CancellationToken,
     ) -> Result<ToolOutput, String> {
-        let workspace = self.workspace.clone();
+        let paths = self.paths.clone();
         let cancellation = cancellation.clone();
-        tokio::task::spawn_blocking(move || Self { workspace }.search_blocking(args, &cancellation))
+        tokio::task::spawn_blocking(move || Self { paths }.search_blocking(args, &cancellation))
             .await
             .map_err(|error| format!("Search task failed: {}", error))?
     }
@@ -57,10 +53,10 @@ impl SearchTool {
             .filter(|value| !value.is_empty())
             .ok_or_else(|| "Search query is required.".to_string())?;
 
-        let base_path = args
-            .path
-            .map(|p| self.resolve_path(&p))
-            .unwrap_or_else(|| self.workspace.clone());
+        let base_path = match args.path {
+            Some(path) => self.paths.resolve(&path, Access::Read)?,
+            None => self.paths.workspace().to_path_buf(),
+        };
 
         let include_glob = args
             .include
@@ -163,7 +159,7 @@ impl Tool for SearchTool {
     fn definition(&self) -> ToolDefinition {
         ToolDefinition {
             name: "Search".to_string(),
-            description: "Search file contents using plain text. Paths are relative to the workspace unless absolute.".to_string(),
+            description: "Search file contents using plain text. Paths are relative to the workspace unless absolute; @name/... paths address named mounts.".to_string(),
             input_schema: json!({
                 "type": "object",
                 "properties": {
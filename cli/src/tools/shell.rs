@@ -1,7 +1,8 @@
 use crate::protocol::{DeviceExecEventParams, ToolDefinition};
+use crate::tools::paths::{Access, PathResolver};
 use crate::tools::{Tool, ToolOutput};
 use async_trait::async_trait;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
@@ -13,6 +14,10 @@ use tokio::process::{ChildStdin, Command};
 use tokio::sync::{broadcast, Mutex as AsyncMutex};
 use uuid::Uuid;
 
+mod spool;
+
+use spool::Spool;
+
 const DEFAULT_TIMEOUT_MS: u64 = 5 * 60 * 1000;
 const DEFAULT_YIELD_MS: u64 = 5_000;
 const MIN_YIELD_MS: u64 = 250;
@@ -20,6 +25,7 @@ const MAX_YIELD_MS: u64 = 30_000;
 const MAX_OUTPUT_CHARS: usize = 200_000;
 const TAIL_CHARS: usize = 4_000;
 const COMPLETED_SESSION_RETENTION_MS: u64 = 10 * 60 * 1000;
+const SPOOL_POLL_INTERVAL: Duration = Duration::from_millis(100);
 
 #[derive(Clone)]
 struct ProcessHandle {
@@ -47,6 +53,8 @@ struct ProcessSnapshot {
 
 struct ProcessState {
     session_id: String,
+    /// Device identity that started the session, when several share a daemon.
+    owner: Option<String>,
     cwd: String,
     pid: Option<u32>,
     started_at: i64,
@@ -63,25 +71,36 @@ struct ProcessState {
     tail: String,
     truncated: bool,
     started_notified: bool,
+    /// Where a persistent session's output and exit status are kept.
+    spool: Option<Spool>,
+    /// Device cron job that started the session.
+    job_id: Option<String>,
 }
 
-static EXEC_EVENT_BUS: OnceLock<broadcast::Sender<DeviceExecEventParams>> = OnceLock::new();
+/// Exec event tagged with the device identity whose session produced it.
+#[derive(Debug, Clone)]
+pub struct ExecEvent {
+    pub owner: Option<String>,
+    pub params: DeviceExecEventParams,
+}
+
+static EXEC_EVENT_BUS: OnceLock<broadcast::Sender<ExecEvent>> = OnceLock::new();
 static PROCESS_REGISTRY: OnceLock<Arc<AsyncMutex<HashMap<String, ProcessHandle>>>> =
     OnceLock::new();
 
-fn exec_event_bus() -> &'static broadcast::Sender<DeviceExecEventParams> {
+fn exec_event_bus() -> &'static broadcast::Sender<ExecEvent> {
     EXEC_EVENT_BUS.get_or_init(|| {
         let (tx, _rx) = broadcast::channel(256);
         tx
     })
 }
 
-pub fn subscribe_exec_events() -> broadcast::Receiver<DeviceExecEventParams> {
+pub fn subscribe_exec_events() -> broadcast::Receiver<ExecEvent> {
     exec_event_bus().subscribe()
 }
 
-fn emit_exec_event(event: DeviceExecEventParams) {
-    let _ = exec_event_bus().send(event);
+fn emit_exec_event(owner: Option<String>, params: DeviceExecEventParams) {
+    let _ = exec_event_bus().send(ExecEvent { owner, params });
 }
 
 fn process_registry() -> &'static Arc<AsyncMutex<HashMap<String, ProcessHandle>>> {
@@ -97,14 +116,112 @@ async fn store_process(handle: ProcessHandle) {
     registry.insert(session_id, handle);
 }
 
-async fn get_process(session_id: &str) -> Option<ProcessHandle> {
-    let registry = process_registry().lock().await;
-    registry.get(session_id).cloned()
+/// Look up a session started by `owner`; other identities' sessions are
+/// reported as unknown.
+async fn get_process(session_id: &str, owner: Option<&str>) -> Option<ProcessHandle> {
+    let handle = {
+        let registry = process_registry().lock().await;
+        registry.get(session_id).cloned()?
+    };
+    let owned = handle.state.lock().await.owner.as_deref() == owner;
+    owned.then_some(handle)
+}
+
+/// Summary of a managed shell session, for local diagnostics.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShellSessionSummary {
+    pub session_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_id: Option<String>,
+    pub pid: Option<u32>,
+    pub cwd: String,
+    pub status: String,
+    pub started_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ended_at: Option<i64>,
+    pub backgrounded: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<String>,
+}
+
+/// Sessions still held in the process registry, oldest first. Completed
+/// sessions stay listed until their retention period runs out.
+pub async fn list_shell_sessions() -> Vec<ShellSessionSummary> {
+    let handles = {
+        let registry = process_registry().lock().await;
+        registry.values().cloned().collect::<Vec<_>>()
+    };
+    let mut sessions = Vec::with_capacity(handles.len());
+    for handle in handles {
+        let state = handle.state.lock().await;
+        sessions.push(ShellSessionSummary {
+            session_id: state.session_id.clone(),
+            device_id: state.owner.clone(),
+            pid: state.pid,
+            cwd: state.cwd.clone(),
+            status: state.status.clone(),
+            started_at: state.started_at,
+            ended_at: state.ended_at,
+            backgrounded: state.backgrounded,
+            job_id: state.job_id.clone(),
+        });
+    }
+    sessions.sort_by_key(|session| session.started_at);
+    sessions
+}
+
+/// Give up on `owner`'s background sessions that are still running, as the
+/// daemon is about to exit without them, and return a `detached` exec event
+/// for each. They send no further events. Persistent sessions are left for
+/// the next daemon to adopt.
+pub async fn detach_shell_sessions(owner: Option<&str>) -> Vec<DeviceExecEventParams> {
+    let handles = {
+        let registry = process_registry().lock().await;
+        registry.values().cloned().collect::<Vec<_>>()
+    };
+    let mut events = Vec::new();
+    for handle in handles {
+        let mut state = handle.state.lock().await;
+        if state.owner.as_deref() != owner
+            || !state.backgrounded
+            || state.ended_at.is_some()
+            || state.spool.is_some()
+        {
+            continue;
+        }
+        state.backgrounded = false;
+        state.status = "detached".to_string();
+        events.push(DeviceExecEventParams {
+            event_id: Uuid::new_v4().to_string(),
+            session_id: state.session_id.clone(),
+            event: "detached".to_string(),
+            call_id: None,
+            exit_code: None,
+            signal: None,
+            output_tail: if state.tail.is_empty() {
+                None
+            } else {
+                Some(state.tail.clone())
+            },
+            started_at: Some(state.started_at),
+            ended_at: None,
+            job_id: state.job_id.clone(),
+        });
+    }
+    events
 }
 
 async fn remove_process(session_id: &str) {
-    let mut registry = process_registry().lock().await;
-    registry.remove(session_id);
+    let handle = {
+        let mut registry = process_registry().lock().await;
+        registry.remove(session_id)
+    };
+    if let Some(handle) = handle {
+        if let Some(spool) = &handle.state.lock().await.spool {
+            spool.remove();
+        }
+    }
 }
 
 fn schedule_process_removal(session_id: String, delay: Duration) {
@@ -187,6 +304,9 @@ fn snapshot_from_state(state: &ProcessState) -> ProcessSnapshot {
 
 fn snapshot_and_drain_from_state(state: &mut ProcessState) -> ProcessSnapshot {
     let output = std::mem::take(&mut state.pending_output);
+    if let Some(spool) = state.spool.as_mut() {
+        spool.mark_drained();
+    }
     ProcessSnapshot {
         session_id: state.session_id.clone(),
         cwd: state.cwd.clone(),
@@ -449,53 +569,215 @@ async fn mark_backgrounded(handle: &ProcessHandle, call_id: Option<String>) -> P
     state.backgrounded = true;
     if !state.started_notified {
         state.started_notified = true;
-        emit_exec_event(DeviceExecEventParams {
-            event_id: Uuid::new_v4().to_string(),
-            session_id: state.session_id.clone(),
-            event: "started".to_string(),
-            call_id,
-            exit_code: None,
-            signal: None,
-            output_tail: if state.tail.is_empty() {
-                None
-            } else {
-                Some(state.tail.clone())
+        emit_exec_event(
+            state.owner.clone(),
+            DeviceExecEventParams {
+                event_id: Uuid::new_v4().to_string(),
+                session_id: state.session_id.clone(),
+                event: "started".to_string(),
+                call_id,
+                exit_code: None,
+                signal: None,
+                output_tail: if state.tail.is_empty() {
+                    None
+                } else {
+                    Some(state.tail.clone())
+                },
+                started_at: Some(state.started_at),
+                ended_at: None,
+                job_id: state.job_id.clone(),
             },
-            started_at: Some(state.started_at),
-            ended_at: None,
-        });
+        );
     }
     snapshot_and_drain_from_state(&mut state)
 }
 
+/// Take a persistent session's new output from its spool files.
+fn read_spool(state: &mut ProcessState) {
+    let Some(spool) = state.spool.as_mut() else {
+        return;
+    };
+    let [stdout, stderr] = spool.read_until([u64::MAX; 2]);
+    append_output(state, &stdout, OutputStream::Stdout);
+    append_output(state, &stderr, OutputStream::Stderr);
+}
+
+/// Follow a persistent session's output until the process ends.
+async fn poll_spool(state: Arc<AsyncMutex<ProcessState>>) {
+    loop {
+        tokio::time::sleep(SPOOL_POLL_INTERVAL).await;
+        let mut lock = state.lock().await;
+        if lock.ended_at.is_some() {
+            return;
+        }
+        read_spool(&mut lock);
+    }
+}
+
+/// Settle the status of a session whose `ended_at`, `exit_code` and
+/// `signal` are set, and return the name of its completion event.
+fn settle_status(state: &mut ProcessState) -> &'static str {
+    state.status = if state.timed_out {
+        "timed_out".to_string()
+    } else if state.exit_code == Some(0) && state.signal.is_none() {
+        "completed".to_string()
+    } else {
+        "failed".to_string()
+    };
+    if state.timed_out {
+        "timed_out"
+    } else if state.status == "completed" {
+        "finished"
+    } else {
+        "failed"
+    }
+}
+
+/// Emit the completion event of an ended session, if it was backgrounded,
+/// and drop it from the registry once the retention period is over.
+async fn finish_session(state: &Arc<AsyncMutex<ProcessState>>) {
+    let (snapshot, should_emit_event, event_name, owner, job_id) = {
+        let mut lock = state.lock().await;
+        read_spool(&mut lock);
+        let event_name = settle_status(&mut lock);
+        if lock.backgrounded {
+            if let Some(spool) = &lock.spool {
+                spool.mark_reported();
+            }
+        }
+        (
+            snapshot_from_state(&lock),
+            lock.backgrounded,
+            event_name.to_string(),
+            lock.owner.clone(),
+            lock.job_id.clone(),
+        )
+    };
+
+    let session_id = snapshot.session_id.clone();
+
+    if should_emit_event {
+        emit_exec_event(
+            owner,
+            DeviceExecEventParams {
+                event_id: Uuid::new_v4().to_string(),
+                session_id: session_id.clone(),
+                event: event_name,
+                call_id: None,
+                exit_code: snapshot.exit_code,
+                signal: snapshot.signal,
+                output_tail: if snapshot.tail.is_empty() {
+                    None
+                } else {
+                    Some(snapshot.tail)
+                },
+                started_at: Some(snapshot.started_at),
+                ended_at: snapshot.ended_at,
+                job_id,
+            },
+        );
+    }
+
+    schedule_process_removal(
+        session_id,
+        Duration::from_millis(COMPLETED_SESSION_RETENTION_MS),
+    );
+}
+
+/// Start `command` in its own process group. With a `spool_root` the
+/// session is persistent: its output goes to files there instead of pipes,
+/// so that it survives the daemon and can be adopted after a restart.
 async fn launch_managed_process(
     command: String,
     cwd: PathBuf,
     timeout_ms: u64,
+    owner: Option<String>,
+    job_id: Option<String>,
+    spool_root: Option<&Path>,
 ) -> Result<(ProcessHandle, ForegroundProcessGuard), String> {
     let shell = resolve_shell_program();
-    let mut cmd = Command::new(&shell.executable);
+    let session_id = Uuid::new_v4().to_string();
+    #[cfg(unix)]
+    let spool = spool_root
+        .map(|root| {
+            Spool::create(root, &session_id)
+                .map_err(|e| format!("Failed to create session spool: {}", e))
+        })
+        .transpose()?;
+    #[cfg(not(unix))]
+    let spool: Option<(Spool, std::fs::File, std::fs::File)> = {
+        let _ = spool_root;
+        None
+    };
+
+    let mut cmd = match &spool {
+        Some((spool, _, _)) => {
+            let mut cmd = Command::new("/bin/sh");
+            cmd.arg("-c")
+                .arg(spool::EXIT_STATUS_WRAPPER)
+                .arg("gsv-shell")
+                .arg(spool.exit_path())
+                .arg(&shell.executable);
+            cmd
+        }
+        None => Command::new(&shell.executable),
+    };
     cmd.args(&shell.launch_args).arg(&command);
     cmd.current_dir(&cwd);
     cmd.stdin(Stdio::piped());
-    cmd.stdout(Stdio::piped());
-    cmd.stderr(Stdio::piped());
+    let spool = match spool {
+        Some((spool, stdout, stderr)) => {
+            cmd.stdout(Stdio::from(stdout));
+            cmd.stderr(Stdio::from(stderr));
+            Some(spool)
+        }
+        None => {
+            cmd.stdout(Stdio::piped());
+            cmd.stderr(Stdio::piped());
+            None
+        }
+    };
     #[cfg(unix)]
     cmd.process_group(0);
 
-    let mut child = cmd
-        .spawn()
-        .map_err(|e| format_shell_spawn_error(&shell.executable, &e))?;
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            if let Some(spool) = &spool {
+                spool.remove();
+            }
+            return Err(format_shell_spawn_error(&shell.executable, &e));
+        }
+    };
 
     let pid = child.id();
     let stdin = child.stdin.take();
     let stdout = child.stdout.take();
     let stderr = child.stderr.take();
-    let session_id = Uuid::new_v4().to_string();
     let started_at = now_ms();
 
+    if let (Some(spool), Some(pid)) = (&spool, pid) {
+        let record = spool::SessionRecord {
+            session_id: session_id.clone(),
+            owner: owner.clone(),
+            pid,
+            cwd: cwd.display().to_string(),
+            started_at,
+            timeout_at: (timeout_ms > 0)
+                .then(|| started_at.saturating_add(i64::try_from(timeout_ms).unwrap_or(i64::MAX))),
+            job_id: job_id.clone(),
+        };
+        if let Err(e) = spool.write_record(&record) {
+            force_terminate_pid(pid);
+            spool.remove();
+            return Err(format!("Failed to record shell session: {}", e));
+        }
+    }
+    let spooled = spool.is_some();
+
     let state = Arc::new(AsyncMutex::new(ProcessState {
         session_id: session_id.clone(),
+        owner,
         cwd: cwd.display().to_string(),
         pid,
         started_at,
@@ -512,6 +794,8 @@ async fn launch_managed_process(
         tail: String::new(),
         truncated: false,
         started_notified: false,
+        spool,
+        job_id,
     }));
     let handle = ProcessHandle {
         state: state.clone(),
@@ -519,6 +803,9 @@ async fn launch_managed_process(
     };
     let foreground = ForegroundProcessGuard::new(pid, session_id);
 
+    if spooled {
+        tokio::spawn(poll_spool(state.clone()));
+    }
     if let Some(stdout) = stdout {
         tokio::spawn(pump_stream(stdout, state.clone(), OutputStream::Stdout));
     }
@@ -547,8 +834,7 @@ async fn launch_managed_process(
 
     tokio::spawn(async move {
         let wait_result = child.wait().await;
-
-        let (snapshot, should_emit_event, event_name) = {
+        {
             let mut lock = state.lock().await;
             lock.ended_at = Some(now_ms());
             match wait_result {
@@ -566,59 +852,193 @@ async fn launch_managed_process(
                     );
                 }
             }
+        }
+        finish_session(&state).await;
+    });
 
-            lock.status = if lock.timed_out {
-                "timed_out".to_string()
-            } else if lock.exit_code == Some(0) && lock.signal.is_none() {
-                "completed".to_string()
-            } else {
-                "failed".to_string()
-            };
+    store_process(handle.clone()).await;
 
-            let snapshot = snapshot_from_state(&lock);
-            let event_name = if lock.timed_out {
-                "timed_out"
-            } else if lock.status == "completed" {
-                "finished"
-            } else {
-                "failed"
-            };
-            (snapshot, lock.backgrounded, event_name.to_string())
-        };
+    Ok((handle, foreground))
+}
 
-        let session_id = snapshot.session_id.clone();
+/// Start a background session. Only these are persistent, as their output
+/// is not needed right away.
+async fn start_background(
+    command: String,
+    cwd: PathBuf,
+    timeout_ms: u64,
+    owner: Option<String>,
+    job_id: Option<String>,
+) -> Result<ProcessSnapshot, String> {
+    let (handle, mut foreground) =
+        launch_managed_process(command, cwd, timeout_ms, owner, job_id, spool::root()).await?;
+    let snapshot = mark_backgrounded(&handle, None).await;
+    foreground.disarm();
+    Ok(snapshot)
+}
 
-        if should_emit_event {
-            emit_exec_event(DeviceExecEventParams {
-                event_id: Uuid::new_v4().to_string(),
-                session_id: session_id.clone(),
-                event: event_name,
-                call_id: None,
-                exit_code: snapshot.exit_code,
-                signal: snapshot.signal,
-                output_tail: if snapshot.tail.is_empty() {
-                    None
-                } else {
-                    Some(snapshot.tail)
-                },
-                started_at: Some(snapshot.started_at),
-                ended_at: snapshot.ended_at,
-            });
+/// Start `command` in the background for `owner`, as `shell.exec` with
+/// `background: true` does, and return the session id. Its exec events
+/// carry `job_id`. A `timeout_ms` of 0 lets it run without a limit.
+pub async fn spawn_background_shell(
+    command: String,
+    cwd: PathBuf,
+    timeout_ms: u64,
+    owner: Option<String>,
+    job_id: Option<String>,
+) -> Result<String, String> {
+    start_background(command, cwd, timeout_ms, owner, job_id)
+        .await
+        .map(|snapshot| snapshot.session_id)
+}
+
+/// Whether the process group a persistent session was started as still
+/// has its leader.
+#[cfg(unix)]
+fn process_group_alive(pid: u32) -> bool {
+    let Ok(pid) = i32::try_from(pid) else {
+        return false;
+    };
+    // SAFETY: getpgid only looks the process up; it has no side effects.
+    unsafe { libc::getpgid(pid) == pid }
+}
+
+/// Keep persistent sessions in this directory, so that they outlive the
+/// daemon. Only the first call has an effect.
+pub fn set_shell_session_dir(dir: PathBuf) {
+    spool::set_root(dir);
+}
+
+/// Take over `owner`'s persistent sessions left in the session directory by
+/// a previous daemon: polls find them again and their completion events
+/// are still sent. Returns how many were adopted.
+pub async fn adopt_shell_sessions(owner: Option<&str>) -> usize {
+    #[cfg(unix)]
+    {
+        match spool::root() {
+            Some(root) => adopt_sessions_from(root, owner).await,
+            None => 0,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = owner;
+        0
+    }
+}
+
+#[cfg(unix)]
+async fn adopt_sessions_from(root: &Path, owner: Option<&str>) -> usize {
+    let mut adopted = 0;
+    for (record, mut spool) in Spool::load_all(root) {
+        if record.owner.as_deref() != owner
+            || process_registry()
+                .lock()
+                .await
+                .contains_key(&record.session_id)
+        {
+            continue;
+        }
+        let mut state = ProcessState {
+            session_id: record.session_id.clone(),
+            owner: record.owner.clone(),
+            cwd: record.cwd.clone(),
+            pid: Some(record.pid),
+            started_at: record.started_at,
+            ended_at: None,
+            status: "running".to_string(),
+            exit_code: None,
+            signal: None,
+            timed_out: false,
+            backgrounded: true,
+            stdout: String::new(),
+            stderr: String::new(),
+            output: String::new(),
+            pending_output: String::new(),
+            tail: String::new(),
+            truncated: false,
+            started_notified: true,
+            spool: None,
+            job_id: record.job_id.clone(),
+        };
+        // Output polls already returned is history; the rest is still due.
+        let [stdout, stderr] = spool.read_until(spool.drained());
+        append_output(&mut state, &stdout, OutputStream::Stdout);
+        append_output(&mut state, &stderr, OutputStream::Stderr);
+        state.pending_output.clear();
+        state.spool = Some(spool);
+        read_spool(&mut state);
+
+        let state = Arc::new(AsyncMutex::new(state));
+        let handle = ProcessHandle {
+            state: state.clone(),
+            stdin: Arc::new(AsyncMutex::new(None)),
+        };
+        store_process(handle.clone()).await;
+        adopted += 1;
+        tokio::spawn(watch_adopted_session(handle, record.pid, record.timeout_at));
+    }
+    adopted
+}
+
+/// Stand in for `wait` on a process this daemon did not start: follow its
+/// output and timeout until the process group leader is gone, then take
+/// the exit status the wrapper shell recorded.
+#[cfg(unix)]
+async fn watch_adopted_session(handle: ProcessHandle, pid: u32, timeout_at: Option<i64>) {
+    loop {
+        if !process_group_alive(pid) {
+            break;
         }
+        let timed_out = {
+            let mut lock = handle.state.lock().await;
+            read_spool(&mut lock);
+            let expired = !lock.timed_out && timeout_at.is_some_and(|at| now_ms() >= at);
+            if expired {
+                lock.timed_out = true;
+            }
+            expired
+        };
+        if timed_out {
+            let handle = handle.clone();
+            tokio::spawn(async move { terminate_process(&handle).await });
+        }
+        tokio::time::sleep(SPOOL_POLL_INTERVAL).await;
+    }
 
+    let reported = {
+        let mut lock = handle.state.lock().await;
+        lock.ended_at = Some(now_ms());
+        let exit_code = lock.spool.as_ref().and_then(Spool::exit_code);
+        if exit_code.is_none() && !lock.timed_out {
+            append_output(
+                &mut lock,
+                "\n[exit status unknown: the session ended while the daemon was down]",
+                OutputStream::Stderr,
+            );
+        }
+        lock.exit_code = exit_code;
+        lock.spool.as_ref().is_some_and(Spool::is_reported)
+    };
+    if reported {
+        let session_id = {
+            let mut lock = handle.state.lock().await;
+            read_spool(&mut lock);
+            settle_status(&mut lock);
+            lock.session_id.clone()
+        };
         schedule_process_removal(
             session_id,
             Duration::from_millis(COMPLETED_SESSION_RETENTION_MS),
         );
-    });
-
-    store_process(handle.clone()).await;
-
-    Ok((handle, foreground))
+    } else {
+        finish_session(&handle.state).await;
+    }
 }
 
 pub struct ShellTool {
-    workspace: PathBuf,
+    paths: PathResolver,
+    owner: Option<String>,
 }
 
 async fn wait_for_shell_result(handle: &ProcessHandle, yield_ms: u64) -> Value {
@@ -652,16 +1072,18 @@ fn normalize_yield_ms(yield_ms: Option<u64>) -> u64 {
 
 impl ShellTool {
     pub fn new(workspace: PathBuf) -> Self {
-        Self { workspace }
+        Self::with_paths(PathResolver::new(workspace))
     }
 
-    fn resolve_path(&self, path: &str) -> PathBuf {
-        let path = PathBuf::from(path);
-        if path.is_absolute() {
-            path
-        } else {
-            self.workspace.join(path)
-        }
+    pub fn with_paths(paths: PathResolver) -> Self {
+        Self { paths, owner: None }
+    }
+
+    /// Tag sessions with a device identity. Sessions and their exec events
+    /// stay private to the tool set of the identity that started them.
+    pub fn owned_by(mut self, device_id: impl Into<String>) -> Self {
+        self.owner = Some(device_id.into());
+        self
     }
 }
 
@@ -719,7 +1141,7 @@ impl Tool for ShellTool {
             .map(str::trim)
             .filter(|id| !id.is_empty())
         {
-            let handle = get_process(session_id)
+            let handle = get_process(session_id, self.owner.as_deref())
                 .await
                 .ok_or_else(|| format!("Unknown shell session: {}", session_id))?;
             let input = args.input.unwrap_or_default();
@@ -747,21 +1169,21 @@ impl Tool for ShellTool {
             return Err("input must not be empty".to_string());
         }
 
-        let cwd = args
-            .cwd
-            .as_deref()
-            .map(|w| self.resolve_path(w))
-            .unwrap_or_else(|| self.workspace.clone());
+        let cwd = match args.cwd.as_deref() {
+            Some(cwd) => self.paths.resolve(cwd, Access::Read)?,
+            None => self.paths.workspace().to_path_buf(),
+        };
 
         let timeout_ms = args.timeout.unwrap_or(DEFAULT_TIMEOUT_MS);
-        let (handle, mut foreground) = launch_managed_process(command, cwd, timeout_ms).await?;
-
         if args.background == Some(true) {
-            let snapshot = mark_backgrounded(&handle, None).await;
-            foreground.disarm();
+            let snapshot =
+                start_background(command, cwd, timeout_ms, self.owner.clone(), None).await?;
             return Ok(ToolOutput::json(running_result(&snapshot)));
         }
 
+        let (handle, mut foreground) =
+            launch_managed_process(command, cwd, timeout_ms, self.owner.clone(), None, None)
+                .await?;
         let result = wait_for_shell_result(&handle, normalize_yield_ms(args.yield_ms)).await;
         foreground.disarm();
         Ok(ToolOutput::json(result))
@@ -831,7 +1253,7 @@ mod tests {
             pid_file.display()
         );
         let (handle, mut foreground) =
-            launch_managed_process(command, std::env::temp_dir(), 30_000)
+            launch_managed_process(command, std::env::temp_dir(), 30_000, None, None, None)
                 .await
                 .unwrap();
         foreground.disarm();
@@ -903,7 +1325,7 @@ mod tests {
             .await
             .unwrap();
         let session_id = started.data["sessionId"].as_str().unwrap().to_string();
-        let handle = get_process(&session_id).await.unwrap();
+        let handle = get_process(&session_id, None).await.unwrap();
         let pid = started.data["pid"].as_u64().unwrap() as u32;
 
         let poll = tokio::spawn(async move {
@@ -923,4 +1345,197 @@ mod tests {
 
         terminate_process(&handle).await;
     }
+
+    #[tokio::test]
+    async fn detaching_reports_running_background_sessions_once() {
+        let owner = format!("detach-{}", Uuid::new_v4());
+        let tool = ShellTool::new(std::env::temp_dir()).owned_by(owner.clone());
+        let started = tool
+            .execute(json!({
+                "input": "echo ready; sleep 30",
+                "background": true,
+                "timeout": 30_000,
+            }))
+            .await
+            .unwrap();
+        let session_id = started.data["sessionId"].as_str().unwrap().to_string();
+        let handle = get_process(&session_id, Some(&owner)).await.unwrap();
+
+        assert!(detach_shell_sessions(Some("someone-else")).await.is_empty());
+        let detached = detach_shell_sessions(Some(&owner)).await;
+        assert_eq!(detached.len(), 1);
+        assert_eq!(detached[0].session_id, session_id);
+        assert_eq!(detached[0].event, "detached");
+        assert_eq!(handle.state.lock().await.status, "detached");
+        assert!(detach_shell_sessions(Some(&owner)).await.is_empty());
+
+        terminate_process(&handle).await;
+    }
+
+    /// Start `command` the way an earlier daemon would have started a
+    /// persistent session under `root`.
+    fn spawn_previous_session(
+        root: &Path,
+        owner: &str,
+        command: &str,
+    ) -> (String, std::process::Child) {
+        use std::os::unix::process::CommandExt;
+        let session_id = Uuid::new_v4().to_string();
+        let (spool, stdout, stderr) = Spool::create(root, &session_id).unwrap();
+        let mut cmd = std::process::Command::new("/bin/sh");
+        cmd.arg("-c")
+            .arg(spool::EXIT_STATUS_WRAPPER)
+            .arg("gsv-shell")
+            .arg(spool.exit_path())
+            .args(["/bin/sh", "-c", command])
+            .stdin(Stdio::null())
+            .stdout(stdout)
+            .stderr(stderr)
+            .process_group(0);
+        let child = cmd.spawn().unwrap();
+        spool
+            .write_record(&spool::SessionRecord {
+                session_id: session_id.clone(),
+                owner: Some(owner.to_string()),
+                pid: child.id(),
+                cwd: "/tmp".to_string(),
+                started_at: now_ms(),
+                timeout_at: None,
+                job_id: None,
+            })
+            .unwrap();
+        (session_id, child)
+    }
+
+    #[tokio::test]
+    async fn sessions_that_ended_while_the_daemon_was_down_are_reported() {
+        let root = std::env::temp_dir().join(format!("gsv-sessions-{}", Uuid::new_v4()));
+        let owner = format!("adopt-{}", Uuid::new_v4());
+        let (session_id, mut child) = spawn_previous_session(
+            &root,
+            &owner,
+            "printf 'hello\\n'; printf 'oops\\n' >&2; exit 3",
+        );
+        child.wait().unwrap();
+
+        let mut events = subscribe_exec_events();
+        assert_eq!(adopt_sessions_from(&root, Some("someone-else")).await, 0);
+        assert_eq!(adopt_sessions_from(&root, Some(&owner)).await, 1);
+        let event = tokio::time::timeout(Duration::from_secs(3), async {
+            loop {
+                let event = events.recv().await.unwrap();
+                if event.params.session_id == session_id {
+                    return event;
+                }
+            }
+        })
+        .await
+        .expect("no completion event for the adopted session");
+        assert_eq!(event.owner.as_deref(), Some(owner.as_str()));
+        assert_eq!(event.params.event, "failed");
+        assert_eq!(event.params.exit_code, Some(3));
+
+        let result = ShellTool::new(std::env::temp_dir())
+            .owned_by(owner.clone())
+            .execute(json!({ "sessionId": session_id, "input": "" }))
+            .await
+            .unwrap();
+        assert_eq!(result.data["exitCode"], 3);
+        assert_eq!(result.data["stdout"], "hello\n");
+        assert_eq!(result.data["stderr"], "oops\n");
+        assert!(!root.join(&session_id).exists());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn running_sessions_are_adopted_and_watched_until_they_exit() {
+        let root = std::env::temp_dir().join(format!("gsv-sessions-{}", Uuid::new_v4()));
+        let owner = format!("adopt-{}", Uuid::new_v4());
+        let (session_id, mut child) =
+            spawn_previous_session(&root, &owner, "echo started; exec sleep 30");
+        wait_for_file(&root.join(&session_id).join("stdout")).await;
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while std::fs::read_to_string(root.join(&session_id).join("stdout"))
+                .unwrap_or_default()
+                .is_empty()
+            {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("session wrote no output");
+
+        assert_eq!(adopt_sessions_from(&root, Some(&owner)).await, 1);
+        assert_eq!(adopt_sessions_from(&root, Some(&owner)).await, 0);
+        let tool = ShellTool::new(std::env::temp_dir()).owned_by(owner.clone());
+        let result = tool
+            .execute(json!({ "sessionId": session_id, "input": "", "yieldMs": 250 }))
+            .await
+            .unwrap();
+        assert_eq!(result.data["status"], "running");
+        assert_eq!(result.data["output"], "started\n");
+        let error = tool
+            .execute(json!({ "sessionId": session_id, "input": "more\n" }))
+            .await
+            .err()
+            .unwrap();
+        assert!(error.contains("stdin is closed"), "{error}");
+
+        let handle = get_process(&session_id, Some(&owner)).await.unwrap();
+        terminate_process(&handle).await;
+        child.wait().unwrap();
+        tokio::time::timeout(Duration::from_secs(3), async {
+            while handle.state.lock().await.ended_at.is_none() {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("adopted session was not seen to exit");
+        assert_eq!(handle.state.lock().await.status, "failed");
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn persistent_sessions_spool_output_and_exit_status() {
+        let root = std::env::temp_dir().join(format!("gsv-sessions-{}", Uuid::new_v4()));
+        let owner = format!("spool-{}", Uuid::new_v4());
+        let mut events = subscribe_exec_events();
+        let (handle, mut foreground) = launch_managed_process(
+            "echo out; echo err >&2; exit 4".to_string(),
+            std::env::temp_dir(),
+            30_000,
+            Some(owner.clone()),
+            None,
+            Some(&root),
+        )
+        .await
+        .unwrap();
+        foreground.disarm();
+        let session_id = mark_backgrounded(&handle, None).await.session_id;
+        assert!(root.join(&session_id).join("session.json").exists());
+        // Not detached at shutdown: the next daemon adopts it instead.
+        assert!(detach_shell_sessions(Some(&owner)).await.is_empty());
+
+        let event = tokio::time::timeout(Duration::from_secs(15), async {
+            loop {
+                let event = events.recv().await.unwrap();
+                if event.params.session_id == session_id && event.params.event != "started" {
+                    return event;
+                }
+            }
+        })
+        .await
+        .expect("no completion event");
+        assert_eq!(event.params.exit_code, Some(4));
+        let state = handle.state.lock().await;
+        // The login shell's profile may print before the command runs.
+        assert!(state.stdout.ends_with("out\n"));
+        assert!(state.stderr.ends_with("err\n"));
+        assert_eq!(state.spool.as_ref().and_then(Spool::exit_code), Some(4));
+        assert!(state.spool.as_ref().is_some_and(Spool::is_reported));
+        drop(state);
+        remove_process(&session_id).await;
+        assert!(!root.join(&session_id).exists());
+        let _ = std::fs::remove_dir_all(&root);
+    }
 }
@@ -0,0 +1,241 @@
+//! On-disk state of background shell sessions, so that a restarted daemon
+//! can adopt them again.
+//!
+//! Each session gets a directory under the configured root holding
+//! `session.json`, the `stdout` and `stderr` files the process writes to
+//! directly, `drained` with how much of them polls have already returned,
+//! and `exit` with the exit status, written by a wrapper shell once the
+//! command ends. `reported` marks a session whose completion event was sent.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+static SESSION_ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+/// Runs the shell given as its arguments, then records its exit status in
+/// the file named by `$1`.
+pub(super) const EXIT_STATUS_WRAPPER: &str = r#"status="$1"; shift; "$@"; code=$?; printf '%s\n' "$code" > "$status.tmp" && mv "$status.tmp" "$status"; exit "$code""#;
+
+/// Output further behind than this is skipped rather than read; sessions
+/// keep far less than this in memory anyway.
+const MAX_READ_BYTES: u64 = 1 << 20;
+const STREAMS: [&str; 2] = ["stdout", "stderr"];
+
+pub(super) fn set_root(dir: PathBuf) {
+    let _ = SESSION_ROOT.set(dir);
+}
+
+pub(super) fn root() -> Option<&'static Path> {
+    SESSION_ROOT.get().map(PathBuf::as_path)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct SessionRecord {
+    pub(super) session_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) owner: Option<String>,
+    /// Process group leader: the wrapper shell.
+    pub(super) pid: u32,
+    pub(super) cwd: String,
+    pub(super) started_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) timeout_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) job_id: Option<String>,
+}
+
+pub(super) struct Spool {
+    dir: PathBuf,
+    /// Bytes of `stdout` and `stderr` taken into the session so far.
+    read: [u64; 2],
+    /// Bytes of `stdout` and `stderr` already returned to a poll.
+    drained: [u64; 2],
+}
+
+impl Spool {
+    /// Create the session's directory and the files its output goes to.
+    pub(super) fn create(root: &Path, session_id: &str) -> io::Result<(Self, File, File)> {
+        create_private_dir(root)?;
+        let dir = root.join(session_id);
+        fs::create_dir(&dir)?;
+        let stdout = File::create(dir.join(STREAMS[0]))?;
+        let stderr = File::create(dir.join(STREAMS[1]))?;
+        let spool = Self {
+            dir,
+            read: [0; 2],
+            drained: [0; 2],
+        };
+        Ok((spool, stdout, stderr))
+    }
+
+    /// Sessions left in `root`, with nothing read from them yet.
+    pub(super) fn load_all(root: &Path) -> Vec<(SessionRecord, Self)> {
+        let Ok(entries) = fs::read_dir(root) else {
+            return Vec::new();
+        };
+        let mut sessions = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let dir = entry.path();
+                let record = fs::read(dir.join("session.json")).ok()?;
+                let record = serde_json::from_slice::<SessionRecord>(&record).ok()?;
+                let drained = fs::read_to_string(dir.join("drained"))
+                    .ok()
+                    .and_then(|drained| {
+                        let (stdout, stderr) = drained.trim().split_once(' ')?;
+                        Some([stdout.parse().ok()?, stderr.parse().ok()?])
+                    })
+                    .unwrap_or_default();
+                Some((
+                    record,
+                    Self {
+                        dir,
+                        read: [0; 2],
+                        drained,
+                    },
+                ))
+            })
+            .collect::<Vec<_>>();
+        sessions.sort_by_key(|(record, _)| record.started_at);
+        sessions
+    }
+
+    pub(super) fn exit_path(&self) -> PathBuf {
+        self.dir.join("exit")
+    }
+
+    pub(super) fn write_record(&self, record: &SessionRecord) -> io::Result<()> {
+        let json = serde_json::to_vec(record).map_err(io::Error::other)?;
+        let tmp = self.dir.join("session.json.tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(tmp, self.dir.join("session.json"))
+    }
+
+    pub(super) fn drained(&self) -> [u64; 2] {
+        self.drained
+    }
+
+    /// New `stdout` and `stderr` output, up to the given offsets. A
+    /// character split across writes is left for the next read.
+    pub(super) fn read_until(&mut self, limit: [u64; 2]) -> [String; 2] {
+        let mut chunks = [String::new(), String::new()];
+        for (index, chunk) in chunks.iter_mut().enumerate() {
+            let Ok(mut file) = File::open(self.dir.join(STREAMS[index])) else {
+                continue;
+            };
+            let Ok(len) = file.metadata().map(|metadata| metadata.len()) else {
+                continue;
+            };
+            let end = len.min(limit[index]);
+            let start = self.read[index].max(end.saturating_sub(MAX_READ_BYTES));
+            if start >= end || file.seek(SeekFrom::Start(start)).is_err() {
+                continue;
+            }
+            let mut bytes = Vec::new();
+            if file.take(end - start).read_to_end(&mut bytes).is_err() {
+                continue;
+            }
+            let complete = match std::str::from_utf8(&bytes) {
+                Err(error) if error.error_len().is_none() => error.valid_up_to(),
+                _ => bytes.len(),
+            };
+            *chunk =
+                String::from_utf8_lossy(bytes.get(..complete).unwrap_or_default()).into_owned();
+            self.read[index] = start + complete as u64;
+        }
+        chunks
+    }
+
+    /// Everything read so far has been returned to a poll.
+    pub(super) fn mark_drained(&mut self) {
+        if self.drained == self.read {
+            return;
+        }
+        self.drained = self.read;
+        let _ = fs::write(
+            self.dir.join("drained"),
+            format!("{} {}\n", self.read[0], self.read[1]),
+        );
+    }
+
+    pub(super) fn exit_code(&self) -> Option<i32> {
+        fs::read_to_string(self.exit_path())
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    }
+
+    pub(super) fn mark_reported(&self) {
+        let _ = File::create(self.dir.join("reported"));
+    }
+
+    pub(super) fn is_reported(&self) -> bool {
+        self.dir.join("reported").exists()
+    }
+
+    pub(super) fn remove(&self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Session output can hold secrets, so only the daemon's user may read it.
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)
+    }
+    #[cfg(not(unix))]
+    {
+        fs::create_dir_all(dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn split_characters_wait_for_their_remaining_bytes() {
+        let root = std::env::temp_dir().join(format!("gsv-spool-{}", uuid::Uuid::new_v4()));
+        let (mut spool, mut stdout, _stderr) = Spool::create(&root, "session").unwrap();
+        let text = "héllo".as_bytes();
+
+        stdout.write_all(&text[..2]).unwrap();
+        assert_eq!(
+            spool.read_until([u64::MAX; 2]),
+            ["h".to_string(), String::new()]
+        );
+        stdout.write_all(&text[2..]).unwrap();
+        assert_eq!(
+            spool.read_until([u64::MAX; 2]),
+            ["éllo".to_string(), String::new()]
+        );
+
+        spool.mark_drained();
+        spool
+            .write_record(&SessionRecord {
+                session_id: "session".to_string(),
+                owner: None,
+                pid: 1,
+                cwd: "/".to_string(),
+                started_at: 0,
+                timeout_at: None,
+                job_id: None,
+            })
+            .unwrap();
+        let (record, reloaded) = Spool::load_all(&root).pop().unwrap();
+        assert_eq!(record.session_id, "session");
+        assert_eq!(reloaded.drained(), [text.len() as u64, 0]);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -1,34 +1,33 @@
 use crate::protocol::ToolDefinition;
+use crate::tools::paths::{Access, PathResolver};
+use crate::tools::preview::ReplacePlan;
 use crate::tools::{Tool, ToolOutput};
 use async_trait::async_trait;
 use serde::Deserialize;
 use serde_json::{json, Value};
-use std::fs;
 use std::path::PathBuf;
 
 pub struct WriteTool {
-    workspace: PathBuf,
+    paths: PathResolver,
 }
 
 impl WriteTool {
     pub fn new(workspace: PathBuf) -> Self {
-        Self { workspace }
+        Self::with_paths(PathResolver::new(workspace))
     }
 
-    fn resolve_path(&self, path: &str) -> PathBuf {
-        let path = PathBuf::from(path);
-        if path.is_absolute() {
-            path
-        } else {
-            self.workspace.join(path)
-        }
+    pub fn with_paths(paths: PathResolver) -> Self {
+        Self { paths }
     }
 }
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct WriteArgs {
     path: String,
     content: String,
+    #[serde(default)]
+    dry_run: bool,
 }
 
 #[async_trait]
@@ -36,7 +35,7 @@ impl Tool for WriteTool {
     fn definition(&self) -> ToolDefinition {
         ToolDefinition {
             name: "Write".to_string(),
-            description: "Write content to a file. Creates parent directories if needed. Paths are relative to the workspace unless absolute.".to_string(),
+            description: "Write content to a file. Creates parent directories if needed. Paths are relative to the workspace unless absolute; @name/... paths address named mounts.".to_string(),
             input_schema: json!({
                 "type": "object",
                 "properties": {
@@ -47,6 +46,10 @@ impl Tool for WriteTool {
                     "content": {
                         "type": "string",
                         "description": "Content to write to the file"
+                    },
+                    "dryRun": {
+                        "type": "boolean",
+                        "description": "Return a unified diff of the change without writing (default: false)"
                     }
                 },
                 "required": ["path", "content"]
@@ -58,21 +61,17 @@ impl Tool for WriteTool {
         let args: WriteArgs =
             serde_json::from_value(args).map_err(|e| format!("Invalid arguments: {}", e))?;
 
-        let resolved = self.resolve_path(&args.path);
-
-        // Create parent directories if needed
-        if let Some(parent) = resolved.parent() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create directories: {}", e))?;
+        let resolved = self.paths.resolve(&args.path, Access::Write)?;
+        let plan = ReplacePlan::new(resolved, args.content)?;
+        if args.dry_run {
+            return Ok(ToolOutput::json(plan.preview()));
         }
-
-        fs::write(&resolved, &args.content)
-            .map_err(|e| format!("Failed to write '{}': {}", resolved.display(), e))?;
+        plan.apply()?;
 
         Ok(ToolOutput::json(json!({
             "ok": true,
-            "path": resolved.display().to_string(),
-            "size": args.content.len()
+            "path": plan.path().display().to_string(),
+            "size": plan.size()
         })))
     }
 }
@@ -35,6 +35,28 @@ fn shell_background_finish_command() -> &'static str {
     }
 }
 
+/// Poll a background session until it is no longer running. Each poll waits
+/// for the process to exit for as long as the tool allows, so the result does
+/// not depend on how fast the shell starts.
+async fn poll_until_finished(shell: &gsv::tools::ShellTool, session_id: &str) -> serde_json::Value {
+    use gsv::tools::Tool;
+    use serde_json::json;
+
+    loop {
+        let poll = shell
+            .execute(json!({
+                "sessionId": session_id,
+                "input": "",
+                "yieldMs": 30_000
+            }))
+            .await
+            .unwrap();
+        if poll.data["status"] != "running" {
+            return poll.data;
+        }
+    }
+}
+
 fn normalize_shell_path(value: &str) -> String {
     #[cfg(windows)]
     {
@@ -168,21 +190,10 @@ async fn test_shell_session_poll_returns_new_output() {
         .unwrap();
 
     let session_id = start.data["sessionId"].as_str().unwrap().to_string();
-    tokio::time::sleep(std::time::Duration::from_millis(1200)).await;
+    let poll = poll_until_finished(&shell, &session_id).await;
 
-    let poll = shell
-        .execute(json!({
-            "sessionId": session_id,
-            "input": ""
-        }))
-        .await
-        .unwrap();
-
-    assert_eq!(poll.data["status"], "completed");
-    assert!(poll.data["output"]
-        .as_str()
-        .unwrap()
-        .contains("async-finished"));
+    assert_eq!(poll["status"], "completed");
+    assert!(poll["output"].as_str().unwrap().contains("async-finished"));
 }
 
 #[tokio::test]
@@ -202,26 +213,55 @@ async fn test_shell_session_is_removed_after_final_poll() {
         .unwrap();
 
     let session_id = start.data["sessionId"].as_str().unwrap().to_string();
-    tokio::time::sleep(std::time::Duration::from_millis(1200)).await;
+    let poll = poll_until_finished(&shell, &session_id).await;
 
-    let poll = shell
+    assert_eq!(poll["status"], "completed");
+    let err = shell
         .execute(json!({
-            "sessionId": session_id,
+            "sessionId": poll["sessionId"].as_str().unwrap(),
             "input": ""
         }))
         .await
+        .unwrap_err();
+
+    assert!(err.contains("Unknown shell session"));
+}
+
+#[tokio::test]
+async fn test_shell_sessions_are_private_to_their_device() {
+    use gsv::tools::{ShellTool, Tool};
+    use serde_json::json;
+
+    let workspace = std::env::temp_dir();
+    let home = ShellTool::new(workspace.clone()).owned_by("home");
+    let work = ShellTool::new(workspace.clone()).owned_by("work");
+
+    let start = home
+        .execute(json!({
+            "input": shell_background_finish_command(),
+            "background": true
+        }))
+        .await
         .unwrap();
+    let session_id = start.data["sessionId"].as_str().unwrap().to_string();
 
-    assert_eq!(poll.data["status"], "completed");
-    let err = shell
+    let err = work
         .execute(json!({
-            "sessionId": poll.data["sessionId"].as_str().unwrap(),
+            "sessionId": session_id,
             "input": ""
         }))
         .await
         .unwrap_err();
-
     assert!(err.contains("Unknown shell session"));
+
+    let poll = home
+        .execute(json!({
+            "sessionId": session_id,
+            "input": ""
+        }))
+        .await
+        .unwrap();
+    assert_eq!(poll.data["sessionId"], session_id);
 }
 
 #[tokio::test]
@@ -112,7 +112,12 @@ describe("CapabilityStore", () => {
         "ai.text.generate",
         "ai.transcription.create",
         "codemode.*",
+        "cron.*",
+        "data.query",
+        "desktop.*",
         "fs.*",
+        "git.*",
+        "host.*",
         "net.fetch",
         "proc.*",
         "repo.apply",
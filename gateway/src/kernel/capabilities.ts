@@ -20,6 +20,11 @@ const DEFAULT_CAPABILITIES: [number, string[]][] = [
     "fs.*",
     "shell.*",
     "net.fetch",
+    "cron.*",
+    "desktop.*",
+    "data.query",
+    "git.*",
+    "host.*",
     "proc.*",
     "signal.*",
     "repo.apply",
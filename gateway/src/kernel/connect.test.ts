@@ -0,0 +1,16 @@
+import { describe, expect, it } from "vitest";
+import { clientUpdateHint } from "./connect";
+
+describe("clientUpdateHint", () => {
+  it("recommends the release of a stable gateway", () => {
+    expect(clientUpdateHint("v0.4.1", "0.4.1")).toEqual({
+      recommendedVersion: "0.4.1",
+      releaseTag: "v0.4.1",
+    });
+  });
+
+  it("sends nothing from builds without a matching release", () => {
+    expect(clientUpdateHint("dev", "0.4.1")).toBeUndefined();
+    expect(clientUpdateHint("v0.4.0", "0.4.1")).toBeUndefined();
+  });
+});
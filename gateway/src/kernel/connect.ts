@@ -15,15 +15,17 @@ import type {
   ConnectResult,
   ConnectionIdentity,
   ProcessIdentity,
+  UpdateHint,
 } from "@humansandmachines/gsv/protocol";
 import type { AuthTokenRole } from "./auth-store";
 import type { CapabilityStore } from "./capabilities";
 import { isValidCapability } from "./capabilities";
 import type { KernelContext } from "./context";
-import { SERVER_RELEASE } from "../version";
+import { SERVER_RELEASE, SERVER_VERSION } from "../version";
 import { ensureAccountHomeLayout } from "./account-home";
 import { ensurePublicAssetStorageLayout } from "../public-assets";
 import { USER_CONNECTION_SIGNALS } from "./user-signals";
+import { negotiateCodec } from "../protocol/frame-codec";
 
 export type ConnectOutcome =
   | { ok: true; identity: ConnectionIdentity; result: ConnectResult }
@@ -187,6 +189,8 @@ export async function handleConnect(
       return { ok: false, code: 103, message: "Invalid client role" };
   }
 
+  const codec = negotiateCodec(args.codec);
+  const update = role === "driver" ? clientUpdateHint() : undefined;
   const result: ConnectResult = {
     protocol: 2,
     server: {
@@ -197,11 +201,28 @@ export async function handleConnect(
     identity: connectionIdentity,
     syscalls: capabilities,
     signals: buildSignalList(role),
+    ...(codec ? { codec } : {}),
+    ...(update ? { update } : {}),
   };
 
   return { ok: true, identity: connectionIdentity, result };
 }
 
+/**
+ * The release devices should run. A stable gateway, whose release is the
+ * `v<version>` tag, recommends its own version, and `gsv device` updates
+ * itself when older. Other builds have no matching release assets.
+ */
+export function clientUpdateHint(
+  release: string = SERVER_RELEASE,
+  version: string = SERVER_VERSION,
+): UpdateHint | undefined {
+  if (release !== `v${version}`) {
+    return undefined;
+  }
+  return { recommendedVersion: version, releaseTag: release };
+}
+
 type IdentityOutcome =
   | { ok: true; identity: ProcessIdentity }
   | { ok: false; error: string };
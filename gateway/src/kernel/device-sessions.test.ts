@@ -0,0 +1,52 @@
+import { afterEach, describe, expect, it, vi } from "vitest";
+import { runWithRealKernelSql } from "../test-support/real-kernel-sql";
+import { DeviceSessionStore } from "./device-sessions";
+
+describe("DeviceSessionStore", () => {
+  afterEach(() => vi.restoreAllMocks());
+
+  it("resumes a suspended session with its token until it expires", async () => {
+    await runWithRealKernelSql((sql) => {
+      const now = vi.spyOn(Date, "now").mockReturnValue(1_000);
+      const sessions = new DeviceSessionStore(sql);
+
+      const first = sessions.open("laptop", 1000);
+      expect(first).toEqual({ token: expect.any(String), resumed: false, abandoned: false });
+      expect(sessions.suspend("laptop", 5_000)).toBe(first.token);
+
+      expect(sessions.open("laptop", 2000, first.token).resumed).toBe(false);
+      const second = sessions.open("laptop", 1000);
+      sessions.suspend("laptop", 5_000);
+      expect(sessions.open("laptop", 1000, second.token))
+        .toEqual({ token: second.token, resumed: true, abandoned: false });
+
+      sessions.suspend("laptop", 5_000);
+      now.mockReturnValue(6_000);
+      const late = sessions.open("laptop", 1000, second.token);
+      expect(late.resumed).toBe(false);
+      expect(late.abandoned).toBe(true);
+    });
+  });
+
+  it("expires only the session it was scheduled for", async () => {
+    await runWithRealKernelSql((sql) => {
+      const sessions = new DeviceSessionStore(sql);
+      const first = sessions.open("laptop", 1000);
+      sessions.suspend("laptop");
+      const second = sessions.open("laptop", 1000, first.token);
+      expect(second.resumed).toBe(true);
+
+      expect(sessions.expire("laptop", first.token)).toBe(false);
+      sessions.suspend("laptop");
+      expect(sessions.expire("laptop", "other")).toBe(false);
+      expect(sessions.expire("laptop", first.token)).toBe(true);
+      expect(sessions.suspend("laptop")).toBeNull();
+
+      sessions.open("laptop", 1000);
+      expect(sessions.discard("laptop")).toBe(false);
+      sessions.open("laptop", 1000);
+      sessions.suspend("laptop");
+      expect(sessions.discard("laptop")).toBe(true);
+    });
+  });
+});
@@ -0,0 +1,107 @@
+/**
+ * DeviceSessionStore — resumable driver sessions.
+ *
+ * A driver that asks for a session in `sys.connect` gets a token. When its
+ * socket drops, the session is suspended instead of failing the device's
+ * routes at once; a reconnect presenting the token before the session
+ * expires picks those routes up again.
+ */
+
+export const DEVICE_SESSION_TTL_MS = 120_000;
+
+export type DeviceSessionGrant = {
+  token: string;
+  resumed: boolean;
+  /** A suspended session existed and was not resumed. */
+  abandoned: boolean;
+};
+
+type DeviceSessionRow = {
+  token: string;
+  owner_uid: number;
+  expires_at: number | null;
+};
+
+export class DeviceSessionStore {
+  constructor(private readonly sql: SqlStorage) {}
+
+  /**
+   * Resume the device's session when `token` matches a live one of the same
+   * owner, else start a new one.
+   */
+  open(deviceId: string, ownerUid: number, token?: string): DeviceSessionGrant {
+    const now = Date.now();
+    const existing = this.get(deviceId);
+    const live = existing !== null &&
+      existing.owner_uid === ownerUid &&
+      (existing.expires_at === null || existing.expires_at > now);
+    const suspended = existing !== null && existing.expires_at !== null;
+
+    if (live && token && existing.token === token) {
+      this.sql.exec(
+        "UPDATE device_sessions SET expires_at = NULL WHERE device_id = ?",
+        deviceId,
+      );
+      return { token, resumed: true, abandoned: false };
+    }
+
+    const next = crypto.randomUUID();
+    this.sql.exec(
+      `INSERT OR REPLACE INTO device_sessions (device_id, token, owner_uid, created_at, expires_at)
+       VALUES (?, ?, ?, ?, NULL)`,
+      deviceId,
+      next,
+      ownerUid,
+      now,
+    );
+    return { token: next, resumed: false, abandoned: suspended };
+  }
+
+  /**
+   * Start the expiry clock of a device whose socket dropped. Returns the
+   * token to expire later, or null when the device has no session.
+   */
+  suspend(deviceId: string, ttlMs: number = DEVICE_SESSION_TTL_MS): string | null {
+    const existing = this.get(deviceId);
+    if (!existing) {
+      return null;
+    }
+    this.sql.exec(
+      "UPDATE device_sessions SET expires_at = ? WHERE device_id = ?",
+      Date.now() + ttlMs,
+      deviceId,
+    );
+    return existing.token;
+  }
+
+  /**
+   * Drop a suspended session that was not resumed in time. Returns false if
+   * it was resumed or replaced meanwhile.
+   */
+  expire(deviceId: string, token: string): boolean {
+    const existing = this.get(deviceId);
+    if (!existing || existing.token !== token || existing.expires_at === null) {
+      return false;
+    }
+    this.sql.exec("DELETE FROM device_sessions WHERE device_id = ?", deviceId);
+    return true;
+  }
+
+  /** Forget the device's session. Returns whether a suspended one existed. */
+  discard(deviceId: string): boolean {
+    const existing = this.get(deviceId);
+    if (!existing) {
+      return false;
+    }
+    this.sql.exec("DELETE FROM device_sessions WHERE device_id = ?", deviceId);
+    return existing.expires_at !== null;
+  }
+
+  private get(deviceId: string): DeviceSessionRow | null {
+    const rows = [...this.sql.exec<DeviceSessionRow>(
+      "SELECT token, owner_uid, expires_at FROM device_sessions WHERE device_id = ?",
+      deviceId,
+    )];
+    return rows[0] ?? null;
+  }
+}
@@ -56,6 +56,27 @@ describe("routed frame deadlines", () => {
       args: { input: "sleep 120", timeout: 120_000 },
     })).toBe(130_000);
   });
+
+  it("waits for long device data queries", () => {
+    expect(routedFrameTtlMs({
+      type: "req",
+      id: "query-default",
+      call: "data.query",
+      args: { path: "events.csv", sql: "select count(*) from data" },
+    })).toBe(60_000);
+    expect(routedFrameTtlMs({
+      type: "req",
+      id: "query-long",
+      call: "data.query",
+      args: { path: "events.csv", sql: "select count(*) from data", timeoutMs: 120_000 },
+    })).toBe(130_000);
+    expect(routedFrameTtlMs({
+      type: "req",
+      id: "query-capped",
+      call: "data.query",
+      args: { path: "events.csv", sql: "select count(*) from data", timeoutMs: 3_600_000 },
+    })).toBe(310_000);
+  });
 });
 
 function sendFrame(connection: { send(message: string): void }, frame: unknown): void {
@@ -130,6 +151,79 @@ describe("dispatch", () => {
     expect(cancelRoute).not.toHaveBeenCalled();
   });
 
+  it("routes device cron calls to the target device", async () => {
+    const send = vi.fn();
+    const registerRoute = vi.fn(async () => ({ cancel: vi.fn() }));
+    const implementsList = ["fs.*", "shell.exec", "cron.*"];
+    const deps = {
+      sendFrame,
+      connections: new Map([
+        ["conn_1", {
+          id: "conn_1",
+          state: {
+            step: "connected",
+            identity: {
+              role: "driver",
+              process: { uid: 1000, gid: 1000, gids: [1000], username: "sam", home: "/home/sam" },
+              capabilities: ["*"],
+              device: "laptop",
+              implements: implementsList,
+            },
+          },
+          send,
+        }],
+      ]),
+      registerRoute,
+      shellSessions: { get: vi.fn() },
+    } as unknown as DispatchDeps;
+    const ctx = {
+      ...makeContext(),
+      devices: {
+        canAccess: vi.fn(() => true),
+        get: vi.fn(() => deviceRecord("laptop", true, implementsList)),
+      },
+    } as unknown as KernelContext;
+
+    const result = await dispatch(
+      {
+        type: "req",
+        id: "req_cron",
+        call: "cron.list",
+        args: { target: "laptop" },
+      } as RequestFrame<"cron.list">,
+      { type: "process", id: "proc_1" },
+      ctx,
+      deps,
+    );
+
+    expect(result).toEqual({ handled: false });
+    expect(registerRoute).toHaveBeenCalledWith(expect.objectContaining({
+      id: "req_cron",
+      call: "cron.list",
+      deviceId: "laptop",
+    }));
+    expect(send).toHaveBeenCalledWith(JSON.stringify({
+      type: "req",
+      id: "req_cron",
+      call: "cron.list",
+      args: {},
+    }));
+
+    const untargeted = await dispatch(
+      { type: "req", id: "req_native", call: "cron.list", args: {} } as RequestFrame<"cron.list">,
+      { type: "process", id: "proc_1" },
+      ctx,
+      deps,
+    );
+    expect(untargeted).toEqual({
+      handled: true,
+      response: expect.objectContaining({
+        ok: false,
+        error: expect.objectContaining({ code: 400, message: "cron.list requires a device target" }),
+      }),
+    });
+  });
+
   it("does not route work to a superseded driver connection", async () => {
     const registerRoute = vi.fn();
     const deps = {
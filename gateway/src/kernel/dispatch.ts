@@ -17,7 +17,7 @@ import type {
   ResponseFrame,
   ResponseOkFrame,
 } from "../protocol/frames";
-import { isRoutableSyscall, type SyscallName } from "../syscalls";
+import { isDeviceOnlySyscall, isRoutableSyscall, type SyscallName } from "../syscalls";
 import type { KernelContext } from "./context";
 import type { RouteOrigin } from "./routing";
 import type { ShellSessionRecord, ShellSessionStore } from "./shell-sessions";
@@ -156,6 +156,8 @@ const DEFAULT_DEVICE_TTL_MS = 60_000;
 // The process watchdog is ten minutes; routing must not preempt it.
 const DEFAULT_SHELL_DEVICE_TTL_MS = 11 * 60_000;
 const SHELL_TIMEOUT_GRACE_MS = 10_000;
+// Devices cap data.query at five minutes.
+const DATA_QUERY_MAX_TTL_MS = 5 * 60_000 + SHELL_TIMEOUT_GRACE_MS;
 
 export async function dispatch(
   frame: RequestFrame,
@@ -225,6 +227,13 @@ export async function dispatch(
     return routeToTarget(frame, routedTarget, origin, ctx, deps);
   }
 
+  if (isDeviceOnlySyscall(frame.call)) {
+    return {
+      handled: true,
+      response: errFrame(frame.id, 400, `${frame.call} requires a device target`),
+    };
+  }
+
   if (target && frame.call !== "ai.text.generate") {
     delete raw.target;
   }
@@ -670,6 +679,16 @@ export function routedFrameTtlMs(frame: RequestFrame): number {
   if (frame.call === "net.fetch") {
     return normalizeNetFetchTimeoutMs(timeout.timeoutMs);
   }
+  if (frame.call === "data.query") {
+    const requested = timeout.timeoutMs;
+    if (typeof requested !== "number" || !Number.isFinite(requested) || requested <= 0) {
+      return DEFAULT_DEVICE_TTL_MS;
+    }
+    return Math.min(
+      DATA_QUERY_MAX_TTL_MS,
+      Math.max(DEFAULT_DEVICE_TTL_MS, Math.trunc(requested) + SHELL_TIMEOUT_GRACE_MS),
+    );
+  }
   return DEFAULT_DEVICE_TTL_MS;
 }
 
@@ -5,10 +5,14 @@ vi.mock("../shared/utils", () => ({
 }));
 
 import { sendFrameToProcess } from "../shared/utils";
+import { runWithRealKernelSql } from "../test-support/real-kernel-sql";
+import { DeviceSessionStore } from "./device-sessions";
 import { Kernel } from "./do";
+import { RoutingTable } from "./routing";
 import {
   BINARY_FRAME_CANCEL,
   BINARY_FRAME_DATA,
+  BINARY_FRAME_DEFLATE,
   BINARY_FRAME_END,
   buildBinaryFrame,
   parseBinaryFrame,
@@ -754,6 +758,87 @@ describe("Kernel device connection cleanup", () => {
     expect(kernel.failRoutesForDriverConnection).toHaveBeenCalledWith(oldConnection.id);
   });
 
+  it("suspends a resumable driver instead of failing its routes", () => {
+    const connection = {
+      id: "driver-connection",
+      state: {
+        step: "connected",
+        identity: { role: "driver", device: "laptop" },
+        resumable: true,
+      },
+    };
+    const kernel = Object.create(Kernel.prototype) as any;
+    kernel.connections = new Map([[connection.id, connection]]);
+    kernel.activeRequests = new Map();
+    kernel.closeFrameBodyChannel = vi.fn();
+    kernel.devices = { setOnline: vi.fn() };
+    kernel.broadcastDeviceStatus = vi.fn();
+    kernel.deviceSessions = { suspend: vi.fn(() => "token-1") };
+    kernel.routes = { detachDevice: vi.fn(() => []) };
+    kernel.routedBodies = new Map([["upload-1", { cancel: vi.fn() }]]);
+    kernel.failDeviceRoutes = vi.fn();
+    kernel.failRoutesForDevice = vi.fn();
+    kernel.failRoutesForConnection = vi.fn();
+    kernel.runRoutes = { clearForConnection: vi.fn() };
+    kernel.schedule = vi.fn(async () => ({ id: "session-expiry" }));
+    kernel.ctx = { waitUntil: vi.fn() };
+
+    kernel.onClose(connection);
+
+    expect(kernel.devices.setOnline).toHaveBeenCalledWith("laptop", false);
+    expect(kernel.failRoutesForDevice).not.toHaveBeenCalled();
+    expect(kernel.deviceSessions.suspend).toHaveBeenCalledWith("laptop");
+    const keep = kernel.routes.detachDevice.mock.calls[0][1];
+    expect(keep("shell-1")).toBe(true);
+    expect(keep("upload-1")).toBe(false);
+    expect(kernel.schedule).toHaveBeenCalledWith(
+      120,
+      "onDeviceSessionExpired",
+      { deviceId: "laptop", token: "token-1" },
+    );
+  });
+
+  it("resumes a driver session and fails routes the driver no longer owes", async () => {
+    await runWithRealKernelSql((sql) => {
+      const kernel = Object.create(Kernel.prototype) as any;
+      kernel.deviceSessions = new DeviceSessionStore(sql);
+      kernel.routes = new RoutingTable(sql);
+      kernel.shellSessions = { failForDevice: vi.fn() };
+      kernel.failDeviceRoutes = vi.fn();
+      const origin = { type: "process", id: "process-1" };
+
+      const first = kernel.openDeviceSession("connection-1", "laptop", 1000, {});
+      expect(first).toEqual({ token: expect.any(String), resumed: false, ttlMs: 120_000 });
+      kernel.routes.register("shell-1", "shell.exec", origin, "laptop", "connection-1");
+      kernel.routes.register("read-1", "fs.read", origin, "laptop", "connection-1");
+      kernel.deviceSessions.suspend("laptop");
+      kernel.routes.detachDevice("laptop", () => true);
+
+      expect(kernel.openDeviceSession("connection-2", "laptop", 1000, {
+        token: first.token,
+        pending: ["shell-1"],
+      })).toEqual({ token: first.token, resumed: true, ttlMs: 120_000 });
+      expect(kernel.routes.get("shell-1")?.driverConnectionId).toBe("connection-2");
+      expect(kernel.routes.get("read-1")).toBeNull();
+      expect(kernel.failDeviceRoutes).toHaveBeenCalledWith([
+        expect.objectContaining({ id: "read-1", deviceId: "laptop" }),
+      ]);
+      expect(kernel.shellSessions.failForDevice).not.toHaveBeenCalled();
+
+      kernel.deviceSessions.suspend("laptop");
+      kernel.routes.detachDevice("laptop", () => true);
+      const restarted = kernel.openDeviceSession("connection-3", "laptop", 1000, {
+        token: "stale",
+        pending: ["shell-1"],
+      });
+      expect(restarted.resumed).toBe(false);
+      expect(restarted.token).not.toBe(first.token);
+      expect(kernel.routes.get("shell-1")).toBeNull();
+      expect(kernel.shellSessions.failForDevice)
+        .toHaveBeenCalledWith("laptop", "Device disconnected");
+    });
+  });
+
   it("replies to an authoritative driver ping on the same connection", () => {
     const connection = {
       id: "driver-connection",
@@ -781,6 +866,94 @@ describe("Kernel device connection cleanup", () => {
     });
   });
 
+  it("stops routing to a driver that announces shutdown", () => {
+    const connection = {
+      id: "driver-connection",
+      state: {
+        step: "connected",
+        identity: { role: "driver", device: "laptop" },
+      } as Record<string, unknown>,
+      setState: vi.fn((state) => {
+        connection.state = state;
+      }),
+    };
+    const kernel = Object.create(Kernel.prototype) as any;
+    kernel.connections = new Map([[connection.id, connection]]);
+    kernel.devices = { setOnline: vi.fn() };
+    kernel.broadcastDeviceStatus = vi.fn();
+    kernel.shellSessions = { get: vi.fn(() => null), rememberDeviceSession: vi.fn() };
+
+    kernel.handleSig(connection, {
+      type: "sig",
+      signal: "device.offline",
+      payload: { reason: "shutdown", activeRequests: 1, gracePeriodMs: 25000 },
+    });
+
+    expect(connection.state.draining).toBe(true);
+    expect(kernel.findDeviceConnection("laptop")).toBeNull();
+    expect(kernel.devices.setOnline).toHaveBeenCalledWith("laptop", false);
+    expect(kernel.broadcastDeviceStatus).toHaveBeenCalledWith("laptop", "disconnected");
+
+    kernel.handleSig(connection, {
+      type: "sig",
+      signal: "exec.status",
+      payload: { sessionId: "session-1", event: "detached" },
+    });
+
+    expect(kernel.shellSessions.rememberDeviceSession).toHaveBeenCalledWith(
+      "session-1",
+      "laptop",
+      "failed",
+      { exitCode: null, error: "Session detached by device shutdown" },
+    );
+  });
+
+  it("decodes negotiated codec messages in arrival order", async () => {
+    const connection = {
+      id: "driver-connection",
+      state: {
+        step: "connected",
+        identity: { role: "driver", device: "laptop" },
+        codec: { encoding: "msgpack", compression: "deflate" },
+      },
+    };
+    const kernel = Object.create(Kernel.prototype) as any;
+    kernel.pendingInboundDecodes = new Map();
+    const seen: unknown[] = [];
+    kernel.handleBinaryMessage = vi.fn((_connection, data: ArrayBuffer) => {
+      const frame = parseBinaryFrame(data)!;
+      seen.push({ streamId: frame.streamId, flags: frame.flags, length: frame.payload.byteLength });
+    });
+    kernel.dispatchFrame = vi.fn(async (_connection, frame) => {
+      seen.push(frame);
+    });
+
+    const payload = new TextEncoder().encode("a".repeat(2000));
+    const deflated = new Uint8Array(
+      await new Response(
+        new Blob([payload]).stream().pipeThrough(new CompressionStream("deflate-raw")),
+      ).arrayBuffer(),
+    );
+    // {"type":"sig","signal":"device.ping"} as MessagePack on the control stream.
+    const control = new Uint8Array([
+      0, 0, 0, 0, 0x20,
+      0x82, 0xa4, ...new TextEncoder().encode("type"), 0xa3, ...new TextEncoder().encode("sig"),
+      0xa6, ...new TextEncoder().encode("signal"), 0xab, ...new TextEncoder().encode("device.ping"),
+    ]);
+
+    await Promise.all([
+      kernel.onMessage(connection, buildBinaryFrame(7, BINARY_FRAME_DATA | BINARY_FRAME_DEFLATE, deflated)),
+      kernel.onMessage(connection, control),
+      kernel.onMessage(connection, buildBinaryFrame(7, BINARY_FRAME_END)),
+    ]);
+
+    expect(seen).toEqual([
+      { streamId: 7, flags: BINARY_FRAME_DATA, length: 2000 },
+      { type: "sig", signal: "device.ping" },
+      { streamId: 7, flags: BINARY_FRAME_END, length: 0 },
+    ]);
+  });
+
   it("aborts native requests when their origin disconnects", () => {
     const controller = new AbortController();
     const connection = {
@@ -832,6 +1005,7 @@ describe("Kernel device connection cleanup", () => {
       connections: Map<string, unknown>;
       disconnectDeviceConnections(deviceId: string, reason: string): void;
       failRoutesForDevice: ReturnType<typeof vi.fn>;
+      deviceSessions: { discard: ReturnType<typeof vi.fn> };
       runRoutes: {
         clearForConnection: ReturnType<typeof vi.fn>;
       };
@@ -842,6 +1016,7 @@ describe("Kernel device connection cleanup", () => {
       ["user", user],
     ]);
     kernel.failRoutesForDevice = vi.fn();
+    kernel.deviceSessions = { discard: vi.fn() };
     kernel.runRoutes = {
       clearForConnection: vi.fn(),
     };
@@ -855,6 +1030,7 @@ describe("Kernel device connection cleanup", () => {
     expect(kernel.connections.has("beta")).toBe(true);
     expect(kernel.connections.has("user")).toBe(true);
     expect(kernel.runRoutes.clearForConnection).toHaveBeenCalledWith("alpha");
+    expect(kernel.deviceSessions.discard).toHaveBeenCalledWith("node-alpha");
     expect(kernel.failRoutesForDevice).toHaveBeenCalledWith("node-alpha");
   });
 });
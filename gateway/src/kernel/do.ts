@@ -23,7 +23,10 @@ import type {
   AdapterMediaPart,
   AdapterSurface,
   BinaryBody,
+  ConnectArgs,
   ConnectionIdentity,
+  DeviceSession,
+  NegotiatedCodec,
   NetFetchArgs,
   ProcessIdentity,
   ScheduleRecord,
@@ -44,6 +47,7 @@ import { AuthStore } from "./auth-store";
 import { CapabilityStore, hasCapability } from "./capabilities";
 import { ConfigStore } from "./config";
 import { DeviceRegistry } from "./devices";
+import { DEVICE_SESSION_TTL_MS, DeviceSessionStore } from "./device-sessions";
 import {
   RoutingTable,
   type FailedDeviceRoute,
@@ -106,6 +110,7 @@ import type {
   ProcessScheduleDeliverRequestFrame,
   ProcessScheduleDeliverResponseFrame,
 } from "../protocol/process-frames";
+import { decodeInboundMessage, type InboundMessage } from "../protocol/frame-codec";
 import { isRepoPublic } from "./repo-visibility";
 import { canReadRepo, canWriteRepo } from "./repo";
 import { handleProcSpawn } from "./proc-handlers";
@@ -170,8 +175,19 @@ function adapterSignalRetryDelayMs(attempt: number): number {
 type ConnectionState = {
   step: "pending" | "connected" | "superseded";
   identity?: ConnectionIdentity;
+  /** A driver that sent `device.offline`: it finishes running requests but takes no new ones. */
+  draining?: boolean;
+  /** Codec agreed in `sys.connect`; unset connections only send JSON text frames. */
+  codec?: NegotiatedCodec;
   clientId?: string;
   clientPlatform?: string;
+  /** A driver holding a resumable session: its routes wait for a reconnect. */
+  resumable?: boolean;
+};
+
+type DeviceSessionExpiry = {
+  deviceId: string;
+  token: string;
 };
 
 type ProcessNetFetchOptions = {
@@ -207,6 +223,7 @@ export class Kernel extends Host<Env> {
   private readonly caps: CapabilityStore;
   private readonly config: ConfigStore;
   private readonly devices: DeviceRegistry;
+  private readonly deviceSessions: DeviceSessionStore;
   private readonly routes: RoutingTable;
   private readonly shellSessions: ShellSessionStore;
   private readonly procs: ProcessRegistry;
@@ -221,6 +238,7 @@ export class Kernel extends Host<Env> {
   private readonly pendingKernelResponses = new Map<string, (frame: ResponseFrame) => void>();
   private readonly pendingProcessSignals = new Map<string, Promise<void>>();
   private readonly frameBodyChannels = new Map<string, BinaryBodyChannel>();
+  private readonly pendingInboundDecodes = new Map<string, Promise<unknown>>();
   private readonly routedBodies = new Map<
     string,
     { cancel(reason?: unknown): Promise<void> }
@@ -248,6 +266,8 @@ export class Kernel extends Host<Env> {
 
     this.devices = new DeviceRegistry(sql);
 
+    this.deviceSessions = new DeviceSessionStore(sql);
+
     this.routes = new RoutingTable(sql);
 
     this.shellSessions = new ShellSessionStore(sql);
@@ -423,6 +443,7 @@ export class Kernel extends Host<Env> {
 
   onClose(connection: Connection): void {
     this.closeFrameBodyChannel(connection.id);
+    this.pendingInboundDecodes.delete(connection.id);
     const state = connection.state as ConnectionState | undefined;
     if (!state) return;
 
@@ -439,8 +460,15 @@ export class Kernel extends Host<Env> {
     if (identity?.role === "driver") {
       if (state.step === "connected" && !this.findDeviceConnection(identity.device)) {
         this.devices.setOnline(identity.device, false);
-        this.broadcastDeviceStatus(identity.device, "disconnected");
-        this.failRoutesForDevice(identity.device);
+        if (!state.draining) {
+          this.broadcastDeviceStatus(identity.device, "disconnected");
+        }
+        if (state.resumable && !state.draining) {
+          this.suspendDeviceSession(identity.device);
+        } else {
+          this.deviceSessions.discard(identity.device);
+          this.failRoutesForDevice(identity.device);
+        }
       } else {
         this.failRoutesForDriverConnection(connection.id);
       }
@@ -451,6 +479,32 @@ export class Kernel extends Host<Env> {
   }
 
   async onMessage(connection: Connection<ConnectionState>, message: WSMessage): Promise<void> {
+    if (connection.state?.codec) {
+      let inbound: InboundMessage;
+      try {
+        inbound = await this.decodeInOrder(connection, message);
+      } catch (err) {
+        const errorMessage = err instanceof Error ? err.message : String(err);
+        this.sendError(connection, "?", 400, errorMessage);
+        return;
+      }
+      switch (inbound.type) {
+        case "body":
+          this.handleBinaryMessage(connection, inbound.data);
+          return;
+        case "frame":
+          if (!inbound.frame || typeof inbound.frame !== "object") {
+            this.sendError(connection, "?", 400, "Invalid frame");
+            return;
+          }
+          await this.dispatchFrame(connection, inbound.frame as Frame);
+          return;
+        case "text":
+          message = inbound.text;
+          break;
+      }
+    }
+
     if (typeof message !== "string") {
       this.handleBinaryMessage(connection, message);
       return;
@@ -468,6 +522,32 @@ export class Kernel extends Host<Env> {
       return;
     }
 
+    await this.dispatchFrame(connection, parsed);
+  }
+
+  /**
+   * Decode a message on a connection with a negotiated codec. Inflating is
+   * asynchronous, so decodes are chained per connection to keep control
+   * frames and body chunks in the order they arrived.
+   */
+  private decodeInOrder(
+    connection: Connection<ConnectionState>,
+    message: WSMessage,
+  ): Promise<InboundMessage> {
+    const previous = this.pendingInboundDecodes.get(connection.id) ?? Promise.resolve();
+    const decoded = previous.then(() => decodeInboundMessage(message));
+    const queued = decoded
+      .catch(() => undefined)
+      .finally(() => {
+        if (this.pendingInboundDecodes.get(connection.id) === queued) {
+          this.pendingInboundDecodes.delete(connection.id);
+        }
+      });
+    this.pendingInboundDecodes.set(connection.id, queued);
+    return decoded;
+  }
+
+  private async dispatchFrame(connection: Connection<ConnectionState>, parsed: Frame): Promise<void> {
     const valid = parsed.type === "req"
       ? typeof parsed.id === "string" && typeof parsed.call === "string"
       : parsed.type === "res"
@@ -2038,7 +2118,7 @@ export class Kernel extends Host<Env> {
 
   private findDeviceConnection(deviceId: string): Connection<ConnectionState> | null {
     for (const [, conn] of this.connections) {
-      if (this.isConnectionForDevice(conn, deviceId)) {
+      if (this.isConnectionForDevice(conn, deviceId) && !conn.state?.draining) {
         return conn;
       }
     }
@@ -2066,6 +2146,7 @@ export class Kernel extends Host<Env> {
     }
 
     if (closed) {
+      this.deviceSessions.discard(deviceId);
       this.failRoutesForDevice(deviceId);
     }
   }
@@ -2297,13 +2378,27 @@ export class Kernel extends Host<Env> {
       return;
     }
 
+    const session = outcome.identity.role === "driver"
+      ? this.openDeviceSession(
+        connection.id,
+        outcome.identity.device,
+        outcome.identity.process.uid,
+        frame.args?.session,
+      )
+      : undefined;
+    if (session) {
+      outcome.result.session = session;
+    }
+
     const clientId = frame.args?.client?.id?.trim();
     const clientPlatform = frame.args?.client?.platform?.trim();
     const newState = {
       step: "connected",
       identity: outcome.identity,
+      codec: outcome.result.codec,
       clientId: clientId || undefined,
       clientPlatform: clientPlatform || undefined,
+      ...(session ? { resumable: true } : {}),
     } satisfies ConnectionState & { step: "connected"; identity: ConnectionIdentity };
     this.activateConnection(connection, newState);
 
@@ -2492,6 +2587,11 @@ export class Kernel extends Host<Env> {
       return;
     }
 
+    if (frame.signal === "device.offline") {
+      this.handleDeviceOffline(connection, targetId);
+      return;
+    }
+
     if (frame.signal !== "exec.status") {
       return;
     }
@@ -2502,13 +2602,34 @@ export class Kernel extends Host<Env> {
       return;
     }
 
-    const status = shellStatusFromEvent(typeof payload?.event === "string" ? payload.event : "");
+    const event = typeof payload?.event === "string" ? payload.event : "";
+    const status = shellStatusFromEvent(event);
     this.shellSessions.rememberDeviceSession(sessionId, targetId, status, {
       exitCode: typeof payload?.exitCode === "number" ? payload.exitCode : null,
-      error: typeof payload?.signal === "string" ? payload.signal : null,
+      error: typeof payload?.signal === "string"
+        ? payload.signal
+        : event === "detached" ? "Session detached by device shutdown" : null,
     });
   }
 
+  /**
+   * A driver announced that it is shutting down. Requests already routed to
+   * it still complete on this connection; new ones see the device offline
+   * unless another connection serves it.
+   */
+  private handleDeviceOffline(connection: Connection<ConnectionState>, deviceId: string): void {
+    const state = connection.state;
+    if (!state || state.draining) {
+      return;
+    }
+    connection.setState({ ...state, draining: true });
+    if (this.findDeviceConnection(deviceId)) {
+      return;
+    }
+    this.devices.setOnline(deviceId, false);
+    this.broadcastDeviceStatus(deviceId, "disconnected");
+  }
+
   private recordShellSessionFromResponse(deviceId: string, frame: ResponseFrame): void {
     if (!frame.ok) {
       return;
@@ -2976,6 +3097,70 @@ export class Kernel extends Host<Env> {
     };
   }
 
+  /**
+   * Start or resume the driver's session. A resumed driver takes over the
+   * routes it still lists as pending and every other route of the device
+   * fails, as on a plain disconnect. A driver that asks for no session, or
+   * whose token is no longer valid, ends the suspended one.
+   */
+  private openDeviceSession(
+    connectionId: string,
+    deviceId: string,
+    ownerUid: number,
+    requested: ConnectArgs["session"],
+  ): DeviceSession | undefined {
+    if (!requested) {
+      if (this.deviceSessions.discard(deviceId)) {
+        this.abandonDeviceRoutes(deviceId, connectionId);
+      }
+      return undefined;
+    }
+
+    const grant = this.deviceSessions.open(deviceId, ownerUid, requested.token);
+    if (grant.resumed) {
+      const pending = new Set(requested.pending ?? []);
+      this.failDeviceRoutes(this.routes.reattachDevice(deviceId, connectionId, pending));
+    } else if (grant.abandoned) {
+      this.abandonDeviceRoutes(deviceId, connectionId);
+    }
+    return { token: grant.token, resumed: grant.resumed, ttlMs: DEVICE_SESSION_TTL_MS };
+  }
+
+  private abandonDeviceRoutes(deviceId: string, connectionId: string): void {
+    this.shellSessions.failForDevice(deviceId, "Device disconnected");
+    this.failDeviceRoutes(this.routes.reattachDevice(deviceId, connectionId, new Set()));
+  }
+
+  /**
+   * Keep the routes of a driver whose socket dropped until its session
+   * expires. Routes still streaming a request body cannot continue on a new
+   * socket and fail now.
+   */
+  private suspendDeviceSession(deviceId: string): void {
+    const token = this.deviceSessions.suspend(deviceId);
+    if (!token) {
+      this.failRoutesForDevice(deviceId);
+      return;
+    }
+    this.failDeviceRoutes(
+      this.routes.detachDevice(deviceId, (id) => !this.routedBodies.has(id)),
+    );
+    this.ctx.waitUntil(this.schedule(
+      DEVICE_SESSION_TTL_MS / 1000,
+      "onDeviceSessionExpired",
+      { deviceId, token } satisfies DeviceSessionExpiry,
+    ));
+  }
+
+  /**
+   * Schedule callback — fired when a suspended driver session runs out.
+   */
+  async onDeviceSessionExpired(expiry: DeviceSessionExpiry): Promise<void> {
+    if (this.deviceSessions.expire(expiry.deviceId, expiry.token)) {
+      this.failRoutesForDevice(expiry.deviceId);
+    }
+  }
+
   private failRoutesForDevice(deviceId: string): void {
     this.shellSessions.failForDevice(deviceId, "Device disconnected");
     this.failDeviceRoutes(this.routes.failForDevice(deviceId));
@@ -3162,7 +3347,7 @@ export class Kernel extends Host<Env> {
       if (!state || state.step !== "connected" || !state.identity) continue;
 
       this.connections.set(connection.id, connection);
-      if (state.identity.role === "driver") {
+      if (state.identity.role === "driver" && !state.draining) {
         onlineTargets.add(state.identity.device);
         this.devices.setOnline(state.identity.device, true);
       }
@@ -3272,7 +3457,9 @@ function shellStatusFromEvent(event: string): ShellSessionStatus {
   if (event === "finished") {
     return "completed";
   }
-  if (event === "failed" || event === "timed_out") {
+  // A `detached` session was abandoned by a driver that shut down; it can
+  // no longer be polled.
+  if (event === "failed" || event === "timed_out" || event === "detached") {
     return "failed";
   }
   return "running";
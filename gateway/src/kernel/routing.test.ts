@@ -63,4 +63,27 @@ describe("RoutingTable", () => {
       );
     });
   });
+
+  it("detaches device routes and hands pending ones to a resumed connection", async () => {
+    await runWithRealKernelSql((sql) => {
+      const routes = new RoutingTable(sql);
+      const origin = { type: "process", id: "process-1" } as const;
+      routes.register("shell-1", "shell.exec", origin, "laptop", "old-connection");
+      routes.register("upload-1", "fs.write", origin, "laptop", "old-connection");
+      routes.register("read-1", "fs.read", origin, "laptop", "old-connection");
+      routes.register("other-1", "fs.read", origin, "desktop", "desktop-connection");
+
+      expect(routes.detachDevice("laptop", (id) => id !== "upload-1")).toEqual([
+        expect.objectContaining({ id: "upload-1", deviceId: "laptop" }),
+      ]);
+      expect(routes.get("shell-1")?.driverConnectionId).toBeNull();
+
+      expect(routes.reattachDevice("laptop", "new-connection", new Set(["shell-1"]))).toEqual([
+        expect.objectContaining({ id: "read-1", deviceId: "laptop" }),
+      ]);
+      expect(routes.get("shell-1")?.driverConnectionId).toBe("new-connection");
+      expect(routes.get("read-1")).toBeNull();
+      expect(routes.get("other-1")?.driverConnectionId).toBe("desktop-connection");
+    });
+  });
 });
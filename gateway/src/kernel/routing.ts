@@ -178,6 +178,75 @@ export class RoutingTable {
     }));
   }
 
+  /**
+   * Unbind the device's routes from its dropped driver connection so a
+   * resumed session can take them over. Routes `keep` rejects are removed and
+   * returned to fail now.
+   */
+  detachDevice(deviceId: string, keep: (id: string) => boolean): FailedDeviceRoute[] {
+    const failed: FailedDeviceRoute[] = [];
+    for (const route of this.listForDevice(deviceId)) {
+      if (keep(route.id)) {
+        this.sql.exec(
+          "UPDATE routing_table SET driver_connection_id = NULL WHERE id = ?",
+          route.id,
+        );
+      } else {
+        this.sql.exec("DELETE FROM routing_table WHERE id = ?", route.id);
+        failed.push(route);
+      }
+    }
+    return failed;
+  }
+
+  /**
+   * Bind the device's routes that the resuming driver still owes a response
+   * for to its new connection, whether they were detached or still on a
+   * socket that has not closed yet. The others are removed and returned to
+   * fail.
+   */
+  reattachDevice(
+    deviceId: string,
+    driverConnectionId: string,
+    pending: ReadonlySet<string>,
+  ): FailedDeviceRoute[] {
+    const failed: FailedDeviceRoute[] = [];
+    for (const route of this.listForDevice(deviceId)) {
+      if (pending.has(route.id)) {
+        this.sql.exec(
+          "UPDATE routing_table SET driver_connection_id = ? WHERE id = ?",
+          driverConnectionId,
+          route.id,
+        );
+      } else {
+        this.sql.exec("DELETE FROM routing_table WHERE id = ?", route.id);
+        failed.push(route);
+      }
+    }
+    return failed;
+  }
+
+  private listForDevice(deviceId: string): FailedDeviceRoute[] {
+    const rows = [...this.sql.exec<{
+      id: string;
+      origin_type: string;
+      origin_id: string;
+      device_id: string;
+      schedule_id: string | null;
+    }>(
+      `SELECT id, origin_type, origin_id, device_id, schedule_id
+       FROM routing_table WHERE device_id = ?`,
+      deviceId,
+    )];
+
+    return rows.map((row) => ({
+      id: row.id,
+      origin: { type: row.origin_type as RouteOrigin["type"], id: row.origin_id },
+      deviceId: row.device_id,
+      scheduleId: row.schedule_id,
+    }));
+  }
+
   failForConnection(connectionId: string): {
     id: string;
     deviceId: string;
@@ -31,7 +31,7 @@ function createTableStatement(name: string): string {
 describe("kernel schema migrations", () => {
   it("starts the kernel component at a v1 baseline", () => {
     expect(KERNEL_SCHEMA_COMPONENT).toBe("kernel");
-    expect(KERNEL_MIGRATIONS).toHaveLength(19);
+    expect(KERNEL_MIGRATIONS).toHaveLength(20);
     expect(KERNEL_MIGRATIONS[0]).toMatchObject({
       id: 1,
       name: "initial_kernel_schema",
@@ -108,6 +108,10 @@ describe("kernel schema migrations", () => {
       id: 19,
       name: "remove_notifications",
     });
+    expect(KERNEL_MIGRATIONS[19]).toMatchObject({
+      id: 20,
+      name: "add_device_sessions",
+    });
   });
 
   it("creates the current kernel table set", () => {
@@ -145,6 +149,7 @@ describe("kernel schema migrations", () => {
       "oauth_accounts",
       "user_mcp_servers",
       "adapter_ingress_receipts",
+      "device_sessions",
     ]);
   });
 
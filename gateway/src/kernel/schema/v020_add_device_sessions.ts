@@ -0,0 +1,17 @@
+import type { SqlMigration } from "../../schema/runner";
+
+export const KERNEL_V020_ADD_DEVICE_SESSIONS: SqlMigration = {
+  id: 20,
+  name: "add_device_sessions",
+  statements: [
+    `
+      CREATE TABLE IF NOT EXISTS device_sessions (
+        device_id   TEXT    PRIMARY KEY,
+        token       TEXT    NOT NULL,
+        owner_uid   INTEGER NOT NULL,
+        created_at  INTEGER NOT NULL,
+        expires_at  INTEGER
+      )
+    `,
+  ],
+};
@@ -0,0 +1,70 @@
+import { describe, expect, it } from "vitest";
+import { decodeInboundMessage, decodeMsgpack, negotiateCodec } from "./frame-codec";
+
+function hex(value: string): Uint8Array {
+  return Uint8Array.from(value.match(/../g)!.map((byte) => parseInt(byte, 16)));
+}
+
+// Encoded by the CLI's FrameCodec (rmp-serde, named fields) for
+// {"type":"res","id":"r1","ok":true,"data":{"big":70000,"f":1.5,"limit":10,"list":[1,"two"],"neg":-5,"none":null}}.
+const MSGPACK_RESPONSE = hex(
+  "0000000020"
+  + "84a474797065a3726573a26964a27231a26f6bc3a464617461"
+  + "86a3626967ce00011170a166cb3ff8000000000000a56c696d69740a"
+  + "a46c6973749201a374776fa36e6567fba46e6f6e65c0",
+);
+
+// Stream 7 DATA chunk of 2000 "a" bytes, deflated by the CLI.
+const DEFLATED_BODY = hex("07000000114a1c05a321301a02a321301a02a321301a02800df9100000");
+
+describe("negotiateCodec", () => {
+  it("picks MessagePack and deflate when offered", () => {
+    expect(negotiateCodec({ encodings: ["msgpack", "json"], compression: ["deflate"] }))
+      .toEqual({ encoding: "msgpack", compression: "deflate" });
+    expect(negotiateCodec({ encodings: ["json"], compression: ["deflate"] }))
+      .toEqual({ encoding: "json", compression: "deflate" });
+  });
+
+  it("stays on plain JSON without a usable offer", () => {
+    expect(negotiateCodec(undefined)).toBeUndefined();
+    expect(negotiateCodec({ encodings: ["json"] })).toBeUndefined();
+    expect(negotiateCodec({ encodings: ["cbor"], compression: ["zstd"] })).toBeUndefined();
+  });
+});
+
+describe("decodeInboundMessage", () => {
+  it("decodes a MessagePack control frame from the CLI", async () => {
+    await expect(decodeInboundMessage(MSGPACK_RESPONSE)).resolves.toEqual({
+      type: "frame",
+      frame: {
+        type: "res",
+        id: "r1",
+        ok: true,
+        data: { big: 70000, f: 1.5, limit: 10, list: [1, "two"], neg: -5, none: null },
+      },
+    });
+  });
+
+  it("inflates body chunks and clears the deflate flag", async () => {
+    const inbound = await decodeInboundMessage(DEFLATED_BODY);
+    expect(inbound.type).toBe("body");
+    const bytes = new Uint8Array((inbound as { data: ArrayBuffer }).data);
+    expect(Array.from(bytes.subarray(0, 5))).toEqual([7, 0, 0, 0, 1]);
+    expect(bytes.byteLength).toBe(5 + 2000);
+    expect(bytes.subarray(5).every((byte) => byte === 0x61)).toBe(true);
+  });
+
+  it("passes text frames and uncompressed body chunks through", async () => {
+    await expect(decodeInboundMessage("{}")).resolves.toEqual({ type: "text", text: "{}" });
+    const chunk = hex("0700000001ff");
+    await expect(decodeInboundMessage(chunk)).resolves.toEqual({ type: "body", data: chunk });
+  });
+
+  it("rejects corrupt payloads", async () => {
+    await expect(decodeInboundMessage(hex("0000000010ffff"))).rejects.toThrow(
+      "Invalid deflate payload",
+    );
+    expect(() => decodeMsgpack(hex("92"))).toThrow("truncated");
+    expect(() => decodeMsgpack(hex("c7"))).toThrow("unsupported type 0xc7");
+  });
+});
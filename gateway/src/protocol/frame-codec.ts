@@ -0,0 +1,263 @@
+/**
+ * Frame codecs negotiated during `sys.connect`.
+ *
+ * A client may offer MessagePack control frames and per-message deflate.
+ * Once the gateway has agreed, the client sends control frames as binary
+ * messages on stream 0 and may deflate body chunks. The gateway itself keeps
+ * replying with JSON text frames, which stay valid under every codec.
+ */
+
+import {
+  BINARY_FRAME_DEFLATE,
+  BINARY_FRAME_HEADER_BYTES,
+  BINARY_FRAME_MSGPACK,
+  CONTROL_FRAME_STREAM_ID,
+  buildBinaryFrame,
+  type NegotiatedCodec,
+} from "@humansandmachines/gsv/protocol";
+
+/** Refuse to inflate a single message beyond this size. */
+const MAX_INFLATED_BYTES = 64 * 1024 * 1024;
+
+export type InboundMessage =
+  | { type: "text"; text: string }
+  | { type: "frame"; frame: unknown }
+  | { type: "body"; data: ArrayBuffer | ArrayBufferView };
+
+/**
+ * Pick the codec for a connection from the client's `codec` offer.
+ * Returns undefined when the client offered nothing this gateway supports
+ * beyond plain JSON, so the connection stays on text frames.
+ */
+export function negotiateCodec(offer: unknown): NegotiatedCodec | undefined {
+  if (!offer || typeof offer !== "object") {
+    return undefined;
+  }
+  const { encodings, compression } = offer as { encodings?: unknown; compression?: unknown };
+  const encoding = Array.isArray(encodings)
+    ? encodings.find((value) => value === "msgpack" || value === "json")
+    : undefined;
+  const deflate = Array.isArray(compression) && compression.includes("deflate");
+  if (encoding !== "msgpack" && !deflate) {
+    return undefined;
+  }
+  return {
+    encoding: encoding ?? "json",
+    ...(deflate ? { compression: "deflate" as const } : {}),
+  };
+}
+
+/**
+ * Decode an inbound WebSocket message on a connection with a negotiated codec.
+ * Body chunks come back inflated with the deflate flag cleared, ready for the
+ * binary body channel.
+ */
+export async function decodeInboundMessage(
+  message: string | ArrayBuffer | ArrayBufferView,
+): Promise<InboundMessage> {
+  if (typeof message === "string") {
+    return { type: "text", text: message };
+  }
+  const bytes = message instanceof ArrayBuffer
+    ? new Uint8Array(message)
+    : new Uint8Array(message.buffer, message.byteOffset, message.byteLength);
+  if (bytes.byteLength < BINARY_FRAME_HEADER_BYTES) {
+    return { type: "body", data: message };
+  }
+
+  const view = new DataView(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+  const streamId = view.getUint32(0, true);
+  const flags = view.getUint8(4);
+  const compressed = (flags & BINARY_FRAME_DEFLATE) !== 0;
+  if (streamId !== CONTROL_FRAME_STREAM_ID && !compressed) {
+    return { type: "body", data: message };
+  }
+
+  let payload = bytes.subarray(BINARY_FRAME_HEADER_BYTES);
+  if (compressed) {
+    payload = await inflateRaw(payload);
+  }
+  if (streamId !== CONTROL_FRAME_STREAM_ID) {
+    return {
+      type: "body",
+      data: buildBinaryFrame(streamId, flags & ~BINARY_FRAME_DEFLATE, payload),
+    };
+  }
+
+  if ((flags & BINARY_FRAME_MSGPACK) !== 0) {
+    return { type: "frame", frame: decodeMsgpack(payload) };
+  }
+  try {
+    return { type: "frame", frame: JSON.parse(new TextDecoder().decode(payload)) };
+  } catch {
+    throw new Error("Malformed JSON");
+  }
+}
+
+async function inflateRaw(payload: Uint8Array): Promise<Uint8Array> {
+  const reader = new Blob([new Uint8Array(payload)])
+    .stream()
+    .pipeThrough(new DecompressionStream("deflate-raw"))
+    .getReader();
+  const chunks: Uint8Array[] = [];
+  let length = 0;
+  for (;;) {
+    let chunk: ReadableStreamReadResult<Uint8Array>;
+    try {
+      chunk = await reader.read();
+    } catch {
+      throw new Error("Invalid deflate payload");
+    }
+    if (chunk.done) {
+      break;
+    }
+    length += chunk.value.byteLength;
+    if (length > MAX_INFLATED_BYTES) {
+      await reader.cancel().catch(() => undefined);
+      throw new Error(`Inflated frame exceeds ${MAX_INFLATED_BYTES} bytes`);
+    }
+    chunks.push(chunk.value);
+  }
+
+  const inflated = new Uint8Array(length);
+  let offset = 0;
+  for (const chunk of chunks) {
+    inflated.set(chunk, offset);
+    offset += chunk.byteLength;
+  }
+  return inflated;
+}
+
+/**
+ * Decode one MessagePack value. Covers the types a JSON-shaped frame can
+ * produce; extension types are rejected.
+ */
+export function decodeMsgpack(bytes: Uint8Array): unknown {
+  const reader = new MsgpackReader(bytes);
+  const value = reader.value();
+  if (reader.offset !== bytes.byteLength) {
+    throw new Error("Invalid MessagePack frame: trailing bytes");
+  }
+  return value;
+}
+
+class MsgpackReader {
+  offset = 0;
+  private readonly view: DataView;
+  private readonly text = new TextDecoder();
+
+  constructor(private readonly bytes: Uint8Array) {
+    this.view = new DataView(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+  }
+
+  value(): unknown {
+    const tag = this.uint(1);
+    if (tag <= 0x7f) return tag;
+    if (tag >= 0xe0) return tag - 0x100;
+    if (tag >= 0x80 && tag <= 0x8f) return this.map(tag & 0x0f);
+    if (tag >= 0x90 && tag <= 0x9f) return this.array(tag & 0x0f);
+    if (tag >= 0xa0 && tag <= 0xbf) return this.string(tag & 0x1f);
+
+    switch (tag) {
+      case 0xc0: return null;
+      case 0xc2: return false;
+      case 0xc3: return true;
+      case 0xc4: return this.take(this.uint(1)).slice();
+      case 0xc5: return this.take(this.uint(2)).slice();
+      case 0xc6: return this.take(this.uint(4)).slice();
+      case 0xca: return this.float(4);
+      case 0xcb: return this.float(8);
+      case 0xcc: return this.uint(1);
+      case 0xcd: return this.uint(2);
+      case 0xce: return this.uint(4);
+      case 0xcf: return this.int64(false);
+      case 0xd0: return this.int(1);
+      case 0xd1: return this.int(2);
+      case 0xd2: return this.int(4);
+      case 0xd3: return this.int64(true);
+      case 0xd9: return this.string(this.uint(1));
+      case 0xda: return this.string(this.uint(2));
+      case 0xdb: return this.string(this.uint(4));
+      case 0xdc: return this.array(this.uint(2));
+      case 0xdd: return this.array(this.uint(4));
+      case 0xde: return this.map(this.uint(2));
+      case 0xdf: return this.map(this.uint(4));
+      default:
+        throw new Error(`Invalid MessagePack frame: unsupported type 0x${tag.toString(16)}`);
+    }
+  }
+
+  private map(size: number): Record<string, unknown> {
+    const record: Record<string, unknown> = {};
+    for (let index = 0; index < size; index += 1) {
+      const key = this.value();
+      if (typeof key !== "string") {
+        throw new Error("Invalid MessagePack frame: map keys must be strings");
+      }
+      // Define rather than assign so "__proto__" stays an own key, as with JSON.parse.
+      Object.defineProperty(record, key, {
+        value: this.value(),
+        enumerable: true,
+        writable: true,
+        configurable: true,
+      });
+    }
+    return record;
+  }
+
+  private array(size: number): unknown[] {
+    const values: unknown[] = [];
+    for (let index = 0; index < size; index += 1) {
+      values.push(this.value());
+    }
+    return values;
+  }
+
+  private string(length: number): string {
+    return this.text.decode(this.take(length));
+  }
+
+  private take(length: number): Uint8Array {
+    if (this.offset + length > this.bytes.byteLength) {
+      throw new Error("Invalid MessagePack frame: truncated");
+    }
+    const slice = this.bytes.subarray(this.offset, this.offset + length);
+    this.offset += length;
+    return slice;
+  }
+
+  private advance(length: number): number {
+    const start = this.offset;
+    this.take(length);
+    return start;
+  }
+
+  private uint(size: 1 | 2 | 4): number {
+    const start = this.advance(size);
+    switch (size) {
+      case 1: return this.view.getUint8(start);
+      case 2: return this.view.getUint16(start);
+      case 4: return this.view.getUint32(start);
+    }
+  }
+
+  private int(size: 1 | 2 | 4): number {
+    const start = this.advance(size);
+    switch (size) {
+      case 1: return this.view.getInt8(start);
+      case 2: return this.view.getInt16(start);
+      case 4: return this.view.getInt32(start);
+    }
+  }
+
+  private int64(signed: boolean): number {
+    const start = this.advance(8);
+    // Same precision as JSON.parse for integers beyond 2^53.
+    return Number(signed ? this.view.getBigInt64(start) : this.view.getBigUint64(start));
+  }
+
+  private float(size: 4 | 8): number {
+    const start = this.advance(size);
+    return size === 4 ? this.view.getFloat32(start) : this.view.getFloat64(start);
+  }
+}
@@ -13,6 +13,11 @@ type SyscallDomain =
   | "fs"
   | "shell"
   | "net"
+  | "cron"
+  | "host"
+  | "git"
+  | "data"
+  | "desktop"
   | "codemode"
   | "proc"
   | "repo"
@@ -31,9 +36,13 @@ function domainOf(syscall: SyscallName): SyscallDomain {
  * Domains that support device routing via the `target` field.
  * `shell` always requires a device target. `fs` can be native (R2) or device.
  * `net` can exit either from the gateway Worker or from a connected device.
+ * The DEVICE_ONLY_DOMAINS exist only on CLI devices.
  * `proc` is kernel-internal (no device routing).
  */
-const ROUTABLE_DOMAINS: SyscallDomain[] = ["fs", "shell", "net"];
+const ROUTABLE_DOMAINS: SyscallDomain[] = ["fs", "shell", "net", "cron", "host", "git", "data", "desktop"];
+
+/** Routable domains with no native implementation: a device target is required. */
+const DEVICE_ONLY_DOMAINS: SyscallDomain[] = ["cron", "host", "git", "data", "desktop"];
 const TARGET_SCHEMA_INLINE_LIMIT = 10;
 
 /**
@@ -91,3 +100,7 @@ function formatTargetSchemaDescription(devices: string[]): string {
 export function isRoutableSyscall(call: SyscallName): boolean {
   return ROUTABLE_DOMAINS.includes(domainOf(call));
 }
+
+export function isDeviceOnlySyscall(call: SyscallName): boolean {
+  return DEVICE_ONLY_DOMAINS.includes(domainOf(call));
+}
@@ -5,6 +5,13 @@ export const BINARY_FRAME_END = 1 << 1;
 export const BINARY_FRAME_ERROR = 1 << 2;
 /** The receiver no longer wants the peer's outgoing stream. */
 export const BINARY_FRAME_CANCEL = 1 << 3;
+/** The payload is raw-deflate compressed. Only valid after codec negotiation. */
+export const BINARY_FRAME_DEFLATE = 1 << 4;
+/** Control stream only: the payload is a MessagePack frame instead of JSON. */
+export const BINARY_FRAME_MSGPACK = 1 << 5;
+
+/** Reserved stream carrying control frames once a codec is negotiated. */
+export const CONTROL_FRAME_STREAM_ID = 0;
 
 export type BinaryFrame = {
   streamId: number;
@@ -8,6 +8,7 @@ export const BODY_SYSCALL_NAMES = [
   "fs.transfer.send",
   "fs.transfer.receive",
   "net.fetch",
+  "data.query",
   "proc.media.read",
   "proc.media.write",
   "ai.transcription.create",
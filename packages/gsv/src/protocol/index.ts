@@ -2,6 +2,11 @@ export type * from "./syscalls/system";
 export type * from "./syscalls/fs";
 export type * from "./syscalls/shell";
 export type * from "./syscalls/net";
+export type * from "./syscalls/cron";
+export type * from "./syscalls/host";
+export type * from "./syscalls/git";
+export type * from "./syscalls/data";
+export type * from "./syscalls/desktop";
 export type * from "./syscalls/codemode";
 export type * from "./syscalls/repositories";
 export type * from "./syscalls/proc";
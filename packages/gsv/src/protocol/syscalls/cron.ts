@@ -0,0 +1,43 @@
+/**
+ * Device cron: recurring commands a CLI device runs itself, including while it
+ * is disconnected. Always routed to a device through `target`.
+ */
+
+export type CronJob = {
+  id: string;
+  name?: string;
+  schedule: string;
+  timezone?: string;
+  command: string;
+  cwd?: string;
+  timeoutMs?: number;
+  createdAt: number;
+  lastRunAt?: number;
+  lastSessionId?: string;
+  lastError?: string;
+  nextRunAt?: number;
+};
+
+export type CronAddArgs = {
+  target?: string;
+  schedule: string;
+  command: string;
+  timezone?: string;
+  cwd?: string;
+  name?: string;
+  timeoutMs?: number;
+};
+
+export type CronAddResult = { job: CronJob };
+
+export type CronListArgs = { target?: string };
+
+export type CronListResult = { jobs: CronJob[] };
+
+export type CronRemoveArgs = { target?: string; id: string };
+
+export type CronRemoveResult = { removed: CronJob };
+
+export type CronRunArgs = { target?: string; id: string };
+
+export type CronRunResult = { jobId: string; sessionId: string; status: "running" };
@@ -0,0 +1,43 @@
+/**
+ * Device data: one read-only SQL statement over SQLite databases or CSV, TSV
+ * and JSON Lines files on a CLI device. Always routed to a device through
+ * `target`. Large results come back as an `application/x-ndjson` body.
+ */
+
+export type DataColumn = {
+  name: string;
+  type: "integer" | "real" | "text" | "blob" | "numeric" | "mixed" | "null";
+  declaredType?: string;
+};
+
+export type DataQueryArgs = {
+  target?: string;
+  path?: string;
+  format?: "sqlite" | "csv" | "tsv" | "jsonl";
+  table?: string;
+  tables?: Record<string, string>;
+  sql: string;
+  params?: unknown[];
+  maxRows?: number;
+  timeoutMs?: number;
+  /** CSV and TSV; default true. */
+  header?: boolean;
+  body?: boolean;
+};
+
+export type DataQueryResult = {
+  path: string | null;
+  columns: DataColumn[];
+  rows?: unknown[][];
+  rowCount: number;
+  truncated: boolean;
+  elapsedMs: number;
+  contentType?: "application/x-ndjson";
+  tables?: Array<{
+    table: string;
+    path: string;
+    format: "csv" | "tsv" | "jsonl";
+    rows: number;
+    columns: Array<{ name: string; type: string }>;
+  }>;
+};
@@ -0,0 +1,32 @@
+/**
+ * Device desktop: notifications and the clipboard of a Linux workstation that
+ * sets `device.desktop`. Always routed to a device through `target`.
+ */
+
+export type DesktopNotifyArgs = {
+  target?: string;
+  summary: string;
+  body?: string;
+  /** Default normal. */
+  urgency?: "low" | "normal" | "critical";
+  /** Theme icon name or file:// URI. */
+  icon?: string;
+  timeoutMs?: number;
+  replacesId?: number;
+};
+
+export type DesktopNotifyResult =
+  | { delivered: true; id: number }
+  | { delivered: false; reason: string };
+
+export type DesktopClipboardReadArgs = { target?: string };
+
+export type DesktopClipboardReadResult =
+  | { available: true; text: string | null }
+  | { available: false; reason: string };
+
+export type DesktopClipboardWriteArgs = { target?: string; text: string };
+
+export type DesktopClipboardWriteResult =
+  | { written: true; bytes: number }
+  | { written: false; reason: string };
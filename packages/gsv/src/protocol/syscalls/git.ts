@@ -0,0 +1,151 @@
+/**
+ * Device git: structured results from git checkouts on a CLI device.
+ * Always routed to a device through `target`.
+ */
+
+import type { RepoDiffFile, RepoDiffStats } from "./repositories";
+
+export type GitChange = {
+  path: string;
+  status: "modified" | "added" | "deleted" | "renamed" | "copied" | "typechange" | "unmerged";
+  origPath?: string;
+};
+
+export type GitBranchState = {
+  head: string | null;
+  oid: string | null;
+  upstream: string | null;
+  ahead: number;
+  behind: number;
+  detached: boolean;
+};
+
+export type GitStatus = {
+  branch: GitBranchState;
+  staged: GitChange[];
+  unstaged: GitChange[];
+  untracked: string[];
+  conflicted: Array<{ path: string; status: string }>;
+  clean: boolean;
+};
+
+export type GitSignature = { name: string; email: string; time: number };
+
+export type GitCommit = {
+  hash: string;
+  parents: string[];
+  author: GitSignature;
+  committer: GitSignature;
+  subject: string;
+  body: string;
+  files: GitChange[];
+};
+
+export type GitStash = { index: number; ref: string; commit: string; message: string };
+
+export type GitBranch = {
+  name: string;
+  remote: boolean;
+  current: boolean;
+  commit: string;
+  upstream: string | null;
+  ahead: number;
+  behind: number;
+  upstreamGone: boolean;
+  subject: string;
+};
+
+export type GitStatusArgs = { target?: string; cwd?: string };
+
+export type GitStatusResult = GitStatus;
+
+export type GitLogArgs = {
+  target?: string;
+  cwd?: string;
+  ref?: string;
+  paths?: string[];
+  /** Defaults to 50, at most 1000. */
+  limit?: number;
+  skip?: number;
+  files?: boolean;
+};
+
+export type GitLogResult = { commits: GitCommit[] };
+
+export type GitDiffArgs = {
+  target?: string;
+  cwd?: string;
+  staged?: boolean;
+  from?: string;
+  to?: string;
+  paths?: string[];
+  context?: number;
+};
+
+export type GitDiffResult = { files: RepoDiffFile[]; stats: RepoDiffStats };
+
+export type GitBranchArgs = { target?: string; cwd?: string; all?: boolean };
+
+export type GitBranchResult = {
+  current: string | null;
+  detached: boolean;
+  branches: GitBranch[];
+};
+
+export type GitCommitArgs = {
+  target?: string;
+  cwd?: string;
+  message: string;
+  all?: boolean;
+  paths?: string[];
+  amend?: boolean;
+  allowEmpty?: boolean;
+};
+
+export type GitCommitResult = { commit: GitCommit };
+
+export type GitCheckoutArgs = {
+  target?: string;
+  cwd?: string;
+  ref?: string;
+  create?: boolean;
+  paths?: string[];
+};
+
+export type GitCheckoutResult = GitStatus & { restored: string[] };
+
+export type GitStashArgs = {
+  target?: string;
+  cwd?: string;
+  action?: "list" | "push" | "pop" | "apply" | "drop";
+  message?: string;
+  index?: number;
+  includeUntracked?: boolean;
+  paths?: string[];
+};
+
+export type GitStashResult =
+  | { stashes: GitStash[] }
+  | { created: boolean; stash: GitStash | null }
+  | { action: string; ref: string; stashes: GitStash[] };
+
+export type GitBlameArgs = {
+  target?: string;
+  path: string;
+  ref?: string;
+  startLine?: number;
+  endLine?: number;
+};
+
+export type GitBlameResult = {
+  path: string;
+  lines: Array<{
+    line: number;
+    commit: string;
+    author: string | null;
+    authorMail: string | null;
+    authorTime: number | null;
+    summary: string | null;
+    content: string;
+  }>;
+};
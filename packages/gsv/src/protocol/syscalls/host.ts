@@ -0,0 +1,69 @@
+/**
+ * Device host: what a device runs on (`host.info`, every CLI device) and, on
+ * Linux, its processes and sockets read from `/proc`. Always routed to a
+ * device through `target`.
+ */
+
+export type SystemInfo = {
+  os: { family: string; arch: string; name?: string; version?: string; kernel?: string; hostname?: string };
+  cpu: { model?: string; cores: number; physicalCores?: number };
+  memory?: { totalBytes: number; availableBytes: number };
+  disks?: Array<{ mount: string; filesystem?: string; totalBytes: number; availableBytes: number }>;
+  gpus?: Array<{ name: string; vendor?: string; memoryBytes?: number }>;
+  battery?: { percent?: number; state: "charging" | "discharging" | "full" | "not_charging" | "unknown" };
+  network?: Array<{ name: string; addresses: string[] }>;
+  toolchains?: Array<{ name: "python" | "node" | "docker" | "cargo" | "rustc" | "go"; path: string; version?: string }>;
+};
+
+export type HostInfoArgs = { target?: string };
+
+export type HostInfoResult = SystemInfo;
+
+export type HostProcess = {
+  pid: number;
+  ppid: number;
+  uid: number;
+  user?: string;
+  name: string;
+  cmdline: string;
+  state: string;
+  threads: number;
+  cpuPercent: number;
+  rssBytes: number;
+  startedAt: number;
+};
+
+export type HostPsArgs = {
+  target?: string;
+  user?: string;
+  name?: string;
+  pids?: number[];
+  ppid?: number;
+  sort?: "cpu" | "rss" | "pid" | "start";
+  limit?: number;
+};
+
+export type HostPsResult = {
+  processes: HostProcess[];
+  total: number;
+  truncated: boolean;
+  sampleMs: number;
+};
+
+export type HostKillArgs = { target?: string; pid: number; signal?: string | number };
+
+export type HostKillResult = { pid: number; signal: string; sent: true };
+
+export type HostPortsArgs = { target?: string; protocol?: "tcp" | "udp"; port?: number };
+
+export type HostPort = {
+  protocol: "tcp" | "tcp6" | "udp" | "udp6";
+  address: string;
+  port: number;
+  uid: number;
+  user?: string | null;
+  pid?: number | null;
+  process?: string | null;
+};
+
+export type HostPortsResult = { ports: HostPort[] };
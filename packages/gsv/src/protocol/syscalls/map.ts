@@ -20,6 +20,53 @@ import type {
 } from "./fs";
 import type { ShellExecArgs, ShellExecResult } from "./shell";
 import type { NetFetchArgs, NetFetchResult } from "./net";
+import type {
+  CronAddArgs,
+  CronAddResult,
+  CronListArgs,
+  CronListResult,
+  CronRemoveArgs,
+  CronRemoveResult,
+  CronRunArgs,
+  CronRunResult,
+} from "./cron";
+import type {
+  HostInfoArgs,
+  HostInfoResult,
+  HostKillArgs,
+  HostKillResult,
+  HostPortsArgs,
+  HostPortsResult,
+  HostPsArgs,
+  HostPsResult,
+} from "./host";
+import type {
+  GitBlameArgs,
+  GitBlameResult,
+  GitBranchArgs,
+  GitBranchResult,
+  GitCheckoutArgs,
+  GitCheckoutResult,
+  GitCommitArgs,
+  GitCommitResult,
+  GitDiffArgs,
+  GitDiffResult,
+  GitLogArgs,
+  GitLogResult,
+  GitStashArgs,
+  GitStashResult,
+  GitStatusArgs,
+  GitStatusResult,
+} from "./git";
+import type { DataQueryArgs, DataQueryResult } from "./data";
+import type {
+  DesktopClipboardReadArgs,
+  DesktopClipboardReadResult,
+  DesktopClipboardWriteArgs,
+  DesktopClipboardWriteResult,
+  DesktopNotifyArgs,
+  DesktopNotifyResult,
+} from "./desktop";
 import type {
   CodeModeExecArgs,
   CodeModeExecResult,
@@ -230,6 +277,31 @@ export type SyscallDomains = {
 
   "net.fetch": { args: NetFetchArgs; result: NetFetchResult };
 
+  "cron.add": { args: CronAddArgs; result: CronAddResult };
+  "cron.list": { args: CronListArgs; result: CronListResult };
+  "cron.remove": { args: CronRemoveArgs; result: CronRemoveResult };
+  "cron.run": { args: CronRunArgs; result: CronRunResult };
+
+  "host.info": { args: HostInfoArgs; result: HostInfoResult };
+  "host.ps": { args: HostPsArgs; result: HostPsResult };
+  "host.kill": { args: HostKillArgs; result: HostKillResult };
+  "host.ports": { args: HostPortsArgs; result: HostPortsResult };
+
+  "git.status": { args: GitStatusArgs; result: GitStatusResult };
+  "git.log": { args: GitLogArgs; result: GitLogResult };
+  "git.diff": { args: GitDiffArgs; result: GitDiffResult };
+  "git.branch": { args: GitBranchArgs; result: GitBranchResult };
+  "git.commit": { args: GitCommitArgs; result: GitCommitResult };
+  "git.checkout": { args: GitCheckoutArgs; result: GitCheckoutResult };
+  "git.stash": { args: GitStashArgs; result: GitStashResult };
+  "git.blame": { args: GitBlameArgs; result: GitBlameResult };
+
+  "data.query": { args: DataQueryArgs; result: DataQueryResult };
+
+  "desktop.notify": { args: DesktopNotifyArgs; result: DesktopNotifyResult };
+  "desktop.clipboard.read": { args: DesktopClipboardReadArgs; result: DesktopClipboardReadResult };
+  "desktop.clipboard.write": { args: DesktopClipboardWriteArgs; result: DesktopClipboardWriteResult };
+
   "codemode.exec": { args: CodeModeExecArgs; result: CodeModeExecResult };
   "codemode.run": { args: CodeModeRunArgs; result: CodeModeRunResult };
 
@@ -47,6 +47,43 @@ export type ConnectArgs = {
     password?: string;
     token?: string;
   };
+  codec?: CodecOffer;
+  session?: DeviceSessionResume;
+};
+
+/**
+ * Resumable driver session asked for in `sys.connect`. A reconnecting driver
+ * presents the previous `token` and the request ids it still owes responses
+ * for; without a token it asks for a new session.
+ */
+export type DeviceSessionResume = {
+  token?: string;
+  pending?: string[];
+};
+
+/** Session granted to a driver; `resumed` when the presented token was live. */
+export type DeviceSession = {
+  token: string;
+  resumed: boolean;
+  ttlMs: number;
+};
+
+/** Control-frame encoding. JSON text frames are always understood. */
+export type FrameEncoding = "json" | "msgpack";
+
+/** Per-message compression for control frames and body chunks. */
+export type FrameCompression = "deflate";
+
+/** Frame codecs the client accepts, preferred first. */
+export type CodecOffer = {
+  encodings: FrameEncoding[];
+  compression?: FrameCompression[];
+};
+
+/** Codec the gateway picked from the client's offer. */
+export type NegotiatedCodec = {
+  encoding: FrameEncoding;
+  compression?: FrameCompression;
 };
 
 export type ServerBuild = {
@@ -54,6 +91,17 @@ export type ServerBuild = {
   release: string;
 };
 
+/**
+ * Client versions the gateway expects. Below `minimumVersion` the gateway may
+ * refuse the connection; below `recommendedVersion` it keeps working.
+ * `releaseTag` defaults to `v<version>`.
+ */
+export type UpdateHint = {
+  minimumVersion?: string;
+  recommendedVersion?: string;
+  releaseTag?: string;
+};
+
 export type ConnectResult = {
   protocol: number;
   server: ServerBuild & {
@@ -62,6 +110,9 @@ export type ConnectResult = {
   identity: ConnectionIdentity;
   syscalls: string[];
   signals: string[];
+  codec?: NegotiatedCodec;
+  update?: UpdateHint;
+  session?: DeviceSession;
 };
 
 export type UserPermissions = {